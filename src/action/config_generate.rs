@@ -3,10 +3,12 @@ use std::path::PathBuf;
 
 use clap::ArgMatches;
 
-use crate::util::cli::prompt_yes;
-use crate::util::error::{quit, quit_error, ErrorHintsBuilder};
+use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
 
-/// Invoke config test command.
+/// Default config file template, documenting all options and their defaults.
+const TEMPLATE: &[u8] = include_bytes!("../../res/lazymc.toml");
+
+/// Invoke config generate command.
 pub fn invoke(matches: &ArgMatches) {
     // Get config path, attempt to canonicalize
     let mut path = PathBuf::from(matches.get_one::<String>("config").unwrap());
@@ -14,21 +16,22 @@ pub fn invoke(matches: &ArgMatches) {
         path = p;
     }
 
-    // Confirm to overwrite if it exists
-    if path.is_file()
-        && !prompt_yes(
-            &format!(
-                "Config file already exists, overwrite?\nPath: {}",
+    // Refuse to overwrite existing file unless forced
+    if path.is_file() && !matches.get_flag("force") {
+        quit_error_msg(
+            format!(
+                "Config file already exists at: {}",
                 path.to_str().unwrap_or("?")
             ),
-            Some(true),
-        )
-    {
-        quit();
+            ErrorHintsBuilder::default()
+                .add_info("use '--force' to overwrite it".into())
+                .build()
+                .unwrap(),
+        );
     }
 
     // Generate file
-    if let Err(err) = fs::write(&path, include_bytes!("../../res/lazymc.toml")) {
+    if let Err(err) = fs::write(&path, TEMPLATE) {
         quit_error(
             anyhow!(err).context("Failed to generate config file"),
             ErrorHintsBuilder::default().build().unwrap(),
@@ -37,3 +40,77 @@ pub fn invoke(matches: &ArgMatches) {
 
     eprintln!("Config saved at: {}", path.to_str().unwrap_or("?"));
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_template_matches_defaults() {
+        let template: Config = toml::from_str(std::str::from_utf8(TEMPLATE).unwrap()).unwrap();
+
+        // Template must only set required options, everything else is a commented default
+        let defaults: Config = toml::from_str(&format!(
+            "[server]\ncommand = {:?}\n[config]\nversion = {:?}",
+            template.server.command,
+            template.config.version.as_deref().unwrap(),
+        ))
+        .unwrap();
+        assert_eq!(format!("{template:?}"), format!("{defaults:?}"));
+    }
+
+    #[test]
+    fn test_template_documents_defaults() {
+        // Options documented with an example value rather than their default
+        const EXAMPLES: &[&str] = &[
+            "sleeping_protocol",
+            "sleeping_version_name",
+            "command_args",
+            "working_dir",
+            "lock_file",
+            "env",
+            "sleep_method",
+            "freeze_process",
+            "forge",
+            "sample",
+            "udp_port",
+            "secret",
+            "banned_message",
+            "online_mode",
+            "event_socket",
+            "log_filter",
+            "health_address",
+            "http_address",
+            "status_cache_file",
+            "outbound_bind",
+            "socks5_proxy",
+            "password_file",
+        ];
+
+        let template = std::str::from_utf8(TEMPLATE).unwrap();
+        let defaults: Config = toml::from_str(template).unwrap();
+
+        // Uncomment all single line options, and ensure they equal the defaults
+        let uncommented: String = template
+            .lines()
+            .map(|line| {
+                let option = match line.strip_prefix('#') {
+                    Some(option) => option,
+                    None => return format!("{line}\n"),
+                };
+                let documented = option
+                    .split_once(" = ")
+                    .map_or(false, |(key, _)| !EXAMPLES.contains(&key))
+                    && toml::from_str::<toml::Value>(option).is_ok();
+                if documented {
+                    format!("{option}\n")
+                } else {
+                    format!("{line}\n")
+                }
+            })
+            .collect();
+        let documented: Config = toml::from_str(&uncommented).unwrap();
+        assert_eq!(format!("{documented:?}"), format!("{defaults:?}"));
+    }
+}
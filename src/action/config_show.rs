@@ -0,0 +1,86 @@
+use clap::ArgMatches;
+
+use crate::config::{self, Config};
+use crate::util::error::{quit_error, ErrorHintsBuilder};
+
+/// Value secrets are replaced with when redacted.
+const REDACTED: &str = "<redacted>";
+
+/// Config keys holding secrets, by section.
+const SECRETS: [(&str, &str); 2] = [("rcon", "password"), ("wake", "secret")];
+
+/// Invoke config show command.
+///
+/// Prints the effective configuration, after merging environment variables and CLI overrides.
+pub fn invoke(matches: &ArgMatches) {
+    let config = config::load(matches);
+
+    match show(&config, matches.get_flag("redact")) {
+        Ok(toml) => print!("{toml}"),
+        Err(err) => quit_error(
+            anyhow!(err).context("Failed to serialize config"),
+            ErrorHintsBuilder::default().build().unwrap(),
+        ),
+    }
+}
+
+/// Serialize the given configuration as TOML, optionally redacting secrets.
+fn show(config: &Config, redact: bool) -> Result<String, toml::ser::Error> {
+    let mut value = toml::Value::try_from(config)?;
+
+    if redact {
+        for (section, key) in SECRETS {
+            let secret = value
+                .get_mut(section)
+                .and_then(|section| section.get_mut(key))
+                .filter(|secret| secret.as_str().map_or(true, |s| !s.is_empty()));
+            if let Some(secret) = secret {
+                *secret = REDACTED.into();
+            }
+        }
+    }
+
+    toml::to_string_pretty(&value)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+
+    #[test]
+    fn test_show_env_override() {
+        let path = env::temp_dir().join(format!(
+            "lazymc-test-config-show-{}.toml",
+            std::process::id()
+        ));
+        fs::write(
+            &path,
+            "[server]\ncommand = \"java -jar server.jar\"\n[time]\nsleep_after = 60\n[wake]\nsecret = \"hunter2\"",
+        )
+        .unwrap();
+        env::set_var("SHOWTEST_TIME__SLEEP_AFTER", "120");
+        let config = Config::load(
+            path.clone(),
+            "SHOWTEST_",
+            toml::Value::Table(Default::default()),
+        );
+        env::remove_var("SHOWTEST_TIME__SLEEP_AFTER");
+        let _ = fs::remove_file(&path);
+        let config = config.unwrap();
+
+        // Output parses back, with the env override applied on top of the file
+        let shown: Config = toml::from_str(&show(&config, false).unwrap()).unwrap();
+        assert_eq!(shown.time.sleep_after, 120);
+        assert_eq!(shown.server.command, "java -jar server.jar");
+        assert_eq!(shown.wake.secret.as_deref(), Some("hunter2"));
+        assert_eq!(shown.advanced.tcp_backlog, config.advanced.tcp_backlog);
+
+        // Secrets are redacted on request
+        let shown: Config = toml::from_str(&show(&config, true).unwrap()).unwrap();
+        assert_eq!(shown.wake.secret.as_deref(), Some(REDACTED));
+        assert_eq!(shown.time.sleep_after, 120);
+    }
+}
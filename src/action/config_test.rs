@@ -2,7 +2,9 @@ use std::path::PathBuf;
 
 use clap::ArgMatches;
 
-use crate::config::{self, Config};
+use crate::config::{self, Config, Server as ConfigServer};
+use crate::persist;
+use crate::server;
 use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
 
 /// Invoke config test command.
@@ -12,10 +14,12 @@ pub fn invoke(matches: &ArgMatches) {
     if let Ok(p) = path.canonicalize() {
         path = p;
     }
+    let prefix = config::env_prefix(matches);
+    let cli = config::collect_cli_config(matches);
 
     if path.is_file() {
         // Config file exists — load and test it (with env overrides)
-        let _config = match Config::load(path) {
+        let config = match Config::load(path, &prefix, cli) {
             Ok(config) => config,
             Err(err) => {
                 quit_error(
@@ -26,9 +30,10 @@ pub fn invoke(matches: &ArgMatches) {
         };
 
         eprintln!("Config loaded successfully!");
-    } else if config::has_env_config() {
+        warn_sleep_after(&config);
+    } else if config::has_env_config(&prefix) || config::has_cli_config(&cli) {
         // No config file, but LAZYMC_ env vars present — test env-only config
-        let _config = match Config::from_env() {
+        let config = match Config::from_env(&prefix, cli) {
             Ok(config) => config,
             Err(err) => {
                 quit_error(
@@ -39,12 +44,14 @@ pub fn invoke(matches: &ArgMatches) {
         };
 
         eprintln!("Config loaded successfully from environment variables!");
+        warn_sleep_after(&config);
     } else {
         quit_error_msg(
             format!(
                 "Config file does not exist at: {}\n\
-                 Hint: you can also configure lazymc entirely through LAZYMC_ environment variables.",
-                path.to_str().unwrap_or("?")
+                 Hint: you can also configure lazymc entirely through {} environment variables.",
+                path.to_str().unwrap_or("?"),
+                prefix,
             ),
             ErrorHintsBuilder::default().build().unwrap(),
         );
@@ -52,3 +59,11 @@ pub fn invoke(matches: &ArgMatches) {
 
     // TODO: do additional config tests: server dir correct, command set?
 }
+
+/// Warn if the server sleeps sooner than the startup time learned in the server directory.
+fn warn_sleep_after(config: &Config) {
+    let startup = ConfigServer::server_directory(config)
+        .and_then(|dir| persist::load::<persist::State>(&dir.join(persist::FILE)).ok())
+        .and_then(|state| state.startup.average());
+    server::warn_sleep_after(config, startup);
+}
@@ -0,0 +1,99 @@
+use std::io::Read;
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use clap::ArgMatches;
+
+use crate::config;
+use crate::service::health::HEALTHY;
+use crate::util::error::{quit_error_msg, ErrorHintsBuilder};
+
+/// Timeout for connecting to and reading from the health check endpoint.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Invoke health command.
+///
+/// Errors if the running lazymc instance is unreachable or unhealthy.
+pub fn invoke(matches: &ArgMatches) -> Result<(), ()> {
+    let config = config::load(matches);
+
+    let addr = match config.advanced.health_address {
+        Some(addr) => addr,
+        None => quit_error_msg(
+            "Health checks are not enabled",
+            ErrorHintsBuilder::default()
+                .add_info("set 'advanced.health_address' in the config file".into())
+                .build()
+                .unwrap(),
+        ),
+    };
+
+    match check(addr) {
+        Ok(()) => {
+            eprintln!("Healthy");
+            Ok(())
+        }
+        Err(err) => {
+            eprintln!("Unhealthy: {err}");
+            Err(())
+        }
+    }
+}
+
+/// Check health of the lazymc instance at the given health check address.
+fn check(addr: SocketAddr) -> Result<(), String> {
+    let mut stream = TcpStream::connect_timeout(&addr, TIMEOUT)
+        .map_err(|err| format!("failed to connect to {addr}: {err}"))?;
+    stream
+        .set_read_timeout(Some(TIMEOUT))
+        .map_err(|err| err.to_string())?;
+
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .map_err(|err| format!("failed to read health check response: {err}"))?;
+
+    match response.trim() {
+        HEALTHY => Ok(()),
+        other => Err(other.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    use super::*;
+
+    /// Serve a single health check with the given response.
+    fn mock(response: &'static str) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        addr
+    }
+
+    #[test]
+    fn test_check() {
+        assert_eq!(check(mock("ok\n")), Ok(()));
+        assert_eq!(
+            check(mock("unhealthy: server monitor stalled\n")),
+            Err("unhealthy: server monitor stalled".into())
+        );
+    }
+
+    #[test]
+    fn test_check_down() {
+        // Nothing listens on the address of a dropped listener
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        assert!(check(addr).is_err());
+    }
+}
@@ -1,3 +1,5 @@
 pub mod config_generate;
+pub mod config_show;
 pub mod config_test;
+pub mod health;
 pub mod start;
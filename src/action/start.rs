@@ -4,9 +4,15 @@ use std::sync::Arc;
 use clap::ArgMatches;
 
 use crate::config::{self, Config, Server as ConfigServer};
+#[cfg(feature = "rcon")]
+use crate::config::{RandomizePassword, Rcon};
 use crate::mc::server_properties;
+#[cfg(feature = "rcon")]
+use crate::persist;
 use crate::proto;
+use crate::runtime;
 use crate::service;
+use crate::util::error::{quit_error, ErrorHints};
 
 /// RCON randomized password length.
 #[cfg(feature = "rcon")]
@@ -18,6 +24,9 @@ pub fn invoke(matches: &ArgMatches) -> Result<(), ()> {
     #[allow(unused_mut)]
     let mut config = config::load(matches);
 
+    // Refuse to start if another instance manages this server, before touching its files
+    let lock_file = service::server::lock(&config);
+
     // Prepare RCON if enabled
     #[cfg(feature = "rcon")]
     prepare_rcon(&mut config);
@@ -25,9 +34,24 @@ pub fn invoke(matches: &ArgMatches) -> Result<(), ()> {
     // Rewrite server server.properties file
     rewrite_server_properties(&config);
 
-    // Start server service
+    // Build runtime to complete all work on
+    let runtime = runtime::build(&config).map_err(|err| {
+        quit_error(
+            anyhow!(err).context("Failed to start runtime"),
+            ErrorHints::default(),
+        );
+    })?;
+
+    // Start server service, or only start and stop server once on dry run
     let config = Arc::new(config);
-    service::server::service(config)
+    let dry_run = matches
+        .subcommand_matches("start")
+        .map(|matches| matches.get_flag("dry-run"))
+        .unwrap_or(false);
+    if dry_run {
+        return runtime.block_on(service::dry_run::service(config, lock_file));
+    }
+    runtime.block_on(service::server::service(config, lock_file))
 }
 
 /// Prepare RCON.
@@ -52,7 +76,7 @@ fn prepare_rcon(config: &mut Config) {
     }
 
     // Must configure RCON password with no randomization
-    if config.server.address.port() == config.rcon.port {
+    if config.server.address.tcp().map(|addr| addr.port()) == Some(config.rcon.port) {
         quit_error_msg(
             "RCON port cannot be the same as the server",
             ErrorHintsBuilder::default()
@@ -63,7 +87,7 @@ fn prepare_rcon(config: &mut Config) {
     }
 
     // Must configure RCON password with no randomization
-    if config.rcon.password.trim().is_empty() && !config.rcon.randomize_password {
+    if config.rcon.password.trim().is_empty() && !config.rcon.randomize_password.is_enabled() {
         quit_error_msg(
             "RCON password can't be empty, or enable randomization",
             ErrorHintsBuilder::default()
@@ -75,7 +99,7 @@ fn prepare_rcon(config: &mut Config) {
     }
 
     // RCON password randomization
-    if config.rcon.randomize_password {
+    if config.rcon.randomize_password.is_enabled() {
         // Must enable server.properties rewrite
         if !config.advanced.rewrite_server_properties {
             quit_error_msg(
@@ -94,10 +118,35 @@ fn prepare_rcon(config: &mut Config) {
         }
 
         // Randomize password
-        config.rcon.password = generate_random_password();
+        config.rcon.password = randomize_password(config);
     }
 }
 
+/// Get randomized RCON password, and write it to the password file.
+///
+/// Reuses the password from the password file if randomizing once.
+#[cfg(feature = "rcon")]
+fn randomize_password(config: &Config) -> String {
+    let file = Rcon::password_file(config);
+    if config.rcon.randomize_password == RandomizePassword::Once {
+        match file.as_deref().and_then(persist::read_secret) {
+            Some(password) => return password,
+            None if file.is_none() => {
+                warn!(target: "lazymc", "Cannot keep randomized RCON password, server directory not configured (server.directory)");
+            }
+            None => {}
+        }
+    }
+
+    let password = generate_random_password();
+    if let Some(file) = file {
+        if let Err(err) = persist::write_secret(&file, &password) {
+            warn!(target: "lazymc", "Failed to write RCON password to {}: {}", file.display(), err);
+        }
+    }
+    password
+}
+
 /// Generate secure random password.
 #[cfg(feature = "rcon")]
 fn generate_random_password() -> String {
@@ -130,26 +179,36 @@ fn rewrite_server_properties(config: &Config) {
 
     // Build list of changes
     #[allow(unused_mut)]
-    let mut changes = HashMap::from([
-        ("server-ip", config.server.address.ip().to_string()),
-        ("server-port", config.server.address.port().to_string()),
-        ("enable-status", "true".into()),
-        ("query.port", config.server.address.port().to_string()),
-    ]);
+    let mut changes = HashMap::from([("enable-status", "true".into())]);
+
+    // Set server address, unless connecting to server over Unix socket
+    if let Some(addr) = config.server.address.tcp() {
+        changes.extend([
+            ("server-ip", addr.ip().to_string()),
+            ("server-port", addr.port().to_string()),
+            ("query.port", addr.port().to_string()),
+        ]);
 
-    // If connecting to server over non-loopback address, disable proxy blocking
-    if !config.server.address.ip().is_loopback() {
-        changes.extend([("prevent-proxy-connections", "false".into())]);
+        // If connecting to server over non-loopback address, disable proxy blocking
+        if !addr.ip().is_loopback() {
+            changes.extend([("prevent-proxy-connections", "false".into())]);
+        }
     }
 
     // Update network compression threshold for lobby mode
-    if config.join.methods.contains(&config::Method::Lobby) {
+    if config.join.methods.iter().any(|m| m.uses_lobby()) {
         changes.extend([(
             "network-compression-threshold",
             proto::COMPRESSION_THRESHOLD.to_string(),
         )]);
     }
 
+    // Accept clients transferred from the lobby
+    #[cfg(feature = "lobby")]
+    if config.join.lobby.transfer_on_ready {
+        changes.extend([("accepts-transfers", "true".into())]);
+    }
+
     // Add RCON configuration
     #[cfg(feature = "rcon")]
     if config.rcon.enabled {
@@ -163,3 +222,55 @@ fn rewrite_server_properties(config: &Config) {
     // Rewrite file
     server_properties::rewrite_dir(dir, changes)
 }
+
+#[cfg(all(test, feature = "rcon"))]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    /// Build config randomizing the RCON password with the given mode, writing to the given file.
+    fn rcon_config(mode: &str, file: &std::path::Path) -> Config {
+        crate::util::test::config(&format!(
+            "[rcon]\nenabled = true\nrandomize_password = {mode}\npassword_file = {:?}",
+            file.display().to_string(),
+        ))
+    }
+
+    #[test]
+    fn test_randomize_password_written() {
+        let file = std::env::temp_dir().join(format!(
+            "lazymc-test-rcon-always-{}-{}",
+            std::process::id(),
+            persist::RCON_PASSWORD_FILE
+        ));
+        let config = rcon_config("true", &file);
+
+        // New password on each start, written for other tools to read
+        let password = randomize_password(&config);
+        assert_eq!(password.len(), RCON_PASSWORD_LENGTH);
+        assert_eq!(persist::read_secret(&file), Some(password.clone()));
+        let next = randomize_password(&config);
+        assert_ne!(next, password);
+        assert_eq!(persist::read_secret(&file), Some(next));
+
+        let _ = fs::remove_file(&file);
+    }
+
+    #[test]
+    fn test_randomize_password_once() {
+        let file = std::env::temp_dir().join(format!(
+            "lazymc-test-rcon-once-{}-{}",
+            std::process::id(),
+            persist::RCON_PASSWORD_FILE
+        ));
+        let config = rcon_config("\"once\"", &file);
+
+        // Password is kept across restarts
+        let password = randomize_password(&config);
+        assert_eq!(randomize_password(&config), password);
+        assert_eq!(persist::read_secret(&file), Some(password));
+
+        let _ = fs::remove_file(&file);
+    }
+}
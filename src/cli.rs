@@ -1,4 +1,4 @@
-use clap::{Arg, Command};
+use clap::{Arg, ArgAction, Command};
 
 /// The clap app for CLI argument parsing.
 pub fn app() -> Command {
@@ -9,7 +9,13 @@ pub fn app() -> Command {
         .subcommand(
             Command::new("start")
                 .alias("run")
-                .about("Start lazymc and server (default)"),
+                .about("Start lazymc and server (default)")
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Start and stop server once to validate setup, then quit"),
+                ),
         )
         .subcommand(
             Command::new("config")
@@ -20,9 +26,29 @@ pub fn app() -> Command {
                 .subcommand(
                     Command::new("generate")
                         .alias("gen")
-                        .about("Generate config"),
+                        .about("Generate config")
+                        .arg(
+                            Arg::new("force")
+                                .short('f')
+                                .long("force")
+                                .action(ArgAction::SetTrue)
+                                .help("Overwrite existing config file"),
+                        ),
                 )
-                .subcommand(Command::new("test").about("Test config")),
+                .subcommand(Command::new("test").about("Test config"))
+                .subcommand(
+                    Command::new("show")
+                        .about("Show effective config, with environment and CLI overrides applied")
+                        .arg(
+                            Arg::new("redact")
+                                .long("redact")
+                                .action(ArgAction::SetTrue)
+                                .help("Redact secrets, such as the RCON password"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("health").about("Check health of running lazymc, for liveness probes"),
         )
         .arg(
             Arg::new("config")
@@ -35,6 +61,15 @@ pub fn app() -> Command {
                 .help("Use config file")
                 .num_args(1),
         )
+        .arg(
+            Arg::new("env-prefix")
+                .long("env-prefix")
+                .global(true)
+                .value_name("PREFIX")
+                .default_value(crate::config::ENV_PREFIX)
+                .help("Read config from environment variables with this prefix")
+                .num_args(1),
+        )
         .arg(
             Arg::new("public-address")
                 .long("public-address")
@@ -43,4 +78,45 @@ pub fn app() -> Command {
                 .help("Public address to listen on (overrides config and env)")
                 .num_args(1),
         )
+        .arg(
+            Arg::new("server-command")
+                .long("server-command")
+                .global(true)
+                .value_name("COMMAND")
+                .help("Command to start the server (overrides config and env)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("server-address")
+                .long("server-address")
+                .global(true)
+                .value_name("ADDRESS")
+                .help("Server address to proxy to (overrides config and env)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("sleep-after")
+                .long("sleep-after")
+                .global(true)
+                .value_name("SECONDS")
+                .value_parser(clap::value_parser!(u32))
+                .help("Sleep server after seconds without players (overrides config and env)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("no-rcon")
+                .long("no-rcon")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Disable RCON (overrides config and env)"),
+        )
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .global(true)
+                .value_name("KEY=VALUE")
+                .action(ArgAction::Append)
+                .help("Set config value by key path, such as time.sleep_after=60 (overrides config and env)")
+                .num_args(1),
+        )
 }
@@ -1,17 +1,25 @@
+use std::collections::BTreeMap;
 use std::env;
 use std::fs;
 use std::io;
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
 use std::path::PathBuf;
 
+use chrono::{Local, NaiveDateTime};
 use clap::ArgMatches;
-use serde::Deserialize;
+use serde::de::{self, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use toml::map::Map;
 use version_compare::Cmp;
 
+use crate::logger;
+use crate::net::Endpoint;
+use crate::persist;
 use crate::proto;
+use crate::util::cidr::Cidr;
 use crate::util::error::{quit_error, quit_error_msg, ErrorHintsBuilder};
-use crate::util::serde::to_socket_addrs;
+use crate::util::schedule::{TimeOfDay, TimeWindow};
+use crate::util::serde::{to_endpoint, to_socket_addrs};
 
 /// Default configuration file location.
 pub const CONFIG_FILE: &str = "lazymc.toml";
@@ -19,8 +27,8 @@ pub const CONFIG_FILE: &str = "lazymc.toml";
 /// Configuration version user should be using, or warning will be shown.
 const CONFIG_VERSION: &str = "0.2.8";
 
-/// Prefix for environment variable-based configuration.
-const ENV_PREFIX: &str = "LAZYMC_";
+/// Default prefix for environment variable-based configuration.
+pub const ENV_PREFIX: &str = "LAZYMC_";
 
 /// Section separator in environment variable names.
 const ENV_SEPARATOR: &str = "__";
@@ -35,10 +43,12 @@ pub fn load(matches: &ArgMatches) -> Config {
     if let Ok(p) = path.canonicalize() {
         path = p;
     }
+    let prefix = env_prefix(matches);
+    let cli = collect_cli_config(matches);
 
     let mut config = if path.is_file() {
-        // Load from file, then merge env overrides
-        match Config::load(path) {
+        // Load from file, then merge env and CLI overrides
+        match Config::load(path, &prefix, cli) {
             Ok(config) => config,
             Err(err) => {
                 quit_error(
@@ -51,9 +61,9 @@ pub fn load(matches: &ArgMatches) -> Config {
                 );
             }
         }
-    } else if has_env_config() {
-        // No config file, but env vars present — build config from env
-        match Config::from_env() {
+    } else if has_env_config(&prefix) || has_cli_config(&cli) {
+        // No config file, but env vars or CLI overrides present — build config from them
+        match Config::from_env(&prefix, cli) {
             Ok(config) => config,
             Err(err) => {
                 quit_error(
@@ -66,8 +76,9 @@ pub fn load(matches: &ArgMatches) -> Config {
         quit_error_msg(
             format!(
                 "Config file does not exist: {}\n\
-                 Hint: you can also configure lazymc entirely through LAZYMC_ environment variables.",
-                path.to_str().unwrap_or("?")
+                 Hint: you can also configure lazymc entirely through {} environment variables.",
+                path.to_str().unwrap_or("?"),
+                prefix,
             ),
             ErrorHintsBuilder::default()
                 .config(true)
@@ -80,9 +91,61 @@ pub fn load(matches: &ArgMatches) -> Config {
     // Apply CLI flag overrides (highest priority)
     apply_cli_overrides(&mut config, matches);
 
+    // Configured log filter takes precedence over RUST_LOG
+    if let Some(filter) = &config.advanced.log_filter {
+        logger::set_filter(filter);
+    }
+
     config
 }
 
+/// Collect config overrides from CLI flags into a nested TOML table.
+///
+/// Generic `--set key.path=value` overrides are applied first, so dedicated flags take precedence.
+/// Values for `--set` are type-inferred the same way as environment variables.
+pub fn collect_cli_config(matches: &ArgMatches) -> toml::Value {
+    let mut root = Map::new();
+
+    for set in matches.get_many::<String>("set").into_iter().flatten() {
+        let (key, value) = match set.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key, value),
+            _ => quit_error_msg(
+                format!("Invalid --set override, expected key.path=value: {set}"),
+                ErrorHintsBuilder::default().build().unwrap(),
+            ),
+        };
+        let keys: Vec<String> = key.trim().split('.').map(|s| s.to_string()).collect();
+        insert_nested(&mut root, &keys, infer_toml_value(value));
+    }
+
+    let mut set = |keys: &[&str], value: toml::Value| {
+        let keys: Vec<String> = keys.iter().map(|s| s.to_string()).collect();
+        insert_nested(&mut root, &keys, value);
+    };
+    if let Some(command) = matches.get_one::<String>("server-command") {
+        set(&["server", "command"], toml::Value::String(command.clone()));
+    }
+    if let Some(address) = matches.get_one::<String>("server-address") {
+        set(&["server", "address"], toml::Value::String(address.clone()));
+    }
+    if let Some(sleep_after) = matches.get_one::<u32>("sleep-after") {
+        set(
+            &["time", "sleep_after"],
+            toml::Value::Integer(*sleep_after as i64),
+        );
+    }
+    if matches.get_flag("no-rcon") {
+        set(&["rcon", "enabled"], toml::Value::Boolean(false));
+    }
+
+    toml::Value::Table(root)
+}
+
+/// Check whether any config overrides are set through CLI flags.
+pub fn has_cli_config(cli: &toml::Value) -> bool {
+    cli.as_table().map_or(false, |t| !t.is_empty())
+}
+
 /// Apply CLI flag overrides to the config. CLI flags have the highest priority.
 fn apply_cli_overrides(config: &mut Config, matches: &ArgMatches) {
     if let Some(addr_str) = matches.get_one::<String>("public-address") {
@@ -101,13 +164,28 @@ fn apply_cli_overrides(config: &mut Config, matches: &ArgMatches) {
     }
 }
 
-/// Check whether any `LAZYMC_` environment variables are set.
-pub fn has_env_config() -> bool {
-    env::vars().any(|(k, _)| k.starts_with(ENV_PREFIX))
+/// Get the environment variable prefix to use, `LAZYMC_` unless overridden on the CLI.
+///
+/// A trailing underscore is added if missing.
+pub fn env_prefix(matches: &ArgMatches) -> String {
+    let prefix = matches
+        .get_one::<String>("env-prefix")
+        .map(|p| p.as_str())
+        .unwrap_or(ENV_PREFIX);
+    if prefix.ends_with('_') {
+        prefix.into()
+    } else {
+        format!("{prefix}_")
+    }
+}
+
+/// Check whether any environment variables with the given prefix are set.
+pub fn has_env_config(prefix: &str) -> bool {
+    env::vars().any(|(k, _)| k.starts_with(prefix))
 }
 
 /// Configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Config {
     /// Configuration path if known.
     ///
@@ -126,6 +204,10 @@ pub struct Config {
     #[serde(default)]
     pub time: Time,
 
+    /// Schedule configuration.
+    #[serde(default)]
+    pub schedule: Schedule,
+
     /// MOTD configuration.
     #[serde(default)]
     pub motd: Motd,
@@ -138,6 +220,18 @@ pub struct Config {
     #[serde(default)]
     pub lockout: Lockout,
 
+    /// Maintenance mode.
+    #[serde(default)]
+    pub maintenance: Maintenance,
+
+    /// Access control.
+    #[serde(default)]
+    pub access: Access,
+
+    /// Wake configuration.
+    #[serde(default)]
+    pub wake: Wake,
+
     /// RCON configuration.
     #[serde(default)]
     pub rcon: Rcon,
@@ -152,24 +246,31 @@ pub struct Config {
 }
 
 impl Config {
-    /// Load configuration from file, with env var overrides merged in.
-    pub fn load(path: PathBuf) -> Result<Self, io::Error> {
+    /// Load configuration from file, with overrides from env vars with the given prefix merged in.
+    ///
+    /// The given CLI overrides are merged in last.
+    pub fn load(path: PathBuf, env_prefix: &str, cli: toml::Value) -> Result<Self, io::Error> {
         let data = fs::read_to_string(&path)?;
         let mut file_value: toml::Value = toml::from_str(&data).map_err(io::Error::other)?;
 
-        // Merge env var overrides on top of file config
-        let env_value = collect_env_config();
+        // Merge env var and CLI overrides on top of file config
+        let env_value = collect_env_config(env_prefix);
         if env_value.as_table().map_or(false, |t| !t.is_empty()) {
             file_value = deep_merge(file_value, env_value);
         }
+        if has_cli_config(&cli) {
+            file_value = deep_merge(file_value, cli);
+        }
 
-        Self::from_value(file_value, Some(path))
+        Self::from_value(file_value, Some(path)).map_err(|err| env_error(err, env_prefix))
     }
 
-    /// Build configuration purely from environment variables and serde defaults.
-    pub fn from_env() -> Result<Self, io::Error> {
-        let env_value = collect_env_config();
-        Self::from_value(env_value, None)
+    /// Build configuration purely from environment variables with the given prefix and serde defaults.
+    ///
+    /// The given CLI overrides are merged in last.
+    pub fn from_env(env_prefix: &str, cli: toml::Value) -> Result<Self, io::Error> {
+        let env_value = deep_merge(collect_env_config(env_prefix), cli);
+        Self::from_value(env_value, None).map_err(|err| env_error(err, env_prefix))
     }
 
     /// Shared deserialization, version check, and path assignment.
@@ -190,6 +291,38 @@ impl Config {
             },
         }
 
+        if config.server.start_command().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no valid server start command, set server.command or server.command_args",
+            ));
+        }
+
+        // Validate join methods
+        config.join.dedup_methods();
+        if config.join.methods.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no join methods configured, join.methods can't be empty",
+            ));
+        }
+        SleepMethod::validate(&config, FREEZE_SUPPORTED)?;
+
+        if config.advanced.proxy_buffer_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "advanced.proxy_buffer_size can't be 0",
+            ));
+        }
+
+        let unreachable = config.join.unreachable_methods();
+        if !unreachable.is_empty() {
+            warn!(target: "lazymc::config",
+                "Join methods {:?} are never used, the method before them always handles the client",
+                unreachable,
+            );
+        }
+
         if let Some(p) = path {
             config.path.replace(p);
         }
@@ -199,7 +332,7 @@ impl Config {
 }
 
 /// Public configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Public {
     /// Public address.
@@ -211,6 +344,18 @@ pub struct Public {
 
     /// Minecraft protocol version hint.
     pub protocol: u32,
+
+    /// Which version to report in status while server is not online.
+    pub status_version: StatusVersion,
+
+    /// Protocol version reported in status while server is not online, overrides hint.
+    pub sleeping_protocol: Option<u32>,
+
+    /// Version name reported in status while server is not online, overrides hint.
+    pub sleeping_version_name: Option<String>,
+
+    /// Synthetic latency in milliseconds added to ping responses while server is not online.
+    pub ping_delay_ms: u32,
 }
 
 impl Default for Public {
@@ -219,12 +364,30 @@ impl Default for Public {
             address: "0.0.0.0:25565".parse().unwrap(),
             version: proto::PROTO_DEFAULT_VERSION.to_string(),
             protocol: proto::PROTO_DEFAULT_PROTOCOL,
+            status_version: StatusVersion::LastSeen,
+            sleeping_protocol: None,
+            sleeping_version_name: None,
+            ping_delay_ms: 0,
         }
     }
 }
 
+/// Version reported in status while server is not online.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatusVersion {
+    /// Last version seen from the server, or the configured hint if never seen.
+    LastSeen,
+
+    /// Configured version hint.
+    Configured,
+
+    /// Protocol version the client sent in its handshake.
+    Mirror,
+}
+
 /// Server configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 pub struct Server {
     /// Server directory.
     ///
@@ -233,19 +396,47 @@ pub struct Server {
     directory: Option<PathBuf>,
 
     /// Start command.
+    ///
+    /// Split into arguments shell-style, but not run through a shell.
+    #[serde(default)]
     pub command: String,
 
-    /// Server address.
-    #[serde(
-        deserialize_with = "to_socket_addrs",
-        default = "server_address_default"
-    )]
-    pub address: SocketAddr,
+    /// Start command as explicit argument list, overrides `command`.
+    ///
+    /// Executed directly without any splitting or quoting.
+    #[serde(default)]
+    pub command_args: Vec<String>,
+
+    /// Working directory for start command, overrides server directory.
+    ///
+    /// Private because you should use `Server::working_directory()` instead.
+    #[serde(default)]
+    working_dir: Option<PathBuf>,
+
+    /// Extra environment variables for start command.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Server address, or Unix socket path prefixed with `unix:`.
+    #[serde(deserialize_with = "to_endpoint", default = "server_address_default")]
+    pub address: Endpoint,
+
+    /// How to put the server to sleep when no players online.
+    ///
+    /// Private because you should use `SleepMethod::of()` instead.
+    #[serde(default)]
+    sleep_method: Option<SleepMethod>,
 
     /// Freeze the server process instead of restarting it when no players online, making it start up faster.
     /// Only works on Unix (Linux or MacOS)
-    #[serde(default = "bool_true")]
-    pub freeze_process: bool,
+    ///
+    /// Deprecated, use `sleep_method` instead. Ignored if that is set.
+    #[serde(default)]
+    freeze_process: Option<bool>,
+
+    /// What to do if freezing is requested, but not supported on this platform.
+    #[serde(default)]
+    pub freeze_unsupported: FreezeUnsupported,
 
     /// Immediately wake server when starting lazymc.
     #[serde(default)]
@@ -255,26 +446,67 @@ pub struct Server {
     #[serde(default)]
     pub wake_on_crash: bool,
 
+    /// Wake server when starting lazymc if it was online and active within this many seconds before
+    /// lazymc quit. Disabled if 0.
+    #[serde(default)]
+    pub resume_window: u32,
+
     /// Probe required server details when starting lazymc, wakes server on start.
     #[serde(default)]
     pub probe_on_start: bool,
 
+    /// Put server back to sleep right after probing, if no player joined in the meantime.
+    #[serde(default)]
+    pub sleep_after_probe: bool,
+
     /// Whether this server runs forge.
+    ///
+    /// Detected from the server status if not set.
     #[serde(default)]
-    pub forge: bool,
+    pub forge: Option<bool>,
 
     /// Server starting timeout. Force kill server process if it takes longer.
     #[serde(default = "u32_300")]
     pub start_timeout: u32,
 
     /// Server stopping timeout. Force kill server process if it takes longer.
-    #[serde(default = "u32_150")]
+    #[serde(default = "u32_300")]
     pub stop_timeout: u32,
 
+    /// Action when the server takes longer to start than the start timeout.
+    #[serde(default)]
+    pub start_timeout_action: StartTimeoutAction,
+
+    /// Maximum number of times to retry starting the server after a start timeout, for the retry
+    /// action.
+    #[serde(default = "u32_3")]
+    pub start_timeout_retries: u32,
+
+    /// Force kill server process this many seconds after sending it a stop signal.
+    ///
+    /// Independent of stop timeout, disabled if 0.
+    #[serde(default)]
+    pub graceful_kill_timeout: u32,
+
+    /// How to detect the server is ready after starting.
+    ///
+    /// Private because you should use `ReadyCheck::of()` instead.
+    #[serde(default)]
+    ready_check: ReadyCheck,
+
     /// To wake server, user must be in server whitelist if enabled on server.
     #[serde(default = "bool_true")]
     pub wake_whitelist: bool,
 
+    /// Don't wake server if it accepts no players, because max-players is 0 in server.properties.
+    #[serde(default = "bool_true")]
+    pub wake_max_players: bool,
+
+    /// Whether server is in online mode, detected from server.properties if not set.
+    #[serde(default)]
+    #[cfg_attr(not(feature = "lobby"), allow(dead_code))]
+    pub online_mode: Option<bool>,
+
     /// Block banned IPs as listed in banned-ips.json in server directory.
     #[serde(default = "bool_true")]
     pub block_banned_ips: bool,
@@ -283,6 +515,16 @@ pub struct Server {
     #[serde(default)]
     pub drop_banned_ips: bool,
 
+    /// Kick players listed in banned-players.json in server directory before waking the server.
+    #[serde(default)]
+    pub block_banned_players: bool,
+
+    /// Kick message for banned IPs, `{reason}` is replaced with the ban reason.
+    ///
+    /// Uses the Minecraft ban message if not set.
+    #[serde(default)]
+    pub banned_message: Option<String>,
+
     /// Add HAProxy v2 header to proxied connections.
     #[serde(default)]
     pub send_proxy_v2: bool,
@@ -299,18 +541,64 @@ impl Server {
             None => config.server.directory.clone(),
         }
     }
+
+    /// Get the working directory for the start command.
+    ///
+    /// Uses server directory if not configured. This does not check whether it exists.
+    pub fn working_directory(config: &Config) -> Option<PathBuf> {
+        let dir = match config.server.working_dir.as_ref() {
+            Some(dir) => dir,
+            None => return Self::server_directory(config),
+        };
+
+        // Get directory, relative to config directory if known
+        match config.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => Some(config_dir.join(dir)),
+            None => Some(dir.clone()),
+        }
+    }
+
+    /// Get the start command arguments, including the program.
+    ///
+    /// Returns `None` if the command is empty or can't be split.
+    pub fn start_command(&self) -> Option<Vec<String>> {
+        let args = if self.command_args.is_empty() {
+            shlex::split(&self.command)?
+        } else {
+            self.command_args.clone()
+        };
+        args.first()
+            .map_or(false, |program| !program.is_empty())
+            .then_some(args)
+    }
 }
 
 /// Time configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Time {
     /// Sleep after number of seconds.
     pub sleep_after: u32,
 
     /// Minimum time in seconds to stay online when server is started.
-    #[serde(default, alias = "minimum_online_time")]
+    #[serde(alias = "minimum_online_time")]
     pub min_online_time: u32,
+
+    /// What counts as idle.
+    pub idle_when: IdleWhen,
+
+    /// Minimum number of online players to keep the server awake.
+    pub keep_awake_min_players: u32,
+
+    /// Seconds to wait before confirming the server is still idle, right before sleeping.
+    pub sleep_confirm_delay: u32,
+}
+
+impl Time {
+    /// Whether the given number of online players keeps the server awake.
+    pub fn keeps_awake(&self, online: u32) -> bool {
+        online > 0 && online >= self.keep_awake_min_players
+    }
 }
 
 impl Default for Time {
@@ -318,12 +606,41 @@ impl Default for Time {
         Self {
             sleep_after: 60,
             min_online_time: 60,
+            idle_when: IdleWhen::NoPlayers,
+            keep_awake_min_players: 1,
+            sleep_confirm_delay: 2,
         }
     }
 }
 
+/// Schedule configuration.
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct Schedule {
+    /// Times to wake the server at, regardless of players, so it is ready for peak hours.
+    pub prewarm: Vec<TimeOfDay>,
+}
+
+impl Schedule {
+    /// Check whether the server should be prewarmed at the given local time.
+    pub fn prewarms_at(&self, at: &NaiveDateTime) -> bool {
+        self.prewarm.iter().any(|time| time.matches(at))
+    }
+}
+
+/// When the server is considered idle.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum IdleWhen {
+    /// No players are online.
+    NoPlayers,
+
+    /// No clients are connected through lazymc, even if not logged in.
+    NoConnections,
+}
+
 /// MOTD configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Motd {
     /// MOTD when server is sleeping.
@@ -335,8 +652,17 @@ pub struct Motd {
     /// MOTD when server is stopping.
     pub stopping: String,
 
+    /// MOTD when server is frozen.
+    pub frozen: String,
+
     /// Use MOTD from Minecraft server once known.
     pub from_server: bool,
+
+    /// Lines shown as player sample while server is not online.
+    pub sample: Vec<String>,
+
+    /// Show last known online players as player sample instead, if any.
+    pub sample_from_server: bool,
 }
 
 impl Default for Motd {
@@ -345,13 +671,16 @@ impl Default for Motd {
             sleeping: "☠ Server is sleeping\n§2☻ Join to start it up".into(),
             starting: "§2☻ Server is starting...\n§7⌛ Please wait...".into(),
             stopping: "☠ Server going to sleep...\n⌛ Please wait...".into(),
+            frozen: "☠ Server is frozen\n§2☻ Join to resume it instantly".into(),
             from_server: false,
+            sample: vec![],
+            sample_from_server: false,
         }
     }
 }
 
 /// Join method types.
-#[derive(Debug, Deserialize, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum Method {
     /// Kick client with message.
@@ -365,15 +694,44 @@ pub enum Method {
 
     /// Keep client in temporary fake lobby until server is ready.
     Lobby,
+
+    /// Keep client in fake lobby in order until there's room on the server.
+    Queue,
+}
+
+impl Method {
+    /// Whether this method always consumes the client, so no method after it is ever used.
+    pub fn is_final(self) -> bool {
+        matches!(self, Method::Kick | Method::Forward)
+    }
+
+    /// Whether this method puts the client in the lobby world.
+    pub fn uses_lobby(self) -> bool {
+        matches!(self, Method::Lobby | Method::Queue)
+    }
+}
+
+/// Action for joining clients when the started server is full.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FullAction {
+    /// Proxy client to the server, relaying its own disconnect reason.
+    Passthrough,
+
+    /// Occupy client with the join methods, as if the server is starting.
+    Queue,
 }
 
 /// Join configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Join {
     /// Join methods.
     pub methods: Vec<Method>,
 
+    /// What to do with joining clients when the started server is full.
+    pub full_action: FullAction,
+
     /// Join kick configuration.
     #[serde(default)]
     pub kick: JoinKick,
@@ -389,22 +747,48 @@ pub struct Join {
     /// Join lobby configuration.
     #[serde(default)]
     pub lobby: JoinLobby,
+
+    /// Join queue configuration.
+    #[serde(default)]
+    pub queue: JoinQueue,
+}
+
+impl Join {
+    /// Remove duplicate join methods, keeping the first occurrence.
+    fn dedup_methods(&mut self) {
+        let mut seen = Vec::with_capacity(self.methods.len());
+        self.methods.retain(|method| {
+            let new = !seen.contains(method);
+            seen.push(*method);
+            new
+        });
+    }
+
+    /// Join methods that are never used because a final method comes before them.
+    fn unreachable_methods(&self) -> &[Method] {
+        match self.methods.iter().position(|method| method.is_final()) {
+            Some(i) => &self.methods[i + 1..],
+            None => &[],
+        }
+    }
 }
 
 impl Default for Join {
     fn default() -> Self {
         Self {
             methods: vec![Method::Hold, Method::Kick],
+            full_action: FullAction::Passthrough,
             kick: Default::default(),
             hold: Default::default(),
             forward: Default::default(),
             lobby: Default::default(),
+            queue: Default::default(),
         }
     }
 }
 
 /// Join kick configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct JoinKick {
     /// Kick message when server is starting.
@@ -412,6 +796,18 @@ pub struct JoinKick {
 
     /// Kick message when server is stopping.
     pub stopping: String,
+
+    /// Kick message when lazymc is shutting down.
+    pub draining: String,
+
+    /// Kick message when client is not whitelisted to wake the server.
+    pub not_whitelisted: String,
+
+    /// Kick message when the server accepts no players.
+    pub full: String,
+
+    /// Kick message when the server is held after failing to start.
+    pub held: String,
 }
 
 impl Default for JoinKick {
@@ -419,31 +815,45 @@ impl Default for JoinKick {
         Self {
             starting: "Server is starting... §c♥§r\n\nThis may take some time.\n\nPlease try to reconnect in a minute.".into(),
             stopping: "Server is going to sleep... §7☠§r\n\nPlease try to reconnect in a minute to wake it again.".into(),
+            draining: "Server is restarting... §7⌛§r\n\nPlease try to reconnect in a minute.".into(),
+            not_whitelisted: "You are not white-listed on this server!".into(),
+            full: "The server is full!".into(),
+            held: "Server failed to start, it must be woken manually by an operator.".into(),
         }
     }
 }
 
 /// Join hold configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct JoinHold {
     /// Hold client for number of seconds on connect while server starts.
     pub timeout: u32,
+
+    /// Keep held client alive, to hold it beyond the Minecraft client timeout.
+    pub keepalive: bool,
+
+    /// Hold the client that wakes the server, even if the hold method is not configured.
+    pub first_joiner: bool,
 }
 
 impl Default for JoinHold {
     fn default() -> Self {
-        Self { timeout: 25 }
+        Self {
+            timeout: 25,
+            keepalive: false,
+            first_joiner: false,
+        }
     }
 }
 
 /// Join forward configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct JoinForward {
     /// IP and port to forward to.
-    #[serde(deserialize_with = "to_socket_addrs")]
-    pub address: SocketAddr,
+    #[serde(deserialize_with = "to_endpoint")]
+    pub address: Endpoint,
 
     /// Add HAProxy v2 header to proxied connections.
     #[serde(default)]
@@ -453,13 +863,13 @@ pub struct JoinForward {
 impl Default for JoinForward {
     fn default() -> Self {
         Self {
-            address: "127.0.0.1:25565".parse().unwrap(),
+            address: Endpoint::Tcp("127.0.0.1:25565".parse().unwrap()),
             send_proxy_v2: false,
         }
     }
 }
 /// Join lobby configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct JoinLobby {
     /// Hold client in lobby for number of seconds on connect while server starts.
@@ -470,6 +880,12 @@ pub struct JoinLobby {
 
     /// Sound effect to play when server is ready.
     pub ready_sound: Option<String>,
+
+    /// Keep teleporting client to its spawn position, so it doesn't fall or float away.
+    pub keep_alive_position: bool,
+
+    /// Transfer client to the server once ready, for Minecraft 1.20.5 and newer.
+    pub transfer_on_ready: bool,
 }
 
 impl Default for JoinLobby {
@@ -478,12 +894,34 @@ impl Default for JoinLobby {
             timeout: 10 * 60,
             message: "§2Server is starting\n§7⌛ Please wait...".into(),
             ready_sound: Some("block.note_block.chime".into()),
+            keep_alive_position: true,
+            transfer_on_ready: false,
+        }
+    }
+}
+
+/// Join queue configuration.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct JoinQueue {
+    /// Maximum number of queued clients, unlimited if 0.
+    pub max_slots: u32,
+
+    /// Message banner in lobby shown to queued client.
+    pub position_message: String,
+}
+
+impl Default for JoinQueue {
+    fn default() -> Self {
+        Self {
+            max_slots: 0,
+            position_message: "§2You are in the queue\n§7Position {position} of {size}".into(),
         }
     }
 }
 
 /// Lockout configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Lockout {
     /// Enable to prevent everybody from connecting through lazymc. Instantly kicks player.
@@ -491,6 +929,29 @@ pub struct Lockout {
 
     /// Kick players with following message.
     pub message: String,
+
+    /// Only lock out during these time windows, always if empty.
+    pub schedule: Vec<TimeWindow>,
+
+    /// IPs or networks that can still connect during lockout.
+    pub exempt: Vec<Cidr>,
+}
+
+impl Lockout {
+    /// Check whether the given IP is locked out right now.
+    pub fn is_locked_out(&self, ip: &IpAddr) -> bool {
+        self.is_locked_out_at(ip, &Local::now().naive_local())
+    }
+
+    /// Check whether the given IP is locked out at the given local time.
+    pub fn is_locked_out_at(&self, ip: &IpAddr, at: &NaiveDateTime) -> bool {
+        self.is_active_at(at) && !self.exempt.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Check whether lockout is active at the given local time.
+    pub fn is_active_at(&self, at: &NaiveDateTime) -> bool {
+        self.enabled && (self.schedule.is_empty() || self.schedule.iter().any(|w| w.contains(at)))
+    }
 }
 
 impl Default for Lockout {
@@ -498,12 +959,92 @@ impl Default for Lockout {
         Self {
             enabled: false,
             message: "Server is closed §7☠§r\n\nPlease come back another time.".into(),
+            schedule: vec![],
+            exempt: vec![],
+        }
+    }
+}
+
+/// Maintenance mode configuration.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Maintenance {
+    /// Enable to keep the server asleep, show maintenance status and kick joining players.
+    pub enabled: bool,
+
+    /// MOTD shown during maintenance.
+    pub motd: String,
+
+    /// Version name shown in place of the player count during maintenance, regular if empty.
+    pub version: String,
+
+    /// Kick players with following message.
+    pub message: String,
+
+    /// IPs or networks that can still connect and wake the server during maintenance.
+    pub exempt: Vec<Cidr>,
+}
+
+impl Maintenance {
+    /// Check whether maintenance applies to the given IP.
+    pub fn applies_to(&self, ip: &IpAddr) -> bool {
+        self.enabled && !self.exempt.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+impl Default for Maintenance {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            motd: "§6Under maintenance §7⚒§r\nPlease come back later.".into(),
+            version: "Maintenance".into(),
+            message: "Server is under maintenance §7⚒§r\n\nPlease come back later.".into(),
+            exempt: vec![],
+        }
+    }
+}
+
+/// Access control configuration.
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct Access {
+    /// Only allow these IPs or networks to connect, if any.
+    pub allow: Vec<Cidr>,
+
+    /// Refuse these IPs or networks. Takes precedence over `allow`.
+    pub deny: Vec<Cidr>,
+}
+
+impl Access {
+    /// Check whether the given IP is allowed to connect.
+    pub fn is_allowed(&self, ip: &IpAddr) -> bool {
+        if self.deny.iter().any(|cidr| cidr.contains(ip)) {
+            return false;
         }
+        self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(ip))
+    }
+}
+
+/// Out-of-band wake configuration.
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct Wake {
+    /// UDP port to listen on for wake packets, disabled if not set.
+    pub udp_port: Option<u16>,
+
+    /// Shared secret a wake packet must contain.
+    pub secret: Option<String>,
+}
+
+impl Wake {
+    /// Whether wake packets are enabled, a port and secret must be configured.
+    pub fn is_enabled(&self) -> bool {
+        self.udp_port.is_some() && !self.secret.as_deref().unwrap_or("").is_empty()
     }
 }
 
 /// RCON configuration.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, Serialize)]
 #[serde(default)]
 pub struct Rcon {
     /// Enable sleeping server through RCON.
@@ -515,11 +1056,23 @@ pub struct Rcon {
     /// Server RCON password.
     pub password: String,
 
-    /// Randomize server RCON password on each start.
-    pub randomize_password: bool,
+    /// Randomize server RCON password on each start, or once.
+    pub randomize_password: RandomizePassword,
+
+    /// File to write randomized RCON password to, for other local tools to read.
+    pub password_file: Option<PathBuf>,
 
     /// Add HAProxy v2 header to RCON connections.
     pub send_proxy_v2: bool,
+
+    /// Timeout in seconds for connecting and authenticating.
+    pub connect_timeout: u32,
+
+    /// Timeout in seconds for a command response.
+    pub command_timeout: u32,
+
+    /// Time in seconds to wait for server to stop after RCON stop, before sending a stop signal.
+    pub stop_grace_period: u32,
 }
 
 impl Default for Rcon {
@@ -528,78 +1081,598 @@ impl Default for Rcon {
             enabled: cfg!(windows),
             port: 25575,
             password: "".into(),
-            randomize_password: true,
+            randomize_password: RandomizePassword::Always,
+            password_file: None,
             send_proxy_v2: false,
+            connect_timeout: 5,
+            command_timeout: 10,
+            stop_grace_period: 30,
         }
     }
 }
 
-/// Advanced configuration.
-#[derive(Debug, Deserialize)]
-#[serde(default)]
-pub struct Advanced {
-    /// Rewrite server.properties.
-    pub rewrite_server_properties: bool,
-}
+#[cfg(feature = "rcon")]
+impl Rcon {
+    /// Get the file to write the randomized RCON password to.
+    ///
+    /// Uses file in server directory if not configured.
+    pub fn password_file(config: &Config) -> Option<PathBuf> {
+        let file = match config.rcon.password_file.as_ref() {
+            Some(file) => file,
+            None => {
+                return Server::server_directory(config)
+                    .map(|dir| dir.join(persist::RCON_PASSWORD_FILE));
+            }
+        };
 
-impl Default for Advanced {
-    fn default() -> Self {
-        Self {
-            rewrite_server_properties: true,
+        // Get file, relative to config directory if known
+        match config.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => Some(config_dir.join(file)),
+            None => Some(file.clone()),
         }
     }
 }
 
-/// Config configuration.
-#[derive(Debug, Deserialize, Default)]
-#[serde(default)]
-pub struct ConfigConfig {
-    /// Configuration for lazymc version.
-    pub version: Option<String>,
-}
+/// RCON password randomization.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum RandomizePassword {
+    /// Use configured password.
+    Never,
 
-fn option_pathbuf_dot() -> Option<PathBuf> {
-    Some(".".into())
-}
+    /// Generate new password on each start.
+    #[default]
+    Always,
 
-fn server_address_default() -> SocketAddr {
-    "127.0.0.1:25566".parse().unwrap()
+    /// Generate password once, and keep it across restarts.
+    Once,
 }
 
-fn u32_300() -> u32 {
-    300
+#[cfg(feature = "rcon")]
+impl RandomizePassword {
+    /// Whether the password is randomized.
+    pub fn is_enabled(self) -> bool {
+        self != Self::Never
+    }
 }
 
-fn u32_150() -> u32 {
-    300
-}
+impl<'de> Deserialize<'de> for RandomizePassword {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Bool(bool),
+            Str(String),
+        }
 
-fn bool_true() -> bool {
-    true
+        match Raw::deserialize(d)? {
+            Raw::Bool(true) => Ok(Self::Always),
+            Raw::Bool(false) => Ok(Self::Never),
+            Raw::Str(mode) if mode == "once" => Ok(Self::Once),
+            Raw::Str(mode) => Err(de::Error::invalid_value(
+                Unexpected::Str(&mode),
+                &"true, false or \"once\"",
+            )),
+        }
+    }
 }
 
-/// Collect all `LAZYMC_` environment variables into a nested TOML table.
-///
-/// Variable names are split on `__` (double underscore) to form nested keys.
-/// For example, `LAZYMC_SERVER__ADDRESS` becomes `server.address`.
-fn collect_env_config() -> toml::Value {
-    let mut root = Map::new();
-
-    for (key, value) in env::vars() {
-        if let Some(suffix) = key.strip_prefix(ENV_PREFIX) {
-            if suffix.is_empty() {
-                continue;
-            }
-            let parts: Vec<String> = suffix.split(ENV_SEPARATOR).map(|s| s.to_lowercase()).collect();
-            let toml_val = infer_toml_value(&value);
-            insert_nested(&mut root, &parts, toml_val);
+impl Serialize for RandomizePassword {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Never => s.serialize_bool(false),
+            Self::Always => s.serialize_bool(true),
+            Self::Once => s.serialize_str("once"),
         }
     }
-
-    toml::Value::Table(root)
 }
 
-/// Recursively insert a value into nested TOML tables given a list of key parts.
+/// Advanced configuration.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Advanced {
+    /// Rewrite server.properties.
+    pub rewrite_server_properties: bool,
+
+    /// TCP listen backlog for the public address.
+    pub tcp_backlog: u32,
+
+    /// Set TCP_NODELAY on proxied connections.
+    pub tcp_nodelay: bool,
+
+    /// Enable TCP keepalive on proxied connections.
+    pub tcp_keepalive: bool,
+
+    /// Size in bytes of the buffer used to relay proxied data in each direction.
+    pub proxy_buffer_size: u32,
+
+    /// Local IP to bind outgoing connections to the server to, uses the default route if not set.
+    pub outbound_bind: Option<IpAddr>,
+
+    /// SOCKS5 proxy to tunnel outgoing connections to the server through, direct if not set.
+    pub socks5_proxy: Option<SocketAddr>,
+
+    /// Only count online players as activity if they move, observed through RCON.
+    pub activity_detection: bool,
+
+    /// Time in seconds resolved server and forward hostnames are cached for.
+    pub dns_ttl: u32,
+
+    /// Time in seconds a client has to send its handshake before it is dropped, disabled if 0.
+    pub handshake_timeout: u32,
+
+    /// Maximum number of client connections handled at the same time, unlimited if 0.
+    pub max_connections: u32,
+
+    /// Read PROXY protocol header from connections of trusted proxies, for the real client IP.
+    pub accept_proxy_protocol: bool,
+
+    /// Proxies trusted to send a PROXY protocol header.
+    pub trusted_proxies: Vec<Cidr>,
+
+    /// Stop the server when lazymc quits.
+    pub stop_server_on_exit: bool,
+
+    /// Time in seconds to wait for connections to complete when lazymc quits.
+    pub shutdown_timeout: u32,
+
+    /// Only consider server started once it responds to status, not just to ping.
+    pub require_status_for_ready: bool,
+
+    /// Wake server when a client requests its status, such as from the server list.
+    pub wake_on_status: bool,
+
+    /// Serve status from cache while server is online, refresh after this many seconds.
+    ///
+    /// Disabled if 0, status requests are then proxied to the server.
+    pub status_cache_ttl: u32,
+
+    /// Number of consecutive failed status polls before the server is considered offline.
+    pub status_fail_threshold: u32,
+
+    /// Maximum size in bytes of server status JSON, fields are dropped to fit, unlimited if 0.
+    pub status_max_size: u32,
+
+    /// Maximum length in characters of the server description, truncated if longer, unlimited if 0.
+    ///
+    /// Descriptions that are chat components are dropped instead, truncating would break them.
+    pub status_max_description: u32,
+
+    /// How to handle server console output.
+    pub server_output: ServerOutput,
+
+    /// Prefix for captured server console output lines.
+    pub server_output_prefix: String,
+
+    /// Mark server started once a captured output line matches, disabled if empty.
+    pub ready_regex: String,
+
+    /// Echo lines typed into lazymc's console, with the server state, as they are forwarded.
+    pub stdin_echo: bool,
+
+    /// Prefix for echoed console lines.
+    pub stdin_echo_prefix: String,
+
+    /// Persist server statistics to a file in the server directory.
+    pub persist_stats: bool,
+
+    /// Action to take when the server process runs but is unreachable.
+    pub unreachable_action: UnreachableAction,
+
+    /// Number of consecutive unreachable polls before taking the unreachable action.
+    pub unreachable_threshold: u32,
+
+    /// Signal the process group of the server process, rather than only the process itself.
+    pub signal_process_group: bool,
+
+    /// Discover the Java child process of the server process, and signal it directly.
+    pub signal_java_child: bool,
+
+    /// lazymc lock file path, defaults to lock file in server directory.
+    lock_file: Option<PathBuf>,
+
+    /// Unix socket path to stream server events on, disabled if not set.
+    event_socket: Option<PathBuf>,
+
+    /// Log filter, such as `lazymc::monitor=debug,lazymc=info`, overrides `RUST_LOG` if set.
+    pub log_filter: Option<String>,
+
+    /// Address to respond to health checks on, disabled if not set.
+    pub health_address: Option<SocketAddr>,
+
+    /// Address to serve the HTTP ping endpoint on, disabled if not set.
+    pub http_address: Option<SocketAddr>,
+
+    /// File to cache the last server status in, disabled if not set.
+    status_cache_file: Option<PathBuf>,
+
+    /// Number of runtime worker threads, one per CPU core if 0.
+    pub worker_threads: u32,
+
+    /// Maximum number of threads for blocking work such as reading stdin, tokio default if 0.
+    pub blocking_threads: u32,
+}
+
+impl Advanced {
+    /// Whether player activity is detected through RCON, instead of online players being active.
+    pub fn detects_activity(config: &Config) -> bool {
+        cfg!(feature = "rcon") && config.advanced.activity_detection && config.rcon.enabled
+    }
+
+    /// Whether a PROXY protocol header must be read from connections of the given peer.
+    pub fn expects_proxy_header(&self, ip: &IpAddr) -> bool {
+        self.accept_proxy_protocol && self.trusted_proxies.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Get the lazymc lock file path.
+    ///
+    /// Uses lock file in server directory if not configured.
+    pub fn lock_file(config: &Config) -> Option<PathBuf> {
+        let file = match config.advanced.lock_file.as_ref() {
+            Some(file) => file,
+            None => {
+                return Server::server_directory(config).map(|dir| dir.join(persist::LOCK_FILE));
+            }
+        };
+
+        // Get file, relative to config directory if known
+        match config.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => Some(config_dir.join(file)),
+            None => Some(file.clone()),
+        }
+    }
+
+    /// Get the event socket path, if configured.
+    pub fn event_socket(config: &Config) -> Option<PathBuf> {
+        let file = config.advanced.event_socket.as_ref()?;
+
+        // Get file, relative to config directory if known
+        match config.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => Some(config_dir.join(file)),
+            None => Some(file.clone()),
+        }
+    }
+
+    /// Get the status cache file path, if configured.
+    pub fn status_cache_file(config: &Config) -> Option<PathBuf> {
+        let file = config.advanced.status_cache_file.as_ref()?;
+
+        // Get file, relative to config directory if known
+        match config.path.as_ref().and_then(|p| p.parent()) {
+            Some(config_dir) => Some(config_dir.join(file)),
+            None => Some(file.clone()),
+        }
+    }
+}
+
+impl Default for Advanced {
+    fn default() -> Self {
+        Self {
+            rewrite_server_properties: true,
+            tcp_backlog: 1024,
+            tcp_nodelay: false,
+            tcp_keepalive: false,
+            proxy_buffer_size: 8 * 1024,
+            outbound_bind: None,
+            socks5_proxy: None,
+            activity_detection: false,
+            dns_ttl: 30,
+            handshake_timeout: 30,
+            max_connections: 0,
+            accept_proxy_protocol: false,
+            trusted_proxies: Vec::new(),
+            stop_server_on_exit: true,
+            shutdown_timeout: 10,
+            require_status_for_ready: false,
+            wake_on_status: false,
+            status_cache_ttl: 0,
+            status_fail_threshold: 1,
+            status_max_size: 65536,
+            status_max_description: 1024,
+            server_output: ServerOutput::Inherit,
+            server_output_prefix: "".into(),
+            ready_regex: r#"Done \([0-9.,]+s\)! For help, type "help""#.into(),
+            stdin_echo: false,
+            stdin_echo_prefix: "> ".into(),
+            persist_stats: false,
+            unreachable_action: UnreachableAction::None,
+            unreachable_threshold: 15,
+            signal_process_group: true,
+            signal_java_child: false,
+            lock_file: None,
+            event_socket: None,
+            log_filter: None,
+            health_address: None,
+            http_address: None,
+            status_cache_file: None,
+            worker_threads: 0,
+            blocking_threads: 0,
+        }
+    }
+}
+
+/// Server console output handling.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ServerOutput {
+    /// Server writes to lazymc's stdout and stderr directly.
+    Inherit,
+
+    /// Capture output and emit it through lazymc's logger.
+    Capture,
+}
+
+/// Action when the server process runs but is unreachable.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum UnreachableAction {
+    /// Do nothing.
+    None,
+
+    /// Kill and start the server again.
+    Restart,
+
+    /// Kill the server.
+    Kill,
+}
+
+/// Action when the server takes longer to start than the start timeout.
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum StartTimeoutAction {
+    /// Kill the server.
+    #[default]
+    Kill,
+
+    /// Kill and start the server again, up to the configured number of retries.
+    Retry,
+
+    /// Kill the server, and don't start it again until it is woken manually.
+    Hold,
+}
+
+/// How to detect a starting server is ready, from the earliest signal to accept.
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadyCheck {
+    /// Server responds with a full status.
+    Status,
+
+    /// Server responds to a ping, or with a full status.
+    #[default]
+    Ping,
+
+    /// Server port accepts a connection, or any of the above.
+    Port,
+
+    /// Captured server output matches the ready regex, only.
+    Stdout,
+}
+
+impl ReadyCheck {
+    /// Get the configured readiness check.
+    ///
+    /// Legacy `advanced.require_status_for_ready` implies the status check.
+    pub fn of(config: &Config) -> Self {
+        if config.advanced.require_status_for_ready {
+            return Self::Status;
+        }
+        config.server.ready_check
+    }
+
+    /// Whether the given readiness signal marks a starting server as ready with this check.
+    ///
+    /// A matching output line is always accepted, it is only configured for that purpose.
+    pub fn accepts(self, signal: ReadyCheck) -> bool {
+        match (self, signal) {
+            (_, Self::Stdout) => true,
+            (Self::Stdout, _) => false,
+            (Self::Status, signal) => signal == Self::Status,
+            (Self::Ping, signal) => signal != Self::Port,
+            (Self::Port, _) => true,
+        }
+    }
+}
+
+/// Whether freezing the server process is supported on this platform.
+const FREEZE_SUPPORTED: bool = cfg!(unix);
+
+/// How to put the server to sleep.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SleepMethod {
+    /// Stop the server process, start it again when woken.
+    Stop,
+
+    /// Freeze the server process, resume it when woken. Only works on Unix.
+    Freeze,
+}
+
+impl SleepMethod {
+    /// Get the sleep method to use.
+    ///
+    /// Defaults to freezing. Falls back to stopping on platforms that don't support freezing.
+    pub fn of(config: &Config) -> Self {
+        Self::resolve(config, FREEZE_SUPPORTED)
+    }
+
+    /// Get the sleep method to use, given whether freezing is supported.
+    fn resolve(config: &Config, freeze_supported: bool) -> Self {
+        match Self::requested(config).unwrap_or(Self::Freeze) {
+            Self::Freeze if !freeze_supported => Self::Stop,
+            method => method,
+        }
+    }
+
+    /// Get the sleep method explicitly set in the config, if any.
+    ///
+    /// Falls back to legacy `server.freeze_process` if not set.
+    fn requested(config: &Config) -> Option<Self> {
+        config.server.sleep_method.or_else(|| {
+            config
+                .server
+                .freeze_process
+                .map(|freeze| if freeze { Self::Freeze } else { Self::Stop })
+        })
+    }
+
+    /// Validate the requested sleep method is supported, given whether freezing is supported.
+    ///
+    /// Errors if freezing is requested but not supported, unless configured to fall back.
+    fn validate(config: &Config, freeze_supported: bool) -> Result<(), io::Error> {
+        if Self::requested(config) != Some(Self::Freeze) || freeze_supported {
+            return Ok(());
+        }
+
+        match config.server.freeze_unsupported {
+            FreezeUnsupported::Stop => {
+                warn!(target: "lazymc::config", "Freezing the server process is not supported on this platform, stopping it instead");
+                Ok(())
+            }
+            FreezeUnsupported::Error => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "freezing the server process is not supported on this platform, set server.sleep_method = \"stop\"",
+            )),
+        }
+    }
+
+    /// Method name, as used in the config.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Stop => "stop",
+            Self::Freeze => "freeze",
+        }
+    }
+}
+
+/// What to do if freezing is requested, but not supported on this platform.
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FreezeUnsupported {
+    /// Stop the server instead, with a warning.
+    #[default]
+    Stop,
+
+    /// Error when loading the config.
+    Error,
+}
+
+/// Config configuration.
+#[derive(Debug, Deserialize, Serialize, Default)]
+#[serde(default)]
+pub struct ConfigConfig {
+    /// Configuration for lazymc version.
+    pub version: Option<String>,
+}
+
+fn option_pathbuf_dot() -> Option<PathBuf> {
+    Some(".".into())
+}
+
+fn server_address_default() -> Endpoint {
+    Endpoint::Tcp("127.0.0.1:25566".parse().unwrap())
+}
+
+fn u32_300() -> u32 {
+    300
+}
+
+fn u32_3() -> u32 {
+    3
+}
+
+fn bool_true() -> bool {
+    true
+}
+
+/// Collect all environment variables with the given prefix, such as `LAZYMC_`, into a nested TOML
+/// table.
+///
+/// Variable names are split on `__` (double underscore) to form nested keys.
+/// For example, `LAZYMC_SERVER__ADDRESS` becomes `server.address`.
+fn collect_env_config(prefix: &str) -> toml::Value {
+    let mut root = Map::new();
+
+    for var in env_vars(prefix) {
+        insert_nested(&mut root, &var.keys, var.value);
+    }
+
+    toml::Value::Table(root)
+}
+
+/// Environment variable holding a config value.
+struct EnvVar {
+    /// Variable name.
+    name: String,
+
+    /// Config keys the variable maps to.
+    keys: Vec<String>,
+
+    /// Inferred config value.
+    value: toml::Value,
+}
+
+/// Get all environment variables with the given prefix, with their inferred config values.
+fn env_vars(prefix: &str) -> Vec<EnvVar> {
+    env::vars()
+        .filter_map(|(name, value)| {
+            let suffix = name.strip_prefix(prefix)?;
+            if suffix.is_empty() {
+                return None;
+            }
+            let keys = suffix.split(ENV_SEPARATOR).map(|s| s.to_lowercase()).collect();
+            let value = infer_toml_value(&value);
+            Some(EnvVar { name, keys, value })
+        })
+        .collect()
+}
+
+/// Add the environment variable that caused a config deserialization error to its message.
+///
+/// The error names the failing config key, such as ``in `time.sleep_after` ``. Errors for keys
+/// not set through environment variables are returned as is.
+fn env_error(err: io::Error, prefix: &str) -> io::Error {
+    let message = err.to_string();
+    let key = match message
+        .lines()
+        .last()
+        .and_then(|line| line.strip_prefix("in `"))
+        .and_then(|line| line.strip_suffix('`'))
+    {
+        Some(key) => key,
+        None => return err,
+    };
+
+    // Find variable for this key, or the closest parent key
+    let var = env_vars(prefix)
+        .into_iter()
+        .filter(|var| {
+            let var_key = var.keys.join(".");
+            key == var_key
+                || key.starts_with(&format!("{var_key}."))
+                || key.starts_with(&format!("{var_key}["))
+        })
+        .max_by_key(|var| var.keys.len());
+
+    match var {
+        Some(var) => io::Error::new(
+            err.kind(),
+            format!(
+                "{message}\nfrom environment variable {} (inferred as {})",
+                var.name,
+                var.value.type_str(),
+            ),
+        ),
+        None => err,
+    }
+}
+
+/// Recursively insert a value into nested TOML tables given a list of key parts.
 fn insert_nested(table: &mut Map<String, toml::Value>, keys: &[String], value: toml::Value) {
     match keys.len() {
         0 => {}
@@ -619,6 +1692,7 @@ fn insert_nested(table: &mut Map<String, toml::Value>, keys: &[String], value: t
 
 /// Infer the TOML type from a string value.
 ///
+/// - Starts with `{` or `[` and parses as JSON → Table or Array, nested as in the JSON
 /// - Wrapped in `[`…`]` → Array (split on commas, infer each element)
 /// - `"true"`/`"false"` → Boolean
 /// - Parseable as `i64` → Integer
@@ -630,6 +1704,11 @@ fn infer_toml_value(s: &str) -> toml::Value {
     // Allows explicit single-element arrays like [kick] that would otherwise
     // be inferred as a plain string.
     let trimmed = s.trim();
+    if trimmed.starts_with('{') || trimmed.starts_with('[') {
+        if let Some(value) = serde_json::from_str(trimmed).ok().and_then(json_to_toml) {
+            return value;
+        }
+    }
     if trimmed.starts_with('[') && trimmed.ends_with(']') {
         let inner = &trimmed[1..trimmed.len() - 1];
         let items: Vec<toml::Value> = inner
@@ -674,6 +1753,31 @@ fn infer_toml_value(s: &str) -> toml::Value {
     toml::Value::String(unescape_basic(s))
 }
 
+/// Convert a JSON value into the equivalent TOML value.
+///
+/// TOML has no null, so `null` values and object entries are dropped. Returns `None` for a top
+/// level `null`.
+fn json_to_toml(value: serde_json::Value) -> Option<toml::Value> {
+    Some(match value {
+        serde_json::Value::Null => return None,
+        serde_json::Value::Bool(b) => toml::Value::Boolean(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64()?),
+        },
+        serde_json::Value::String(s) => toml::Value::String(s),
+        serde_json::Value::Array(items) => {
+            toml::Value::Array(items.into_iter().filter_map(json_to_toml).collect())
+        }
+        serde_json::Value::Object(entries) => toml::Value::Table(
+            entries
+                .into_iter()
+                .filter_map(|(k, v)| Some((k, json_to_toml(v)?)))
+                .collect(),
+        ),
+    })
+}
+
 /// Unescape common backslash escape sequences in a string (`\n`, `\t`, `\\`, `\r`).
 fn unescape_basic(s: &str) -> String {
     let mut out = String::with_capacity(s.len());
@@ -873,7 +1977,7 @@ mod tests {
         env::set_var("LAZYMC_SERVER__ADDRESS", "127.0.0.1:25577");
         env::set_var("LAZYMC_RCON__ENABLED", "true");
 
-        let value = collect_env_config();
+        let value = collect_env_config(ENV_PREFIX);
         let table = value.as_table().unwrap();
 
         let server = table["server"].as_table().unwrap();
@@ -893,6 +1997,95 @@ mod tests {
         env::remove_var("LAZYMC_RCON__ENABLED");
     }
 
+    #[test]
+    fn test_collect_env_config_custom_prefix() {
+        env::set_var("LAZYMC_PREFIXTEST__VALUE", "default");
+        env::set_var("PREFIXTEST_SERVER__COMMAND", "java -jar other.jar");
+
+        let value = collect_env_config("PREFIXTEST_");
+        let table = value.as_table().unwrap();
+
+        // Reads variables with custom prefix, ignores default-prefixed ones
+        assert_eq!(table.len(), 1);
+        assert_eq!(
+            table["server"]["command"].as_str().unwrap(),
+            "java -jar other.jar"
+        );
+        assert!(has_env_config("PREFIXTEST_"));
+
+        // Default prefix reads its own namespace
+        let value = collect_env_config(ENV_PREFIX);
+        assert_eq!(value["prefixtest"]["value"].as_str().unwrap(), "default");
+
+        // Clean up
+        env::remove_var("LAZYMC_PREFIXTEST__VALUE");
+        env::remove_var("PREFIXTEST_SERVER__COMMAND");
+    }
+
+    #[test]
+    fn test_env_error_names_variable() {
+        env::set_var("ENVERRORTEST_SERVER__COMMAND", "java -jar server.jar");
+        env::set_var("ENVERRORTEST_TIME__SLEEP_AFTER", "sixty");
+
+        let err = Config::from_env("ENVERRORTEST_", toml::Value::Table(Map::new()))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("ENVERRORTEST_TIME__SLEEP_AFTER"), "{}", err);
+        assert!(err.contains("inferred as string"), "{}", err);
+
+        // Clean up
+        env::remove_var("ENVERRORTEST_SERVER__COMMAND");
+        env::remove_var("ENVERRORTEST_TIME__SLEEP_AFTER");
+    }
+
+    /// Load config with the given CLI arguments applied on top.
+    fn load_with_cli(args: &[&str]) -> Config {
+        let matches = crate::cli::app()
+            .try_get_matches_from(["lazymc"].iter().chain(args))
+            .unwrap();
+        let base: toml::Value = toml::from_str(
+            "[server]\ncommand = \"true\"\naddress = \"127.0.0.1:25566\"\n[time]\nsleep_after = 60\n[rcon]\nenabled = true",
+        )
+        .unwrap();
+        Config::from_value(deep_merge(base, collect_cli_config(&matches)), None).unwrap()
+    }
+
+    #[test]
+    fn test_cli_overrides() {
+        let config = load_with_cli(&[]);
+        assert_eq!(config.server.command, "true");
+        assert_eq!(config.time.sleep_after, 60);
+        assert!(config.rcon.enabled);
+
+        let config = load_with_cli(&["--server-command", "java -jar server.jar"]);
+        assert_eq!(config.server.command, "java -jar server.jar");
+
+        let config = load_with_cli(&["--server-address", "127.0.0.1:25570"]);
+        assert_eq!(config.server.address.to_string(), "127.0.0.1:25570");
+
+        let config = load_with_cli(&["--sleep-after", "120"]);
+        assert_eq!(config.time.sleep_after, 120);
+
+        let config = load_with_cli(&["--no-rcon"]);
+        assert!(!config.rcon.enabled);
+    }
+
+    #[test]
+    fn test_cli_set_override() {
+        let config = load_with_cli(&[
+            "--set",
+            "join.kick.starting=Starting up",
+            "--set",
+            "time.sleep_after=30",
+        ]);
+        assert_eq!(config.join.kick.starting, "Starting up");
+        assert_eq!(config.time.sleep_after, 30);
+
+        // Dedicated flags take precedence over generic overrides
+        let config = load_with_cli(&["--set", "time.sleep_after=30", "--sleep-after", "90"]);
+        assert_eq!(config.time.sleep_after, 90);
+    }
+
     #[test]
     fn test_infer_toml_value_bracket_single_element_array() {
         let val = infer_toml_value("[kick]");
@@ -920,6 +2113,40 @@ mod tests {
         assert_eq!(val, toml::Value::Array(vec![]));
     }
 
+    #[test]
+    fn test_infer_toml_value_json_object() {
+        let val = infer_toml_value(r#"{"enabled": true, "port": 25575, "motd": {"text": "hi"}}"#);
+        let expected: toml::Value = toml::from_str(
+            r#"
+            enabled = true
+            port = 25575
+            [motd]
+            text = "hi"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(val, expected);
+    }
+
+    #[test]
+    fn test_infer_toml_value_json_array_of_objects() {
+        let val = infer_toml_value(
+            r#"[{"name": "survival", "address": "127.0.0.1:25566"}, {"name": "creative", "weight": 1.5, "extra": null}]"#,
+        );
+        let expected: toml::Value = toml::from_str(
+            r#"
+            [[servers]]
+            name = "survival"
+            address = "127.0.0.1:25566"
+            [[servers]]
+            name = "creative"
+            weight = 1.5
+            "#,
+        )
+        .unwrap();
+        assert_eq!(val, expected["servers"]);
+    }
+
     #[test]
     fn test_deep_merge_scalar_into_array() {
         let base: toml::Value = toml::from_str(
@@ -943,4 +2170,267 @@ mod tests {
         assert_eq!(methods.len(), 1);
         assert_eq!(methods[0].as_str().unwrap(), "kick");
     }
+
+    #[test]
+    fn test_lockout_exempt() {
+        let lockout: Lockout = toml::from_str(
+            r#"
+            enabled = true
+            exempt = ["10.0.0.0/8"]
+            "#,
+        )
+        .unwrap();
+        assert!(!lockout.is_locked_out(&"10.1.2.3".parse().unwrap()));
+        assert!(lockout.is_locked_out(&"192.168.1.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_ready_check_accepts() {
+        use ReadyCheck::*;
+
+        let accepted = |check: ReadyCheck| {
+            [Status, Ping, Port, Stdout]
+                .into_iter()
+                .filter(|signal| check.accepts(*signal))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(accepted(Status), [Status, Stdout]);
+        assert_eq!(accepted(Ping), [Status, Ping, Stdout]);
+        assert_eq!(accepted(Port), [Status, Ping, Port, Stdout]);
+        assert_eq!(accepted(Stdout), [Stdout]);
+
+        // Legacy option implies status check
+        let config: Config = crate::util::test::config(
+            "ready_check = \"port\"\n[advanced]\nrequire_status_for_ready = true",
+        );
+        assert_eq!(ReadyCheck::of(&config), Status);
+    }
+
+    #[test]
+    fn test_sleep_method_of() {
+        let method = |server: &str| {
+            let config: Config = crate::util::test::config(server);
+            SleepMethod::resolve(&config, true)
+        };
+        assert_eq!(method(""), SleepMethod::Freeze);
+        assert_eq!(method("sleep_method = \"stop\""), SleepMethod::Stop);
+
+        // Legacy option is used if no method is set
+        assert_eq!(method("freeze_process = false"), SleepMethod::Stop);
+        assert_eq!(
+            method("freeze_process = false\nsleep_method = \"freeze\""),
+            SleepMethod::Freeze
+        );
+    }
+
+    #[test]
+    fn test_sleep_method_unsupported() {
+        let config = crate::util::test::config;
+
+        // Falls back to stopping
+        let fallback = config("sleep_method = \"freeze\"");
+        assert!(SleepMethod::validate(&fallback, false).is_ok());
+        assert_eq!(SleepMethod::resolve(&fallback, false), SleepMethod::Stop);
+        assert_eq!(SleepMethod::resolve(&config(""), false), SleepMethod::Stop);
+
+        // Errors if configured, only if freezing was requested
+        let error = config("freeze_process = true\nfreeze_unsupported = \"error\"");
+        assert!(SleepMethod::validate(&error, false).is_err());
+        assert!(SleepMethod::validate(&error, true).is_ok());
+        let error = config("freeze_unsupported = \"error\"");
+        assert!(SleepMethod::validate(&error, false).is_ok());
+    }
+
+    #[test]
+    fn test_maintenance_exempt() {
+        let maintenance: Maintenance =
+            toml::from_str("enabled = true\nexempt = [\"10.0.0.0/8\"]").unwrap();
+        assert!(!maintenance.applies_to(&"10.1.2.3".parse().unwrap()));
+        assert!(maintenance.applies_to(&"192.168.1.2".parse().unwrap()));
+
+        // Applies to nobody if disabled
+        assert!(!Maintenance::default().applies_to(&"192.168.1.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_lockout_schedule() {
+        let lockout: Lockout = toml::from_str(
+            r#"
+            enabled = true
+            schedule = ["mon-fri 02:00-08:00"]
+            "#,
+        )
+        .unwrap();
+        let ip = "192.168.1.2".parse().unwrap();
+        let at = |day, hour| {
+            chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(hour, 0, 0)
+                .unwrap()
+        };
+
+        // Monday 1 january 2024
+        assert!(!lockout.is_locked_out_at(&ip, &at(1, 1)));
+        assert!(lockout.is_locked_out_at(&ip, &at(1, 2)));
+        assert!(lockout.is_locked_out_at(&ip, &at(1, 7)));
+        assert!(!lockout.is_locked_out_at(&ip, &at(1, 8)));
+
+        // Not on saturday
+        assert!(!lockout.is_locked_out_at(&ip, &at(6, 3)));
+
+        // Schedule does nothing if not enabled
+        let lockout = Lockout {
+            enabled: false,
+            ..lockout
+        };
+        assert!(!lockout.is_locked_out_at(&ip, &at(1, 3)));
+    }
+
+    #[test]
+    fn test_access_allow_only() {
+        let access: Access = toml::from_str(r#"allow = ["10.0.0.0/8", "192.168.1.2"]"#).unwrap();
+        assert!(access.is_allowed(&"10.1.2.3".parse().unwrap()));
+        assert!(access.is_allowed(&"192.168.1.2".parse().unwrap()));
+        assert!(!access.is_allowed(&"192.168.1.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_access_deny_only() {
+        let access: Access = toml::from_str(r#"deny = ["10.0.0.0/8"]"#).unwrap();
+        assert!(!access.is_allowed(&"10.1.2.3".parse().unwrap()));
+        assert!(access.is_allowed(&"192.168.1.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_access_deny_wins() {
+        let access: Access = toml::from_str(
+            r#"
+            allow = ["10.0.0.0/8"]
+            deny = ["10.0.0.1"]
+            "#,
+        )
+        .unwrap();
+        assert!(!access.is_allowed(&"10.0.0.1".parse().unwrap()));
+        assert!(access.is_allowed(&"10.0.0.2".parse().unwrap()));
+        assert!(!access.is_allowed(&"192.168.1.2".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_server_command_required() {
+        let value: toml::Value = toml::from_str("[server]\ndirectory = \".\"").unwrap();
+        assert!(Config::from_value(value, None).is_err());
+
+        // Commands that split into no program are rejected
+        for command in [" ", "''", "java -jar 'server.jar"] {
+            let value: toml::Value =
+                toml::from_str(&format!("[server]\ncommand = {:?}", command)).unwrap();
+            assert!(Config::from_value(value, None).is_err(), "{:?}", command);
+        }
+
+        let value: toml::Value =
+            toml::from_str("[server]\ncommand_args = [\"java\", \"-jar\", \"server.jar\"]")
+                .unwrap();
+        let config = Config::from_value(value, None).unwrap();
+        assert_eq!(config.server.command, "");
+        assert_eq!(config.server.command_args, ["java", "-jar", "server.jar"]);
+    }
+
+    #[test]
+    fn test_join_methods_dedup() {
+        let value: toml::Value = toml::from_str(
+            "[server]\ncommand = \"true\"\n[join]\nmethods = [\"hold\", \"lobby\", \"hold\", \"kick\"]",
+        )
+        .unwrap();
+        let config = Config::from_value(value, None).unwrap();
+        assert_eq!(
+            config.join.methods,
+            [Method::Hold, Method::Lobby, Method::Kick]
+        );
+        assert!(config.join.unreachable_methods().is_empty());
+    }
+
+    #[test]
+    fn test_join_methods_empty() {
+        let value: toml::Value =
+            toml::from_str("[server]\ncommand = \"true\"\n[join]\nmethods = []").unwrap();
+        assert!(Config::from_value(value, None).is_err());
+    }
+
+    #[test]
+    fn test_proxy_buffer_size_zero() {
+        let value: toml::Value =
+            toml::from_str("[server]\ncommand = \"true\"\n[advanced]\nproxy_buffer_size = 0")
+                .unwrap();
+        assert!(Config::from_value(value, None).is_err());
+    }
+
+    #[test]
+    fn test_join_methods_unreachable() {
+        let join: Join = toml::from_str(r#"methods = ["hold", "kick", "lobby"]"#).unwrap();
+        assert_eq!(join.unreachable_methods(), [Method::Lobby]);
+
+        let join: Join = toml::from_str(r#"methods = ["forward", "kick"]"#).unwrap();
+        assert_eq!(join.unreachable_methods(), [Method::Kick]);
+
+        // Forward is a fallback for lobby, which gives up if the server can't be used
+        let join: Join = toml::from_str(r#"methods = ["lobby", "forward"]"#).unwrap();
+        assert!(join.unreachable_methods().is_empty());
+    }
+
+    #[test]
+    fn test_lock_file() {
+        let mut config: Config = crate::util::test::config("directory = \"server\"");
+        config.path = Some(PathBuf::from("/etc/lazymc/lazymc.toml"));
+        assert_eq!(
+            Advanced::lock_file(&config),
+            Some(PathBuf::from("/etc/lazymc/server/lazymc.lock"))
+        );
+
+        config.advanced.lock_file = Some("/run/lazymc.lock".into());
+        assert_eq!(
+            Advanced::lock_file(&config),
+            Some(PathBuf::from("/run/lazymc.lock"))
+        );
+    }
+
+    #[test]
+    fn test_wake_enabled() {
+        let config: Config = crate::util::test::config("");
+        assert!(!config.wake.is_enabled());
+
+        // Secret is required
+        let config: Config = crate::util::test::config("[wake]\nudp_port = 25580");
+        assert!(!config.wake.is_enabled());
+        let config: Config =
+            crate::util::test::config("[wake]\nudp_port = 25580\nsecret = \"hunter2\"");
+        assert!(config.wake.is_enabled());
+    }
+
+    #[test]
+    fn test_server_address_endpoint() {
+        let config: Config = crate::util::test::config("");
+        assert_eq!(
+            config.server.address,
+            Endpoint::Tcp("127.0.0.1:25566".parse().unwrap())
+        );
+
+        #[cfg(unix)]
+        {
+            let config: Config =
+                crate::util::test::config("address = \"unix:/run/minecraft.sock\"");
+            assert_eq!(
+                config.server.address,
+                Endpoint::Unix("/run/minecraft.sock".into())
+            );
+            assert_eq!(config.server.address.tcp(), None);
+        }
+
+        // Hostnames are kept to resolve again later
+        let config: Config = crate::util::test::config("address = \"localhost:25570\"");
+        assert!(matches!(config.server.address, Endpoint::Host(_)));
+        assert_eq!(
+            config.server.address.handshake_host(),
+            ("localhost".into(), 25570)
+        );
+    }
 }
@@ -0,0 +1,72 @@
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::server::State;
+
+/// Maximum number of events buffered for a subscriber that falls behind.
+const CAPACITY: usize = 64;
+
+/// Server event, for local integrations to follow.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// Server state changed.
+    State { from: State, to: State },
+
+    /// Number of online players changed.
+    Players { online: u32, max: u32 },
+
+    /// Server is woken, by a joining player if known.
+    Wake { username: Option<String> },
+
+    /// Server is put to sleep after being idle.
+    Sleep,
+}
+
+/// Event source, broadcasts events to all subscribers.
+#[derive(Debug)]
+pub struct Events {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Events {
+    /// Emit an event to all current subscribers.
+    pub fn emit(&self, event: Event) {
+        // Fails if nobody is subscribed, which is fine
+        let _ = self.sender.send(event);
+    }
+
+    /// Subscribe to events emitted from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for Events {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(CAPACITY).0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_event() {
+        let event = Event::State {
+            from: State::Stopped,
+            to: State::Starting,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"state","from":"stopped","to":"starting"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Event::Sleep).unwrap(),
+            r#"{"event":"sleep"}"#
+        );
+    }
+}
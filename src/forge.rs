@@ -29,6 +29,54 @@ use crate::proto::packets;
 #[cfg(feature = "lobby")]
 use crate::server::Server;
 
+/// Mod loader, as detected from server status.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum ModLoader {
+    /// Forge, 1.13 or newer.
+    Forge,
+
+    /// NeoForge.
+    NeoForge,
+
+    /// Legacy Forge (FML), before 1.13.
+    LegacyForge,
+}
+
+impl ModLoader {
+    /// Whether this mod loader uses the modern Forge login handshake.
+    pub fn is_forge(self) -> bool {
+        matches!(self, Self::Forge | Self::NeoForge)
+    }
+}
+
+/// Detect mod loader from server status JSON.
+///
+/// Forge and NeoForge add a `forgeData` object, legacy Forge adds a `modinfo` object.
+pub fn detect_mod_loader(status: &serde_json::Value) -> Option<ModLoader> {
+    if let Some(forge_data) = status.get("forgeData") {
+        let neoforge = forge_data
+            .get("mods")
+            .and_then(|mods| mods.as_array())
+            .map(|mods| {
+                mods.iter()
+                    .any(|m| m.get("modId").and_then(|id| id.as_str()) == Some("neoforge"))
+            })
+            .unwrap_or(false);
+        return Some(if neoforge {
+            ModLoader::NeoForge
+        } else {
+            ModLoader::Forge
+        });
+    }
+
+    match status.get("modinfo") {
+        Some(modinfo) if modinfo.get("type").and_then(|t| t.as_str()) == Some("FML") => {
+            Some(ModLoader::LegacyForge)
+        }
+        _ => None,
+    }
+}
+
 /// Forge status magic.
 pub const STATUS_MAGIC: &str = "\0FML2\0";
 
@@ -253,3 +301,53 @@ async fn drain_forge_responses(
 
     Err(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_mod_loader_forge() {
+        let status = serde_json::json!({
+            "version": { "name": "1.20.1", "protocol": 763 },
+            "forgeData": {
+                "channels": [],
+                "mods": [{ "modId": "forge", "modmarker": "47.2.0" }],
+                "fmlNetworkVersion": 3,
+            },
+        });
+        assert_eq!(detect_mod_loader(&status), Some(ModLoader::Forge));
+    }
+
+    #[test]
+    fn test_detect_mod_loader_neoforge() {
+        let status = serde_json::json!({
+            "forgeData": { "mods": [{ "modId": "neoforge", "modmarker": "20.4.80" }] },
+        });
+        assert_eq!(detect_mod_loader(&status), Some(ModLoader::NeoForge));
+    }
+
+    #[test]
+    fn test_detect_mod_loader_legacy_and_vanilla() {
+        let status = serde_json::json!({ "modinfo": { "type": "FML", "modList": [] } });
+        assert_eq!(detect_mod_loader(&status), Some(ModLoader::LegacyForge));
+
+        let status = serde_json::json!({ "version": { "name": "1.20.4", "protocol": 765 } });
+        assert_eq!(detect_mod_loader(&status), None);
+    }
+
+    #[test]
+    fn test_detected_forge_sets_server_flag() {
+        let (server, _) = crate::server::Server::new();
+        let config: crate::config::Config = crate::util::test::config("");
+        assert!(!server.is_forge(&config));
+
+        let status = serde_json::json!({ "forgeData": { "mods": [] } });
+        server.set_mod_loader(detect_mod_loader(&status));
+        assert!(server.is_forge(&config));
+
+        // Configured value overrides detection
+        let config: crate::config::Config = crate::util::test::config("forge = false");
+        assert!(!server.is_forge(&config));
+    }
+}
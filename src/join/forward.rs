@@ -4,29 +4,193 @@ use bytes::BytesMut;
 use tokio::net::TcpStream;
 
 use crate::config::*;
+use crate::proto::client::Client;
 use crate::proxy::ProxyHeader;
-use crate::service;
+use crate::service::{self, server::Permit};
 
 use super::MethodResult;
 
 /// Forward the client.
 pub async fn occupy(
+    client: &Client,
     config: Arc<Config>,
     inbound: TcpStream,
     inbound_history: &mut BytesMut,
+    permit: Permit,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using forward method to occupy joining client");
 
-    debug!(target: "lazymc", "Forwarding client to {:?}!", config.join.forward.address);
+    debug!(target: "lazymc", "Forwarding client to {}!", config.join.forward.address);
 
     service::server::route_proxy_address_queue(
         inbound,
+        config.clone(),
+        client.peer,
         ProxyHeader::Proxy.not_none(config.join.forward.send_proxy_v2),
-        config.join.forward.address,
+        config.join.forward.address.clone(),
         inbound_history.clone(),
+        permit,
     );
 
     // TODO: do not consume, continue on proxy connect failure
 
     Ok(MethodResult::Consumed)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, ReadBuf};
+    use tokio::net::TcpListener;
+    use tokio::sync::Notify;
+    use tokio::time;
+
+    use super::*;
+    use crate::proto::packet::RawPacket;
+    use crate::proxy::{self, Connection};
+    use crate::util::test::{login_packets, stream_pair};
+
+    /// Encode login disconnect packet, as sent by a server rejecting the client.
+    fn disconnect_packet() -> Vec<u8> {
+        RawPacket::new(0, br#"{"text":"Outdated client!"}"#.to_vec())
+            .encode_with_len(&Client::dummy())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_forward_login_disconnect() {
+        // Backend that rejects every login, and closes the connection
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "[join.forward]\naddress = \"{}\"",
+            backend.local_addr().unwrap(),
+        )));
+        tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut login = vec![0u8; login_packets("player").len()];
+            stream.read_exact(&mut login).await.unwrap();
+            stream.write_all(&disconnect_packet()).await.unwrap();
+        });
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, peer) = listener.accept().await.unwrap();
+        let mut history = BytesMut::from(login_packets("player").as_slice());
+        assert!(matches!(
+            occupy(
+                &Client::new(peer),
+                config,
+                inbound,
+                &mut history,
+                crate::util::test::permit(),
+            )
+            .await,
+            Ok(MethodResult::Consumed)
+        ));
+
+        // Disconnect is relayed to the client unchanged, and the connection is closed
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, disconnect_packet());
+    }
+
+    /// Backend that takes the forwarded login, then fails to receive anything else.
+    struct RejectingServer {
+        /// Login bytes still accepted.
+        login: usize,
+
+        /// Notified once receiving failed.
+        failed: Arc<Notify>,
+
+        /// Data the backend sends.
+        sent: DuplexStream,
+    }
+
+    impl AsyncRead for RejectingServer {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            Pin::new(&mut self.sent).poll_read(cx, buf)
+        }
+    }
+
+    impl AsyncWrite for RejectingServer {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            if self.login == 0 {
+                self.failed.notify_one();
+                return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+            }
+            let len = buf.len().min(self.login);
+            self.login -= len;
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_forward_login_disconnect_client_error() {
+        let config: Config = crate::util::test::config("");
+        let (mut client, inbound) = stream_pair().await;
+        let (mut backend, sent) = tokio::io::duplex(1024);
+        let failed = Arc::new(Notify::new());
+        let server = RejectingServer {
+            login: login_packets("player").len(),
+            failed: failed.clone(),
+            sent,
+        };
+
+        // Forward login, as occupying the client does
+        let connection = Connection::new(inbound.peer_addr().unwrap(), "backend");
+        let forward = tokio::spawn(async move {
+            proxy::proxy_inbound_outbound_with_queue(
+                &config,
+                connection,
+                inbound,
+                server,
+                &[],
+                &login_packets("player"),
+            )
+            .await
+            .map_err(|err| err.to_string())
+        });
+
+        // Relaying to the server fails while its disconnect is still in flight
+        client.write_all(b"plugin response").await.unwrap();
+        time::timeout(Duration::from_secs(5), failed.notified())
+            .await
+            .unwrap();
+        backend.write_all(&disconnect_packet()).await.unwrap();
+        drop(backend);
+
+        // Disconnect is still relayed to the client
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, disconnect_packet());
+        assert!(forward.await.unwrap().is_err());
+    }
+}
@@ -1,23 +1,41 @@
+use std::future::Future;
 use std::ops::Deref;
 use std::sync::Arc;
 use std::time::Duration;
 
 use bytes::BytesMut;
+use minecraft_protocol::version::v1_14_4::login::LoginPluginRequest;
 use tokio::net::TcpStream;
 use tokio::time;
 
 use crate::config::*;
+use crate::proto::client::Client;
+use crate::proto::packet;
+use crate::proto::packets;
 use crate::server::{Server, State};
-use crate::service;
+use crate::service::{self, server::Permit};
 
 use super::MethodResult;
 
+/// Interval to send keep-alive requests to held clients at.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Timeout for responses on in-flight keep-alive requests once holding is done.
+const KEEP_ALIVE_RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Login plugin channel used for keep-alive requests.
+///
+/// Clients don't know this channel, and always respond that they don't understand it.
+const KEEP_ALIVE_CHANNEL: &str = "lazymc:keep_alive";
+
 /// Hold the client.
 pub async fn occupy(
+    client: &Client,
     config: Arc<Config>,
     server: Arc<Server>,
-    inbound: TcpStream,
+    mut inbound: TcpStream,
     inbound_history: &mut BytesMut,
+    permit: Permit,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using hold method to occupy joining client");
 
@@ -27,8 +45,14 @@ pub async fn occupy(
     }
 
     // Start holding, consume client
-    if hold(&config, &server).await? {
-        service::server::route_proxy_queue(inbound, config, inbound_history.clone());
+    if hold(client, &config, &server, &mut inbound, inbound_history).await? {
+        service::server::route_proxy_queue(
+            inbound,
+            config,
+            client.peer,
+            inbound_history.clone(),
+            permit,
+        );
         return Ok(MethodResult::Consumed);
     }
 
@@ -39,7 +63,13 @@ pub async fn occupy(
 ///
 /// Returns holding status. `true` if client is held and it should be proxied, `false` it was held
 /// but it timed out.
-async fn hold<'a>(config: &Config, server: &Server) -> Result<bool, ()> {
+async fn hold<'a>(
+    client: &Client,
+    config: &Config,
+    server: &Server,
+    inbound: &mut TcpStream,
+    inbound_history: &mut BytesMut,
+) -> Result<bool, ()> {
     trace!(target: "lazymc", "Started holding client");
 
     // A task to wait for suitable server state
@@ -69,7 +99,7 @@ async fn hold<'a>(config: &Config, server: &Server) -> Result<bool, ()> {
                 }
 
                 // Server stopped, this shouldn't happen, disconnect
-                State::Stopped => {
+                State::Stopped | State::Frozen => {
                     error!(target: "lazymc", "Server stopped for held client, disconnecting");
                     break false;
                 }
@@ -77,25 +107,194 @@ async fn hold<'a>(config: &Config, server: &Server) -> Result<bool, ()> {
         }
     };
 
+    // Keep client alive while waiting if enabled
+    let task = async {
+        if config.join.hold.keepalive {
+            keep_alive_until(
+                client,
+                inbound,
+                inbound_history,
+                KEEP_ALIVE_INTERVAL,
+                task_wait,
+            )
+            .await
+        } else {
+            Ok(task_wait.await)
+        }
+    };
+
     // Wait for server state with timeout
-    let timeout = Duration::from_secs(config.join.hold.timeout as u64);
-    match time::timeout(timeout, task_wait).await {
+    let timeout = hold_timeout(config, server).await;
+    match time::timeout(timeout, task).await {
+        // Client disconnected while holding
+        Ok(Err(())) => {
+            debug!(target: "lazymc", "Held client disconnected");
+            Err(())
+        }
+
         // Relay client to proxy
-        Ok(true) => {
+        Ok(Ok(true)) => {
             info!(target: "lazymc", "Server ready for held client, relaying to server");
             Ok(true)
         }
 
         // Server stopping/stopped, this shouldn't happen, kick
-        Ok(false) => {
+        Ok(Ok(false)) => {
             warn!(target: "lazymc", "Server stopping for held client");
             Ok(false)
         }
 
         // Timeout reached, kick with starting message
         Err(_) => {
-            warn!(target: "lazymc", "Held client reached timeout of {}s", config.join.hold.timeout);
+            warn!(target: "lazymc", "Held client reached timeout of {}s", timeout.as_secs());
             Ok(false)
         }
     }
 }
+
+/// Get the hold timeout.
+///
+/// Extends the configured timeout to the learned server startup time if the server usually takes
+/// longer to start.
+async fn hold_timeout(config: &Config, server: &Server) -> Duration {
+    let timeout = Duration::from_secs(config.join.hold.timeout as u64);
+    match server.startup_time().await {
+        Some(startup) if startup > timeout => {
+            debug!(target: "lazymc", "Extending hold timeout to learned server startup time of {}s", startup.as_secs());
+            startup
+        }
+        _ => timeout,
+    }
+}
+
+/// Keep a held client alive until the given task completes.
+///
+/// The login phase has no keep-alive packet. Instead, a login plugin request is sent periodically,
+/// which the client must respond to. This keeps traffic flowing both ways so neither end times
+/// out. Responses are consumed here, other packets are appended to the inbound history to relay
+/// to the server later.
+///
+/// Returns the task result, or an error if the client disconnected.
+async fn keep_alive_until(
+    client: &Client,
+    inbound: &mut TcpStream,
+    inbound_history: &mut BytesMut,
+    interval: Duration,
+    task: impl Future<Output = bool>,
+) -> Result<bool, ()> {
+    let (mut reader, mut writer) = inbound.split();
+    let mut buf = BytesMut::new();
+    let mut ticker = time::interval_at(time::Instant::now() + interval, interval);
+    let mut message_id = 0;
+    let mut pending = 0;
+    tokio::pin!(task);
+
+    let result = loop {
+        tokio::select! {
+            result = &mut task => break result,
+            _ = ticker.tick() => {
+                trace!(target: "lazymc", "Sending keep-alive to held client");
+                message_id += 1;
+                let packet = LoginPluginRequest {
+                    message_id,
+                    channel: KEEP_ALIVE_CHANNEL.into(),
+                    data: vec![],
+                };
+                packet::write_packet(packet, client, &mut writer).await?;
+                pending += 1;
+            }
+            packet = packet::read_packet(client, &mut buf, &mut reader) => {
+                match packet? {
+                    Some((packet, _)) if packet.id == packets::login::SERVER_LOGIN_PLUGIN_RESPONSE => {
+                        pending -= 1;
+                    }
+                    Some((_, raw)) => inbound_history.extend(raw),
+                    None => return Err(()),
+                }
+            }
+        }
+    };
+
+    // Consume responses on in-flight requests, the server must never receive these
+    let drain = async {
+        while pending > 0 {
+            match packet::read_packet(client, &mut buf, &mut reader).await? {
+                Some((packet, _)) if packet.id == packets::login::SERVER_LOGIN_PLUGIN_RESPONSE => {
+                    pending -= 1;
+                }
+                Some((_, raw)) => inbound_history.extend(raw),
+                None => return Err(()),
+            }
+        }
+        Ok(())
+    };
+    time::timeout(KEEP_ALIVE_RESPONSE_TIMEOUT, drain)
+        .await
+        .map_err(|_| {
+            warn!(target: "lazymc", "Held client did not respond to keep-alive");
+        })??;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::decoder::Decoder;
+    use minecraft_protocol::version::v1_14_4::login::LoginPluginResponse;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_keep_alive_cadence() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Fake Minecraft client, responds to and counts keep-alive requests
+        let fake_client = tokio::spawn(async move {
+            let client = Client::dummy();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            let (mut reader, mut writer) = stream.split();
+            let mut buf = BytesMut::new();
+            let mut count = 0;
+            while let Ok(Some((packet, _))) =
+                packet::read_packet(&client, &mut buf, &mut reader).await
+            {
+                assert_eq!(packet.id, packets::login::CLIENT_LOGIN_PLUGIN_REQUEST);
+                let request = LoginPluginRequest::decode(&mut packet.data.as_slice()).unwrap();
+                assert_eq!(request.channel, KEEP_ALIVE_CHANNEL);
+                count += 1;
+
+                let response = LoginPluginResponse {
+                    message_id: request.message_id,
+                    successful: false,
+                    data: vec![],
+                };
+                packet::write_packet(response, &client, &mut writer)
+                    .await
+                    .unwrap();
+            }
+            count
+        });
+
+        let (mut inbound, _) = listener.accept().await.unwrap();
+        let mut history = BytesMut::new();
+        let task = async {
+            time::sleep(Duration::from_millis(275)).await;
+            true
+        };
+        let result = keep_alive_until(
+            &Client::dummy(),
+            &mut inbound,
+            &mut history,
+            Duration::from_millis(50),
+            task,
+        )
+        .await;
+        drop(inbound);
+
+        assert_eq!(result, Ok(true));
+        assert!(history.is_empty());
+        assert_eq!(fake_client.await.unwrap(), 5);
+    }
+}
@@ -2,9 +2,9 @@ use tokio::net::TcpStream;
 
 use crate::config::*;
 use crate::net;
-use crate::proto::action;
 use crate::proto::client::Client;
-use crate::server::{self, Server};
+use crate::rejection::{self, Rejection};
+use crate::server::Server;
 
 use super::MethodResult;
 
@@ -18,13 +18,8 @@ pub async fn occupy(
     trace!(target: "lazymc", "Using kick method to occupy joining client");
 
     // Select message and kick
-    let msg = match server.state() {
-        server::State::Starting | server::State::Stopped | server::State::Started => {
-            &config.join.kick.starting
-        }
-        server::State::Stopping => &config.join.kick.stopping,
-    };
-    action::kick(client, msg, &mut inbound.split().1).await?;
+    let rejection = Rejection::from_state(server.state());
+    rejection::reject(client, config, &rejection, &mut inbound.split().1).await?;
 
     // Gracefully close connection
     net::close_tcp_stream(inbound).await.map_err(|_| ())?;
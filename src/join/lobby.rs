@@ -7,6 +7,7 @@ use crate::config::*;
 use crate::lobby;
 use crate::proto::client::{Client, ClientInfo};
 use crate::server::Server;
+use crate::service::server::Permit;
 
 use super::MethodResult;
 
@@ -18,6 +19,7 @@ pub async fn occupy(
     server: Arc<Server>,
     inbound: TcpStream,
     inbound_queue: BytesMut,
+    permit: Permit,
 ) -> Result<MethodResult, ()> {
     trace!(target: "lazymc", "Using lobby method to occupy joining client");
 
@@ -27,8 +29,31 @@ pub async fn occupy(
         return Ok(MethodResult::Continue(inbound));
     }
 
+    // Lobby can't do encryption, server must be in offline mode
+    if server.is_online_mode(&config).await {
+        warn!(target: "lazymc", "Client connected but lobby requires server in offline mode, using next join method");
+        return Ok(MethodResult::Continue(inbound));
+    }
+
+    // Lobby must support protocol version of client
+    let protocol = client_info.protocol().unwrap_or(config.public.protocol);
+    if !lobby::supports_protocol(&config, protocol) {
+        warn!(target: "lazymc", "Client connected but lobby does not support protocol version {}, using next join method", protocol);
+        return Ok(MethodResult::Continue(inbound));
+    }
+
     // Start lobby
-    lobby::serve(client, client_info, inbound, config, server, inbound_queue).await?;
+    lobby::serve(
+        client,
+        client_info,
+        inbound,
+        config,
+        server,
+        inbound_queue,
+        None,
+        permit,
+    )
+    .await?;
 
     // TODO: do not consume client here, allow other join method on fail
 
@@ -36,11 +61,81 @@ pub async fn occupy(
 }
 
 /// Check whether we still have to probe before we can use the lobby.
-async fn must_still_probe(config: &Config, server: &Server) -> bool {
-    must_probe(config) && server.probed_join_game.read().await.is_none()
+pub(super) async fn must_still_probe(config: &Config, server: &Server) -> bool {
+    // Forge needs probed join game
+    server.is_forge(config) && server.probed_join_game.read().await.is_none()
 }
 
-/// Check whether we must have probed data.
-fn must_probe(config: &Config) -> bool {
-    config.server.forge
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use minecraft_protocol::decoder::Decoder;
+    use minecraft_protocol::version::v1_14_4::login::SetCompression;
+    use tokio::time;
+
+    use super::*;
+    use crate::mc::server_properties::Properties;
+    use crate::proto::client::ClientState;
+    use crate::proto::{packet, packets};
+    use crate::server::State;
+    use crate::util::test::{login_start_packet, permit, stream_pair};
+
+    #[tokio::test]
+    async fn test_offline_login_without_encryption() {
+        let config: Arc<Config> = Arc::new(crate::util::test::config(""));
+        let (server, _) = Server::new();
+        server
+            .set_properties(Properties::parse("online-mode=false"))
+            .await;
+        server.update_state(State::Starting, &config).await;
+        let server = Arc::new(server);
+
+        // 1.20.1 client joining the lobby
+        let client_info = ClientInfo {
+            protocol: Some(763),
+            username: Some("Steve".into()),
+            ..ClientInfo::empty()
+        };
+        let (mut player, inbound) = stream_pair().await;
+        let lobby = tokio::spawn(async move {
+            let client = Client::dummy();
+            client.set_state(ClientState::Login);
+            let queue = BytesMut::from(login_start_packet("Steve").as_slice());
+            let _ = occupy(
+                &client,
+                client_info,
+                config,
+                server,
+                inbound,
+                queue,
+                permit(),
+            )
+            .await;
+        });
+
+        // Login completes without the lobby requesting encryption
+        let client = Client::dummy();
+        client.set_state(ClientState::Login);
+        let mut buf = BytesMut::new();
+        loop {
+            let read = packet::read_packet(&client, &mut buf, &mut player);
+            let (packet, _) = time::timeout(Duration::from_secs(5), read)
+                .await
+                .unwrap()
+                .unwrap()
+                .expect("lobby closed connection before login success");
+            assert_ne!(packet.id, packets::login::CLIENT_ENCRYPTION_REQUEST);
+            match packet.id {
+                packets::login::CLIENT_SET_COMPRESSION => {
+                    let compression = SetCompression::decode(&mut packet.data.as_slice()).unwrap();
+                    client.set_compression(compression.threshold);
+                }
+                packets::login::CLIENT_LOGIN_SUCCESS => break,
+                _ => {}
+            }
+        }
+
+        lobby.abort();
+    }
 }
@@ -7,12 +7,15 @@ use crate::config::*;
 use crate::net;
 use crate::proto::client::{Client, ClientInfo, ClientState};
 use crate::server::Server;
+use crate::service::server::Permit;
 
 pub mod forward;
 pub mod hold;
 pub mod kick;
 #[cfg(feature = "lobby")]
 pub mod lobby;
+#[cfg(feature = "lobby")]
+pub mod queue;
 
 /// A result returned by a join occupy method.
 pub enum MethodResult {
@@ -34,6 +37,7 @@ pub async fn occupy(
     mut inbound: TcpStream,
     mut inbound_history: BytesMut,
     #[allow(unused_variables)] login_queue: BytesMut,
+    permit: Permit,
 ) -> Result<(), ()> {
     // Assert state is correct
     assert_eq!(
@@ -43,7 +47,7 @@ pub async fn occupy(
     );
 
     // Go through all configured join methods
-    for method in &config.join.methods {
+    for method in &methods(&config.join, &client_info) {
         // Invoke method, take result
         let result = match method {
             // Kick method, immediately kick client
@@ -52,17 +56,26 @@ pub async fn occupy(
             // Hold method, hold client connection while server starts
             Method::Hold => {
                 hold::occupy(
+                    &client,
                     config.clone(),
                     server.clone(),
                     inbound,
                     &mut inbound_history,
+                    permit.clone(),
                 )
                 .await?
             }
 
             // Forward method, forward client connection while server starts
             Method::Forward => {
-                forward::occupy(config.clone(), inbound, &mut inbound_history).await?
+                forward::occupy(
+                    &client,
+                    config.clone(),
+                    inbound,
+                    &mut inbound_history,
+                    permit.clone(),
+                )
+                .await?
             }
 
             // Lobby method, keep client in lobby while server starts
@@ -75,6 +88,7 @@ pub async fn occupy(
                     server.clone(),
                     inbound,
                     login_queue.clone(),
+                    permit.clone(),
                 )
                 .await?
             }
@@ -85,6 +99,28 @@ pub async fn occupy(
                 error!(target: "lazymc", "Lobby join method not supported in this lazymc build");
                 MethodResult::Continue(inbound)
             }
+
+            // Queue method, keep client in lobby until there's room on the server
+            #[cfg(feature = "lobby")]
+            Method::Queue => {
+                queue::occupy(
+                    &client,
+                    client_info.clone(),
+                    config.clone(),
+                    server.clone(),
+                    inbound,
+                    login_queue.clone(),
+                    permit.clone(),
+                )
+                .await?
+            }
+
+            // Queue method, keep client in lobby until there's room on the server
+            #[cfg(not(feature = "lobby"))]
+            Method::Queue => {
+                error!(target: "lazymc", "Queue join method not supported in this lazymc build");
+                MethodResult::Continue(inbound)
+            }
         };
 
         // Handle method result
@@ -104,3 +140,42 @@ pub async fn occupy(
 
     Ok(())
 }
+
+/// Join methods to occupy the given client with.
+///
+/// The client that woke the server is held first if configured, others use the configured methods.
+fn methods(config: &Join, client_info: &ClientInfo) -> Vec<Method> {
+    if !(client_info.woke && config.hold.first_joiner) {
+        return config.methods.clone();
+    }
+
+    let mut methods = vec![Method::Hold];
+    methods.extend(config.methods.iter().filter(|m| **m != Method::Hold));
+    methods
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_methods_first_joiner() {
+        let config: Config = crate::util::test::config(
+            "[join]\nmethods = [\"kick\"]\n[join.hold]\nfirst_joiner = true",
+        );
+
+        // Client that woke the server is held first, later clients are kicked
+        let waker = ClientInfo {
+            woke: true,
+            ..ClientInfo::empty()
+        };
+        assert_eq!(
+            methods(&config.join, &waker),
+            vec![Method::Hold, Method::Kick]
+        );
+        assert_eq!(
+            methods(&config.join, &ClientInfo::empty()),
+            vec![Method::Kick]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use bytes::BytesMut;
+use tokio::net::TcpStream;
+use tokio::time;
+
+use crate::config::*;
+use crate::lobby;
+use crate::proto::client::{Client, ClientInfo};
+use crate::server::{Server, State};
+use crate::service::server::Permit;
+
+use super::lobby::must_still_probe;
+use super::MethodResult;
+
+/// Interval to check whether the first queued client can be admitted at.
+const ADMIT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Time an admitted client takes up a slot before it shows up in the server status.
+///
+/// The server status is polled periodically, and joining the server takes a moment, so recently
+/// admitted clients are not counted as online players yet.
+const ADMIT_GRACE: Duration = Duration::from_secs(10);
+
+/// Queue the client.
+pub async fn occupy(
+    client: &Client,
+    client_info: ClientInfo,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    inbound: TcpStream,
+    inbound_queue: BytesMut,
+    permit: Permit,
+) -> Result<MethodResult, ()> {
+    trace!(target: "lazymc", "Using queue method to occupy joining client");
+
+    // Must be ready to lobby
+    if must_still_probe(&config, &server).await {
+        warn!(target: "lazymc", "Client connected but lobby is not ready, using next join method, probing not completed");
+        return Ok(MethodResult::Continue(inbound));
+    }
+
+    // Lobby can't do encryption, server must be in offline mode
+    if server.is_online_mode(&config).await {
+        warn!(target: "lazymc", "Client connected but queue requires server in offline mode, using next join method");
+        return Ok(MethodResult::Continue(inbound));
+    }
+
+    // Server must be starting or full
+    if server.state() == State::Started && !server.is_full().await && server.queue.is_empty() {
+        return Ok(MethodResult::Continue(inbound));
+    }
+
+    // Lobby must support protocol version of client
+    let protocol = client_info.protocol().unwrap_or(config.public.protocol);
+    if !lobby::supports_protocol(&config, protocol) {
+        warn!(target: "lazymc", "Client connected but queue lobby does not support protocol version {}, using next join method", protocol);
+        return Ok(MethodResult::Continue(inbound));
+    }
+
+    // Must have slot in queue
+    let ticket = match server.queue.enqueue(config.join.queue.max_slots) {
+        Some(ticket) => ticket,
+        None => {
+            debug!(target: "lazymc", "Join queue is full, using next join method");
+            return Ok(MethodResult::Continue(inbound));
+        }
+    };
+
+    // Start lobby, keeps client in queue until admitted
+    lobby::serve(
+        client,
+        client_info,
+        inbound,
+        config,
+        server,
+        inbound_queue,
+        Some(ticket),
+        permit,
+    )
+    .await?;
+
+    Ok(MethodResult::Consumed)
+}
+
+/// Wait until the queued client is admitted to the server.
+///
+/// Gives up once the server has been starting for longer than the lobby timeout.
+///
+/// Returns `Ok(false)` if waiting timed out, returns `Err(())` if the server went down while
+/// waiting.
+pub async fn wait_for_turn(server: &Server, config: &Config, ticket: &Ticket) -> Result<bool, ()> {
+    let timeout = Duration::from_secs(config.join.lobby.timeout as u64);
+    let mut interval = time::interval(ADMIT_INTERVAL);
+    let mut starting_since = None;
+
+    loop {
+        interval.tick().await;
+
+        match server.state() {
+            State::Started => starting_since = None,
+            State::Starting => {
+                let since = *starting_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= timeout {
+                    warn!(target: "lazymc", "Queued client waiting for server to come online reached timeout of {}s", timeout.as_secs());
+                    return Ok(false);
+                }
+                continue;
+            }
+            State::Stopping | State::Stopped | State::Frozen => return Err(()),
+        }
+
+        if ticket.try_admit(server.free_slots().await) {
+            debug!(target: "lazymc", "Admitted client from join queue");
+            return Ok(true);
+        }
+    }
+}
+
+/// Build position message for a queued client.
+///
+/// Replaces `{position}` and `{size}` in the configured message.
+pub fn position_message(config: &JoinQueue, position: usize, size: usize) -> String {
+    config
+        .position_message
+        .replace("{position}", &position.to_string())
+        .replace("{size}", &size.to_string())
+}
+
+/// Ordered queue of joining clients, waiting for a free slot on the server.
+#[derive(Debug, Default)]
+pub struct Queue {
+    inner: Mutex<QueueInner>,
+}
+
+#[derive(Debug, Default)]
+struct QueueInner {
+    /// ID for the next ticket.
+    next_id: u64,
+
+    /// Queued ticket IDs, in order.
+    tickets: VecDeque<u64>,
+
+    /// Times clients were recently admitted at.
+    admitted: Vec<Instant>,
+}
+
+impl Queue {
+    /// Add a client to the back of the queue.
+    ///
+    /// Returns `None` if the queue already holds the maximum number of clients, unlimited if 0.
+    pub fn enqueue(self: &Arc<Self>, max: u32) -> Option<Ticket> {
+        let mut inner = self.inner.lock().unwrap();
+        if max > 0 && inner.tickets.len() >= max as usize {
+            return None;
+        }
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.tickets.push_back(id);
+
+        Some(Ticket {
+            queue: self.clone(),
+            id,
+        })
+    }
+
+    /// Number of queued clients.
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().tickets.len()
+    }
+
+    /// Whether no clients are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Place of a client in the join queue.
+///
+/// The client leaves the queue when this is dropped.
+#[derive(Debug)]
+pub struct Ticket {
+    queue: Arc<Queue>,
+    id: u64,
+}
+
+impl Ticket {
+    /// Position in the queue, starting at 1, and the queue size.
+    ///
+    /// Returns `None` once admitted.
+    pub fn position(&self) -> Option<(usize, usize)> {
+        let inner = self.queue.inner.lock().unwrap();
+        let index = inner.tickets.iter().position(|id| *id == self.id)?;
+        Some((index + 1, inner.tickets.len()))
+    }
+
+    /// Try to admit this client, given the number of free slots on the server.
+    ///
+    /// Only the first client in the queue is admitted, if a slot is free that wasn't taken by a
+    /// recently admitted client. Returns `true` if admitted.
+    pub fn try_admit(&self, free_slots: Option<u32>) -> bool {
+        let mut inner = self.queue.inner.lock().unwrap();
+        if inner.tickets.front() != Some(&self.id) {
+            return false;
+        }
+
+        // Recently admitted clients take up slots not yet reflected in server status
+        let now = Instant::now();
+        inner.admitted.retain(|at| now - *at < ADMIT_GRACE);
+        match free_slots {
+            Some(free) if free as usize > inner.admitted.len() => {}
+            _ => return false,
+        }
+
+        inner.tickets.pop_front();
+        inner.admitted.push(now);
+        true
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let mut inner = self.queue.inner.lock().unwrap();
+        inner.tickets.retain(|id| *id != self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enqueue_ordering() {
+        let queue = Arc::new(Queue::default());
+        let first = queue.enqueue(0).unwrap();
+        let second = queue.enqueue(0).unwrap();
+        let third = queue.enqueue(0).unwrap();
+        assert_eq!(first.position(), Some((1, 3)));
+        assert_eq!(second.position(), Some((2, 3)));
+        assert_eq!(third.position(), Some((3, 3)));
+
+        // Only the first client is admitted, and only if a slot is free
+        assert!(!second.try_admit(Some(5)));
+        assert!(!first.try_admit(Some(0)));
+        assert!(!first.try_admit(None));
+        assert!(first.try_admit(Some(5)));
+        assert_eq!(first.position(), None);
+        assert_eq!(second.position(), Some((1, 2)));
+
+        // Leaving the queue moves clients behind forward
+        drop(second);
+        assert_eq!(third.position(), Some((1, 1)));
+
+        // Admitted client still takes up the single free slot until the status reflects it
+        assert!(!third.try_admit(Some(1)));
+        assert!(third.try_admit(Some(2)));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn test_enqueue_max_slots() {
+        let queue = Arc::new(Queue::default());
+        let _first = queue.enqueue(2).unwrap();
+        let second = queue.enqueue(2).unwrap();
+        assert!(queue.enqueue(2).is_none());
+
+        drop(second);
+        assert!(queue.enqueue(2).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_turn_timeout() {
+        let config = crate::util::test::config("[join.lobby]\ntimeout = 1");
+        let (server, _) = Server::new();
+        server.update_state(State::Starting, &config).await;
+        let queue = Arc::new(Queue::default());
+        let ticket = queue.enqueue(0).unwrap();
+
+        // Gives up while the server keeps starting
+        let result = time::timeout(
+            Duration::from_secs(5),
+            wait_for_turn(&server, &config, &ticket),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result, Ok(false));
+    }
+
+    #[test]
+    fn test_position_message() {
+        let config = JoinQueue {
+            position_message: "§2Server is full\n§7Position {position} of {size}".into(),
+            ..Default::default()
+        };
+        assert_eq!(
+            position_message(&config, 2, 5),
+            "§2Server is full\n§7Position 2 of 5"
+        );
+        assert_eq!(
+            position_message(&JoinQueue::default(), 1, 1),
+            "§2You are in the queue\n§7Position 1 of 1"
+        );
+    }
+}
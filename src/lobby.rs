@@ -1,4 +1,5 @@
 use std::io::ErrorKind;
+use std::net::SocketAddr;
 use std::ops::Deref;
 use std::sync::Arc;
 use std::time::Duration;
@@ -17,18 +18,30 @@ use tokio::time;
 
 use crate::config::*;
 use crate::forge;
+use crate::join::queue::{self, Ticket};
 use crate::mc::uuid;
 use crate::net;
 use crate::proto;
+use crate::proto::action;
 use crate::proto::client::{Client, ClientInfo, ClientState};
+use crate::proto::packet::RawPacket;
+use crate::proto::packets::configuration;
 use crate::proto::packets::play::join_game::JoinGameData;
 use crate::proto::{packet, packets};
 use crate::proxy;
+use crate::rejection::Rejection;
 use crate::server::{Server, State};
+use crate::service::server::Permit;
+use crate::types;
 
 /// Interval to send keep-alive packets at.
 pub const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(10);
 
+/// Interval to teleport the client back to its spawn position at, if enabled.
+///
+/// Short enough for the client not to fall far or be kicked for floating in between.
+const POSITION_INTERVAL: Duration = Duration::from_secs(1);
+
 /// Timeout for creating new server connection for lobby client.
 const SERVER_CONNECT_TIMEOUT: Duration = Duration::from_secs(2 * 60);
 
@@ -45,9 +58,23 @@ const SERVER_JOIN_GAME_TIMEOUT: Duration = Duration::from_secs(20);
 /// See warning at: <https://wiki.vg/Protocol#Login_Success>
 const SERVER_WARMUP: Duration = Duration::from_secs(1);
 
+/// First protocol version without strict error handling in login success, Minecraft 1.21.2.
+const LOGIN_SUCCESS_STRICT_ERRORS_REMOVED: u32 = 768;
+
+/// Whether the lobby supports clients with the given protocol version.
+///
+/// Lobby world play packets are only implemented for versions before Minecraft 1.20.2, clients
+/// going through the configuration state can only be held there and transferred when ready.
+pub fn supports_protocol(config: &Config, protocol: u32) -> bool {
+    !configuration::has_configuration(protocol)
+        || (config.join.lobby.transfer_on_ready && configuration::supports_transfer(protocol))
+}
+
 /// Serve lobby service for given client connection.
 ///
 /// The client must be in the login state, or this will error.
+///
+/// If a join queue ticket is given, the client stays in the lobby until it is admitted.
 // TODO: do not drop error here, return Box<dyn Error>
 // TODO: on error, nicely kick client with message
 pub async fn serve(
@@ -57,6 +84,8 @@ pub async fn serve(
     config: Arc<Config>,
     server: Arc<Server>,
     queue: BytesMut,
+    ticket: Option<Ticket>,
+    permit: Permit,
 ) -> Result<(), ()> {
     let (mut reader, mut writer) = inbound.split();
 
@@ -72,6 +101,13 @@ pub async fn serve(
         return Err(());
     }
 
+    // Lobby must support protocol version of client
+    let protocol = client_info.protocol().unwrap_or(config.public.protocol);
+    if !supports_protocol(&config, protocol) {
+        error!(target: "lazymc::lobby", "Lobby does not support protocol version {}, closing connection", protocol);
+        return Err(());
+    }
+
     // Incoming buffer
     let mut inbound_buf = queue;
 
@@ -98,10 +134,11 @@ pub async fn serve(
             debug!(target: "lazymc::lobby", "Login on lobby server (user: {})", login_start.name);
 
             // Replay Forge payload
-            if config.server.forge {
+            if server.is_forge(&config) {
                 forge::replay_login_payload(client, &mut inbound, server.clone(), &mut inbound_buf)
                     .await?;
-                let (_returned_reader, returned_writer) = inbound.split();
+                let (returned_reader, returned_writer) = inbound.split();
+                reader = returned_reader;
                 writer = returned_writer;
             }
 
@@ -113,7 +150,24 @@ pub async fn serve(
             }
 
             // Respond with login success, switch to play state
-            respond_login_success(client, &mut writer, &login_start).await?;
+            respond_login_success(client, protocol, &mut writer, &login_start).await?;
+
+            // Hold client in configuration state, transfer it to the server once ready
+            if config.join.lobby.transfer_on_ready && configuration::supports_transfer(protocol) {
+                stage_transfer(
+                    client,
+                    &client_info,
+                    &server,
+                    &config,
+                    &mut reader,
+                    &mut inbound_buf,
+                    &mut writer,
+                    ticket.as_ref(),
+                )
+                .await?;
+                break;
+            }
+
             client.set_state(ClientState::Play);
 
             trace!(target: "lazymc::lobby", "Client login success, sending required play packets for lobby world");
@@ -121,13 +175,17 @@ pub async fn serve(
             // Send packets to client required to get into workable play state for lobby world
             send_lobby_play_packets(client, &client_info, &mut writer, &server).await?;
 
-            // Wait for server to come online
-            stage_wait(client, &client_info, &server, &config, &mut writer).await?;
+            // Wait for server to come online, read packets client sends in the meantime
+            select! {
+                a = stage_wait(client, &client_info, &server, &config, &mut writer, ticket.as_ref()) => a,
+                b = read_client_packets(client, &client_info, &mut reader, &mut inbound_buf) => b,
+            }?;
 
             // Start new connection to server
             let server_client_info = client_info.clone();
             let (server_client, mut outbound, mut server_buf) =
-                connect_to_server(&server_client_info, &inbound, &config).await?;
+                connect_to_server(&server_client_info, &inbound, client.peer, &config, &server)
+                    .await?;
             let (returned_reader, returned_writer) = inbound.split();
             reader = returned_reader;
             writer = returned_writer;
@@ -164,7 +222,7 @@ pub async fn serve(
 
             // Client and server connection ready now, move client to proxy
             debug!(target: "lazymc::lobby", "Server connection ready, relaying lobby client to proxy");
-            route_proxy(inbound, outbound, server_buf);
+            route_proxy(inbound, client.peer, outbound, server_buf, config, permit);
 
             return Ok(());
         }
@@ -194,12 +252,30 @@ async fn respond_set_compression(
 // TODO: support online mode here
 async fn respond_login_success(
     client: &Client,
+    protocol: u32,
     writer: &mut WriteHalf<'_>,
     login_start: &LoginStart,
 ) -> Result<(), ()> {
+    let uuid = uuid::offline_player_uuid(&login_start.name);
+
+    // Since 1.20.5, login success has no properties and strict error handling until 1.21.2
+    if configuration::supports_transfer(protocol) {
+        let mut data = uuid.as_bytes().to_vec();
+        data.extend(types::encode_string(&login_start.name)?);
+        data.extend(types::encode_var_int(0)?);
+        if protocol < LOGIN_SUCCESS_STRICT_ERRORS_REMOVED {
+            data.push(0);
+        }
+        let packet = RawPacket::new(packets::login::CLIENT_LOGIN_SUCCESS, data);
+        return writer
+            .write_all(&packet.encode_with_len(client)?)
+            .await
+            .map_err(|_| ());
+    }
+
     packet::write_packet(
         LoginSuccess {
-            uuid: uuid::offline_player_uuid(&login_start.name),
+            uuid,
             username: login_start.name.clone(),
         },
         client,
@@ -257,22 +333,36 @@ async fn send_lobby_play_packets(
 /// An infinite keep-alive loop.
 ///
 /// This will keep sending keep-alive and title packets to the client until it is dropped.
+/// Queued clients are shown their position in the title. If enabled, the client is teleported
+/// back to its spawn position in between.
 async fn keep_alive_loop(
     client: &Client,
     client_info: &ClientInfo,
     writer: &mut WriteHalf<'_>,
     config: &Config,
+    ticket: Option<&Ticket>,
 ) -> Result<(), ()> {
     let mut interval = time::interval(KEEP_ALIVE_INTERVAL);
+    let mut position_interval = time::interval(POSITION_INTERVAL);
 
     loop {
-        interval.tick().await;
+        select! {
+            _ = interval.tick() => {}
+            _ = position_interval.tick(), if config.join.lobby.keep_alive_position => {
+                packets::play::player_pos::send(client, client_info, writer).await?;
+                continue;
+            }
+        }
 
         trace!(target: "lazymc::lobby", "Sending keep-alive sequence to lobby client");
 
         // Send keep alive and title packets
         packets::play::keep_alive::send(client, client_info, writer).await?;
-        packets::play::title::send(client, client_info, writer, &config.join.lobby.message).await?;
+        let message = match ticket.and_then(Ticket::position) {
+            Some((position, size)) => queue::position_message(&config.join.queue, position, size),
+            None => config.join.lobby.message.clone(),
+        };
+        packets::play::title::send(client, client_info, writer, &message).await?;
 
         // TODO: verify we receive correct keep alive response
     }
@@ -280,7 +370,7 @@ async fn keep_alive_loop(
 
 /// Waiting stage.
 ///
-/// In this stage we wait for the server to come online.
+/// In this stage we wait for the server to come online, and for queued clients to be admitted.
 ///
 /// During this stage we keep sending keep-alive and title packets to the client to keep it active.
 async fn stage_wait(
@@ -289,10 +379,140 @@ async fn stage_wait(
     server: &Server,
     config: &Config,
     writer: &mut WriteHalf<'_>,
+    ticket: Option<&Ticket>,
 ) -> Result<(), ()> {
+    let wait = async {
+        match ticket {
+            Some(ticket) => queue::wait_for_turn(server, config, ticket).await,
+            None => wait_for_server(server, config).await.map(|_| true),
+        }
+    };
+
+    let ready = select! {
+        a = keep_alive_loop(client, client_info, writer, config, ticket) => a.map(|_| true),
+        b = wait => b,
+    }?;
+
+    // Kick queued client if the server didn't come online in time
+    if !ready {
+        action::kick(client, &Rejection::Starting.message(config), writer).await?;
+        return Err(());
+    }
+
+    Ok(())
+}
+
+/// Read packets the lobby client sends while it waits.
+///
+/// Packets are compressed once compression is enabled for the client. Keep-alive responses are
+/// read, other packets are ignored.
+///
+/// Returns `Err(())` once the client disconnects.
+async fn read_client_packets(
+    client: &Client,
+    client_info: &ClientInfo,
+    reader: &mut ReadHalf<'_>,
+    buf: &mut BytesMut,
+) -> Result<(), ()> {
+    loop {
+        let (packet, _raw) = match packet::read_packet(client, buf, reader).await? {
+            Some(packet) => packet,
+            None => {
+                debug!(target: "lazymc::lobby", "Lobby client disconnected while waiting");
+                return Err(());
+            }
+        };
+
+        if let Some(id) = packets::play::keep_alive::decode_response(client_info, &packet) {
+            trace!(target: "lazymc::lobby", "Got keep-alive response from lobby client (id: {})", id);
+        }
+    }
+}
+
+/// Transfer stage.
+///
+/// Holds the client in the configuration state while waiting for the server to come online, and
+/// for queued clients to be admitted. Then transfers the client to the server, it reconnects
+/// through lazymc on its own.
+#[allow(clippy::too_many_arguments)]
+async fn stage_transfer(
+    client: &Client,
+    client_info: &ClientInfo,
+    server: &Server,
+    config: &Config,
+    reader: &mut ReadHalf<'_>,
+    buf: &mut BytesMut,
+    writer: &mut WriteHalf<'_>,
+    ticket: Option<&Ticket>,
+) -> Result<(), ()> {
+    // Wait for login acknowledgement, switches to configuration state
+    configuration::wait_for_packet(
+        client,
+        reader,
+        buf,
+        packets::login::SERVER_LOGIN_ACKNOWLEDGED,
+    )
+    .await?;
+    client.set_state(ClientState::Configuration);
+
+    // Keep client alive until server is ready, ignore packets client sends in the meantime
+    let keep_alive = async {
+        let mut interval = time::interval(KEEP_ALIVE_INTERVAL);
+        for id in 0.. {
+            interval.tick().await;
+            trace!(target: "lazymc::lobby", "Sending keep-alive to lobby client in configuration");
+            configuration::send_keep_alive(client, writer, id).await?;
+        }
+        Ok(())
+    };
+    let wait = async {
+        match ticket {
+            Some(ticket) => match queue::wait_for_turn(server, config, ticket).await? {
+                true => Ok(()),
+                false => Err(()),
+            },
+            None => wait_for_server(server, config).await,
+        }
+    };
+    let read = async {
+        while let Some((packet, _raw)) = packet::read_packet(client, buf, reader).await? {
+            trace!(target: "lazymc::lobby", "Ignoring packet 0x{:02X} from lobby client in configuration", packet.id);
+        }
+        debug!(target: "lazymc::lobby", "Lobby client disconnected while waiting");
+        Err(())
+    };
     select! {
-        a = keep_alive_loop(client, client_info, writer, config) => a,
-        b = wait_for_server(server, config) => b,
+        a = keep_alive => a,
+        b = wait => b,
+        c = read => c,
+    }?;
+
+    // Transfer client to the address it connected to
+    let (host, port) = transfer_address(client_info, config);
+    debug!(target: "lazymc::lobby", "Server ready, transferring lobby client to {}:{}", host, port);
+    configuration::send_transfer(client, writer, &host, port).await
+}
+
+/// Get address to transfer the lobby client to.
+///
+/// Uses the address from the client handshake, as the client reached lazymc through it, falls
+/// back to the public address.
+fn transfer_address(client_info: &ClientInfo, config: &Config) -> (String, u16) {
+    match client_info.handshake.as_ref() {
+        // Strip Forge markers from host
+        Some(handshake) => (
+            handshake
+                .server_addr
+                .split('\0')
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            handshake.server_port,
+        ),
+        None => (
+            config.public.address.ip().to_string(),
+            config.public.address.port(),
+        ),
     }
 }
 
@@ -323,7 +543,7 @@ async fn wait_for_server(server: &Server, config: &Config) -> Result<(), ()> {
                 }
 
                 // Server stopping, this shouldn't happen, kick
-                State::Stopping | State::Stopped => {
+                State::Stopping | State::Stopped | State::Frozen => {
                     break false;
                 }
             }
@@ -357,11 +577,13 @@ async fn wait_for_server(server: &Server, config: &Config) -> Result<(), ()> {
 async fn connect_to_server(
     client_info: &ClientInfo,
     inbound: &TcpStream,
+    peer: SocketAddr,
     config: &Config,
+    server: &Server,
 ) -> Result<(Client, TcpStream, BytesMut), ()> {
     time::timeout(
         SERVER_CONNECT_TIMEOUT,
-        connect_to_server_no_timeout(client_info, inbound, config),
+        connect_to_server_no_timeout(client_info, inbound, peer, config, server),
     )
     .await
     .map_err(|_| {
@@ -376,19 +598,25 @@ async fn connect_to_server(
 async fn connect_to_server_no_timeout(
     client_info: &ClientInfo,
     inbound: &TcpStream,
+    peer: SocketAddr,
     config: &Config,
+    server: &Server,
 ) -> Result<(Client, TcpStream, BytesMut), ()> {
     // Open connection
     // TODO: on connect fail, ping server and redirect to serve_status if offline
-    let mut outbound = TcpStream::connect(config.server.address)
+    let addr = net::resolve(config, &config.server.address)
         .await
-        .map_err(|_| ())?;
+        .map_err(|_| ())?
+        .ok_or_else(|| {
+        error!(target: "lazymc::lobby", "Lobby connecting to server over Unix socket is not supported");
+    })?;
+    let mut outbound = net::connect(config, addr).await.map_err(|_| ())?;
 
     // Add proxy header
     if config.server.send_proxy_v2 {
         trace!(target: "lazymc::lobby", "Sending client proxy header for server connection");
         outbound
-            .write_all(&proxy::stream_proxy_header(inbound).map_err(|_| ())?)
+            .write_all(&proxy::stream_proxy_header(inbound, peer).map_err(|_| ())?)
             .await
             .map_err(|_| ())?;
     }
@@ -470,7 +698,7 @@ async fn connect_to_server_no_timeout(
         {
             error!(
                 target: "lazymc::lobby",
-                "Got encryption request from server, this is unsupported. Server must be in offline mode to use lobby.",
+                "Got encryption request from server, this is unsupported. Server must be in offline mode to use lobby, set server.online_mode = true in lazymc config to skip lobby.",
             );
 
             break;
@@ -487,7 +715,7 @@ async fn connect_to_server_no_timeout(
                 })?;
 
             // Respond with Forge messages
-            if config.server.forge {
+            if server.is_forge(config) {
                 trace!(target: "lazymc::lobby", "Got login plugin request from server, responding with Forge reply");
 
                 // Respond to Forge login plugin request
@@ -630,18 +858,34 @@ async fn wait_for_server_join_game_no_timeout(
 /// Route our lobby client through the proxy to the real server, spawning a new task.
 ///
 /// `inbound_queue` is used for data already received from the server, that needs to be pushed to
-/// the client.
+/// the client. The given connection permit is held until the spawned task completes.
 #[inline]
-pub fn route_proxy(inbound: TcpStream, outbound: TcpStream, inbound_queue: BytesMut) {
+pub fn route_proxy(
+    inbound: TcpStream,
+    peer: SocketAddr,
+    outbound: TcpStream,
+    inbound_queue: BytesMut,
+    config: Arc<Config>,
+    permit: Permit,
+) {
     // When server is online, proxy all
     let service = async move {
-        proxy::proxy_inbound_outbound_with_queue(inbound, outbound, &inbound_queue, &[])
-            .map(|r| {
-                if let Err(err) = r {
-                    warn!(target: "lazymc", "Failed to proxy: {}", err);
-                }
-            })
-            .await
+        let _permit = permit;
+        let connection = proxy::Connection::new(peer, &config.server.address);
+        proxy::proxy_inbound_outbound_with_queue(
+            &config,
+            connection,
+            inbound,
+            outbound,
+            &inbound_queue,
+            &[],
+        )
+        .map(|r| {
+            if let Err(err) = r {
+                warn!(target: "lazymc", "Failed to proxy: {}", err);
+            }
+        })
+        .await
     };
 
     tokio::spawn(service);
@@ -662,3 +906,153 @@ async fn drain_stream(reader: &mut ReadHalf<'_>) -> Result<(), ()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::version::v1_14_4::handshake::Handshake;
+    use minecraft_protocol::version::v1_17;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn test_supports_protocol() {
+        let config: Config = crate::util::test::config("");
+        assert!(supports_protocol(&config, 763));
+        assert!(!supports_protocol(&config, 764));
+        assert!(!supports_protocol(&config, 766));
+
+        // Clients that can be transferred are held in configuration state
+        let config: Config = crate::util::test::config("[join.lobby]\ntransfer_on_ready = true");
+        assert!(!supports_protocol(&config, 765));
+        assert!(supports_protocol(&config, 766));
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive_position() {
+        let config: Config = crate::util::test::config("[join.lobby]\nkeep_alive_position = true");
+        let client_info = ClientInfo {
+            protocol: Some(v1_17::PROTOCOL),
+            ..ClientInfo::empty()
+        };
+        let client = Client::dummy();
+        client.set_state(ClientState::Play);
+        let (mut player, mut lobby) = crate::util::test::stream_pair().await;
+
+        // Run lobby keep alive loop for a few position intervals
+        {
+            let (_, mut writer) = lobby.split();
+            let run = keep_alive_loop(&client, &client_info, &mut writer, &config, None);
+            assert!(time::timeout(POSITION_INTERVAL * 5 / 2, run).await.is_err());
+        }
+        drop(lobby);
+
+        // Collect all packets the player received
+        let (mut reader, _) = player.split();
+        let mut buf = BytesMut::new();
+        let (mut keep_alives, mut teleports) = (0, vec![]);
+        while let Some((packet, _)) = packet::read_packet(&client, &mut buf, &mut reader)
+            .await
+            .unwrap()
+        {
+            if packet.id == v1_17::game::ClientBoundKeepAlive::PACKET_ID {
+                keep_alives += 1;
+            } else if packet.id == v1_17::game::PlayerPositionAndLook::PACKET_ID {
+                teleports.push(
+                    v1_17::game::PlayerPositionAndLook::decode(&mut packet.data.as_slice())
+                        .unwrap(),
+                );
+            }
+        }
+        assert_eq!(keep_alives, 1);
+        assert_eq!(teleports.len(), 3);
+
+        // Player is kept at a fixed location, each teleport with a new ID
+        for teleport in &teleports {
+            assert_eq!((teleport.x, teleport.y, teleport.z), (0.0, 0.0, 0.0));
+            assert_eq!((teleport.yaw, teleport.pitch), (0.0, 90.0));
+        }
+        assert!(teleports
+            .windows(2)
+            .all(|t| t[0].teleport_id < t[1].teleport_id));
+    }
+
+    #[tokio::test]
+    async fn test_transfer_on_ready() {
+        let config: Config = crate::util::test::config("[join.lobby]\ntransfer_on_ready = true");
+        let (server, _) = Server::new();
+        server.update_state(State::Starting, &config).await;
+
+        // 1.20.5 client that connected through a public host name
+        let client_info = ClientInfo {
+            handshake: Some(Handshake {
+                protocol_version: 766,
+                server_addr: "mc.example.com".into(),
+                server_port: 25565,
+                next_state: 2,
+            }),
+            ..ClientInfo::empty()
+        };
+        let lobby_client = Client::dummy();
+        lobby_client.set_state(ClientState::Login);
+        let player_client = Client::dummy();
+        player_client.set_state(ClientState::Configuration);
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let connect = TcpStream::connect(listener.local_addr().unwrap());
+        let (player, accepted) = tokio::join!(connect, listener.accept());
+        let (mut player, mut inbound) = (player.unwrap(), accepted.unwrap().0);
+
+        let lobby = async {
+            let (mut reader, mut writer) = inbound.split();
+            stage_transfer(
+                &lobby_client,
+                &client_info,
+                &server,
+                &config,
+                &mut reader,
+                &mut BytesMut::new(),
+                &mut writer,
+                None,
+            )
+            .await
+        };
+        let join = async {
+            let (mut reader, mut writer) = player.split();
+            let mut buf = BytesMut::new();
+            let ack = RawPacket::new(packets::login::SERVER_LOGIN_ACKNOWLEDGED, vec![]);
+            writer
+                .write_all(&ack.encode_with_len(&player_client).unwrap())
+                .await
+                .unwrap();
+
+            // Kept alive in configuration until the server is ready
+            let (packet, _raw) = packet::read_packet(&player_client, &mut buf, &mut reader)
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(packet.id, configuration::CLIENT_KEEP_ALIVE_V1_20_5);
+            server.update_state(State::Started, &config).await;
+
+            loop {
+                let (packet, _raw) = packet::read_packet(&player_client, &mut buf, &mut reader)
+                    .await
+                    .unwrap()
+                    .unwrap();
+                if packet.id == configuration::CLIENT_TRANSFER {
+                    break packet.data;
+                }
+            }
+        };
+        let (transferred, data) =
+            time::timeout(Duration::from_secs(5), async { tokio::join!(lobby, join) })
+                .await
+                .unwrap();
+        assert!(transferred.is_ok());
+
+        // Transferred to the host and port the client connected to
+        let mut expected = types::encode_string("mc.example.com").unwrap();
+        expected.extend(types::encode_var_int(25565).unwrap());
+        assert_eq!(data, expected);
+    }
+}
@@ -0,0 +1,95 @@
+use std::env;
+use std::sync::RwLock;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Global logger, installed once and reconfigurable afterwards.
+static LOGGER: Logger = Logger {
+    inner: RwLock::new(None),
+};
+
+/// Logger that can be reconfigured after it is installed.
+///
+/// The log filter from the config is only known after the logger is initialized, as loading the
+/// config logs itself.
+struct Logger {
+    inner: RwLock<Option<Box<dyn Log>>>,
+}
+
+impl Logger {
+    /// Replace the inner logger, and update the global maximum log level to match.
+    fn replace(&self, (logger, level): (Box<dyn Log>, LevelFilter)) {
+        self.inner.write().unwrap().replace(logger);
+        log::set_max_level(level);
+    }
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        match &*self.inner.read().unwrap() {
+            Some(logger) => logger.enabled(metadata),
+            None => false,
+        }
+    }
+
+    fn log(&self, record: &Record) {
+        if let Some(logger) = &*self.inner.read().unwrap() {
+            logger.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(logger) = &*self.inner.read().unwrap() {
+            logger.flush();
+        }
+    }
+}
+
+/// Initialize global logger, filtered through `RUST_LOG`.
+pub fn init() {
+    LOGGER.replace(build(&env::var("RUST_LOG").unwrap_or_default()));
+    log::set_logger(&LOGGER).expect("failed to initialize logger");
+}
+
+/// Set log filter, such as `lazymc::monitor=debug,lazymc=info`.
+///
+/// Takes precedence over `RUST_LOG`.
+pub fn set_filter(filter: &str) {
+    LOGGER.replace(build(filter));
+}
+
+/// Build logger with the given filter.
+fn build(filter: &str) -> (Box<dyn Log>, LevelFilter) {
+    let logger = pretty_env_logger::formatted_builder()
+        .parse_filters(filter)
+        .build();
+    let level = logger.filter();
+    (Box::new(logger), level)
+}
+
+#[cfg(test)]
+mod tests {
+    use log::Level;
+
+    use super::*;
+
+    /// Whether the logger logs at the given level for the given target.
+    fn enabled(logger: &dyn Log, target: &str, level: Level) -> bool {
+        logger.enabled(&Metadata::builder().target(target).level(level).build())
+    }
+
+    #[test]
+    fn test_parse_filter() {
+        let (logger, level) = build("lazymc::monitor=debug,lazymc=info");
+        assert_eq!(level, LevelFilter::Debug);
+
+        assert!(enabled(&*logger, "lazymc::monitor", Level::Debug));
+        assert!(!enabled(&*logger, "lazymc::monitor", Level::Trace));
+        assert!(enabled(&*logger, "lazymc", Level::Info));
+        assert!(!enabled(&*logger, "lazymc", Level::Debug));
+        assert!(!enabled(&*logger, "lazymc::config", Level::Debug));
+
+        // Targets not listed are filtered out
+        assert!(!enabled(&*logger, "tokio", Level::Error));
+    }
+}
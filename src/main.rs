@@ -10,17 +10,22 @@ extern crate log;
 pub(crate) mod action;
 pub(crate) mod cli;
 pub(crate) mod config;
+pub(crate) mod event;
 pub(crate) mod forge;
 pub(crate) mod join;
 #[cfg(feature = "lobby")]
 pub(crate) mod lobby;
+pub(crate) mod logger;
 pub(crate) mod mc;
 pub(crate) mod monitor;
 pub(crate) mod net;
 pub(crate) mod os;
+pub(crate) mod persist;
 pub(crate) mod probe;
 pub(crate) mod proto;
 pub(crate) mod proxy;
+pub(crate) mod rejection;
+pub(crate) mod runtime;
 pub(crate) mod server;
 pub(crate) mod service;
 pub(crate) mod status;
@@ -59,7 +64,7 @@ fn init_log() {
     }
 
     // Initialize logger
-    pretty_env_logger::init();
+    logger::init();
 }
 
 /// Invoke an action.
@@ -78,9 +83,19 @@ fn invoke_action(app: Command) -> Result<(), ()> {
             return Ok(());
         }
 
+        if let Some(matches) = matches.subcommand_matches("show") {
+            action::config_show::invoke(matches);
+            return Ok(());
+        }
+
         unreachable!();
     }
 
+    // Health check
+    if let Some(matches) = matches.subcommand_matches("health") {
+        return action::health::invoke(matches);
+    }
+
     // Start server
     action::start::invoke(&matches)
 }
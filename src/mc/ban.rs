@@ -7,9 +7,14 @@ use std::path::Path;
 use chrono::{DateTime, Utc};
 use serde::Deserialize;
 
+use crate::mc::uuid;
+
 /// File name.
 pub const FILE: &str = "banned-ips.json";
 
+/// Banned players file name.
+pub const PLAYERS_FILE: &str = "banned-players.json";
+
 /// The forever expiry literal.
 const EXPIRY_FOREVER: &str = "forever";
 
@@ -62,30 +67,95 @@ pub struct BannedIp {
 impl BannedIp {
     /// Check if this entry is currently banned.
     pub fn is_banned(&self) -> bool {
-        // Get expiry time
-        let expires = match &self.expires {
-            Some(expires) => expires,
-            None => return true,
-        };
-
-        // If expiry is forever, the user is banned
-        if expires.trim().to_lowercase() == EXPIRY_FOREVER {
-            return true;
-        }
+        is_active(self.expires.as_deref())
+    }
+}
 
-        // Parse expiry time, check if it has passed
-        let expiry = match DateTime::parse_from_str(expires, "%Y-%m-%d %H:%M:%S %z") {
-            Ok(expiry) => expiry,
-            Err(err) => {
-                error!(target: "lazymc", "Failed to parse ban expiry '{}', assuming still banned: {}", expires, err);
-                return true;
-            }
-        };
+/// List of banned players.
+#[derive(Debug, Default)]
+pub struct BannedPlayers {
+    /// List of banned players.
+    players: Vec<BannedPlayer>,
+}
 
-        expiry > Utc::now()
+impl BannedPlayers {
+    /// Get active ban entry for the given username if it exists.
+    ///
+    /// Matches the username case-insensitively like the server does, or the UUID the player has
+    /// on an offline mode server. Players on an online mode server are matched by username.
+    pub fn get(&self, username: &str) -> Option<BannedPlayer> {
+        let offline_uuid = uuid::offline_player_uuid(username).to_string();
+        self.players
+            .iter()
+            .filter(|player| player.is_banned())
+            .find(|player| {
+                player.name.eq_ignore_ascii_case(username)
+                    || player
+                        .uuid
+                        .as_deref()
+                        .map(|uuid| uuid.eq_ignore_ascii_case(&offline_uuid))
+                        .unwrap_or(false)
+            })
+            .cloned()
     }
 }
 
+/// A banned player entry.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BannedPlayer {
+    /// Banned player UUID.
+    pub uuid: Option<String>,
+
+    /// Banned player username.
+    pub name: String,
+
+    /// Ban creation time.
+    #[allow(dead_code)]
+    pub created: Option<String>,
+
+    /// Ban source.
+    #[allow(dead_code)]
+    pub source: Option<String>,
+
+    /// Ban expiry time.
+    pub expires: Option<String>,
+
+    /// Ban reason.
+    pub reason: Option<String>,
+}
+
+impl BannedPlayer {
+    /// Check if this entry is currently banned.
+    pub fn is_banned(&self) -> bool {
+        is_active(self.expires.as_deref())
+    }
+}
+
+/// Check if a ban with the given expiry time is currently active.
+fn is_active(expires: Option<&str>) -> bool {
+    // Get expiry time
+    let expires = match expires {
+        Some(expires) => expires,
+        None => return true,
+    };
+
+    // If expiry is forever, the user is banned
+    if expires.trim().to_lowercase() == EXPIRY_FOREVER {
+        return true;
+    }
+
+    // Parse expiry time, check if it has passed
+    let expiry = match DateTime::parse_from_str(expires, "%Y-%m-%d %H:%M:%S %z") {
+        Ok(expiry) => expiry,
+        Err(err) => {
+            error!(target: "lazymc", "Failed to parse ban expiry '{}', assuming still banned: {}", expires, err);
+            return true;
+        }
+    };
+
+    expiry > Utc::now()
+}
+
 /// Load banned IPs from file.
 pub fn load(path: &Path) -> Result<BannedIps, Box<dyn Error>> {
     // Load file contents
@@ -99,3 +169,45 @@ pub fn load(path: &Path) -> Result<BannedIps, Box<dyn Error>> {
     let ips = ips.into_iter().map(|ip| (ip.ip, ip)).collect();
     Ok(BannedIps { ips })
 }
+
+/// Load banned players from file.
+pub fn load_players(path: &Path) -> Result<BannedPlayers, Box<dyn Error>> {
+    // Load file contents
+    let contents = fs::read_to_string(path)?;
+
+    // Parse contents
+    let players: Vec<BannedPlayer> = serde_json::from_str(&contents)?;
+    debug!(target: "lazymc", "Loaded {} banned players", players.len());
+
+    Ok(BannedPlayers { players })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_banned_players() {
+        let players: Vec<BannedPlayer> = serde_json::from_str(&format!(
+            r#"[
+                {{"uuid": null, "name": "Griefer", "expires": "forever", "reason": "griefing"}},
+                {{"uuid": "{}", "name": "OldName"}},
+                {{"uuid": null, "name": "Pardoned", "expires": "2000-01-01 00:00:00 +0000"}}
+            ]"#,
+            uuid::offline_player_uuid("NewName"),
+        ))
+        .unwrap();
+        let players = BannedPlayers { players };
+
+        // Usernames match case-insensitively
+        let ban = players.get("griefer").unwrap();
+        assert_eq!(ban.reason.as_deref(), Some("griefing"));
+
+        // Offline mode entries match by UUID, even if the listed name differs
+        assert!(players.get("NewName").is_some());
+
+        // Expired bans and other players are not banned
+        assert!(players.get("Pardoned").is_none());
+        assert!(players.get("Steve").is_none());
+    }
+}
@@ -1,3 +1,5 @@
+use std::io;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use rust_rcon::{Connection, Error as RconError};
@@ -6,7 +8,9 @@ use tokio::net::TcpStream;
 use tokio::time;
 
 use crate::config::Config;
+use crate::net;
 use crate::proxy;
+use crate::util::text::strip_formatting;
 
 /// Minecraft RCON quirk.
 ///
@@ -18,17 +22,34 @@ const QUIRK_RCON_GRACE_TIME: Duration = Duration::from_millis(200);
 /// An RCON client.
 pub struct Rcon {
     con: Connection<TcpStream>,
+
+    /// Command response timeout.
+    timeout: Duration,
 }
 
 impl Rcon {
     /// Connect to a host.
+    ///
+    /// Times out after the configured connect timeout.
     pub async fn connect(
         config: &Config,
-        addr: &str,
+        addr: SocketAddr,
+        pass: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let timeout = Duration::from_secs(config.rcon.connect_timeout as u64);
+        match time::timeout(timeout, Self::connect_inner(config, addr, pass)).await {
+            Ok(result) => result,
+            Err(_) => Err(timed_out("RCON connect timed out").into()),
+        }
+    }
+
+    async fn connect_inner(
+        config: &Config,
+        addr: SocketAddr,
         pass: &str,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         // Connect to our TCP stream
-        let mut stream = TcpStream::connect(addr).await?;
+        let mut stream = net::connect(config, addr).await?;
 
         // Add proxy header
         if config.rcon.send_proxy_v2 {
@@ -42,27 +63,45 @@ impl Rcon {
             .handshake(stream, pass)
             .await?;
 
-        Ok(Self { con })
+        Ok(Self {
+            con,
+            timeout: Duration::from_secs(config.rcon.command_timeout as u64),
+        })
     }
 
     /// Connect to a host from the given configuration.
+    ///
+    /// Retries once if connecting fails.
     pub async fn connect_config(config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
-        // RCON address
-        let mut addr = config.server.address;
+        // RCON address, on localhost if connecting to server over Unix socket
+        let mut addr = net::resolve(config, &config.server.address)
+            .await?
+            .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], 0)));
         addr.set_port(config.rcon.port);
-        let addr = addr.to_string();
 
-        Self::connect(config, &addr, &config.rcon.password).await
+        let err = match Self::connect(config, addr, &config.rcon.password).await {
+            Ok(rcon) => return Ok(rcon),
+            Err(err) => err.to_string(),
+        };
+
+        debug!(target: "lazymc::rcon", "Failed to connect to RCON, retrying: {}", err);
+        time::sleep(QUIRK_RCON_GRACE_TIME).await;
+        Self::connect(config, addr, &config.rcon.password).await
     }
 
     /// Send command over RCON.
+    ///
+    /// Times out after the configured command timeout.
     pub async fn cmd(&mut self, cmd: &str) -> Result<String, RconError> {
         // Minecraft quirk
         time::sleep(QUIRK_RCON_GRACE_TIME).await;
 
         // Actually send RCON command
         debug!(target: "lazymc::rcon", "Sending RCON: {}", cmd);
-        self.con.cmd(cmd).await
+        match time::timeout(self.timeout, self.con.cmd(cmd)).await {
+            Ok(result) => result,
+            Err(_) => Err(RconError::Io(timed_out("RCON command timed out"))),
+        }
     }
 
     /// Close connection.
@@ -71,3 +110,273 @@ impl Rcon {
         time::sleep(QUIRK_RCON_GRACE_TIME).await;
     }
 }
+
+/// Build a timed out IO error.
+fn timed_out(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, msg)
+}
+
+/// Online players, as reported by the RCON `list` command.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlayerList {
+    /// Number of online players.
+    pub online: u32,
+
+    /// Maximum number of players, if reported.
+    pub max: Option<u32>,
+
+    /// Names of online players.
+    pub names: Vec<String>,
+}
+
+impl PlayerList {
+    /// Parse `list` command response.
+    ///
+    /// Handles the vanilla format "There are X of a max of Y players online: a, b", the older
+    /// "There are X/Y players online:" format, and Bukkit/Essentials style responses listing
+    /// players on following lines, optionally prefixed by a group name such as "Default: a, b".
+    /// Formatting codes are ignored.
+    ///
+    /// Returns `None` if no player count was found.
+    pub fn parse(response: &str) -> Option<Self> {
+        let response = strip_formatting(response);
+
+        // Header holds the counts, player names follow after the first colon or line break
+        let (header, names) = match response.find([':', '\n']) {
+            Some(i) => (&response[..i], &response[i + 1..]),
+            None => (response.as_str(), ""),
+        };
+
+        let mut numbers = header
+            .split(|c: char| !c.is_ascii_digit())
+            .filter_map(|n| n.parse::<u32>().ok());
+        let online = numbers.next()?;
+        let max = numbers.next();
+
+        let names = names
+            .lines()
+            .map(|line| line.rsplit(':').next().unwrap_or(line))
+            .flat_map(|line| line.split(','))
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| name.to_string())
+            .collect();
+
+        Some(Self { online, max, names })
+    }
+}
+
+/// Player position, as reported by the RCON `data get entity <player> Pos` command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Position {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Position {
+    /// Parse `data get entity <player> Pos` command response.
+    ///
+    /// Handles the vanilla format "Steve has the following entity data: [12.5d, 64.0d, -3.25d]".
+    ///
+    /// Returns `None` if no position was found, such as when the player is not online.
+    pub fn parse(response: &str) -> Option<Self> {
+        let response = strip_formatting(response);
+        let start = response.rfind('[')?;
+        let end = start + response[start..].find(']')?;
+
+        let mut coords = response[start + 1..end]
+            .split(',')
+            .map(|coord| coord.trim().trim_end_matches(['d', 'D']).parse::<f64>());
+        let position = Self {
+            x: coords.next()?.ok()?,
+            y: coords.next()?.ok()?,
+            z: coords.next()?.ok()?,
+        };
+        if coords.next().is_some() {
+            return None;
+        }
+        Some(position)
+    }
+
+    /// Distance to the given position, in blocks.
+    pub fn distance(&self, other: &Position) -> f64 {
+        ((self.x - other.x).powi(2) + (self.y - other.y).powi(2) + (self.z - other.z).powi(2))
+            .sqrt()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Mock RCON server, responding to `list` with one player online.
+    pub(crate) struct MockServer {
+        /// Port the server listens on.
+        pub port: u16,
+
+        /// Number of authentication requests handled.
+        pub auths: Arc<AtomicUsize>,
+
+        /// Number of commands handled.
+        pub commands: Arc<AtomicUsize>,
+    }
+
+    impl MockServer {
+        /// Spawn mock server, delaying each command response by the given time.
+        pub(crate) async fn spawn(delay: Duration) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let auths = Arc::new(AtomicUsize::new(0));
+            let commands = Arc::new(AtomicUsize::new(0));
+
+            let (a, c) = (auths.clone(), commands.clone());
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    tokio::spawn(Self::serve(stream, a.clone(), c.clone(), delay));
+                }
+            });
+
+            Self {
+                port,
+                auths,
+                commands,
+            }
+        }
+
+        async fn serve(
+            mut stream: TcpStream,
+            auths: Arc<AtomicUsize>,
+            commands: Arc<AtomicUsize>,
+            delay: Duration,
+        ) -> std::io::Result<()> {
+            loop {
+                let len = stream.read_i32_le().await?;
+                let id = stream.read_i32_le().await?;
+                let kind = stream.read_i32_le().await?;
+                let mut body = vec![0; len as usize - 8];
+                stream.read_exact(&mut body).await?;
+
+                let response = match kind {
+                    3 => {
+                        auths.fetch_add(1, Ordering::SeqCst);
+                        (2i32, "")
+                    }
+                    _ => {
+                        commands.fetch_add(1, Ordering::SeqCst);
+                        time::sleep(delay).await;
+                        (0, "There are 1 of a max of 20 players online: Steve")
+                    }
+                };
+
+                let mut packet = Vec::new();
+                packet.extend_from_slice(&(10 + response.1.len() as i32).to_le_bytes());
+                packet.extend_from_slice(&id.to_le_bytes());
+                packet.extend_from_slice(&response.0.to_le_bytes());
+                packet.extend_from_slice(response.1.as_bytes());
+                packet.extend_from_slice(&[0, 0]);
+                stream.write_all(&packet).await?;
+            }
+        }
+    }
+
+    /// Build config with short timeouts, connecting to given RCON port.
+    fn config(port: u16) -> Config {
+        crate::util::test::config(&format!(
+            "address = \"127.0.0.1:25566\"\n[rcon]\nport = {}\nconnect_timeout = 1\ncommand_timeout = 1",
+            port,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_command_timeout() {
+        let mock = MockServer::spawn(Duration::from_secs(3)).await;
+        let mut rcon = Rcon::connect_config(&config(mock.port)).await.unwrap();
+
+        let err = rcon.cmd("list").await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            RconError::Io(timed_out("RCON command timed out")).to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connect_timeout_retries() {
+        // Accept connections but never respond to authentication
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        tokio::spawn(async move {
+            let mut streams = vec![];
+            while let Ok((stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                streams.push(stream);
+            }
+        });
+
+        let err = Rcon::connect_config(&config(port)).await.err().unwrap();
+        assert_eq!(err.to_string(), "RCON connect timed out");
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_parse_position() {
+        let position =
+            Position::parse("Steve has the following entity data: [12.5d, 64.0d, -3.25d]").unwrap();
+        assert_eq!(
+            position,
+            Position {
+                x: 12.5,
+                y: 64.0,
+                z: -3.25
+            }
+        );
+        assert_eq!(Position::parse("No entity was found"), None);
+        assert_eq!(
+            Position::parse("Steve has the following entity data: [1.0d, 2.0d]"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_list_vanilla() {
+        let list =
+            PlayerList::parse("There are 2 of a max of 20 players online: Steve, Alex").unwrap();
+        assert_eq!(list.online, 2);
+        assert_eq!(list.max, Some(20));
+        assert_eq!(list.names, ["Steve", "Alex"]);
+
+        let list = PlayerList::parse("There are 1/20 players online:\nSteve").unwrap();
+        assert_eq!(list.online, 1);
+        assert_eq!(list.max, Some(20));
+        assert_eq!(list.names, ["Steve"]);
+    }
+
+    #[test]
+    fn test_parse_list_paper() {
+        let list = PlayerList::parse(
+            "§6There are §c3§6 out of maximum §c50§6 players online.\n§6Default§r: Steve, Alex\n§6Admins§r: Notch",
+        )
+        .unwrap();
+        assert_eq!(list.online, 3);
+        assert_eq!(list.max, Some(50));
+        assert_eq!(list.names, ["Steve", "Alex", "Notch"]);
+    }
+
+    #[test]
+    fn test_parse_list_empty() {
+        let list = PlayerList::parse("There are 0 of a max of 20 players online: ").unwrap();
+        assert_eq!(list.online, 0);
+        assert_eq!(list.max, Some(20));
+        assert!(list.names.is_empty());
+
+        assert_eq!(PlayerList::parse("Unknown command"), None);
+    }
+}
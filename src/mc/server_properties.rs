@@ -5,9 +5,52 @@ use std::path::Path;
 /// File name.
 pub const FILE: &str = "server.properties";
 
-/// EOL in server.properties file.
+/// Backup file extension, appended to the file name.
+const BACKUP_EXT: &str = ".bak";
+
+/// Default EOL in server.properties file, used if the file has no line endings yet.
 const EOL: &str = "\r\n";
 
+/// Server properties lazymc uses for wake decisions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Properties {
+    /// Whether the server whitelist is enabled.
+    pub white_list: bool,
+
+    /// Maximum number of players, if set.
+    pub max_players: Option<u32>,
+
+    /// Whether the server authenticates players with Mojang, if set.
+    pub online_mode: Option<bool>,
+}
+
+impl Properties {
+    /// Parse properties from server.properties file contents.
+    pub fn parse(contents: &str) -> Self {
+        Self {
+            white_list: find_property(contents, "white-list")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            max_players: find_property(contents, "max-players").and_then(|v| v.parse().ok()),
+            online_mode: find_property(contents, "online-mode").and_then(|v| v.parse().ok()),
+        }
+    }
+
+    /// Load properties from the given server.properties file.
+    ///
+    /// Returns `None` if the file could not be read.
+    pub fn load<P: AsRef<Path>>(file: P) -> Option<Self> {
+        fs::read_to_string(file)
+            .ok()
+            .map(|contents| Self::parse(&contents))
+    }
+
+    /// Whether the server accepts no players at all, because `max-players` is 0.
+    pub fn is_closed(&self) -> bool {
+        self.max_players == Some(0)
+    }
+}
+
 /// Try to rewrite changes in server.properties file in dir.
 ///
 /// Prints an error and stops on failure.
@@ -73,6 +116,20 @@ pub fn rewrite_file<P: AsRef<Path>>(file: P, changes: HashMap<&str, String>) {
         }
     };
 
+    // Back up original file before changing it, keep any existing backup of the original
+    let mut backup = file.as_ref().as_os_str().to_owned();
+    backup.push(BACKUP_EXT);
+    if Path::new(&backup).exists() {
+        trace!(target: "lazymc", "Not backing up {} file, backup already exists", FILE);
+    } else if let Err(err) = fs::copy(&file, &backup) {
+        error!(target: "lazymc",
+            "Failed to rewrite {} file, could not create backup: {}",
+            FILE,
+            err,
+        );
+        return;
+    }
+
     // Write changes
     match fs::write(file, contents) {
         Ok(_) => {
@@ -93,6 +150,9 @@ pub fn rewrite_file<P: AsRef<Path>>(file: P, changes: HashMap<&str, String>) {
 
 /// Rewrite file contents with new properties.
 ///
+/// Only the given properties are touched, all other lines including comments are preserved as-is.
+/// The line endings of the original file are kept.
+///
 /// Returns new file contents if anything has changed.
 fn rewrite_contents(contents: String, mut changes: HashMap<&str, String>) -> Option<String> {
     if changes.is_empty() {
@@ -100,6 +160,7 @@ fn rewrite_contents(contents: String, mut changes: HashMap<&str, String>) -> Opt
     }
 
     let mut changed = false;
+    let eol = detect_eol(&contents);
 
     // Build new file
     let mut new_contents: String = contents
@@ -130,14 +191,24 @@ fn rewrite_contents(contents: String, mut changes: HashMap<&str, String>) -> Opt
             line
         })
         .collect::<Vec<_>>()
-        .join(EOL);
+        .join(eol);
 
-    // Append any missed changes
+    // Append any missed changes, sorted for stable output
+    let mut changes: Vec<_> = changes.into_iter().collect();
+    changes.sort();
     for (key, value) in changes {
-        new_contents += &format!("{EOL}{key}={value}");
+        if !new_contents.is_empty() {
+            new_contents += eol;
+        }
+        new_contents += &format!("{key}={value}");
         changed = true;
     }
 
+    // Keep trailing line ending
+    if contents.ends_with('\n') {
+        new_contents += eol;
+    }
+
     // Return new contents if changed
     if changed {
         Some(new_contents)
@@ -146,36 +217,120 @@ fn rewrite_contents(contents: String, mut changes: HashMap<&str, String>) -> Opt
     }
 }
 
-/// Read the given property from the given server.properties file.o
-///
-/// Returns `None` if file does not contain the property.
-pub fn read_property<P: AsRef<Path>>(file: P, property: &str) -> Option<String> {
-    // File must exist
-    if !file.as_ref().is_file() {
-        warn!(target: "lazymc",
-            "Failed to read property from {} file, it does not exist",
-            FILE,
-        );
-        return None;
+/// Detect line ending used in file contents.
+fn detect_eol(contents: &str) -> &'static str {
+    match contents.find('\n') {
+        Some(i) if i > 0 && contents.as_bytes()[i - 1] == b'\r' => "\r\n",
+        Some(_) => "\n",
+        None => EOL,
     }
+}
 
-    // Read contents
-    let contents = match fs::read_to_string(&file) {
-        Ok(contents) => contents,
-        Err(err) => {
-            error!(target: "lazymc",
-                "Failed to read property from {} file, could not load: {}",
-                FILE,
-                err,
-            );
-            return None;
-        }
-    };
-
-    // Find property, return value
+/// Find the given property in server.properties file contents.
+fn find_property(contents: &str, property: &str) -> Option<String> {
     contents
         .lines()
         .filter_map(|line| line.split_once('='))
         .find(|(p, _)| p.trim().to_lowercase() == property.to_lowercase())
         .map(|(_, v)| v.trim().to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_contents_selective() {
+        let contents = "#Minecraft server properties\n\
+                        #Mon Jan 01 00:00:00 UTC 2024\n\
+                        motd=My server\n\
+                        server-port=25565\n\
+                        # custom comment\n\
+                        view-distance=12\n\
+                        server-ip=\n";
+        let changes = HashMap::from([
+            ("server-ip", "127.0.0.1".to_string()),
+            ("server-port", "25566".to_string()),
+            ("enable-rcon", "true".to_string()),
+        ]);
+
+        let new = rewrite_contents(contents.into(), changes).unwrap();
+        assert_eq!(
+            new,
+            "#Minecraft server properties\n\
+             #Mon Jan 01 00:00:00 UTC 2024\n\
+             motd=My server\n\
+             server-port=25566\n\
+             # custom comment\n\
+             view-distance=12\n\
+             server-ip=127.0.0.1\n\
+             enable-rcon=true\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let properties = Properties::parse("white-list=true\nmax-players=20\nonline-mode=false\n");
+        assert!(properties.white_list);
+        assert_eq!(properties.max_players, Some(20));
+        assert_eq!(properties.online_mode, Some(false));
+        assert!(!properties.is_closed());
+
+        let properties = Properties::parse("white-list=false\r\nmax-players=0\r\n");
+        assert!(!properties.white_list);
+        assert_eq!(properties.online_mode, None);
+        assert!(properties.is_closed());
+
+        assert_eq!(Properties::parse(""), Properties::default());
+    }
+
+    #[test]
+    fn test_rewrite_contents_crlf() {
+        let contents = "motd=My server\r\nserver-port=25565\r\n";
+        let changes = HashMap::from([("server-port", "25566".to_string())]);
+
+        let new = rewrite_contents(contents.into(), changes).unwrap();
+        assert_eq!(new, "motd=My server\r\nserver-port=25566\r\n");
+    }
+
+    #[test]
+    fn test_rewrite_contents_unchanged() {
+        let contents = "server-port=25566\n";
+        let changes = HashMap::from([("server-port", "25566".to_string())]);
+        assert_eq!(rewrite_contents(contents.into(), changes), None);
+    }
+
+    #[test]
+    fn test_rewrite_file_backup() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-props-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = "motd=My server\nserver-port=25565\n";
+        fs::write(dir.join(FILE), original).unwrap();
+
+        rewrite_dir(&dir, HashMap::from([("server-port", "25566".to_string())]));
+
+        let contents = fs::read_to_string(dir.join(FILE)).unwrap();
+        let backup = fs::read_to_string(dir.join(format!("{FILE}{BACKUP_EXT}"))).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(contents, "motd=My server\nserver-port=25566\n");
+        assert_eq!(backup, original);
+    }
+
+    #[test]
+    fn test_rewrite_file_backup_keeps_original() {
+        let dir =
+            std::env::temp_dir().join(format!("lazymc-test-props-twice-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let original = "motd=My server\nserver-port=25565\n";
+        fs::write(dir.join(FILE), original).unwrap();
+
+        rewrite_dir(&dir, HashMap::from([("server-port", "25566".to_string())]));
+        rewrite_dir(&dir, HashMap::from([("server-port", "25567".to_string())]));
+
+        let contents = fs::read_to_string(dir.join(FILE)).unwrap();
+        let backup = fs::read_to_string(dir.join(format!("{FILE}{BACKUP_EXT}"))).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        assert_eq!(contents, "motd=My server\nserver-port=25567\n");
+        assert_eq!(backup, original);
+    }
+}
@@ -1,8 +1,13 @@
-use std::net::SocketAddr;
+#[cfg(feature = "rcon")]
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+#[cfg(feature = "rcon")]
+use std::time::Instant;
 
 use bytes::BytesMut;
+use chrono::{Local, NaiveDateTime, TimeDelta, Timelike};
 use minecraft_protocol::decoder::Decoder;
 use minecraft_protocol::version::v1_14_4::handshake::Handshake;
 use minecraft_protocol::version::v1_20_3::status::{
@@ -10,10 +15,16 @@ use minecraft_protocol::version::v1_20_3::status::{
 };
 use rand::Rng;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
 use tokio::time;
 
-use crate::config::Config;
+#[cfg(feature = "rcon")]
+use crate::config::Advanced;
+use crate::config::{Config, ReadyCheck, SleepMethod, UnreachableAction};
+use crate::event::Event;
+use crate::forge;
+#[cfg(feature = "rcon")]
+use crate::mc::rcon::{PlayerList, Position, Rcon};
+use crate::net::{self, Endpoint, Stream};
 use crate::proto::client::{Client, ClientState};
 use crate::proto::{packet, packets};
 use crate::proxy;
@@ -28,32 +39,113 @@ const STATUS_TIMEOUT: u64 = 20;
 /// Ping request timeout in seconds.
 const PING_TIMEOUT: u64 = 10;
 
+/// Port readiness check connect timeout in seconds.
+const PORT_TIMEOUT: u64 = 5;
+
+/// Maximum interval to poll a starting server at, while it is not expected to be ready yet.
+const MAX_STARTING_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Maximum number of malformed packets from the server before giving up on a status or ping.
+const MAX_READ_ERRORS: u32 = 5;
+
+/// Interval to check player activity at, when detecting activity through RCON.
+#[cfg(feature = "rcon")]
+const ACTIVITY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Minimum distance in blocks a player must move between checks to be considered active.
+#[cfg(feature = "rcon")]
+const ACTIVITY_MIN_DISTANCE: f64 = 1.0;
+
+/// Maximum number of minutes to look back for a scheduled prewarm missed between two checks.
+const PREWARM_CATCH_UP_MINUTES: i64 = 10;
+
 /// Monitor server.
 pub async fn monitor_server(config: Arc<Config>, server: Arc<Server>) {
     // Server address
-    let addr = config.server.address;
+    let addr = &config.server.address;
 
     let mut poll_interval = time::interval(MONITOR_POLL_INTERVAL);
 
+    // Persistent RCON connection, reused across polls
+    #[cfg(feature = "rcon")]
+    let mut rcon: Option<Rcon> = None;
+
+    // Number of consecutive polls the server process was unreachable after being online
+    let mut unreachable = 0;
+
+    // Number of consecutive failed status polls
+    let mut failures = 0;
+
+    // Player positions, to detect activity through RCON
+    #[cfg(feature = "rcon")]
+    let mut activity = ActivityTracker::default();
+
+    // Scheduled prewarming
+    let mut prewarm = Prewarm::default();
+
+    // Poll backoff while the server is starting
+    let mut starting_backoff = StartingBackoff::default();
+
     loop {
         poll_interval.tick().await;
+        server.update_monitor_heartbeat().await;
+
+        // Wake server ahead of peak hours as scheduled
+        prewarm
+            .check(&config, &server, &Local::now().naive_local())
+            .await;
+
+        // Don't poll frozen server, it still holds its port but never responds until unfrozen
+        if server.is_frozen() {
+            trace!(target: "lazymc::monitor", "Server is frozen, not polling");
+            unreachable = 0;
+            failures = 0;
+            #[cfg(feature = "rcon")]
+            {
+                rcon = None;
+            }
+            continue;
+        }
+
+        // Back off polling a freshly spawned server until it is expected to be ready, it refuses
+        // connections early on anyway. Start timeouts are still enforced.
+        match server.starting_for().await {
+            Some(starting_for) => {
+                let expected = server.startup_time().await;
+                if !starting_backoff.should_poll(starting_for, expected) {
+                    trace!(target: "lazymc::monitor", "Server is starting, not polling yet");
+                    enforce_deadlines(&config, &server).await;
+                    continue;
+                }
+            }
+            None => starting_backoff = StartingBackoff::default(),
+        }
+
+        // Mark starting server as started once its port accepts, if that is enough to be ready
+        if server.state() == State::Starting {
+            mark_started_on_port(&config, &server).await;
+        }
 
         // Poll server state and update internal status
         trace!(target: "lazymc::monitor", "Fetching status for {} ... ", addr);
+        let was_started = server.state() == State::Started;
         let status = poll_server(&config, &server, addr).await;
+        let reachable = status.is_ok();
+        if reachable {
+            failures = 0;
+        }
         match status {
             // Got status, update
             Ok(Some(status)) => server.update_status(&config, Some(status)).await,
 
-            // Error, reset status
-            Err(_) => server.update_status(&config, None).await,
+            // Error, reset status once it keeps failing
+            Err(_) => status_failed(&config, &server, &mut failures).await,
 
             // Didn't get status, but ping fallback worked
             Ok(None) => {
                 // If server is starting, treat ping success as server being online
                 if server.state() == State::Starting {
-                    info!(target: "lazymc::monitor", "Server responded to ping while starting, marking as started");
-                    server.update_state(State::Started, &config).await;
+                    mark_started_on_ping(&config, &server).await;
                 } else {
                     debug!(target: "lazymc::monitor", "Failed to poll server status, ping fallback succeeded");
 
@@ -61,13 +153,14 @@ pub async fn monitor_server(config: Arc<Config>, server: Arc<Server>) {
                     // alive when players are online but status polling is broken
                     #[cfg(feature = "rcon")]
                     if config.rcon.enabled {
-                        let rcon_result = query_online_players_rcon(&config).await;
+                        let rcon_result = query_online_players_rcon(&config, &mut rcon).await;
                         match rcon_result {
-                            Ok(count) => {
-                                debug!(target: "lazymc::monitor", "RCON reports {} player(s) online", count);
-                                if count > 0 {
+                            Ok(list) => {
+                                debug!(target: "lazymc::monitor", "RCON reports {} player(s) online", list.online);
+                                if rcon_players_keep_awake(&config, list.online) {
                                     server.update_last_active().await;
                                 }
+                                server.update_players_sample(list.names).await;
                             }
                             Err(err) => {
                                 warn!(target: "lazymc::monitor", "RCON player count query failed: {}", err);
@@ -78,17 +171,165 @@ pub async fn monitor_server(config: Arc<Config>, server: Arc<Server>) {
             }
         }
 
-        // Sleep server when it's bedtime
-        if server.should_sleep(&config).await {
-            info!(target: "lazymc::monitor", "Server has been idle, sleeping...");
+        // Count unreachable polls while server process is running, act on it
+        if reachable {
+            unreachable = 0;
+        } else if (was_started || unreachable > 0) && server.has_process().await {
+            unreachable += 1;
+            if let Some(action) = unreachable_action(&config, unreachable) {
+                take_unreachable_action(&config, &server, action, unreachable).await;
+            }
+        }
+
+        // Detect player activity through RCON while online
+        #[cfg(feature = "rcon")]
+        if server.state() == State::Started && Advanced::detects_activity(&config) {
+            activity.check(&config, &server, &mut rcon).await;
+        } else {
+            activity = ActivityTracker::default();
+        }
+
+        // Tear down RCON connection once server goes down
+        #[cfg(feature = "rcon")]
+        if matches!(server.state(), State::Stopping | State::Stopped) {
+            if let Some(rcon) = rcon.take() {
+                rcon.close().await;
+            }
+        }
+
+        // Sleep server when it's bedtime, unless a player rejoined right before
+        if server.should_sleep(&config).await && confirm_sleep(&config, &server).await {
+            let method = SleepMethod::of(&config).name();
+            info!(target: "lazymc::monitor", "Server has been idle, sleeping ({})...", method);
+            server.events.emit(Event::Sleep);
             server.stop(&config).await;
         }
 
-        // Check whether we should force kill server
-        if server.should_kill().await {
-            error!(target: "lazymc::monitor", "Force killing server, took too long to start or stop");
-            if !server.force_kill().await {
-                warn!(target: "lazymc", "Failed to force kill server");
+        enforce_deadlines(&config, &server).await;
+    }
+}
+
+/// Escalate stopping or force kill the server once it took too long to stop or start.
+async fn enforce_deadlines(config: &Config, server: &Server) {
+    // Escalate if server didn't quit in time after RCON stop
+    #[cfg(feature = "rcon")]
+    if server.should_escalate_stop().await {
+        warn!(target: "lazymc::monitor", "Server did not quit in time after RCON stop, escalating");
+        server.escalate_stop(config).await;
+    }
+
+    // Check whether we should force kill server
+    if server.should_kill().await {
+        let killed = if server.state() == State::Starting {
+            server.start_timed_out(config).await
+        } else {
+            error!(target: "lazymc::monitor", "Force killing server, took too long to stop");
+            server.force_kill(config).await
+        };
+        if !killed {
+            warn!(target: "lazymc", "Failed to force kill server");
+        }
+    }
+}
+
+/// Confirm the server should still sleep, right before it is put to sleep.
+///
+/// Waits for the configured delay, then polls the server once more so a player that rejoined at
+/// the last moment cancels sleeping.
+async fn confirm_sleep(config: &Config, server: &Server) -> bool {
+    if config.time.sleep_confirm_delay > 0 {
+        time::sleep(Duration::from_secs(config.time.sleep_confirm_delay as u64)).await;
+    }
+
+    if let Ok(Some(status)) = poll_server(config, server, &config.server.address).await {
+        server.update_status(config, Some(status)).await;
+    }
+
+    if !server.should_sleep(config).await {
+        info!(target: "lazymc::monitor", "Player rejoined right before sleeping, staying online");
+        return false;
+    }
+    true
+}
+
+/// Handle a failed status poll.
+///
+/// Keeps the last known status until the configured number of consecutive polls failed, then
+/// resets it.
+async fn status_failed(config: &Config, server: &Server, failures: &mut u32) {
+    *failures += 1;
+    if *failures < config.advanced.status_fail_threshold {
+        debug!(target: "lazymc::monitor", "Failed to poll server status ({}/{}), keeping last known status", failures, config.advanced.status_fail_threshold);
+        return;
+    }
+
+    server.update_status(config, None).await;
+}
+
+/// Mark starting server as started after it responded to ping.
+///
+/// Does nothing if the configured readiness check doesn't accept a ping.
+async fn mark_started_on_ping(config: &Config, server: &Server) -> bool {
+    if !ReadyCheck::of(config).accepts(ReadyCheck::Ping) {
+        debug!(target: "lazymc::monitor", "Server responded to ping while starting, waiting for it to be ready");
+        return false;
+    }
+
+    info!(target: "lazymc::monitor", "Server responded to ping while starting, marking as started");
+    server.update_state(State::Started, config).await
+}
+
+/// Mark starting server as started once its port accepts a connection.
+///
+/// Does nothing if the configured readiness check doesn't accept an open port.
+async fn mark_started_on_port(config: &Config, server: &Server) -> bool {
+    if !ReadyCheck::of(config).accepts(ReadyCheck::Port) {
+        return false;
+    }
+
+    let connect = net::connect_endpoint(config, &config.server.address);
+    match time::timeout(Duration::from_secs(PORT_TIMEOUT), connect).await {
+        Ok(Ok(_)) => {
+            info!(target: "lazymc::monitor", "Server port accepts connections while starting, marking as started");
+            server.update_state(State::Started, config).await
+        }
+        _ => {
+            trace!(target: "lazymc::monitor", "Server port does not accept connections yet");
+            false
+        }
+    }
+}
+
+/// Action to take after the given number of consecutive unreachable polls.
+///
+/// Only returns an action once, when the configured threshold is reached.
+fn unreachable_action(config: &Config, polls: u32) -> Option<UnreachableAction> {
+    match config.advanced.unreachable_action {
+        UnreachableAction::None => None,
+        action if polls == config.advanced.unreachable_threshold.max(1) => Some(action),
+        _ => None,
+    }
+}
+
+/// Take action on server process that is running but unreachable.
+async fn take_unreachable_action(
+    config: &Config,
+    server: &Server,
+    action: UnreachableAction,
+    polls: u32,
+) {
+    match action {
+        UnreachableAction::None => {}
+        UnreachableAction::Restart => {
+            warn!(target: "lazymc::monitor", "Server unreachable for {} polls, restarting...", polls);
+            if !server.restart(config).await {
+                warn!(target: "lazymc::monitor", "Failed to restart unreachable server");
+            }
+        }
+        UnreachableAction::Kill => {
+            warn!(target: "lazymc::monitor", "Server unreachable for {} polls, killing...", polls);
+            if !server.force_kill(config).await {
+                warn!(target: "lazymc::monitor", "Failed to kill unreachable server");
             }
         }
     }
@@ -101,10 +342,10 @@ pub async fn monitor_server(config: Arc<Config>, server: Arc<Server>) {
 pub async fn poll_server(
     config: &Config,
     server: &Server,
-    addr: SocketAddr,
+    addr: &Endpoint,
 ) -> Result<Option<ServerStatus>, ()> {
     // Fetch status
-    if let Ok(status) = fetch_status(config, addr).await {
+    if let Ok(status) = fetch_status(config, server, addr).await {
         return Ok(Some(status));
     }
 
@@ -122,8 +363,12 @@ pub async fn poll_server(
 }
 
 /// Attemp to fetch status from server.
-async fn fetch_status(config: &Config, addr: SocketAddr) -> Result<ServerStatus, ()> {
-    let mut stream = TcpStream::connect(addr).await.map_err(|_| ())?;
+async fn fetch_status(
+    config: &Config,
+    server: &Server,
+    addr: &Endpoint,
+) -> Result<ServerStatus, ()> {
+    let mut stream = net::connect_endpoint(config, addr).await.map_err(|_| ())?;
 
     // Add proxy header
     if config.server.send_proxy_v2 {
@@ -139,12 +384,12 @@ async fn fetch_status(config: &Config, addr: SocketAddr) -> Result<ServerStatus,
 
     send_handshake(&client, &mut stream, config, addr).await?;
     request_status(&client, &mut stream).await?;
-    wait_for_status_timeout(&client, &mut stream).await
+    wait_for_status_timeout(config, &client, &mut stream, server).await
 }
 
 /// Attemp to ping server.
-async fn do_ping(config: &Config, addr: SocketAddr) -> Result<(), ()> {
-    let mut stream = TcpStream::connect(addr).await.map_err(|_| ())?;
+async fn do_ping(config: &Config, addr: &Endpoint) -> Result<(), ()> {
+    let mut stream = net::connect_endpoint(config, addr).await.map_err(|_| ())?;
 
     // Add proxy header
     if config.server.send_proxy_v2 {
@@ -166,59 +411,89 @@ async fn do_ping(config: &Config, addr: SocketAddr) -> Result<(), ()> {
 /// Send handshake.
 async fn send_handshake(
     client: &Client,
-    stream: &mut TcpStream,
+    stream: &mut Stream,
     config: &Config,
-    addr: SocketAddr,
+    addr: &Endpoint,
 ) -> Result<(), ()> {
+    let (server_addr, server_port) = addr.handshake_host();
     packet::write_packet(
         Handshake {
             protocol_version: config.public.protocol as i32,
-            server_addr: addr.ip().to_string(),
-            server_port: addr.port(),
+            server_addr,
+            server_port,
             next_state: ClientState::Status.to_id(),
         },
         client,
-        &mut stream.split().1,
+        stream,
     )
     .await
 }
 
 /// Send status request.
-async fn request_status(client: &Client, stream: &mut TcpStream) -> Result<(), ()> {
-    packet::write_packet(StatusRequest {}, client, &mut stream.split().1).await
+async fn request_status(client: &Client, stream: &mut Stream) -> Result<(), ()> {
+    packet::write_packet(StatusRequest {}, client, stream).await
 }
 
 /// Send status request.
-async fn send_ping(client: &Client, stream: &mut TcpStream) -> Result<u64, ()> {
+async fn send_ping(client: &Client, stream: &mut Stream) -> Result<u64, ()> {
     let token = rand::thread_rng().gen();
-    packet::write_packet(PingRequest { time: token }, client, &mut stream.split().1).await?;
+    packet::write_packet(PingRequest { time: token }, client, stream).await?;
     Ok(token)
 }
 
 /// Wait for a status response.
-async fn wait_for_status(client: &Client, stream: &mut TcpStream) -> Result<ServerStatus, ()> {
-    // Get stream reader, set up buffer
-    let (mut reader, mut _writer) = stream.split();
+///
+/// Detects the server mod loader from the response.
+async fn wait_for_status(
+    config: &Config,
+    client: &Client,
+    stream: &mut Stream,
+    server: &Server,
+) -> Result<ServerStatus, ()> {
     let mut buf = BytesMut::new();
+    let mut errors = 0;
 
     loop {
-        // Read packet from stream
-        let (packet, _raw) = match packet::read_packet(client, &mut buf, &mut reader).await {
+        // Read packet from stream, give up if server keeps sending garbage
+        let (packet, _raw) = match packet::read_packet(client, &mut buf, stream).await {
             Ok(Some(packet)) => packet,
             Ok(None) => break,
-            Err(_) => continue,
+            Err(_) => {
+                errors += 1;
+                if errors >= MAX_READ_ERRORS {
+                    debug!(target: "lazymc::monitor", "Got too many malformed packets from server");
+                    break;
+                }
+                continue;
+            }
         };
 
         // Catch status response
         if packet.id == packets::status::CLIENT_STATUS {
-            // Try strict protocol decode first
-            if let Ok(status) = StatusResponse::decode(&mut packet.data.as_slice()) {
-                return Ok(status.server_status);
+            // Extract raw JSON
+            let json = match status_json(&packet.data) {
+                Ok(json) => json,
+                Err(()) => {
+                    debug!(target: "lazymc::monitor", "Got malformed status response from server");
+                    return Err(());
+                }
+            };
+            let oversized = !fits_max_size(config, json);
+
+            // Detect mod loader from raw JSON
+            let json = serde_json::from_str(json).ok();
+            server.set_mod_loader(json.as_ref().and_then(forge::detect_mod_loader));
+
+            // Try strict protocol decode first, oversized status must be fitted by lenient parser
+            if !oversized {
+                if let Ok(status) = StatusResponse::decode(&mut packet.data.as_slice()) {
+                    return Ok(sanitize_status(config, status.server_status));
+                }
             }
 
             // Fallback: lenient JSON parse for modded servers (Forge/NeoForge/Fabric)
             // that return non-standard status responses (e.g. description as object)
-            if let Ok(status) = parse_status_json(&packet.data) {
+            if let Ok(status) = parse_status_json(config, &packet.data) {
                 debug!(target: "lazymc::monitor", "Used lenient JSON parser for server status");
                 return Ok(status);
             }
@@ -233,27 +508,35 @@ async fn wait_for_status(client: &Client, stream: &mut TcpStream) -> Result<Serv
 
 /// Wait for a status response.
 async fn wait_for_status_timeout(
+    config: &Config,
     client: &Client,
-    stream: &mut TcpStream,
+    stream: &mut Stream,
+    server: &Server,
 ) -> Result<ServerStatus, ()> {
-    let status = wait_for_status(client, stream);
+    let status = wait_for_status(config, client, stream, server);
     tokio::time::timeout(Duration::from_secs(STATUS_TIMEOUT), status)
         .await
         .map_err(|_| ())?
 }
 
 /// Wait for a status response.
-async fn wait_for_ping(client: &Client, stream: &mut TcpStream, token: u64) -> Result<(), ()> {
-    // Get stream reader, set up buffer
-    let (mut reader, mut _writer) = stream.split();
+async fn wait_for_ping(client: &Client, stream: &mut Stream, token: u64) -> Result<(), ()> {
     let mut buf = BytesMut::new();
+    let mut errors = 0;
 
     loop {
-        // Read packet from stream
-        let (packet, _raw) = match packet::read_packet(client, &mut buf, &mut reader).await {
+        // Read packet from stream, give up if server keeps sending garbage
+        let (packet, _raw) = match packet::read_packet(client, &mut buf, stream).await {
             Ok(Some(packet)) => packet,
             Ok(None) => break,
-            Err(_) => continue,
+            Err(_) => {
+                errors += 1;
+                if errors >= MAX_READ_ERRORS {
+                    debug!(target: "lazymc::monitor", "Got too many malformed packets from server");
+                    break;
+                }
+                continue;
+            }
         };
 
         // Catch ping response
@@ -274,11 +557,7 @@ async fn wait_for_ping(client: &Client, stream: &mut TcpStream, token: u64) -> R
 }
 
 /// Wait for a status response.
-async fn wait_for_ping_timeout(
-    client: &Client,
-    stream: &mut TcpStream,
-    token: u64,
-) -> Result<(), ()> {
+async fn wait_for_ping_timeout(client: &Client, stream: &mut Stream, token: u64) -> Result<(), ()> {
     let status = wait_for_ping(client, stream, token);
     tokio::time::timeout(Duration::from_secs(PING_TIMEOUT), status)
         .await
@@ -290,21 +569,19 @@ async fn wait_for_ping_timeout(
 /// This handles modded servers (Forge/NeoForge/Fabric) that return non-standard status
 /// responses, e.g. `description` as a Chat Component object instead of a plain string.
 /// The packet data is: [var-int string length] [UTF-8 JSON bytes].
-fn parse_status_json(data: &[u8]) -> Result<ServerStatus, ()> {
+///
+/// Fits JSON larger than the configured maximum size, and sanitizes the description.
+fn parse_status_json(config: &Config, data: &[u8]) -> Result<ServerStatus, ()> {
     use minecraft_protocol::version::v1_20_3::status::ServerStatus as StrictStatus;
     use serde_json::Value;
 
-    // Read var-int string length prefix, then extract JSON bytes
-    let (prefix_len, str_len) = crate::types::read_var_int(data)?;
-    let json_bytes = data
-        .get(prefix_len..prefix_len + str_len as usize)
-        .ok_or(())?;
-    let json_str = std::str::from_utf8(json_bytes).map_err(|_| ())?;
+    let json_str = fit_status_json(config, status_json(data)?)?;
+    let json_str = json_str.as_ref();
 
     // Try strict serde first on the raw JSON string (handles edge cases where
     // the var-int decode differed but JSON is actually valid for the struct)
     if let Ok(status) = serde_json::from_str::<StrictStatus>(json_str) {
-        return Ok(status);
+        return Ok(sanitize_status(config, status));
     }
 
     // Parse as generic JSON value
@@ -346,42 +623,806 @@ fn parse_status_json(data: &[u8]) -> Result<ServerStatus, ()> {
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
 
-    Ok(ServerStatus {
-        version: minecraft_protocol::data::server_status::ServerVersion {
-            name: version_name,
-            protocol: version_protocol,
+    Ok(sanitize_status(
+        config,
+        ServerStatus {
+            version: minecraft_protocol::data::server_status::ServerVersion {
+                name: version_name,
+                protocol: version_protocol,
+            },
+            players: minecraft_protocol::data::server_status::OnlinePlayers {
+                online: players_online,
+                max: players_max,
+                sample: vec![],
+            },
+            description,
+            favicon,
         },
-        players: minecraft_protocol::data::server_status::OnlinePlayers {
-            online: players_online,
-            max: players_max,
-            sample: vec![],
-        },
-        description,
-        favicon,
-    })
+    ))
+}
+
+/// Sanitize server status before keeping it and forwarding it to clients.
+///
+/// Strips control characters from the description, and truncates it to the configured maximum
+/// length. A description that is a chat component is dropped instead, as truncating its JSON
+/// would break it.
+fn sanitize_status(config: &Config, mut status: ServerStatus) -> ServerStatus {
+    let max = config.advanced.status_max_description as usize;
+
+    status.description.retain(|c| c == '\n' || !c.is_control());
+    if max > 0 {
+        if let Some((index, _)) = status.description.char_indices().nth(max) {
+            if is_chat_component(&status.description) {
+                debug!(target: "lazymc::monitor", "Server description is too long, dropping it");
+                status.description.clear();
+            } else {
+                status.description.truncate(index);
+            }
+        }
+    }
+
+    status
+}
+
+/// Whether the given description is a chat component in JSON, rather than plain text.
+fn is_chat_component(description: &str) -> bool {
+    matches!(
+        serde_json::from_str(description),
+        Ok(serde_json::Value::Object(_) | serde_json::Value::Array(_))
+    )
+}
+
+/// Extract the JSON string from raw status response packet data.
+///
+/// The packet data is: [var-int string length] [UTF-8 JSON bytes].
+fn status_json(data: &[u8]) -> Result<&str, ()> {
+    let (prefix_len, str_len) = crate::types::read_var_int(data)?;
+    let json_bytes = data
+        .get(prefix_len..prefix_len + str_len as usize)
+        .ok_or(())?;
+    std::str::from_utf8(json_bytes).map_err(|_| ())
 }
 
-/// Query online player count via RCON `list` command.
+/// Whether the given status JSON fits the configured maximum size, unlimited if 0.
+fn fits_max_size(config: &Config, json: &str) -> bool {
+    let max_size = config.advanced.status_max_size as usize;
+    max_size == 0 || json.len() <= max_size
+}
+
+/// Fit status JSON within the configured maximum size.
 ///
-/// Parses the response from the Minecraft `list` command which typically looks like:
-/// "There are X of a max of Y players online: ..."
+/// Oversized status, such as from misbehaving modded servers, has its largest optional fields
+/// dropped until it fits. The version and player counts are always kept, errors if these alone
+/// don't fit.
+fn fit_status_json<'a>(config: &Config, json: &'a str) -> Result<Cow<'a, str>, ()> {
+    if fits_max_size(config, json) {
+        return Ok(Cow::Borrowed(json));
+    }
+
+    let mut root: serde_json::Value = serde_json::from_str(json).map_err(|_| ())?;
+    let fields = root.as_object_mut().ok_or(())?;
+    loop {
+        let json = serde_json::to_string(fields).map_err(|_| ())?;
+        if fits_max_size(config, &json) {
+            return Ok(Cow::Owned(json));
+        }
+
+        // Drop largest optional field, the player sample is optional as well
+        let largest = fields
+            .iter()
+            .filter(|(key, _)| !matches!(key.as_str(), "version" | "players"))
+            .map(|(key, value)| (key.clone(), value.to_string().len()))
+            .max_by_key(|(_, len)| *len);
+        let sample = fields
+            .get("players")
+            .and_then(|players| players.get("sample"))
+            .map(|sample| sample.to_string().len());
+        match (largest, sample) {
+            (largest, Some(sample)) if largest.as_ref().map_or(true, |(_, len)| sample >= *len) => {
+                if let Some(players) = fields.get_mut("players").and_then(|p| p.as_object_mut()) {
+                    players.remove("sample");
+                }
+                debug!(target: "lazymc::monitor", "Server status is too large, dropped player sample");
+            }
+            (Some((key, _)), _) => {
+                fields.remove(&key);
+                debug!(target: "lazymc::monitor", "Server status is too large, dropped '{}' field", key);
+            }
+            (None, None) => return Err(()),
+        }
+    }
+}
+
+/// Whether the given number of online players reported by RCON keeps the server awake.
+///
+/// Never when detecting player activity, only observed activity keeps the server awake then.
 #[cfg(feature = "rcon")]
-async fn query_online_players_rcon(config: &Config) -> Result<u32, String> {
-    use crate::mc::rcon::Rcon;
+fn rcon_players_keep_awake(config: &Config, online: u32) -> bool {
+    config.time.keeps_awake(online) && !Advanced::detects_activity(config)
+}
 
-    let mut rcon = Rcon::connect_config(config)
-        .await
-        .map_err(|e| e.to_string())?;
-    let response = rcon.cmd("list").await.map_err(|e| e.to_string())?;
-    rcon.close().await;
-
-    // Parse "There are X of a max of Y players online: ..."
-    // Also handles variations like "There are X/Y players online"
-    let count = response
-        .split_whitespace()
-        .flat_map(|w| w.parse::<u32>())
-        .next()
-        .unwrap_or(0);
-
-    Ok(count)
+/// Query online players via RCON `list` command.
+///
+/// Reuses the given RCON connection, or connects if there is none. The connection is dropped on
+/// failure so the next query reconnects.
+#[cfg(feature = "rcon")]
+async fn query_online_players_rcon(
+    config: &Config,
+    rcon: &mut Option<Rcon>,
+) -> Result<PlayerList, String> {
+    let con = match rcon {
+        Some(con) => con,
+        None => rcon.insert(
+            Rcon::connect_config(config)
+                .await
+                .map_err(|e| e.to_string())?,
+        ),
+    };
+
+    let response = match con.cmd("list").await {
+        Ok(response) => response,
+        Err(err) => {
+            rcon.take();
+            return Err(err.to_string());
+        }
+    };
+
+    PlayerList::parse(&response).ok_or_else(|| format!("unexpected response: {}", response))
+}
+
+/// Query online player positions via RCON `data get entity` command.
+///
+/// Reuses the given RCON connection, or connects if there is none. The connection is dropped on
+/// failure so the next query reconnects.
+#[cfg(feature = "rcon")]
+async fn query_player_positions_rcon(
+    config: &Config,
+    rcon: &mut Option<Rcon>,
+) -> Result<HashMap<String, Position>, String> {
+    let list = query_online_players_rcon(config, rcon).await?;
+    if list.names.len() < list.online as usize {
+        return Err("player names not listed".into());
+    }
+
+    let mut positions = HashMap::new();
+    for name in list.names {
+        let con = match rcon {
+            Some(con) => con,
+            None => return Err("RCON connection lost".into()),
+        };
+        let response = match con.cmd(&format!("data get entity {name} Pos")).await {
+            Ok(response) => response,
+            Err(err) => {
+                rcon.take();
+                return Err(err.to_string());
+            }
+        };
+        let position = Position::parse(&response)
+            .ok_or_else(|| format!("unexpected response: {}", response))?;
+        positions.insert(name, position);
+    }
+
+    Ok(positions)
+}
+
+/// Player activity detection, by observing player movement through RCON.
+#[cfg(feature = "rcon")]
+#[derive(Debug, Default)]
+struct ActivityTracker {
+    /// Player positions at the last check.
+    positions: HashMap<String, Position>,
+
+    /// Time of the last check.
+    checked_at: Option<Instant>,
+}
+
+#[cfg(feature = "rcon")]
+impl ActivityTracker {
+    /// Check player activity if due, marks the server active if any player moved.
+    ///
+    /// The server is also marked active if activity can't be determined, to never sleep while
+    /// players might be active.
+    async fn check(&mut self, config: &Config, server: &Server, rcon: &mut Option<Rcon>) {
+        if self
+            .checked_at
+            .map_or(false, |t| t.elapsed() < ACTIVITY_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.checked_at = Some(Instant::now());
+
+        match query_player_positions_rcon(config, rcon).await {
+            Ok(positions) => {
+                if self.update(positions) {
+                    debug!(target: "lazymc::monitor", "Player activity detected");
+                    server.update_last_active().await;
+                }
+            }
+            Err(err) => {
+                debug!(target: "lazymc::monitor", "Failed to detect player activity through RCON, assuming active: {}", err);
+                self.positions.clear();
+                server.update_last_active().await;
+            }
+        }
+    }
+
+    /// Update player positions.
+    ///
+    /// Returns whether any player moved, or joined, since the last update.
+    fn update(&mut self, positions: HashMap<String, Position>) -> bool {
+        let active = positions.iter().any(|(name, position)| {
+            self.positions.get(name).map_or(true, |last| {
+                position.distance(last) >= ACTIVITY_MIN_DISTANCE
+            })
+        });
+        self.positions = positions;
+        active
+    }
+}
+
+/// Backoff of status polls while the server is starting.
+///
+/// Polls sparsely right after the server process is spawned, and ramps up to the regular poll
+/// interval as the learned startup time approaches.
+#[derive(Debug, Default)]
+struct StartingBackoff {
+    /// Time since spawning the server at the last poll.
+    last_poll: Duration,
+}
+
+impl StartingBackoff {
+    /// Whether to poll the server now, given how long it is starting and its expected startup time.
+    fn should_poll(&mut self, starting_for: Duration, expected: Option<Duration>) -> bool {
+        // Server process was spawned again
+        if starting_for < self.last_poll {
+            self.last_poll = Duration::ZERO;
+        }
+
+        if starting_for - self.last_poll < starting_poll_interval(starting_for, expected) {
+            return false;
+        }
+        self.last_poll = starting_for;
+        true
+    }
+}
+
+/// Interval to poll a starting server at.
+///
+/// A quarter of the expected remaining startup time, within the regular and maximum poll interval.
+/// Regular if the startup time is not known yet.
+fn starting_poll_interval(starting_for: Duration, expected: Option<Duration>) -> Duration {
+    match expected {
+        Some(expected) => (expected.saturating_sub(starting_for) / 4)
+            .clamp(MONITOR_POLL_INTERVAL, MAX_STARTING_POLL_INTERVAL),
+        None => MONITOR_POLL_INTERVAL,
+    }
+}
+
+/// Scheduled server prewarming, wakes the server at the configured times.
+#[derive(Debug, Default)]
+struct Prewarm {
+    /// Local time prewarming was last checked at.
+    last_check: Option<NaiveDateTime>,
+}
+
+impl Prewarm {
+    /// Whether prewarming is due at the given local time, at most once per scheduled minute.
+    ///
+    /// Also due if a scheduled minute passed since the last check, as checks may skip a minute.
+    fn due(&mut self, config: &Config, at: &NaiveDateTime) -> bool {
+        let minute = |at: &NaiveDateTime| at.with_second(0).and_then(|at| at.with_nanosecond(0));
+        let current = match minute(at) {
+            Some(current) => current,
+            None => return false,
+        };
+
+        // Check minutes since the last check, only the current one on the first check
+        let mut next = match self.last_check.replace(*at).and_then(|last| minute(&last)) {
+            Some(last) => last + TimeDelta::minutes(1),
+            None => current,
+        };
+        next = next.max(current - TimeDelta::minutes(PREWARM_CATCH_UP_MINUTES));
+        while next <= current {
+            if config.schedule.prewarms_at(&next) {
+                return true;
+            }
+            next += TimeDelta::minutes(1);
+        }
+        false
+    }
+
+    /// Wake the server if prewarming is due at the given local time.
+    ///
+    /// A server that is online already is kept awake as if it was just active.
+    ///
+    /// Returns whether the server was woken.
+    async fn check(
+        &mut self,
+        config: &Arc<Config>,
+        server: &Arc<Server>,
+        at: &NaiveDateTime,
+    ) -> bool {
+        if !self.due(config, at) {
+            return false;
+        }
+
+        if config.maintenance.enabled {
+            debug!(target: "lazymc::monitor", "Not prewarming server, maintenance mode is enabled");
+            return false;
+        }
+
+        match server.state() {
+            State::Stopped | State::Frozen => {
+                info!(target: "lazymc::monitor", "Prewarming server as scheduled");
+                Server::start(config.clone(), server.clone(), None).await
+            }
+            State::Started => {
+                debug!(target: "lazymc::monitor", "Server is online at scheduled prewarm, keeping it awake");
+                server.update_last_active().await;
+                false
+            }
+            State::Starting | State::Stopping => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn starting_server(config: &Config) -> Server {
+        let (server, _) = Server::new();
+        server.update_state(State::Starting, config).await;
+        server
+    }
+
+    /// Build raw status response packet data from status JSON.
+    fn status_data(json: &str) -> Vec<u8> {
+        let mut data = crate::types::encode_var_int(json.len() as i32).unwrap();
+        data.extend_from_slice(json.as_bytes());
+        data
+    }
+
+    #[tokio::test]
+    async fn test_confirm_sleep_rejoin() {
+        // Nothing listens on the address of a dropped listener
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "address = \"{addr}\"\n[time]\nsleep_after = 0\nminimum_online_time = 0\nsleep_confirm_delay = 1",
+        )));
+        let server = Arc::new(starting_server(&config).await);
+        server.update_state(State::Started, &config).await;
+        assert!(server.should_sleep(&config).await);
+
+        // Player rejoining during the confirm delay cancels sleeping
+        let confirm = tokio::spawn({
+            let (config, server) = (config.clone(), server.clone());
+            async move { confirm_sleep(&config, &server).await }
+        });
+        let json = r#"{"version":{"name":"1.20.4","protocol":765},"players":{"online":1,"max":20},"description":"A Minecraft Server"}"#;
+        let status = parse_status_json(&config, &status_data(json)).unwrap();
+        server.update_status(&config, Some(status)).await;
+        assert!(!confirm.await.unwrap());
+
+        // Sleeps once the player left again
+        let json = r#"{"version":{"name":"1.20.4","protocol":765},"players":{"online":0,"max":20},"description":"A Minecraft Server"}"#;
+        let status = parse_status_json(&config, &status_data(json)).unwrap();
+        server.update_status(&config, Some(status)).await;
+        assert!(confirm_sleep(&config, &server).await);
+    }
+
+    #[test]
+    fn test_parse_status_json_max_size() {
+        let json = r#"{"version":{"name":"1.20.4","protocol":765},"players":{"online":1,"max":20},"description":{"text":"A Minecraft Server"}}"#;
+        let config: Config = crate::util::test::config("");
+        let status = parse_status_json(&config, &status_data(json)).unwrap();
+        assert_eq!(status.players.online, 1);
+        assert_eq!(status.description, r#"{"text":"A Minecraft Server"}"#);
+
+        // Oversized status has its largest fields dropped until it fits
+        let json = format!(
+            r#"{{"version":{{"name":"1.20.4","protocol":765}},"players":{{"online":1,"max":20,"sample":[{{"name":"Notch","id":"069a79f4-44e9-4726-a5be-fca90e38aaf5"}}]}},"description":"Hello","forgeData":{{"d":"{}"}}}}"#,
+            "A".repeat(512),
+        );
+        let config: Config = crate::util::test::config("[advanced]\nstatus_max_size = 128");
+        let status = parse_status_json(&config, &status_data(&json)).unwrap();
+        assert_eq!(status.players.online, 1);
+        assert_eq!(status.description, "Hello");
+
+        // Fails only if the required fields don't fit
+        let config: Config = crate::util::test::config("[advanced]\nstatus_max_size = 16");
+        assert!(parse_status_json(&config, &status_data(&json)).is_err());
+    }
+
+    #[test]
+    fn test_sanitize_description() {
+        let json = format!(
+            r#"{{"version":{{"name":"1.20.4","protocol":765}},"players":{{"online":0,"max":20}},"description":"\u0007Hello\nWorld{}"}}"#,
+            "!".repeat(2000),
+        );
+        let config: Config = crate::util::test::config("");
+        let status = parse_status_json(&config, &status_data(&json)).unwrap();
+        assert_eq!(status.description.chars().count(), 1024);
+        assert!(status.description.starts_with("Hello\nWorld!"));
+    }
+
+    #[test]
+    fn test_sanitize_description_component() {
+        let config: Config = crate::util::test::config("[advanced]\nstatus_max_description = 32");
+        let json = |text: &str| {
+            format!(
+                r#"{{"version":{{"name":"1.20.4","protocol":765}},"players":{{"online":0,"max":20}},"description":{{"text":"{text}"}}}}"#,
+            )
+        };
+
+        // Short component is kept as is
+        let status = parse_status_json(&config, &status_data(&json("Hello"))).unwrap();
+        assert_eq!(status.description, r#"{"text":"Hello"}"#);
+
+        // Long component is dropped, not truncated to invalid JSON
+        let long = "!".repeat(64);
+        let status = parse_status_json(&config, &status_data(&json(&long))).unwrap();
+        assert_eq!(status.description, "");
+    }
+
+    #[tokio::test]
+    async fn test_status_fail_threshold() {
+        let config: Config = crate::util::test::config("[advanced]\nstatus_fail_threshold = 3");
+        let server = starting_server(&config).await;
+        server.update_state(State::Started, &config).await;
+        let mut failures = 0;
+
+        // Transient failure keeps the server online
+        status_failed(&config, &server, &mut failures).await;
+        assert_eq!(server.state(), State::Started);
+
+        // Repeated failures reset the status
+        status_failed(&config, &server, &mut failures).await;
+        assert_eq!(server.state(), State::Started);
+        status_failed(&config, &server, &mut failures).await;
+        assert_eq!(server.state(), State::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_ping_marks_started() {
+        let config: Config = crate::util::test::config("");
+        let server = starting_server(&config).await;
+
+        assert!(mark_started_on_ping(&config, &server).await);
+        assert_eq!(server.state(), State::Started);
+    }
+
+    #[tokio::test]
+    async fn test_ping_ready_check_stdout() {
+        let config: Config = crate::util::test::config("ready_check = \"stdout\"");
+        let server = starting_server(&config).await;
+
+        assert!(!mark_started_on_ping(&config, &server).await);
+        assert_eq!(server.state(), State::Starting);
+    }
+
+    #[tokio::test]
+    async fn test_ping_requires_status_for_ready() {
+        let config: Config =
+            crate::util::test::config("[advanced]\nrequire_status_for_ready = true");
+        let server = starting_server(&config).await;
+
+        assert!(!mark_started_on_ping(&config, &server).await);
+        assert_eq!(server.state(), State::Starting);
+    }
+
+    #[tokio::test]
+    async fn test_port_marks_started() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let config = |check: &str| -> Config {
+            crate::util::test::config(&format!("address = \"{addr}\"\nready_check = \"{check}\""))
+        };
+
+        // Open port is only enough with the port check
+        for check in ["status", "ping", "stdout"] {
+            let config = config(check);
+            let server = starting_server(&config).await;
+            assert!(!mark_started_on_port(&config, &server).await);
+            assert_eq!(server.state(), State::Starting);
+        }
+        let config = config("port");
+        let server = starting_server(&config).await;
+        assert!(mark_started_on_port(&config, &server).await);
+        assert_eq!(server.state(), State::Started);
+
+        // Closed port is not ready
+        drop(listener);
+        let server = starting_server(&config).await;
+        assert!(!mark_started_on_port(&config, &server).await);
+        assert_eq!(server.state(), State::Starting);
+    }
+
+    #[tokio::test]
+    async fn test_status_ready_check() {
+        let json = r#"{"version":{"name":"1.20.4","protocol":765},"players":{"online":0,"max":20},"description":"A Minecraft Server"}"#;
+
+        // Status marks started, unless waiting for output
+        for (check, expected) in [
+            ("status", State::Started),
+            ("ping", State::Started),
+            ("port", State::Started),
+            ("stdout", State::Starting),
+        ] {
+            let config: Config = crate::util::test::config(&format!("ready_check = \"{check}\""));
+            let server = starting_server(&config).await;
+            let status = parse_status_json(&config, &status_data(json)).unwrap();
+            server.update_status(&config, Some(status)).await;
+            assert_eq!(server.state(), expected, "{check}");
+        }
+    }
+
+    #[cfg(feature = "rcon")]
+    #[tokio::test]
+    async fn test_rcon_connection_reused() {
+        use std::sync::atomic::Ordering;
+
+        use crate::mc::rcon::tests::MockServer;
+
+        let mock = MockServer::spawn(Duration::ZERO).await;
+        let config: Config = crate::util::test::config(&format!(
+            "address = \"127.0.0.1:25566\"\n[rcon]\nport = {}",
+            mock.port,
+        ));
+
+        let mut rcon = None;
+        for _ in 0..3 {
+            let list = query_online_players_rcon(&config, &mut rcon).await.unwrap();
+            assert_eq!(list.names, ["Steve"]);
+        }
+
+        assert_eq!(mock.auths.load(Ordering::SeqCst), 1);
+        assert_eq!(mock.commands.load(Ordering::SeqCst), 3);
+    }
+
+    #[cfg(feature = "rcon")]
+    #[test]
+    fn test_rcon_players_keep_awake() {
+        let config: Config = crate::util::test::config("[rcon]\nenabled = true");
+        assert!(rcon_players_keep_awake(&config, 1));
+        assert!(!rcon_players_keep_awake(&config, 0));
+
+        // Online players don't keep server awake when detecting activity
+        let config: Config = crate::util::test::config(
+            "[rcon]\nenabled = true\n[advanced]\nactivity_detection = true",
+        );
+        assert!(!rcon_players_keep_awake(&config, 1));
+    }
+
+    #[cfg(feature = "rcon")]
+    #[test]
+    fn test_activity_tracker() {
+        let position = |response| Position::parse(response).unwrap();
+        let mut tracker = ActivityTracker::default();
+
+        // Joined player is active, standing still is not, moving is
+        let positions = |steve, alex| {
+            HashMap::from([
+                ("Steve".to_string(), position(steve)),
+                ("Alex".to_string(), position(alex)),
+            ])
+        };
+        let still = "Alex has the following entity data: [0.0d, 64.0d, 0.0d]";
+        assert!(tracker.update(positions(
+            "Steve has the following entity data: [12.5d, 64.0d, -3.25d]",
+            still
+        )));
+        assert!(!tracker.update(positions(
+            "Steve has the following entity data: [12.6d, 64.0d, -3.25d]",
+            still
+        )));
+        assert!(tracker.update(positions(
+            "Steve has the following entity data: [20.0d, 64.0d, -3.25d]",
+            still
+        )));
+
+        // Nobody online is not active
+        assert!(!tracker.update(HashMap::new()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_ping_unix_socket() {
+        use tokio::net::UnixListener;
+
+        let path =
+            std::env::temp_dir().join(format!("lazymc-test-monitor-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+
+        // Echo server, echoes the ping request back as matching ping response
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (mut reader, mut writer) = stream.split();
+            let _ = tokio::io::copy(&mut reader, &mut writer).await;
+        });
+
+        let config: Config =
+            crate::util::test::config(&format!("address = \"unix:{}\"", path.display()));
+        assert_eq!(config.server.address, Endpoint::Unix(path.clone()));
+
+        let result = do_ping(&config, &config.server.address).await;
+        let _ = std::fs::remove_file(&path);
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_ping_junk_terminated() {
+        use tokio::net::TcpListener;
+
+        // Server sending nothing but junk
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            while stream.write_all(&[0xFF; 256]).await.is_ok() {}
+        });
+
+        let config: Config = crate::util::test::config("");
+        let mut stream = net::connect_endpoint(&config, &addr.into()).await.unwrap();
+        let result = time::timeout(
+            Duration::from_secs(5),
+            wait_for_ping(&Client::dummy(), &mut stream, 0),
+        )
+        .await;
+        assert_eq!(result, Ok(Err(())));
+    }
+
+    #[test]
+    fn test_unreachable_action_threshold() {
+        let config: Config = crate::util::test::config(
+            "[advanced]\nunreachable_action = \"restart\"\nunreachable_threshold = 3",
+        );
+        let actions: Vec<_> = (1..=5)
+            .map(|polls| unreachable_action(&config, polls))
+            .collect();
+        assert_eq!(
+            actions,
+            [None, None, Some(UnreachableAction::Restart), None, None]
+        );
+    }
+
+    #[test]
+    fn test_unreachable_action_none() {
+        let config: Config = crate::util::test::config("[advanced]\nunreachable_threshold = 1");
+        assert_eq!(config.advanced.unreachable_action, UnreachableAction::None);
+        assert!((1..=5).all(|polls| unreachable_action(&config, polls).is_none()));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_no_polling_while_frozen() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dir = std::env::temp_dir().join(format!("lazymc-test-frozen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "directory = {dir:?}\naddress = \"{addr}\"\nsleep_method = \"freeze\""
+        )));
+
+        // Freeze a running server
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        crate::persist::write_pid(&dir.join(crate::persist::SERVER_PID_FILE), child.id()).unwrap();
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        assert!(Server::reattach(config.clone(), server.clone(), true).await);
+        assert!(server.stop(&config).await);
+        assert!(server.is_frozen());
+
+        // Frozen server is not polled
+        tokio::spawn(monitor_server(config.clone(), server.clone()));
+        let accept = || time::timeout(MONITOR_POLL_INTERVAL * 2, listener.accept());
+        assert!(accept().await.is_err());
+
+        // Polling resumes once unfrozen
+        assert!(Server::start(config, server.clone(), None).await);
+        assert!(!server.is_frozen());
+        assert!(accept().await.is_ok());
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Local time on the given day of january 2024, the 1st being a monday.
+    fn at(day: u32, hour: u32, minute: u32, second: u32) -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, second)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_prewarm_due_once_per_minute() {
+        let config: Config = crate::util::test::config("[schedule]\nprewarm = [\"18:00\"]");
+        let mut prewarm = Prewarm::default();
+
+        assert!(!prewarm.due(&config, &at(1, 17, 59, 58)));
+        assert!(prewarm.due(&config, &at(1, 18, 0, 0)));
+
+        // Not again on later polls within the same minute
+        assert!(!prewarm.due(&config, &at(1, 18, 0, 2)));
+        assert!(!prewarm.due(&config, &at(1, 18, 0, 58)));
+        assert!(!prewarm.due(&config, &at(1, 18, 1, 0)));
+
+        // Again the next day
+        assert!(prewarm.due(&config, &at(2, 18, 0, 1)));
+    }
+
+    #[test]
+    fn test_prewarm_due_skipped_minute() {
+        let config: Config = crate::util::test::config("[schedule]\nprewarm = [\"18:00\"]");
+        let mut prewarm = Prewarm::default();
+
+        // Checks skipped the scheduled minute, still due once
+        assert!(!prewarm.due(&config, &at(1, 17, 59, 59)));
+        assert!(prewarm.due(&config, &at(1, 18, 1, 1)));
+        assert!(!prewarm.due(&config, &at(1, 18, 1, 3)));
+
+        // Not after a long gap, such as a suspended system
+        assert!(!prewarm.due(&config, &at(1, 17, 59, 59)));
+        assert!(!prewarm.due(&config, &at(2, 18, 30, 0)));
+    }
+
+    #[tokio::test]
+    async fn test_prewarm_wakes_server() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-prewarm-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "directory = {dir:?}\n[schedule]\nprewarm = [\"mon 18:00\"]"
+        )));
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        let mut events = server.events.subscribe();
+        let mut prewarm = Prewarm::default();
+
+        // Nothing happens outside of the schedule
+        assert!(!prewarm.check(&config, &server, &at(1, 17, 0, 0)).await);
+        assert!(events.try_recv().is_err());
+
+        // Scheduled time wakes the sleeping server, without any player
+        assert!(prewarm.check(&config, &server, &at(1, 18, 0, 0)).await);
+        let woke = std::iter::from_fn(|| events.try_recv().ok())
+            .any(|event| event == Event::Wake { username: None });
+        assert!(woke);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_starting_backoff() {
+        let expected = Some(Duration::from_secs(60));
+        let mut backoff = StartingBackoff::default();
+
+        // Polls on the regular interval, spaced out while the server is not expected to be ready
+        let polls: Vec<u64> = (0..=35)
+            .map(|tick| tick * MONITOR_POLL_INTERVAL.as_secs())
+            .filter(|secs| backoff.should_poll(Duration::from_secs(*secs), expected))
+            .collect();
+        assert_eq!(
+            polls,
+            [10, 20, 28, 36, 42, 46, 50, 52, 54, 56, 58, 60, 62, 64, 66, 68, 70]
+        );
+
+        // Starts over once the server is spawned again
+        assert!(!backoff.should_poll(Duration::from_secs(2), expected));
+        assert!(backoff.should_poll(Duration::from_secs(10), expected));
+
+        // Regular interval if startup time is unknown
+        let mut backoff = StartingBackoff::default();
+        assert!(!backoff.should_poll(Duration::ZERO, None));
+        assert!(backoff.should_poll(MONITOR_POLL_INTERVAL, None));
+        assert!(backoff.should_poll(MONITOR_POLL_INTERVAL * 2, None));
+    }
 }
@@ -1,7 +1,138 @@
 use std::error::Error;
+use std::fmt;
+use std::future::Future;
 use std::io;
-use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
+use std::net::SocketAddr;
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use serde::{Serialize, Serializer};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use tokio::io::{AsyncRead, AsyncWrite, AsyncWriteExt, ReadBuf};
+#[cfg(unix)]
+use tokio::net::UnixStream;
+use tokio::net::{lookup_host, TcpListener, TcpSocket, TcpStream};
+
+use crate::config::Config;
+use crate::util::socks5::{self, Target};
+use crate::util::srv;
+
+/// Bind a TCP listener to the given address.
+///
+/// Applies socket options from the configuration, accepted streams must be configured separately
+/// through `configure_stream`.
+///
+/// Binding to the IPv6 wildcard address (`[::]`) listens dual-stack, accepting IPv4 clients too.
+pub fn bind_listener(config: &Config, addr: SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    #[cfg(unix)]
+    socket.set_reuse_address(true)?;
+    if addr.is_ipv6() && addr.ip().is_unspecified() {
+        socket.set_only_v6(false)?;
+    }
+    socket.set_nonblocking(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(i32::try_from(config.advanced.tcp_backlog).unwrap_or(i32::MAX))?;
+
+    TcpListener::from_std(socket.into())
+}
+
+/// Connect a TCP stream to the given address.
+///
+/// Applies socket options from the configuration, and binds to the configured outbound address.
+/// Tunnels through the configured SOCKS5 proxy, if any.
+pub async fn connect(config: &Config, addr: SocketAddr) -> io::Result<TcpStream> {
+    match config.advanced.socks5_proxy {
+        Some(proxy) => connect_socks5(config, proxy, Target::Addr(addr)).await,
+        None => connect_direct(config, addr).await,
+    }
+}
+
+/// Connect a TCP stream to the given host.
+///
+/// The configured SOCKS5 proxy resolves the hostname itself, unless the port is taken from an SRV
+/// record which is always resolved locally.
+async fn connect_host(config: &Config, host: &Host) -> io::Result<TcpStream> {
+    match (config.advanced.socks5_proxy, host.port) {
+        (Some(proxy), Some(port)) => {
+            connect_socks5(config, proxy, Target::Domain(&host.host, port)).await
+        }
+        _ => connect(config, host.resolve(dns_ttl(config)).await?).await,
+    }
+}
+
+/// Connect a TCP stream to the given target through the given SOCKS5 proxy.
+async fn connect_socks5(
+    config: &Config,
+    proxy: SocketAddr,
+    target: Target<'_>,
+) -> io::Result<TcpStream> {
+    let mut stream = connect_direct(config, proxy).await?;
+    socks5::connect(&mut stream, target).await?;
+    Ok(stream)
+}
+
+/// Connect a TCP stream to the given address directly, not through a proxy.
+async fn connect_direct(config: &Config, addr: SocketAddr) -> io::Result<TcpStream> {
+    let stream = match config.advanced.outbound_bind {
+        Some(ip) => {
+            let socket = match addr {
+                SocketAddr::V4(_) => TcpSocket::new_v4()?,
+                SocketAddr::V6(_) => TcpSocket::new_v6()?,
+            };
+            socket.bind(SocketAddr::new(ip, 0))?;
+            socket.connect(addr).await?
+        }
+        None => TcpStream::connect(addr).await?,
+    };
+    configure_stream(config, &stream)?;
+    Ok(stream)
+}
+
+/// Connect a stream to the given endpoint.
+///
+/// Applies socket options from the configuration to TCP streams.
+pub async fn connect_endpoint(config: &Config, endpoint: &Endpoint) -> io::Result<Stream> {
+    match endpoint {
+        Endpoint::Tcp(addr) => connect(config, *addr).await.map(Stream::Tcp),
+        Endpoint::Host(host) => connect_host(config, host).await.map(Stream::Tcp),
+        #[cfg(unix)]
+        Endpoint::Unix(path) => UnixStream::connect(path).await.map(Stream::Unix),
+    }
+}
+
+/// Get the TCP address of the given endpoint, `None` for a Unix socket.
+///
+/// Hostnames are resolved again once the cached address is older than the configured DNS TTL.
+/// Errors if a hostname was never resolved successfully.
+pub async fn resolve(config: &Config, endpoint: &Endpoint) -> io::Result<Option<SocketAddr>> {
+    match endpoint {
+        Endpoint::Tcp(addr) => Ok(Some(*addr)),
+        Endpoint::Host(host) => host.resolve(dns_ttl(config)).await.map(Some),
+        #[cfg(unix)]
+        Endpoint::Unix(_) => Ok(None),
+    }
+}
+
+/// Time resolved hostnames are cached for.
+fn dns_ttl(config: &Config) -> Duration {
+    Duration::from_secs(config.advanced.dns_ttl as u64)
+}
+
+/// Apply socket options from the configuration to the given TCP stream.
+pub fn configure_stream(config: &Config, stream: &TcpStream) -> io::Result<()> {
+    if config.advanced.tcp_nodelay {
+        stream.set_nodelay(true)?;
+    }
+    if config.advanced.tcp_keepalive {
+        SockRef::from(stream).set_keepalive(true)?;
+    }
+    Ok(())
+}
 
 /// Gracefully close given TCP stream.
 ///
@@ -20,3 +151,420 @@ pub async fn close_tcp_stream_ref(stream: &mut TcpStream) -> Result<(), Box<dyn
         Err(err) => Err(err.into()),
     }
 }
+
+/// Endpoint to connect to, a TCP address, a hostname or a Unix socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// TCP socket address.
+    Tcp(SocketAddr),
+
+    /// Hostname and port, resolved again periodically.
+    Host(Arc<Host>),
+
+    /// Unix socket path.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl Endpoint {
+    /// TCP socket address, `None` for a Unix socket or a hostname that isn't resolved yet.
+    pub fn tcp(&self) -> Option<SocketAddr> {
+        match self {
+            Self::Tcp(addr) => Some(*addr),
+            Self::Host(host) => host.addr(),
+            #[cfg(unix)]
+            Self::Unix(_) => None,
+        }
+    }
+
+    /// Host and port to send in handshake packets.
+    ///
+    /// A Unix socket has neither, uses localhost and the default Minecraft port.
+    pub fn handshake_host(&self) -> (String, u16) {
+        match self {
+            Self::Tcp(addr) => (addr.ip().to_string(), addr.port()),
+            Self::Host(host) => (
+                host.host.clone(),
+                host.port
+                    .or_else(|| host.addr().map(|addr| addr.port()))
+                    .unwrap_or(srv::DEFAULT_PORT),
+            ),
+            #[cfg(unix)]
+            Self::Unix(_) => ("localhost".into(), 25565),
+        }
+    }
+}
+
+impl From<SocketAddr> for Endpoint {
+    fn from(addr: SocketAddr) -> Self {
+        Self::Tcp(addr)
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => addr.fmt(f),
+            Self::Host(host) => host.fmt(f),
+            #[cfg(unix)]
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl Serialize for Endpoint {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.collect_str(self)
+    }
+}
+
+/// Hostname and port, resolved again once the cached address is older than the DNS TTL.
+///
+/// A bare hostname without port is resolved through its Minecraft SRV record when it is first
+/// used.
+#[derive(Debug)]
+pub struct Host {
+    /// Hostname.
+    host: String,
+
+    /// Port, `None` to use the SRV record.
+    port: Option<u16>,
+
+    /// Last resolved address, and when it was resolved.
+    resolved: Mutex<Option<(SocketAddr, Instant)>>,
+}
+
+impl Host {
+    /// Construct host, with the address it was just resolved to.
+    pub fn new(host: String, port: Option<u16>, addr: SocketAddr) -> Self {
+        Self {
+            host,
+            port,
+            resolved: Mutex::new(Some((addr, Instant::now()))),
+        }
+    }
+
+    /// Construct host, to resolve when it is first used.
+    pub fn unresolved(host: String, port: Option<u16>) -> Self {
+        Self {
+            host,
+            port,
+            resolved: Mutex::new(None),
+        }
+    }
+
+    /// Last resolved address, `None` if not resolved yet.
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.resolved.lock().unwrap().map(|(addr, _)| addr)
+    }
+
+    /// Get address, resolving the host again if the cached address is older than `ttl`.
+    ///
+    /// Keeps using the last resolved address if resolving fails, errors if there is none.
+    pub async fn resolve(&self, ttl: Duration) -> io::Result<SocketAddr> {
+        self.resolve_with(ttl, |host, port| async move {
+            match port {
+                Some(port) => lookup_host((host.as_str(), port))
+                    .await?
+                    .next()
+                    .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no addresses found")),
+                None => srv::resolve(&host).await,
+            }
+        })
+        .await
+    }
+
+    /// Get address, resolving the host again with the given resolver if the cached address is
+    /// older than `ttl`.
+    async fn resolve_with<F, R>(&self, ttl: Duration, resolver: F) -> io::Result<SocketAddr>
+    where
+        F: FnOnce(String, Option<u16>) -> R,
+        R: Future<Output = io::Result<SocketAddr>>,
+    {
+        let cached = *self.resolved.lock().unwrap();
+        if let Some((addr, resolved_at)) = cached {
+            if resolved_at.elapsed() < ttl {
+                return Ok(addr);
+            }
+        }
+
+        match (resolver(self.host.clone(), self.port).await, cached) {
+            (Ok(resolved), cached) => {
+                match cached {
+                    Some((addr, _)) if resolved != addr => {
+                        info!(target: "lazymc", "Address of {} changed from {} to {}", self, addr, resolved)
+                    }
+                    Some(_) => {}
+                    None => debug!(target: "lazymc", "Resolved {} to {}", self, resolved),
+                }
+                *self.resolved.lock().unwrap() = Some((resolved, Instant::now()));
+                Ok(resolved)
+            }
+            (Err(err), Some((addr, _))) => {
+                warn!(target: "lazymc", "Failed to resolve {}, using last known address {}: {}", self, addr, err);
+                Ok(addr)
+            }
+            (Err(err), None) => {
+                warn!(target: "lazymc", "Failed to resolve {}: {}", self, err);
+                Err(err)
+            }
+        }
+    }
+}
+
+impl PartialEq for Host {
+    fn eq(&self, other: &Self) -> bool {
+        self.host == other.host && self.port == other.port
+    }
+}
+
+impl Eq for Host {}
+
+impl fmt::Display for Host {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.port {
+            Some(port) => write!(f, "{}:{}", self.host, port),
+            None => write!(f, "{}", self.host),
+        }
+    }
+}
+
+/// Stream connected to an endpoint.
+#[derive(Debug)]
+pub enum Stream {
+    /// TCP stream.
+    Tcp(TcpStream),
+
+    /// Unix socket stream.
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl AsyncRead for Stream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for Stream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            #[cfg(unix)]
+            Self::Unix(stream) => Pin::new(stream).poll_shutdown(cx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_socket_options_applied() {
+        let config: Config =
+            crate::util::test::config("[advanced]\ntcp_nodelay = true\ntcp_keepalive = true");
+
+        let listener = bind_listener(&config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let outbound = connect(&config, listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, _) = listener.accept().await.unwrap();
+        configure_stream(&config, &inbound).unwrap();
+
+        for stream in [&inbound, &outbound] {
+            assert!(stream.nodelay().unwrap());
+            assert!(SockRef::from(stream).keepalive().unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_socks5_proxy() {
+        use tokio::io::{copy_bidirectional, AsyncReadExt};
+
+        let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let target_addr = target.local_addr().unwrap();
+
+        // Mock SOCKS5 proxy, connects to the requested address and relays
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut client, _) = proxy.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            client.read_exact(&mut greeting).await.unwrap();
+            client.write_all(&[5, 0]).await.unwrap();
+            let mut request = [0u8; 10];
+            client.read_exact(&mut request).await.unwrap();
+            let port = u16::from_be_bytes([request[8], request[9]]);
+            let addr = SocketAddr::from(([request[4], request[5], request[6], request[7]], port));
+            let mut upstream = TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            let _ = copy_bidirectional(&mut client, &mut upstream).await;
+        });
+
+        let config: Config =
+            crate::util::test::config(&format!("[advanced]\nsocks5_proxy = \"{proxy_addr}\""));
+        let mut outbound = connect(&config, target_addr).await.unwrap();
+        let (mut inbound, _) = target.accept().await.unwrap();
+
+        // Connection is tunneled to the target through the proxy
+        assert_eq!(outbound.peer_addr().unwrap(), proxy_addr);
+        outbound.write_all(b"ping").await.unwrap();
+        let mut data = [0u8; 4];
+        inbound.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"ping");
+    }
+
+    #[tokio::test]
+    async fn test_socks5_proxy_hostname() {
+        use tokio::io::AsyncReadExt;
+
+        // Mock SOCKS5 proxy, accepts the connection if it received the hostname
+        let proxy = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_addr = proxy.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut client, _) = proxy.accept().await.unwrap();
+            let mut greeting = [0u8; 3];
+            client.read_exact(&mut greeting).await.unwrap();
+            client.write_all(&[5, 0]).await.unwrap();
+            let mut request = [0u8; 5];
+            client.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [5, 1, 0, 3, 15]);
+            let mut host = [0u8; 15 + 2];
+            client.read_exact(&mut host).await.unwrap();
+            assert_eq!(&host, b"backend.invalid\x63\xDD");
+            client
+                .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+        });
+
+        // Hostname is not resolvable locally, the proxy resolves it
+        let config: Config =
+            crate::util::test::config(&format!("[advanced]\nsocks5_proxy = \"{proxy_addr}\""));
+        let endpoint = Endpoint::Host(Arc::new(Host::unresolved(
+            "backend.invalid".into(),
+            Some(25565),
+        )));
+        let outbound = connect_endpoint(&config, &endpoint).await.unwrap();
+        assert!(
+            matches!(outbound, Stream::Tcp(stream) if stream.peer_addr().unwrap() == proxy_addr)
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_outbound_bind() {
+        let config: Config = crate::util::test::config("[advanced]\noutbound_bind = \"127.0.0.2\"");
+
+        // Whole 127.0.0.0/8 block is local on Linux
+        let listener = bind_listener(&config, "127.0.0.1:0".parse().unwrap()).unwrap();
+        let outbound = connect(&config, listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (_inbound, peer) = listener.accept().await.unwrap();
+        assert_eq!(
+            outbound.local_addr().unwrap().ip(),
+            IpAddr::from([127, 0, 0, 2])
+        );
+        assert_eq!(peer, outbound.local_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_host_resolved_again_after_ttl() {
+        let old: SocketAddr = "10.0.0.1:25566".parse().unwrap();
+        let new: SocketAddr = "10.0.0.2:25566".parse().unwrap();
+        let host = Host::new("backend".into(), Some(25566), old);
+        let ttl = Duration::from_millis(100);
+
+        // Cached address is used within TTL, changed address is picked up after
+        let resolver = |_, _| async { Ok(new) };
+        assert_eq!(host.resolve_with(ttl, resolver).await.unwrap(), old);
+        tokio::time::sleep(ttl).await;
+        assert_eq!(host.resolve_with(ttl, resolver).await.unwrap(), new);
+        assert_eq!(host.addr(), Some(new));
+
+        // Last known address is kept if resolving fails
+        let failing = |_, _| async { Err(io::Error::new(io::ErrorKind::NotFound, "gone")) };
+        assert_eq!(
+            host.resolve_with(Duration::ZERO, failing).await.unwrap(),
+            new
+        );
+    }
+
+    #[tokio::test]
+    async fn test_host_resolved_on_first_use() {
+        let addr: SocketAddr = "10.0.0.1:25570".parse().unwrap();
+        let host = Host::unresolved("mc.example.com".into(), None);
+        let ttl = Duration::from_secs(60);
+        assert_eq!(host.addr(), None);
+
+        // Fails without a last known address, resolved address is cached
+        let failing = |_, _| async { Err(io::Error::new(io::ErrorKind::NotFound, "gone")) };
+        assert!(host.resolve_with(ttl, failing).await.is_err());
+        let resolver = |_, port| async move {
+            assert_eq!(port, None);
+            Ok(addr)
+        };
+        assert_eq!(host.resolve_with(ttl, resolver).await.unwrap(), addr);
+        assert_eq!(host.resolve_with(ttl, failing).await.unwrap(), addr);
+        assert_eq!(
+            Endpoint::Host(Arc::new(host)).handshake_host(),
+            ("mc.example.com".into(), 25570)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_dual_stack_listener() {
+        let config: Config = crate::util::test::config("");
+
+        // Skip if IPv6 is not available on this host
+        let listener = match bind_listener(&config, "[::]:0".parse().unwrap()) {
+            Ok(listener) => listener,
+            Err(_) => return,
+        };
+        let port = listener.local_addr().unwrap().port();
+
+        for ip in ["127.0.0.1", "::1"] {
+            let addr = SocketAddr::new(ip.parse().unwrap(), port);
+            let _stream = connect(&config, addr).await.unwrap();
+            listener.accept().await.unwrap();
+        }
+    }
+}
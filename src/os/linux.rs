@@ -0,0 +1,81 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+
+/// Find a descendant of the given process by its command name, closest first.
+///
+/// Scans `/proc`, returns `None` if no such descendant is running.
+pub fn find_descendant(pid: u32, name: &str) -> Option<u32> {
+    // Map every process to its children
+    let mut children: HashMap<u32, Vec<(u32, String)>> = HashMap::new();
+    for entry in fs::read_dir("/proc").ok()?.flatten() {
+        let child = match entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse().ok())
+        {
+            Some(child) => child,
+            None => continue,
+        };
+        if let Some((comm, parent)) = stat(child) {
+            children.entry(parent).or_default().push((child, comm));
+        }
+    }
+
+    // Walk descendants breadth first
+    let mut queue = VecDeque::from([pid]);
+    while let Some(parent) = queue.pop_front() {
+        for (child, comm) in children.get(&parent).into_iter().flatten() {
+            if comm == name {
+                return Some(*child);
+            }
+            queue.push_back(*child);
+        }
+    }
+    None
+}
+
+/// Read command name and parent PID of the given process.
+fn stat(pid: u32) -> Option<(String, u32)> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+
+    // Command name is parenthesized and may contain anything, fields follow the last parenthesis
+    let (comm, fields) = stat.split_once('(')?.1.rsplit_once(')')?;
+    let parent = fields.split_whitespace().nth(1)?.parse().ok()?;
+    Some((comm.into(), parent))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+    use std::thread;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_find_descendant() {
+        // Shell wrapping the actual process, like a server start script
+        let mut parent = Command::new("sh")
+            .args(["-c", "sleep 30; true"])
+            .spawn()
+            .unwrap();
+
+        let mut child = None;
+        for _ in 0..100 {
+            child = find_descendant(parent.id(), "sleep");
+            if child.is_some() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let child = child.expect("child process not found");
+        assert_ne!(child, parent.id());
+        assert_eq!(stat(child), Some(("sleep".into(), parent.id())));
+        assert_eq!(find_descendant(parent.id(), "java"), None);
+
+        let _ = crate::os::force_kill(child, false);
+        parent.kill().unwrap();
+        parent.wait().unwrap();
+    }
+}
@@ -1,91 +1,214 @@
+#[cfg(target_os = "linux")]
+pub mod linux;
 #[cfg(windows)]
 pub mod windows;
 
 #[cfg(unix)]
 use nix::{
+    errno::Errno,
     sys::signal::{self, Signal},
     unistd::Pid,
 };
 
-/// Force kill process.
+/// Reason signalling a process failed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SignalError {
+    /// No such process, it quit already or the PID is stale.
+    NoProcess,
+
+    /// Not permitted to signal the process.
+    PermissionDenied,
+
+    /// Signalling processes this way is not supported on this platform.
+    Unsupported,
+
+    /// Any other failure.
+    Failed,
+}
+
+impl SignalError {
+    /// Hint on what to do about this failure, if any.
+    #[cfg(unix)]
+    pub fn hint(self) -> Option<&'static str> {
+        match self {
+            Self::NoProcess => Some("the server process likely quit already, or its PID is stale"),
+            Self::PermissionDenied => Some(
+                "lazymc is not allowed to signal the server process, make sure both run as the same user",
+            ),
+            Self::Unsupported | Self::Failed => None,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<Errno> for SignalError {
+    fn from(errno: Errno) -> Self {
+        match errno {
+            Errno::ESRCH => Self::NoProcess,
+            Errno::EPERM => Self::PermissionDenied,
+            _ => Self::Failed,
+        }
+    }
+}
+
+/// Force kill process, or its process group if `group` is set.
 ///
 /// Results in undefined behavior if PID is invalid.
-#[allow(unreachable_code)]
-pub fn force_kill(pid: u32) -> bool {
+#[allow(unreachable_code, unused_variables)]
+pub fn force_kill(pid: u32, group: bool) -> Result<(), SignalError> {
     #[cfg(unix)]
-    return unix_signal(pid, Signal::SIGKILL);
+    return unix_signal(pid, Signal::SIGKILL, group);
 
     #[cfg(windows)]
     unsafe {
-        return windows::force_kill(pid);
+        return if windows::force_kill(pid) {
+            Ok(())
+        } else {
+            Err(SignalError::Failed)
+        };
     }
 
     unimplemented!("force killing Minecraft server process not implemented on this platform");
 }
 
-/// Gracefully kill process.
+/// Gracefully kill process, or its process group if `group` is set.
 /// Results in undefined behavior if PID is invalid.
 ///
 /// # Panics
 /// Panics on platforms other than Unix.
 #[allow(unreachable_code, dead_code, unused_variables)]
-pub fn kill_gracefully(pid: u32) -> bool {
+pub fn kill_gracefully(pid: u32, group: bool) -> Result<(), SignalError> {
     #[cfg(unix)]
-    return unix_signal(pid, Signal::SIGTERM);
+    return unix_signal(pid, Signal::SIGTERM, group);
 
     unimplemented!(
         "gracefully killing Minecraft server process not implemented on non-Unix platforms"
     );
 }
 
-/// Freeze process.
+/// Freeze process, or its process group if `group` is set.
 /// Results in undefined behavior if PID is invaild.
 ///
-/// # Panics
-/// Panics on platforms other than Unix.
-#[allow(unreachable_code)]
-pub fn freeze(pid: u32) -> bool {
+/// Always fails on platforms other than Unix.
+#[allow(unreachable_code, unused_variables)]
+pub fn freeze(pid: u32, group: bool) -> Result<(), SignalError> {
     #[cfg(unix)]
-    return unix_signal(pid, Signal::SIGSTOP);
+    return unix_signal(pid, Signal::SIGSTOP, group);
 
-    unimplemented!(
-        "freezing the Minecraft server process is not implemented on non-Unix platforms"
-    );
+    error!(target: "lazymc", "Freezing the server process is not supported on this platform");
+    Err(SignalError::Unsupported)
 }
 
-/// Unfreeze process.
+/// Unfreeze process, or its process group if `group` is set.
 /// Results in undefined behavior if PID is invaild.
 ///
-/// # Panics
-/// Panics on platforms other than Unix.
-#[allow(unreachable_code)]
-pub fn unfreeze(pid: u32) -> bool {
+/// Always fails on platforms other than Unix.
+#[allow(unreachable_code, unused_variables)]
+pub fn unfreeze(pid: u32, group: bool) -> Result<(), SignalError> {
     #[cfg(unix)]
-    return unix_signal(pid, Signal::SIGCONT);
+    return unix_signal(pid, Signal::SIGCONT, group);
 
-    unimplemented!(
-        "unfreezing the Minecraft server process is not implemented on non-Unix platforms"
+    error!(target: "lazymc", "Unfreezing the server process is not supported on this platform");
+    Err(SignalError::Unsupported)
+}
+
+/// Check whether a process with the given PID is alive.
+///
+/// A process we're not permitted to signal is alive as well.
+/// Always returns false on platforms other than Unix.
+#[allow(unreachable_code, unused_variables)]
+pub fn is_alive(pid: u32) -> bool {
+    #[cfg(unix)]
+    return matches!(
+        signal::kill(Pid::from_raw(pid as i32), None),
+        Ok(()) | Err(Errno::EPERM)
     );
+
+    false
 }
 
 #[cfg(unix)]
-pub fn unix_signal(pid: u32, signal: Signal) -> bool {
+pub fn unix_signal(pid: u32, signal: Signal, group: bool) -> Result<(), SignalError> {
     // Send signal to the process group (negative PID) so all child processes
     // receive it. This is critical for modded servers launched via wrapper scripts,
     // where the direct PID is the shell and Java runs as a child process.
-    let pgid = -(pid as i32);
-    match signal::kill(Pid::from_raw(pgid), signal) {
-        Ok(()) => true,
-        Err(_) => {
-            // Fallback to sending directly to the process if process group signal fails
-            debug!(target: "lazymc", "Process group signal {signal} failed, trying direct PID");
-            match signal::kill(Pid::from_raw(pid as i32), signal) {
-                Ok(()) => true,
-                Err(err) => {
-                    warn!(target: "lazymc", "Sending {signal} signal to server failed: {err}");
-                    false
-                }
-            }
+    let mut result = signal::kill(signal_target(pid, group), signal);
+
+    // Fallback to sending directly to the process if process group signal fails
+    if let (true, Err(err)) = (group, result) {
+        debug!(target: "lazymc", "Process group signal {signal} failed ({err}), trying direct PID");
+        result = signal::kill(signal_target(pid, false), signal);
+    }
+
+    let err = match result {
+        Ok(()) => return Ok(()),
+        Err(err) => err,
+    };
+
+    let reason = SignalError::from(err);
+    match reason.hint() {
+        Some(hint) => {
+            warn!(target: "lazymc", "Sending {signal} signal to server process {pid} failed: {err}, {hint}")
+        }
+        None => {
+            warn!(target: "lazymc", "Sending {signal} signal to server process {pid} failed: {err}")
         }
     }
+    Err(reason)
+}
+
+/// Get the PID to signal for the given process, its process group (negative PID) if enabled.
+#[cfg(unix)]
+fn signal_target(pid: u32, group: bool) -> Pid {
+    if group {
+        Pid::from_raw(-(pid as i32))
+    } else {
+        Pid::from_raw(pid as i32)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_error_from_errno() {
+        assert_eq!(SignalError::from(Errno::ESRCH), SignalError::NoProcess);
+        assert_eq!(
+            SignalError::from(Errno::EPERM),
+            SignalError::PermissionDenied
+        );
+        assert_eq!(SignalError::from(Errno::EINVAL), SignalError::Failed);
+
+        // Only actionable failures have a hint
+        assert!(SignalError::NoProcess.hint().unwrap().contains("stale"));
+        assert!(SignalError::PermissionDenied
+            .hint()
+            .unwrap()
+            .contains("same user"));
+        assert_eq!(SignalError::Failed.hint(), None);
+    }
+
+    #[test]
+    fn test_is_alive() {
+        assert!(is_alive(std::process::id()));
+        assert!(!is_alive(0x7fff_fff0));
+
+        // Init is alive, even if we're not permitted to signal it
+        assert!(is_alive(1));
+    }
+
+    #[test]
+    fn test_signal_target() {
+        assert_eq!(signal_target(1234, true), Pid::from_raw(-1234));
+        assert_eq!(signal_target(1234, false), Pid::from_raw(1234));
+    }
+
+    #[test]
+    fn test_unix_signal_no_process() {
+        assert_eq!(
+            unix_signal(0x7fff_fff0, Signal::SIGCONT, false),
+            Err(SignalError::NoProcess)
+        );
+    }
 }
@@ -0,0 +1,390 @@
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::os;
+
+/// File name.
+pub const FILE: &str = "lazymc.state.json";
+
+/// Statistics file name.
+pub const STATS_FILE: &str = "lazymc.stats.json";
+
+/// Server process PID file name.
+pub const SERVER_PID_FILE: &str = "lazymc.server.pid";
+
+/// lazymc lock file name.
+pub const LOCK_FILE: &str = "lazymc.lock";
+
+/// Randomized RCON password file name.
+#[cfg(feature = "rcon")]
+pub const RCON_PASSWORD_FILE: &str = "lazymc.rcon-password";
+
+/// Maximum number of samples the startup time average is weighted over.
+///
+/// Older samples fade out, so the average follows the server if it gets slower or faster.
+const STARTUP_SAMPLES: u32 = 10;
+
+/// State lazymc persists across restarts.
+///
+/// Stored in the server directory.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct State {
+    /// Learned server startup time.
+    pub startup: StartupTime,
+
+    /// Last known server version.
+    pub version: Option<Version>,
+
+    /// Last known server activity.
+    pub activity: Option<Activity>,
+}
+
+/// Server activity, to resume an active server after lazymc restarts.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Activity {
+    /// Whether the server was online.
+    pub online: bool,
+
+    /// Time of the last activity on the server, in seconds since the Unix epoch.
+    pub last_active: u64,
+}
+
+impl Activity {
+    /// Whether the server was online, and active within the given number of seconds before `now`.
+    pub fn is_recent(&self, window: u32, now: u64) -> bool {
+        self.online && now.saturating_sub(self.last_active) <= window as u64
+    }
+}
+
+/// Server version, as reported in the server status.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Version {
+    /// Version name.
+    pub name: String,
+
+    /// Protocol version.
+    pub protocol: u32,
+}
+
+/// Learned server startup time.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StartupTime {
+    /// Average startup time in seconds.
+    pub average: f64,
+
+    /// Number of samples in the average, capped at `STARTUP_SAMPLES`.
+    pub samples: u32,
+}
+
+impl StartupTime {
+    /// Record a new startup time sample.
+    pub fn record(&mut self, duration: Duration) {
+        self.samples = (self.samples + 1).min(STARTUP_SAMPLES);
+        self.average += (duration.as_secs_f64() - self.average) / self.samples as f64;
+    }
+
+    /// Get the average startup time, if any was recorded.
+    pub fn average(&self) -> Option<Duration> {
+        if self.samples == 0 {
+            return None;
+        }
+        Some(Duration::from_secs_f64(self.average.max(0.0)))
+    }
+}
+
+/// Cumulative server statistics.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Total time in seconds the server was online.
+    pub uptime: f64,
+
+    /// Total time in seconds the server was sleeping.
+    pub sleep_time: f64,
+
+    /// Number of times the server was woken.
+    pub wakes: u64,
+
+    /// Number of times the server crashed.
+    pub crashes: u64,
+
+    /// Number of attempts to wake the server blocked by the whitelist.
+    pub blocked_wakes: u64,
+}
+
+/// Load persisted state from file.
+///
+/// Returns the default state if the file does not exist.
+pub fn load<T: DeserializeOwned + Default>(path: &Path) -> Result<T, Box<dyn Error>> {
+    if !path.is_file() {
+        return Ok(T::default());
+    }
+
+    let contents = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&contents)?)
+}
+
+/// Read PID from file.
+///
+/// Returns `None` if the file does not exist or is invalid.
+pub fn read_pid(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Write PID to file.
+pub fn write_pid(path: &Path, pid: u32) -> Result<(), Box<dyn Error>> {
+    fs::write(path, format!("{pid}\n"))?;
+    Ok(())
+}
+
+/// Read secret from file.
+///
+/// Returns `None` if the file does not exist or is empty.
+#[cfg(feature = "rcon")]
+pub fn read_secret(path: &Path) -> Option<String> {
+    let secret = fs::read_to_string(path).ok()?.trim().to_string();
+    (!secret.is_empty()).then_some(secret)
+}
+
+/// Write secret to file, only readable by the current user on Unix.
+#[cfg(feature = "rcon")]
+pub fn write_secret(path: &Path, secret: &str) -> Result<(), io::Error> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(path)?;
+    writeln!(file, "{secret}")
+}
+
+/// Acquire lock file for this lazymc instance, holding our own PID.
+///
+/// A lock left behind by a process that is no longer alive is stale, and is reclaimed.
+///
+/// Errors with the PID of the other instance if the lock is held.
+pub fn lock(path: &Path) -> Result<(), LockError> {
+    let pid = std::process::id();
+
+    for _ in 0..2 {
+        match OpenOptions::new().write(true).create_new(true).open(path) {
+            Ok(mut file) => {
+                writeln!(file, "{pid}")?;
+                return Ok(());
+            }
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        // Lock exists, refuse if held by another live instance, reclaim otherwise
+        match read_pid(path) {
+            Some(other) if other != pid && os::is_alive(other) => {
+                return Err(LockError::Held(other))
+            }
+            _ => fs::remove_file(path)?,
+        }
+    }
+
+    Err(io::Error::new(io::ErrorKind::AlreadyExists, "lock file keeps reappearing").into())
+}
+
+/// Release lock file, if it is held by this lazymc instance.
+pub fn unlock(path: &Path) {
+    if read_pid(path) == Some(std::process::id()) {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Lock file error.
+#[derive(Debug)]
+pub enum LockError {
+    /// Lock is held by another live process with the given PID.
+    Held(u32),
+
+    /// Failed to read or write the lock file.
+    Io(io::Error),
+}
+
+impl From<io::Error> for LockError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Save persisted state to file.
+pub fn save<T: Serialize>(path: &Path, state: &T) -> Result<(), Box<dyn Error>> {
+    fs::write(path, serde_json::to_string_pretty(state)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_startup_time_average() {
+        let mut startup = StartupTime::default();
+        assert_eq!(startup.average(), None);
+
+        startup.record(Duration::from_secs(20));
+        startup.record(Duration::from_secs(40));
+        assert_eq!(startup.average(), Some(Duration::from_secs(30)));
+        assert_eq!(startup.samples, 2);
+    }
+
+    #[test]
+    fn test_startup_time_average_caps_samples() {
+        let mut startup = StartupTime::default();
+        for _ in 0..STARTUP_SAMPLES * 2 {
+            startup.record(Duration::from_secs(10));
+        }
+        assert_eq!(startup.samples, STARTUP_SAMPLES);
+
+        // New samples keep moving the average
+        startup.record(Duration::from_secs(110));
+        assert_eq!(startup.average(), Some(Duration::from_secs(20)));
+    }
+
+    #[test]
+    fn test_load_save_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("lazymc-test-{}-{}", std::process::id(), FILE));
+        assert_eq!(load::<State>(&path).unwrap().startup.samples, 0);
+
+        let mut state = State::default();
+        state.startup.record(Duration::from_secs(42));
+        save(&path, &state).unwrap();
+
+        let loaded: State = load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.startup.samples, 1);
+        assert_eq!(loaded.startup.average(), Some(Duration::from_secs(42)));
+    }
+
+    #[test]
+    fn test_load_save_version_roundtrip() {
+        let path = std::env::temp_dir().join(format!(
+            "lazymc-test-version-roundtrip-{}-{}",
+            std::process::id(),
+            FILE
+        ));
+
+        let state = State {
+            version: Some(Version {
+                name: "1.20.4".into(),
+                protocol: 765,
+            }),
+            ..Default::default()
+        };
+        save(&path, &state).unwrap();
+
+        let loaded: State = load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.version, state.version);
+
+        // State files without a version still load
+        assert_eq!(
+            serde_json::from_str::<State>(r#"{"startup":{"average":1.0,"samples":1}}"#)
+                .unwrap()
+                .version,
+            None
+        );
+    }
+
+    #[test]
+    fn test_activity_is_recent() {
+        let activity = Activity {
+            online: true,
+            last_active: 1_000,
+        };
+        assert!(activity.is_recent(300, 1_200));
+        assert!(!activity.is_recent(300, 1_400));
+
+        // Server that went to sleep is never recent
+        let activity = Activity {
+            online: false,
+            ..activity
+        };
+        assert!(!activity.is_recent(300, 1_000));
+    }
+
+    #[cfg(feature = "rcon")]
+    #[test]
+    fn test_write_read_secret() {
+        let path = std::env::temp_dir().join(format!(
+            "lazymc-test-secret-{}-{}",
+            std::process::id(),
+            RCON_PASSWORD_FILE
+        ));
+        assert_eq!(read_secret(&path), None);
+
+        write_secret(&path, "hunter2").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "hunter2\n");
+        assert_eq!(read_secret(&path), Some("hunter2".into()));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = fs::remove_file(&path);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_lock_held() {
+        let path = std::env::temp_dir().join(format!(
+            "lazymc-test-lock-held-{}-{}",
+            std::process::id(),
+            LOCK_FILE
+        ));
+
+        // Lock held by another live instance
+        let mut other = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        write_pid(&path, other.id()).unwrap();
+        let result = lock(&path);
+        assert!(matches!(result, Err(LockError::Held(pid)) if pid == other.id()));
+
+        // Lock of other instance is left alone
+        unlock(&path);
+        assert_eq!(read_pid(&path), Some(other.id()));
+
+        other.kill().unwrap();
+        other.wait().unwrap();
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn test_lock_stale() {
+        let path = std::env::temp_dir().join(format!(
+            "lazymc-test-lock-stale-{}-{}",
+            std::process::id(),
+            LOCK_FILE
+        ));
+
+        // Lock of dead instance is reclaimed
+        write_pid(&path, i32::MAX as u32).unwrap();
+        lock(&path).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+
+        unlock(&path);
+        assert!(!path.exists());
+    }
+}
@@ -15,6 +15,7 @@ use crate::config::Config;
 use crate::forge;
 use crate::net;
 use crate::proto::client::{Client, ClientInfo, ClientState};
+use crate::proto::packets::configuration;
 use crate::proto::packets::play::join_game::JoinGameData;
 use crate::proto::{self, packet, packets};
 use crate::server::{Server, State};
@@ -35,11 +36,14 @@ const PROBE_ONLINE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
 const PROBE_JOIN_GAME_TIMEOUT: Duration = Duration::from_secs(20);
 
 /// Connect to the Minecraft server and probe useful details from it.
-pub async fn probe(config: Arc<Config>, server: Arc<Server>) -> Result<(), ()> {
+///
+/// Returns `true` if the server was started for probing.
+pub async fn probe(config: Arc<Config>, server: Arc<Server>) -> Result<bool, ()> {
     debug!(target: "lazymc::probe", "Starting server probe...");
 
     // Start server if not starting already
-    if Server::start(config.clone(), server.clone(), None).await {
+    let started = Server::start(config.clone(), server.clone(), None).await;
+    if started {
         info!(target: "lazymc::probe", "Starting server to probe...");
     }
 
@@ -55,7 +59,7 @@ pub async fn probe(config: Arc<Config>, server: Arc<Server>) -> Result<(), ()> {
     let forge_payload = connect_to_server(&config, &server).await?;
     *server.forge_payload.write().await = forge_payload;
 
-    Ok(())
+    Ok(started)
 }
 
 /// Wait for the server to come online.
@@ -90,7 +94,7 @@ async fn wait_until_online<'a>(server: &Server) -> Result<bool, ()> {
                 }
 
                 // Server stopped, this shouldn't happen, skip
-                State::Stopped => {
+                State::Stopped | State::Frozen => {
                     error!(target: "lazymc::probe", "Server stopped while trying to probe, skipping");
                     break false;
                 }
@@ -138,9 +142,13 @@ async fn connect_to_server_no_timeout(
 ) -> Result<Vec<Vec<u8>>, ()> {
     // Open connection
     // TODO: on connect fail, ping server and redirect to serve_status if offline
-    let mut outbound = TcpStream::connect(config.server.address)
+    let addr = net::resolve(config, &config.server.address)
         .await
-        .map_err(|_| ())?;
+        .map_err(|_| ())?
+        .ok_or_else(|| {
+            error!(target: "lazymc::probe", "Probing server over Unix socket is not supported");
+        })?;
+    let mut outbound = net::connect(config, addr).await.map_err(|_| ())?;
 
     // Construct temporary server client
     let tmp_client = match outbound.local_addr() {
@@ -156,10 +164,10 @@ async fn connect_to_server_no_timeout(
     let (mut reader, mut writer) = outbound.split();
 
     // Select server address to use, add magic if Forge
-    let server_addr = if config.server.forge {
-        format!("{}{}", config.server.address.ip(), forge::STATUS_MAGIC)
+    let server_addr = if server.is_forge(config) {
+        format!("{}{}", addr.ip(), forge::STATUS_MAGIC)
     } else {
-        config.server.address.ip().to_string()
+        addr.ip().to_string()
     };
 
     // Send handshake packet
@@ -167,7 +175,7 @@ async fn connect_to_server_no_timeout(
         Handshake {
             protocol_version: config.public.protocol as i32,
             server_addr,
-            server_port: config.server.address.port(),
+            server_port: addr.port(),
             next_state: ClientState::Login.to_id(),
         },
         &tmp_client,
@@ -235,7 +243,7 @@ async fn connect_to_server_no_timeout(
             })?;
 
             // Handle plugin requests for Forge
-            if config.server.forge {
+            if server.is_forge(config) {
                 // Record Forge login payload
                 forge_payload.push(raw);
 
@@ -266,6 +274,17 @@ async fn connect_to_server_no_timeout(
         if client_state == ClientState::Login && packet.id == packets::login::CLIENT_LOGIN_SUCCESS {
             trace!(target: "lazymc::probe", "Got login success from server connection, change to play mode");
 
+            // Go through configuration state
+            if configuration::has_configuration(config.public.protocol) {
+                if !configuration::is_supported(config.public.protocol) {
+                    error!(target: "lazymc::probe", "Configuration state of protocol version {} is not supported", config.public.protocol);
+                    break;
+                }
+
+                configuration::configure_client(&tmp_client, &mut reader, &mut buf, &mut writer)
+                    .await?;
+            }
+
             // Switch to play state
             tmp_client.set_state(ClientState::Play);
 
@@ -79,6 +79,9 @@ pub enum ClientState {
     /// State to login to server.
     Login,
 
+    /// State to configure client before play, since Minecraft 1.20.2.
+    Configuration,
+
     /// State to play on the server.
     #[allow(unused)]
     Play,
@@ -91,6 +94,8 @@ impl ClientState {
             0 => Some(Self::Handshake),
             1 => Some(Self::Status),
             2 => Some(Self::Login),
+            // Transfer, logs in after being transferred from another server since 1.20.5
+            3 => Some(Self::Login),
             _ => None,
         }
     }
@@ -101,7 +106,7 @@ impl ClientState {
             Self::Handshake => 0,
             Self::Status => 1,
             Self::Login => 2,
-            Self::Play => -1,
+            Self::Configuration | Self::Play => -1,
         }
     }
 }
@@ -123,6 +128,9 @@ pub struct ClientInfo {
 
     /// Client username.
     pub username: Option<String>,
+
+    /// Whether this client woke the sleeping server.
+    pub woke: bool,
 }
 
 impl ClientInfo {
@@ -0,0 +1,111 @@
+//! Legacy server list ping, as sent by pre-1.7 clients and some server list tools.
+
+use minecraft_protocol::version::v1_20_3::status::ServerStatus;
+
+use crate::util::text::strip_formatting;
+
+/// Legacy server list ping packet ID.
+///
+/// Sent as first byte on the connection, instead of a modern handshake packet length.
+pub const PING: u8 = 0xFE;
+
+/// Legacy ping payload byte, sent after the packet ID by 1.4 and newer clients.
+const PING_PAYLOAD: u8 = 0x01;
+
+/// Legacy kick packet ID, used to respond to a legacy ping.
+const KICK: u8 = 0xFF;
+
+/// Check whether the given first bytes of a connection are a legacy ping.
+pub fn is_ping(data: &[u8]) -> bool {
+    data.first() == Some(&PING)
+}
+
+/// Encode legacy ping response for the given server status.
+///
+/// Responds in the 1.4+ format if the client sent the ping payload, and in the plain beta format
+/// otherwise.
+pub fn status_response(request: &[u8], status: &ServerStatus) -> Vec<u8> {
+    let motd = status.description.replace('\n', " ");
+
+    let response = if request.get(1) == Some(&PING_PAYLOAD) {
+        format!(
+            "§1\0{}\0{}\0{}\0{}\0{}",
+            status.version.protocol,
+            status.version.name,
+            motd,
+            status.players.online,
+            status.players.max,
+        )
+    } else {
+        // Section sign is the field separator here, strip formatting codes from MOTD
+        format!(
+            "{}§{}§{}",
+            strip_formatting(&motd),
+            status.players.online,
+            status.players.max,
+        )
+    };
+
+    let chars: Vec<u16> = response.encode_utf16().collect();
+    let mut data = Vec::with_capacity(3 + chars.len() * 2);
+    data.push(KICK);
+    data.extend_from_slice(&(chars.len() as u16).to_be_bytes());
+    for c in chars {
+        data.extend_from_slice(&c.to_be_bytes());
+    }
+    data
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::data::server_status::{OnlinePlayers, ServerVersion};
+
+    use super::*;
+
+    fn status() -> ServerStatus {
+        ServerStatus {
+            version: ServerVersion {
+                name: "1.20.3".into(),
+                protocol: 765,
+            },
+            players: OnlinePlayers {
+                online: 0,
+                max: 20,
+                sample: vec![],
+            },
+            description: "§2Sleeping\nJoin to start".into(),
+            favicon: None,
+        }
+    }
+
+    /// Decode response string, asserting the header is valid.
+    fn decode(response: &[u8]) -> String {
+        assert_eq!(response[0], KICK);
+        let len = u16::from_be_bytes([response[1], response[2]]) as usize;
+        assert_eq!(response.len(), 3 + len * 2);
+        let chars: Vec<u16> = response[3..]
+            .chunks(2)
+            .map(|c| u16::from_be_bytes([c[0], c[1]]))
+            .collect();
+        String::from_utf16(&chars).unwrap()
+    }
+
+    #[test]
+    fn test_legacy_ping_response() {
+        assert!(is_ping(&[0xFE, 0x01, 0xFA]));
+        assert!(!is_ping(&[0x10, 0x00]));
+
+        let response = decode(&status_response(&[0xFE, 0x01, 0xFA], &status()));
+        let fields: Vec<&str> = response.split('\0').collect();
+        assert_eq!(
+            fields,
+            ["§1", "765", "1.20.3", "§2Sleeping Join to start", "0", "20"]
+        );
+    }
+
+    #[test]
+    fn test_legacy_ping_response_beta() {
+        let response = decode(&status_response(&[0xFE], &status()));
+        assert_eq!(response, "Sleeping Join to start§0§20");
+    }
+}
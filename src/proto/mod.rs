@@ -1,5 +1,6 @@
 pub mod action;
 pub mod client;
+pub mod legacy;
 pub mod packet;
 pub mod packets;
 
@@ -8,16 +8,21 @@ use flate2::Compression;
 use minecraft_protocol::encoder::Encoder;
 use minecraft_protocol::version::PacketId;
 use tokio::io;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::tcp::{ReadHalf, WriteHalf};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 
 use crate::proto::client::Client;
 use crate::proto::BUF_SIZE;
 use crate::types;
 
+/// Maximum packet length the Minecraft protocol allows.
+///
+/// Longer packet lengths are rejected as malformed, instead of buffering bytes until they arrive.
+pub const MAX_PACKET_LEN: i32 = 2_097_151;
+
 /// Raw Minecraft packet.
 ///
 /// Having a packet ID and a raw data byte array.
+#[derive(Debug)]
 pub struct RawPacket {
     /// Packet ID.
     pub id: u8,
@@ -76,6 +81,12 @@ impl RawPacket {
             return Self::read_packet_id_data(buf);
         }
 
+        // Packets below the threshold must not be compressed
+        if data_len < client.compressed() {
+            error!(target: "lazymc", "Compressed packet is below compression threshold ({}b < {}b)", data_len, client.compressed());
+            return Err(());
+        }
+
         // Decompress packet ID and data section
         let mut decompressed = Vec::with_capacity(data_len as usize);
         ZlibDecoder::new(buf)
@@ -161,10 +172,10 @@ impl RawPacket {
 pub async fn read_packet(
     client: &Client,
     buf: &mut BytesMut,
-    stream: &mut ReadHalf<'_>,
+    stream: &mut (impl AsyncRead + Unpin),
 ) -> Result<Option<(RawPacket, Vec<u8>)>, ()> {
-    // Keep reading until we have at least 2 bytes
-    while buf.len() < 2 {
+    // Keep reading until we have at least 2 bytes, and the full packet length
+    while buf.len() < 2 || is_partial_var_int(buf) {
         // Read packet from socket
         let mut tmp = Vec::with_capacity(BUF_SIZE);
         match stream.read_buf(&mut tmp).await {
@@ -190,6 +201,10 @@ pub async fn read_packet(
             return Err(err);
         }
     };
+    if !(0..=MAX_PACKET_LEN).contains(&len) {
+        error!(target: "lazymc", "Malformed packet, invalid packet length ({})", len);
+        return Err(());
+    }
 
     // Keep reading until we have all packet bytes
     while buf.len() < consumed + len as usize {
@@ -219,11 +234,16 @@ pub async fn read_packet(
     Ok(Some((packet, raw.to_vec())))
 }
 
+/// Whether the buffer holds the start of a var-int, which more bytes may complete.
+fn is_partial_var_int(buf: &[u8]) -> bool {
+    buf.len() < 5 && buf.iter().all(|byte| byte & 0x80 != 0)
+}
+
 /// Write packet to stream writer.
 pub async fn write_packet(
     packet: impl PacketId + Encoder + Debug,
     client: &Client,
-    writer: &mut WriteHalf<'_>,
+    writer: &mut (impl AsyncWrite + Unpin),
 ) -> Result<(), ()> {
     let mut data = Vec::new();
     packet.encode(&mut data).map_err(|_| ())?;
@@ -233,3 +253,102 @@ pub async fn write_packet(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::test::stream_pair;
+
+    #[tokio::test]
+    async fn test_read_compressed_packets() {
+        let (mut writer, mut reader) = stream_pair().await;
+        let client = Client::dummy();
+        client.set_compression(64);
+
+        // Packet above threshold is compressed, packet below it is not
+        let large = RawPacket::new(0x10, vec![7; 200]);
+        let small = RawPacket::new(0x11, vec![1, 2, 3]);
+        let large_raw = large.encode_with_len(&client).unwrap();
+        let small_raw = small.encode_with_len(&client).unwrap();
+        assert!(large_raw.len() < 200);
+        writer.write_all(&large_raw).await.unwrap();
+        writer.write_all(&small_raw).await.unwrap();
+        drop(writer);
+
+        let mut buf = BytesMut::new();
+        let (mut read, _) = reader.split();
+        let (packet, raw) = read_packet(&client, &mut buf, &mut read)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((packet.id, packet.data), (0x10, vec![7; 200]));
+        assert_eq!(raw, large_raw);
+
+        let (packet, _) = read_packet(&client, &mut buf, &mut read)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!((packet.id, packet.data), (0x11, vec![1, 2, 3]));
+        assert!(read_packet(&client, &mut buf, &mut read)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_partial_packet_length() {
+        let (mut writer, mut reader) = stream_pair().await;
+        let client = Client::dummy();
+
+        // Length var-int of large packet arrives in pieces
+        let raw = RawPacket::new(0x10, vec![7; 20000])
+            .encode_with_len(&client)
+            .unwrap();
+        let send = async {
+            writer.write_all(&raw[..2]).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            writer.write_all(&raw[2..]).await.unwrap();
+        };
+
+        let mut buf = BytesMut::new();
+        let (mut read, _) = reader.split();
+        let (_, result) = tokio::join!(send, read_packet(&client, &mut buf, &mut read));
+        let (packet, _) = result.unwrap().unwrap();
+        assert_eq!((packet.id, packet.data.len()), (0x10, 20000));
+    }
+
+    #[tokio::test]
+    async fn test_read_oversized_packet_length() {
+        let (mut writer, mut reader) = stream_pair().await;
+        let client = Client::dummy();
+
+        // Junk claiming a huge packet is rejected, without waiting for it to arrive
+        let mut junk = types::encode_var_int(i32::MAX).unwrap();
+        junk.extend_from_slice(&[0xAB; 64]);
+        writer.write_all(&junk).await.unwrap();
+
+        let mut buf = BytesMut::new();
+        let (mut read, _) = reader.split();
+        assert!(read_packet(&client, &mut buf, &mut read).await.is_err());
+    }
+
+    #[test]
+    fn test_decode_compressed_below_threshold() {
+        let packet = RawPacket::new(0x10, vec![7; 100]);
+        let compressing = Client::dummy();
+        compressing.set_compression(16);
+        let raw = packet.encode_with_len(&compressing).unwrap();
+
+        // Client with higher threshold must reject it
+        let client = Client::dummy();
+        client.set_compression(256);
+        assert!(RawPacket::decode_with_len(&client, &raw).is_err());
+
+        // Uncompressed packets are always fine
+        let raw = RawPacket::new(0x10, vec![7; 10])
+            .encode_with_len(&client)
+            .unwrap();
+        let decoded = RawPacket::decode_with_len(&client, &raw).unwrap();
+        assert_eq!(decoded.data, vec![7; 10]);
+    }
+}
@@ -0,0 +1,252 @@
+//! Configuration state, between login and play since Minecraft 1.20.2.
+//!
+//! See: <https://wiki.vg/Protocol#Configuration>
+
+use bytes::BytesMut;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::{ReadHalf, WriteHalf};
+
+use crate::proto::client::{Client, ClientState};
+use crate::proto::packet::{self, RawPacket};
+use crate::proto::packets;
+#[cfg(feature = "lobby")]
+use crate::types;
+
+/// First protocol version with the configuration state, Minecraft 1.20.2.
+pub const PROTOCOL_MIN: u32 = 764;
+
+/// Last protocol version using the packet IDs below, Minecraft 1.20.4.
+///
+/// Newer versions renumbered configuration packets.
+pub const PROTOCOL_MAX: u32 = 765;
+
+pub const CLIENT_FINISH_CONFIGURATION: u8 = 0x02;
+pub const CLIENT_KEEP_ALIVE: u8 = 0x03;
+pub const CLIENT_PING: u8 = 0x04;
+pub const SERVER_FINISH_CONFIGURATION: u8 = 0x02;
+pub const SERVER_KEEP_ALIVE: u8 = 0x03;
+pub const SERVER_PONG: u8 = 0x04;
+
+/// First protocol version with the transfer packet, Minecraft 1.20.5.
+#[cfg(feature = "lobby")]
+pub const TRANSFER_PROTOCOL_MIN: u32 = 766;
+
+/// Configuration packet IDs since Minecraft 1.20.5.
+#[cfg(feature = "lobby")]
+pub const CLIENT_KEEP_ALIVE_V1_20_5: u8 = 0x04;
+#[cfg(feature = "lobby")]
+pub const CLIENT_TRANSFER: u8 = 0x0B;
+
+/// Whether the given protocol version has the configuration state.
+pub fn has_configuration(protocol: u32) -> bool {
+    protocol >= PROTOCOL_MIN
+}
+
+/// Whether the configuration state is supported for the given protocol version.
+pub fn is_supported(protocol: u32) -> bool {
+    (PROTOCOL_MIN..=PROTOCOL_MAX).contains(&protocol)
+}
+
+/// Whether the given protocol version can be transferred to another server.
+#[cfg(feature = "lobby")]
+pub fn supports_transfer(protocol: u32) -> bool {
+    protocol >= TRANSFER_PROTOCOL_MIN
+}
+
+/// Go through configuration state as client, right after receiving login success from the server.
+///
+/// Acknowledges the login, responds to keep-alive and ping packets, and acknowledges the end of
+/// configuration. Other configuration packets, such as registries, are ignored. The client is in
+/// the play state afterwards.
+pub async fn configure_client(
+    client: &Client,
+    reader: &mut ReadHalf<'_>,
+    buf: &mut BytesMut,
+    writer: &mut WriteHalf<'_>,
+) -> Result<(), ()> {
+    // Acknowledge login, switches to configuration state
+    write_raw(
+        client,
+        writer,
+        RawPacket::new(packets::login::SERVER_LOGIN_ACKNOWLEDGED, vec![]),
+    )
+    .await?;
+    client.set_state(ClientState::Configuration);
+
+    loop {
+        let (packet, _raw) = match packet::read_packet(client, buf, reader).await? {
+            Some(packet) => packet,
+            None => {
+                error!(target: "lazymc", "Server closed connection during configuration");
+                return Err(());
+            }
+        };
+
+        match packet.id {
+            // Respond to keep-alive and ping with the same payload
+            CLIENT_KEEP_ALIVE => {
+                write_raw(
+                    client,
+                    writer,
+                    RawPacket::new(SERVER_KEEP_ALIVE, packet.data),
+                )
+                .await?;
+            }
+            CLIENT_PING => {
+                write_raw(client, writer, RawPacket::new(SERVER_PONG, packet.data)).await?;
+            }
+
+            // Acknowledge end of configuration, switches to play state
+            CLIENT_FINISH_CONFIGURATION => {
+                write_raw(
+                    client,
+                    writer,
+                    RawPacket::new(SERVER_FINISH_CONFIGURATION, vec![]),
+                )
+                .await?;
+                client.set_state(ClientState::Play);
+                return Ok(());
+            }
+
+            _ => {}
+        }
+    }
+}
+
+/// Send keep-alive packet to client in configuration state, since Minecraft 1.20.5.
+#[cfg(feature = "lobby")]
+pub async fn send_keep_alive(
+    client: &Client,
+    writer: &mut WriteHalf<'_>,
+    id: u64,
+) -> Result<(), ()> {
+    write_raw(
+        client,
+        writer,
+        RawPacket::new(CLIENT_KEEP_ALIVE_V1_20_5, id.to_be_bytes().to_vec()),
+    )
+    .await
+}
+
+/// Send transfer packet to client in configuration state, since Minecraft 1.20.5.
+///
+/// The client disconnects and connects to the given host and port.
+#[cfg(feature = "lobby")]
+pub async fn send_transfer(
+    client: &Client,
+    writer: &mut WriteHalf<'_>,
+    host: &str,
+    port: u16,
+) -> Result<(), ()> {
+    let mut data = types::encode_string(host)?;
+    data.extend(types::encode_var_int(port as i32)?);
+    write_raw(client, writer, RawPacket::new(CLIENT_TRANSFER, data)).await
+}
+
+/// Wait for a packet with the given ID from the client, ignoring all other packets.
+#[cfg(feature = "lobby")]
+pub async fn wait_for_packet(
+    client: &Client,
+    reader: &mut ReadHalf<'_>,
+    buf: &mut BytesMut,
+    id: u8,
+) -> Result<(), ()> {
+    loop {
+        match packet::read_packet(client, buf, reader).await? {
+            Some((packet, _raw)) if packet.id == id => return Ok(()),
+            Some((packet, _raw)) => {
+                trace!(target: "lazymc", "Ignoring packet 0x{:02X} from client in {:?} state", packet.id, client.state());
+            }
+            None => {
+                debug!(target: "lazymc", "Client closed connection during configuration");
+                return Err(());
+            }
+        }
+    }
+}
+
+/// Write raw packet to stream writer.
+async fn write_raw(
+    client: &Client,
+    writer: &mut WriteHalf<'_>,
+    packet: RawPacket,
+) -> Result<(), ()> {
+    writer
+        .write_all(&packet.encode_with_len(client)?)
+        .await
+        .map_err(|_| ())
+}
+
+#[cfg(all(test, feature = "lobby"))]
+mod tests {
+    use super::*;
+    use crate::util::test::stream_pair;
+
+    #[test]
+    fn test_has_configuration() {
+        assert!(!has_configuration(763));
+        assert!(has_configuration(764));
+        assert!(is_supported(765));
+        assert!(!is_supported(766));
+        assert!(!supports_transfer(765));
+        assert!(supports_transfer(766));
+    }
+
+    #[tokio::test]
+    async fn test_configuration_sequence() {
+        let (mut client_stream, mut server_stream) = stream_pair().await;
+
+        // Both ends just completed login with compression, as a 1.20.2 client would
+        let client = Client::dummy();
+        let server = Client::dummy();
+        for end in [&client, &server] {
+            end.set_state(ClientState::Login);
+            end.set_compression(256);
+        }
+
+        // Registry data, keep-alive in between, large enough to be compressed
+        let registry = RawPacket::new(0x05, vec![10; 1000]);
+        let keep_alive = RawPacket::new(CLIENT_KEEP_ALIVE, 42u64.to_be_bytes().to_vec());
+        let finish = RawPacket::new(CLIENT_FINISH_CONFIGURATION, vec![]);
+
+        let client_task = async {
+            let (mut reader, mut writer) = client_stream.split();
+            configure_client(&client, &mut reader, &mut BytesMut::new(), &mut writer).await
+        };
+        let server_task = async {
+            let (mut reader, mut writer) = server_stream.split();
+            let mut buf = BytesMut::new();
+            wait_for_packet(
+                &server,
+                &mut reader,
+                &mut buf,
+                packets::login::SERVER_LOGIN_ACKNOWLEDGED,
+            )
+            .await?;
+            server.set_state(ClientState::Configuration);
+            for packet in [registry, keep_alive, finish] {
+                write_raw(&server, &mut writer, packet).await?;
+            }
+
+            // Client responds to keep-alive, then acknowledges end of configuration
+            let mut received = Vec::new();
+            for _ in 0..2 {
+                let (packet, _raw) = packet::read_packet(&server, &mut buf, &mut reader)
+                    .await?
+                    .ok_or(())?;
+                received.push((packet.id, packet.data));
+            }
+            Ok::<_, ()>(received)
+        };
+        let (configured, received) = tokio::join!(client_task, server_task);
+        assert!(configured.is_ok());
+        assert_eq!(
+            received.unwrap(),
+            vec![
+                (SERVER_KEEP_ALIVE, 42u64.to_be_bytes().to_vec()),
+                (SERVER_FINISH_CONFIGURATION, vec![]),
+            ]
+        );
+        assert_eq!(client.state(), ClientState::Play);
+    }
+}
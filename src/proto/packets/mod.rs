@@ -1,5 +1,6 @@
 //! Minecraft protocol packet IDs.
 
+pub mod configuration;
 pub mod play;
 
 pub mod handshake {
@@ -28,6 +29,6 @@ pub mod login {
     pub const CLIENT_ENCRYPTION_REQUEST: u8 = EncryptionRequest::PACKET_ID;
     pub const CLIENT_LOGIN_PLUGIN_REQUEST: u8 = LoginPluginRequest::PACKET_ID;
     pub const SERVER_LOGIN_START: u8 = LoginStart::PACKET_ID;
-    #[cfg(feature = "lobby")]
     pub const SERVER_LOGIN_PLUGIN_RESPONSE: u8 = LoginPluginResponse::PACKET_ID;
+    pub const SERVER_LOGIN_ACKNOWLEDGED: u8 = 0x03;
 }
@@ -4,7 +4,7 @@ use minecraft_protocol::version::{v1_16_3, v1_17};
 use tokio::net::tcp::WriteHalf;
 
 use crate::proto::client::{Client, ClientInfo};
-use crate::proto::packet;
+use crate::proto::packet::{self, RawPacket};
 
 /// Auto incrementing ID source for keep alive packets.
 static KEEP_ALIVE_ID: AtomicU64 = AtomicU64::new(0);
@@ -27,3 +27,19 @@ pub async fn send(
         _ => packet::write_packet(v1_17::game::ClientBoundKeepAlive { id }, client, writer).await,
     }
 }
+
+/// Decode keep alive response from client, returns the keep alive ID.
+///
+/// Returns `None` if this is not a keep alive response.
+pub fn decode_response(client_info: &ClientInfo, packet: &RawPacket) -> Option<u64> {
+    let id = match client_info.protocol() {
+        Some(p) if p < v1_17::PROTOCOL => v1_16_3::game::ServerBoundKeepAlive::PACKET_ID,
+        _ => v1_17::game::ServerBoundKeepAlive::PACKET_ID,
+    };
+    if packet.id != id {
+        return None;
+    }
+
+    let data: [u8; 8] = packet.data.get(..8)?.try_into().ok()?;
+    Some(u64::from_be_bytes(data))
+}
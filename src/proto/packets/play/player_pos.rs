@@ -1,26 +1,58 @@
+use std::sync::atomic::{AtomicI32, Ordering};
+
 use minecraft_protocol::version::{v1_16_3, v1_17};
 use tokio::net::tcp::WriteHalf;
 
 use crate::proto::client::{Client, ClientInfo};
 use crate::proto::packet;
 
+/// Auto incrementing ID source for teleports.
+static TELEPORT_ID: AtomicI32 = AtomicI32::new(0);
+
+/// Player position and look, with absolute coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Teleport {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub teleport_id: i32,
+}
+
+/// Teleport to world origin, looking down.
+///
+/// All coordinates are absolute, so repeated teleports keep the player at the same location.
+fn next_teleport() -> Teleport {
+    Teleport {
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+        yaw: 0.0,
+        pitch: 90.0,
+        teleport_id: TELEPORT_ID.fetch_add(1, Ordering::Relaxed),
+    }
+}
+
 /// Move player to world origin.
 pub async fn send(
     client: &Client,
     client_info: &ClientInfo,
     writer: &mut WriteHalf<'_>,
 ) -> Result<(), ()> {
+    let teleport = next_teleport();
+
     match client_info.protocol() {
         Some(p) if p < v1_17::PROTOCOL => {
             packet::write_packet(
                 v1_16_3::game::PlayerPositionAndLook {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                    yaw: 0.0,
-                    pitch: 90.0,
+                    x: teleport.x,
+                    y: teleport.y,
+                    z: teleport.z,
+                    yaw: teleport.yaw,
+                    pitch: teleport.pitch,
                     flags: 0b00000000,
-                    teleport_id: 0,
+                    teleport_id: teleport.teleport_id,
                 },
                 client,
                 writer,
@@ -30,13 +62,13 @@ pub async fn send(
         _ => {
             packet::write_packet(
                 v1_17::game::PlayerPositionAndLook {
-                    x: 0.0,
-                    y: 0.0,
-                    z: 0.0,
-                    yaw: 0.0,
-                    pitch: 90.0,
+                    x: teleport.x,
+                    y: teleport.y,
+                    z: teleport.z,
+                    yaw: teleport.yaw,
+                    pitch: teleport.pitch,
                     flags: 0b00000000,
-                    teleport_id: 0,
+                    teleport_id: teleport.teleport_id,
                     dismount_vehicle: true,
                 },
                 client,
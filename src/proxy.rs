@@ -1,52 +1,255 @@
 use std::error::Error;
-use std::net::SocketAddr;
+use std::fmt;
+use std::net::{SocketAddr, SocketAddrV6};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use bytes::BytesMut;
 use proxy_protocol::version2::{ProxyAddresses, ProxyCommand, ProxyTransportProtocol};
-use proxy_protocol::EncodeError;
+use proxy_protocol::{version1, EncodeError};
 use tokio::io;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 
-use crate::net;
+use crate::config::Config;
+use crate::net::{self, Endpoint};
 
-/// Proxy the inbound stream to a target address.
+/// Signature PROXY protocol v2 headers start with.
+const PROXY_V2_SIGNATURE: [u8; 12] = [
+    0x0D, 0x0A, 0x0D, 0x0A, 0x00, 0x0D, 0x0A, 0x51, 0x55, 0x49, 0x54, 0x0A,
+];
+
+/// Maximum length of a PROXY protocol v1 header.
+const PROXY_V1_MAX_LEN: usize = 107;
+
+/// Aggregate statistics of all proxied sessions.
+pub static STATS: ProxyStats = ProxyStats::new();
+
+/// ID of the next proxied connection.
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Proxy statistics.
+///
+/// Counters are atomic, so they can be updated from the relay loops without locking.
+#[derive(Debug, Default)]
+pub struct ProxyStats {
+    /// Number of currently active sessions.
+    active_sessions: AtomicU64,
+
+    /// Total number of sessions.
+    sessions: AtomicU64,
+
+    /// Bytes relayed from clients to the server.
+    bytes_to_server: AtomicU64,
+
+    /// Bytes relayed from the server to clients.
+    bytes_to_client: AtomicU64,
+}
+
+impl ProxyStats {
+    /// Construct new empty statistics.
+    pub const fn new() -> Self {
+        Self {
+            active_sessions: AtomicU64::new(0),
+            sessions: AtomicU64::new(0),
+            bytes_to_server: AtomicU64::new(0),
+            bytes_to_client: AtomicU64::new(0),
+        }
+    }
+
+    /// Number of currently active sessions.
+    pub fn active_sessions(&self) -> u64 {
+        self.active_sessions.load(Ordering::Relaxed)
+    }
+
+    /// Total number of sessions.
+    pub fn sessions(&self) -> u64 {
+        self.sessions.load(Ordering::Relaxed)
+    }
+
+    /// Bytes relayed from clients to the server.
+    pub fn bytes_to_server(&self) -> u64 {
+        self.bytes_to_server.load(Ordering::Relaxed)
+    }
+
+    /// Bytes relayed from the server to clients.
+    pub fn bytes_to_client(&self) -> u64 {
+        self.bytes_to_client.load(Ordering::Relaxed)
+    }
+}
+
+/// Active session in proxy statistics, ends when dropped.
+struct Session<'a> {
+    stats: &'a ProxyStats,
+
+    /// Bytes relayed from the client to the server in this session.
+    to_server: AtomicU64,
+
+    /// Bytes relayed from the server to the client in this session.
+    to_client: AtomicU64,
+}
+
+impl<'a> Session<'a> {
+    /// Start a new session.
+    fn start(stats: &'a ProxyStats) -> Self {
+        stats.sessions.fetch_add(1, Ordering::Relaxed);
+        stats.active_sessions.fetch_add(1, Ordering::Relaxed);
+        Self {
+            stats,
+            to_server: AtomicU64::new(0),
+            to_client: AtomicU64::new(0),
+        }
+    }
+
+    /// Count bytes relayed to the server.
+    fn relayed_to_server(&self, bytes: u64) {
+        self.to_server.fetch_add(bytes, Ordering::Relaxed);
+        self.stats
+            .bytes_to_server
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Count bytes relayed to the client.
+    fn relayed_to_client(&self, bytes: u64) {
+        self.to_client.fetch_add(bytes, Ordering::Relaxed);
+        self.stats
+            .bytes_to_client
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Session<'_> {
+    fn drop(&mut self) {
+        self.stats.active_sessions.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Proxied connection, identifies it in logs.
+#[derive(Debug, Clone)]
+pub struct Connection {
+    /// Unique connection ID.
+    id: u64,
+
+    /// Client address.
+    client: SocketAddr,
+
+    /// Backend server address.
+    server: String,
+}
+
+impl Connection {
+    /// Identify a new connection of the given client to the given server.
+    ///
+    /// The client address is the real address of the client, as recovered from a PROXY header.
+    pub fn new(client: SocketAddr, server: impl fmt::Display) -> Self {
+        Self {
+            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+            client,
+            server: server.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "connection #{} from {} to {}",
+            self.id, self.client, self.server
+        )
+    }
+}
+
+/// Proxy error, with the context of the failed connection.
+#[derive(Debug)]
+pub struct ProxyError {
+    /// Failed connection.
+    connection: Connection,
+
+    /// Bytes relayed to the server before failing.
+    to_server: u64,
+
+    /// Bytes relayed to the client before failing.
+    to_client: u64,
+
+    /// Underlying error.
+    err: Box<dyn Error>,
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (relayed {} bytes to server, {} bytes to client): {}",
+            self.connection, self.to_server, self.to_client, self.err,
+        )
+    }
+}
+
+impl Error for ProxyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.err.as_ref())
+    }
+}
+
+/// Proxy the inbound stream of the given client to a target address.
 pub async fn proxy(
+    config: &Config,
     inbound: TcpStream,
+    client: SocketAddr,
     proxy_header: ProxyHeader,
-    addr_target: SocketAddr,
+    addr_target: &Endpoint,
 ) -> Result<(), Box<dyn Error>> {
-    proxy_with_queue(inbound, proxy_header, addr_target, &[]).await
+    proxy_with_queue(config, inbound, client, proxy_header, addr_target, &[]).await
 }
 
-/// Proxy the inbound stream to a target address.
+/// Proxy the inbound stream of the given client to a target address.
 ///
 /// Send the queue to the target server before proxying.
 pub async fn proxy_with_queue(
+    config: &Config,
     inbound: TcpStream,
+    client: SocketAddr,
     proxy_header: ProxyHeader,
-    addr_target: SocketAddr,
+    addr_target: &Endpoint,
     queue: &[u8],
 ) -> Result<(), Box<dyn Error>> {
+    let connection = Connection::new(client, addr_target);
+
     // Set up connection to server
     // TODO: on connect fail, ping server and redirect to serve_status if offline
-    let mut outbound = TcpStream::connect(addr_target).await?;
-
-    // Add proxy header
-    match proxy_header {
-        ProxyHeader::None => {}
-        ProxyHeader::Local => {
-            let header = local_proxy_header()?;
-            outbound.write_all(&header).await?;
+    let connect = async {
+        let mut outbound = net::connect_endpoint(config, addr_target).await?;
+
+        // Add proxy header
+        match proxy_header {
+            ProxyHeader::None => {}
+            ProxyHeader::Local => {
+                let header = local_proxy_header()?;
+                outbound.write_all(&header).await?;
+            }
+            ProxyHeader::Proxy => {
+                let header = stream_proxy_header(&inbound, client)?;
+                outbound.write_all(&header).await?;
+            }
         }
-        ProxyHeader::Proxy => {
-            let header = stream_proxy_header(&inbound)?;
-            outbound.write_all(&header).await?;
+
+        Ok::<_, Box<dyn Error>>(outbound)
+    };
+    let outbound = match connect.await {
+        Ok(outbound) => outbound,
+        Err(err) => {
+            return Err(ProxyError {
+                connection,
+                to_server: 0,
+                to_client: 0,
+                err,
+            }
+            .into())
         }
-    }
+    };
 
     // Start proxy on both streams
-    proxy_inbound_outbound_with_queue(inbound, outbound, &[], queue).await
+    proxy_inbound_outbound_with_queue(config, connection, inbound, outbound, &[], queue).await
 }
 
 /// Proxy the inbound stream to a target address.
@@ -54,38 +257,128 @@ pub async fn proxy_with_queue(
 /// Send the queue to the target server before proxying.
 // TODO: find better name for this
 pub async fn proxy_inbound_outbound_with_queue(
+    config: &Config,
+    connection: Connection,
+    inbound: TcpStream,
+    outbound: impl AsyncRead + AsyncWrite + Unpin,
+    inbound_queue: &[u8],
+    outbound_queue: &[u8],
+) -> Result<(), Box<dyn Error>> {
+    let buffer_size = config.advanced.proxy_buffer_size as usize;
+    relay(
+        connection,
+        inbound,
+        outbound,
+        inbound_queue,
+        outbound_queue,
+        buffer_size,
+        &STATS,
+    )
+    .await
+}
+
+/// Relay data between the inbound and outbound stream until both are closed.
+///
+/// Send the queues first. Data is relayed through a buffer of `buffer_size` bytes in each
+/// direction. Transferred bytes are counted in the given statistics.
+///
+/// Errors carry the connection context and the number of bytes relayed before failing.
+async fn relay(
+    connection: Connection,
+    inbound: TcpStream,
+    outbound: impl AsyncRead + AsyncWrite + Unpin,
+    inbound_queue: &[u8],
+    outbound_queue: &[u8],
+    buffer_size: usize,
+    stats: &ProxyStats,
+) -> Result<(), Box<dyn Error>> {
+    let session = Session::start(stats);
+    let result = relay_session(
+        &session,
+        inbound,
+        outbound,
+        inbound_queue,
+        outbound_queue,
+        buffer_size,
+    )
+    .await;
+
+    let to_server = session.to_server.load(Ordering::Relaxed);
+    let to_client = session.to_client.load(Ordering::Relaxed);
+    match result {
+        Ok(()) => {
+            debug!(
+                target: "lazymc",
+                "Proxy session ended, {} relayed {} bytes to server and {} bytes to client",
+                connection,
+                to_server,
+                to_client,
+            );
+            Ok(())
+        }
+        Err(err) => Err(ProxyError {
+            connection,
+            to_server,
+            to_client,
+            err,
+        }
+        .into()),
+    }
+}
+
+/// Relay data of a session, see `relay`.
+async fn relay_session(
+    session: &Session<'_>,
     mut inbound: TcpStream,
-    mut outbound: TcpStream,
+    outbound: impl AsyncRead + AsyncWrite + Unpin,
     inbound_queue: &[u8],
     outbound_queue: &[u8],
+    buffer_size: usize,
 ) -> Result<(), Box<dyn Error>> {
     let (mut ri, mut wi) = inbound.split();
-    let (mut ro, mut wo) = outbound.split();
+    let (mut ro, mut wo) = io::split(outbound);
 
     // Forward queued bytes to client once writable
     if !inbound_queue.is_empty() {
         wi.writable().await?;
         trace!(target: "lazymc", "Relaying {} queued bytes to client", inbound_queue.len());
         wi.write_all(inbound_queue).await?;
+        session.relayed_to_client(inbound_queue.len() as u64);
     }
 
-    // Forward queued bytes to server once writable
+    // Forward queued bytes to server
     if !outbound_queue.is_empty() {
-        wo.writable().await?;
         trace!(target: "lazymc", "Relaying {} queued bytes to server", outbound_queue.len());
         wo.write_all(outbound_queue).await?;
+        session.relayed_to_server(outbound_queue.len() as u64);
     }
 
+    // Once a side is done sending, only close the write half towards the other side, so data
+    // still flowing in the other direction is delivered
     let client_to_server = async {
-        io::copy(&mut ri, &mut wo).await?;
-        wo.shutdown().await
+        let result = copy(&mut ri, &mut wo, buffer_size, |n| {
+            session.relayed_to_server(n)
+        })
+        .await;
+        trace!(target: "lazymc", "Client finished sending, closing write half to server");
+        shutdown_write(&mut wo).await?;
+        result
     };
     let server_to_client = async {
-        io::copy(&mut ro, &mut wi).await?;
-        wi.shutdown().await
+        let result = copy(&mut ro, &mut wi, buffer_size, |n| {
+            session.relayed_to_client(n)
+        })
+        .await;
+        trace!(target: "lazymc", "Server finished sending, closing write half to client");
+        shutdown_write(&mut wi).await?;
+        result
     };
 
-    tokio::try_join!(client_to_server, server_to_client)?;
+    // Relay both directions to completion, so data is not lost if the other direction fails, such
+    // as a login disconnect the server sends right before closing the connection
+    let (to_server, to_client) = tokio::join!(client_to_server, server_to_client);
+    to_server?;
+    to_client?;
 
     // Gracefully close connection if not done already
     net::close_tcp_stream(inbound).await?;
@@ -93,6 +386,40 @@ pub async fn proxy_inbound_outbound_with_queue(
     Ok(())
 }
 
+/// Copy data from reader to writer until EOF through a buffer of `buffer_size` bytes, reporting
+/// transferred bytes to `count`.
+async fn copy<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    buffer_size: usize,
+    count: impl Fn(u64),
+) -> io::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = vec![0u8; buffer_size];
+
+    loop {
+        let read = reader.read(&mut buf).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        writer.write_all(&buf[..read]).await?;
+        count(read as u64);
+    }
+}
+
+/// Close the write half of a stream, signalling EOF to the peer.
+///
+/// Succeeds if the stream is already closed.
+async fn shutdown_write<W: AsyncWrite + Unpin>(writer: &mut W) -> io::Result<()> {
+    match writer.shutdown().await {
+        Err(err) if err.kind() == io::ErrorKind::NotConnected => Ok(()),
+        result => result,
+    }
+}
+
 /// Proxy header.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum ProxyHeader {
@@ -134,34 +461,411 @@ pub fn local_proxy_header() -> Result<BytesMut, EncodeError> {
     proxy_protocol::encode(header)
 }
 
-/// Get the proxy header for the given inbound stream.
+/// Get the proxy header for the given client on the inbound stream.
+///
+/// The client address is the real address of the client, which differs from the peer address of
+/// the inbound stream if it was recovered from a PROXY header.
 ///
 /// This header may be sent over the outbound stream to signal client information.
-pub fn stream_proxy_header(inbound: &TcpStream) -> Result<BytesMut, EncodeError> {
-    // Get peer and local address
-    let peer = inbound
-        .peer_addr()
-        .expect("Peer address not known for TCP stream");
+pub fn stream_proxy_header(
+    inbound: &TcpStream,
+    client: SocketAddr,
+) -> Result<BytesMut, EncodeError> {
+    // Get local address
     let local = inbound
         .local_addr()
         .expect("Local address not known for TCP stream");
 
-    // Build proxy header
+    // Build proxy header, use IPv6 if address families differ
     let header = proxy_protocol::ProxyHeader::Version2 {
         command: ProxyCommand::Proxy,
         transport_protocol: ProxyTransportProtocol::Stream,
-        addresses: match (peer, local) {
+        addresses: match (client, local) {
             (SocketAddr::V4(source), SocketAddr::V4(destination)) => ProxyAddresses::Ipv4 {
                 source,
                 destination,
             },
-            (SocketAddr::V6(source), SocketAddr::V6(destination)) => ProxyAddresses::Ipv6 {
-                source,
-                destination,
+            (source, destination) => ProxyAddresses::Ipv6 {
+                source: to_ipv6(source),
+                destination: to_ipv6(destination),
             },
-            (_, _) => unreachable!(),
         },
     };
 
     proxy_protocol::encode(header)
 }
+
+/// Get the given socket address as IPv6, mapping IPv4 addresses.
+fn to_ipv6(addr: SocketAddr) -> SocketAddrV6 {
+    match addr {
+        SocketAddr::V4(addr) => SocketAddrV6::new(addr.ip().to_ipv6_mapped(), addr.port(), 0, 0),
+        SocketAddr::V6(addr) => addr,
+    }
+}
+
+/// Read a PROXY protocol v1 or v2 header from the inbound stream.
+///
+/// Reads exactly the header, leaving the stream at the start of the proxied data. Returns the
+/// source address, or `None` if the header carries no address, such as for health checks.
+pub async fn read_proxy_header(
+    inbound: &mut (impl AsyncRead + Unpin),
+) -> io::Result<Option<SocketAddr>> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    // Read header, v1 is at least as long as the v2 signature
+    let mut header = vec![0; PROXY_V2_SIGNATURE.len()];
+    inbound.read_exact(&mut header).await?;
+    if header == PROXY_V2_SIGNATURE {
+        let mut fixed = [0; 4];
+        inbound.read_exact(&mut fixed).await?;
+        header.extend_from_slice(&fixed);
+        let start = header.len();
+        header.resize(start + u16::from_be_bytes([fixed[2], fixed[3]]) as usize, 0);
+        inbound.read_exact(&mut header[start..]).await?;
+    } else if header.starts_with(b"PROXY ") {
+        while !header.ends_with(b"\r\n") {
+            if header.len() >= PROXY_V1_MAX_LEN {
+                return Err(invalid("PROXY v1 header too long".into()));
+            }
+            header.push(inbound.read_u8().await?);
+        }
+    } else {
+        return Err(invalid("missing PROXY header".into()));
+    }
+
+    let header = proxy_protocol::parse(&mut header.as_slice())
+        .map_err(|err| invalid(format!("invalid PROXY header: {err}")))?;
+    Ok(match header {
+        proxy_protocol::ProxyHeader::Version1 { addresses } => match addresses {
+            version1::ProxyAddresses::Ipv4 { source, .. } => Some(source.into()),
+            version1::ProxyAddresses::Ipv6 { source, .. } => Some(source.into()),
+            version1::ProxyAddresses::Unknown => None,
+        },
+        proxy_protocol::ProxyHeader::Version2 {
+            command: ProxyCommand::Proxy,
+            addresses,
+            ..
+        } => match addresses {
+            ProxyAddresses::Ipv4 { source, .. } => Some(source.into()),
+            ProxyAddresses::Ipv6 { source, .. } => Some(source.into()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::util::test::stream_pair;
+
+    /// Identify a test connection of the given inbound stream.
+    fn connection(inbound: &TcpStream) -> Connection {
+        Connection::new(inbound.peer_addr().unwrap(), "127.0.0.1:25566")
+    }
+
+    /// Server stream that accepts all data, but fails when read from.
+    struct FailingServer;
+
+    impl AsyncRead for FailingServer {
+        fn poll_read(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            _: &mut io::ReadBuf<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::task::Poll::Ready(Err(io::ErrorKind::ConnectionReset.into()))
+        }
+    }
+
+    impl AsyncWrite for FailingServer {
+        fn poll_write(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+            buf: &[u8],
+        ) -> std::task::Poll<io::Result<usize>> {
+            std::task::Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(
+            self: std::pin::Pin<&mut Self>,
+            _: &mut std::task::Context<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_counts_bytes() {
+        let stats = Arc::new(ProxyStats::new());
+        let (mut client, inbound) = stream_pair().await;
+        let (outbound, mut server) = stream_pair().await;
+
+        let session = tokio::spawn({
+            let stats = stats.clone();
+            async move {
+                relay(
+                    connection(&inbound),
+                    inbound,
+                    outbound,
+                    b"hi",
+                    b"login",
+                    8 * 1024,
+                    &stats,
+                )
+                .await
+                .unwrap();
+            }
+        });
+
+        // Exchange data in both directions, then close
+        client.write_all(b"hello server").await.unwrap();
+        let mut buf = vec![0u8; b"login".len() + b"hello server".len()];
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, b"loginhello server");
+        server.write_all(b"hello").await.unwrap();
+        let mut buf = vec![0u8; b"hi".len() + b"hello".len()];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, b"hihello");
+        assert_eq!(stats.active_sessions(), 1);
+
+        client.shutdown().await.unwrap();
+        server.shutdown().await.unwrap();
+        session.await.unwrap();
+
+        assert_eq!(stats.bytes_to_server(), 17);
+        assert_eq!(stats.bytes_to_client(), 7);
+        assert_eq!(stats.active_sessions(), 0);
+        assert_eq!(stats.sessions(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_relay_half_close() {
+        let stats = Arc::new(ProxyStats::new());
+        let (mut client, inbound) = stream_pair().await;
+        let (outbound, mut server) = stream_pair().await;
+
+        let session = tokio::spawn({
+            let stats = stats.clone();
+            async move {
+                relay(
+                    connection(&inbound),
+                    inbound,
+                    outbound,
+                    &[],
+                    &[],
+                    1024,
+                    &stats,
+                )
+                .await
+                .unwrap();
+            }
+        });
+
+        // Client half-closes after sending, server sees EOF
+        client.write_all(b"request").await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut request = Vec::new();
+        server.read_to_end(&mut request).await.unwrap();
+        assert_eq!(request, b"request");
+
+        // Server still delivers its response to the half-closed client
+        let response = vec![42u8; 256 * 1024];
+        server.write_all(&response).await.unwrap();
+        server.shutdown().await.unwrap();
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+        assert!(received == response, "response truncated");
+
+        session.await.unwrap();
+        assert_eq!(stats.bytes_to_client(), response.len() as u64);
+        assert_eq!(stats.active_sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_relay_half_close_on_error() {
+        let stats = Arc::new(ProxyStats::new());
+        let (mut client, inbound) = stream_pair().await;
+
+        let session = tokio::spawn({
+            let stats = stats.clone();
+            async move {
+                relay(
+                    connection(&inbound),
+                    inbound,
+                    FailingServer,
+                    &[],
+                    &[],
+                    1024,
+                    &stats,
+                )
+                .await
+                .is_err()
+            }
+        });
+
+        // Receiving from the server fails, client sees EOF
+        let mut received = Vec::new();
+        client.read_to_end(&mut received).await.unwrap();
+        assert!(received.is_empty());
+
+        // Client still delivers its data to the server
+        client.write_all(b"request").await.unwrap();
+        client.shutdown().await.unwrap();
+        assert!(session.await.unwrap());
+        assert_eq!(stats.bytes_to_server(), b"request".len() as u64);
+        assert_eq!(stats.active_sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_relay_error_context() {
+        let stats = ProxyStats::new();
+        let (mut client, inbound) = stream_pair().await;
+
+        // Client address recovered from a PROXY header, differs from the inbound peer
+        let source: SocketAddr = "203.0.113.7:51234".parse().unwrap();
+        let connection = Connection::new(source, "127.0.0.1:25566");
+        let id = connection.id;
+
+        client.write_all(b"hello").await.unwrap();
+        client.shutdown().await.unwrap();
+        let err = relay(connection, inbound, FailingServer, &[], &[], 1024, &stats)
+            .await
+            .unwrap_err()
+            .to_string();
+
+        // Error identifies the connection and how far it got
+        assert!(err.starts_with(&format!(
+            "connection #{id} from {source} to 127.0.0.1:25566 (relayed 5 bytes to server, 0 bytes to client): "
+        )), "{err}");
+        assert!(
+            err.ends_with(&io::Error::from(io::ErrorKind::ConnectionReset).to_string()),
+            "{err}"
+        );
+    }
+
+    /// Reader recording the largest read it was asked for.
+    struct RecordingReader<'a> {
+        data: &'a [u8],
+        max_read: usize,
+    }
+
+    impl AsyncRead for RecordingReader<'_> {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut io::ReadBuf<'_>,
+        ) -> std::task::Poll<io::Result<()>> {
+            self.max_read = self.max_read.max(buf.remaining());
+            std::pin::Pin::new(&mut self.data).poll_read(cx, buf)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_copy_buffer_size() {
+        let data = vec![7u8; 10_000];
+        let mut reader = RecordingReader {
+            data: &data,
+            max_read: 0,
+        };
+        let mut written = Vec::new();
+        let counter = AtomicU64::new(0);
+
+        copy(&mut reader, &mut written, 1234, |n| {
+            counter.fetch_add(n, Ordering::Relaxed);
+        })
+        .await
+        .unwrap();
+        assert_eq!(reader.max_read, 1234);
+        assert_eq!(counter.load(Ordering::Relaxed), 10_000);
+        assert_eq!(written, data);
+    }
+
+    #[tokio::test]
+    async fn test_relay_large_transfer() {
+        let stats = Arc::new(ProxyStats::new());
+        let (mut client, inbound) = stream_pair().await;
+        let (outbound, mut server) = stream_pair().await;
+
+        let session = tokio::spawn({
+            let stats = stats.clone();
+            async move {
+                relay(
+                    connection(&inbound),
+                    inbound,
+                    outbound,
+                    &[],
+                    &[],
+                    64 * 1024,
+                    &stats,
+                )
+                .await
+                .unwrap();
+            }
+        });
+
+        // Send a few megabytes, much larger than the buffer
+        let data: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        let send = async {
+            client.write_all(&data).await.unwrap();
+            client.shutdown().await.unwrap();
+        };
+        let receive = async {
+            let mut received = Vec::new();
+            server.read_to_end(&mut received).await.unwrap();
+            received
+        };
+        let ((), received) = tokio::join!(send, receive);
+        assert!(received == data, "relayed data differs");
+
+        server.shutdown().await.unwrap();
+        session.await.unwrap();
+        assert_eq!(stats.bytes_to_server(), data.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn test_read_proxy_header_v1() {
+        let mut inbound: &[u8] = b"PROXY TCP4 203.0.113.7 10.0.0.1 51234 25565\r\nhandshake";
+        let source = read_proxy_header(&mut inbound).await.unwrap();
+        assert_eq!(source, Some("203.0.113.7:51234".parse().unwrap()));
+        assert_eq!(inbound, b"handshake");
+
+        let mut inbound: &[u8] = b"PROXY UNKNOWN\r\n";
+        assert_eq!(read_proxy_header(&mut inbound).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_read_proxy_header_v2() {
+        let mut header = proxy_protocol::encode(proxy_protocol::ProxyHeader::Version2 {
+            command: ProxyCommand::Proxy,
+            transport_protocol: ProxyTransportProtocol::Stream,
+            addresses: ProxyAddresses::Ipv6 {
+                source: "[2001:db8::7]:51234".parse().unwrap(),
+                destination: "[2001:db8::1]:25565".parse().unwrap(),
+            },
+        })
+        .unwrap();
+        header.extend_from_slice(b"handshake");
+
+        let mut inbound = &header[..];
+        let source = read_proxy_header(&mut inbound).await.unwrap();
+        assert_eq!(source, Some("[2001:db8::7]:51234".parse().unwrap()));
+        assert_eq!(inbound, b"handshake");
+
+        // Health checks from the proxy carry no address
+        let local = local_proxy_header().unwrap();
+        assert_eq!(read_proxy_header(&mut &local[..]).await.unwrap(), None);
+
+        // Plain Minecraft handshake is not a PROXY header
+        let mut inbound: &[u8] = b"\x10\x00\xfd\x05\x09localhost";
+        assert!(read_proxy_header(&mut inbound).await.is_err());
+    }
+}
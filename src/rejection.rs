@@ -0,0 +1,167 @@
+use tokio::net::tcp::WriteHalf;
+
+use crate::config::Config;
+use crate::proto::action;
+use crate::proto::client::Client;
+use crate::server::State;
+
+/// The ban message prefix.
+const BAN_MESSAGE_PREFIX: &str = "Your IP address is banned from this server.\nReason: ";
+
+/// The player ban message prefix.
+const PLAYER_BAN_MESSAGE_PREFIX: &str = "You are banned from this server.\nReason: ";
+
+/// Default ban reason if unknown.
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+
+/// Reason for rejecting a joining client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rejection {
+    /// Lockout is enabled.
+    Lockout,
+
+    /// Maintenance mode is enabled.
+    Maintenance,
+
+    /// Client IP is banned, with optional ban reason.
+    Banned(Option<String>),
+
+    /// Player is banned, with optional ban reason.
+    BannedPlayer(Option<String>),
+
+    /// Client is not whitelisted to wake the server.
+    NotWhitelisted,
+
+    /// Server accepts no players.
+    Full,
+
+    /// Server is held after failing to start, until woken manually.
+    Held,
+
+    /// Server is starting.
+    Starting,
+
+    /// Server is stopping.
+    Stopping,
+
+    /// lazymc is shutting down.
+    Draining,
+}
+
+impl Rejection {
+    /// Rejection for a client joining while the server is in the given state.
+    pub fn from_state(state: State) -> Self {
+        match state {
+            State::Starting | State::Stopped | State::Frozen | State::Started => Self::Starting,
+            State::Stopping => Self::Stopping,
+        }
+    }
+
+    /// Get the disconnect message to send for this rejection.
+    pub fn message(&self, config: &Config) -> String {
+        match self {
+            Self::Lockout => config.lockout.message.clone(),
+            Self::Maintenance => config.maintenance.message.clone(),
+            Self::Banned(reason) => {
+                let reason = reason.as_deref().unwrap_or(DEFAULT_BAN_REASON);
+                match &config.server.banned_message {
+                    Some(message) => message.replace("{reason}", reason),
+                    None => format!("{}{}", BAN_MESSAGE_PREFIX, reason),
+                }
+            }
+            Self::BannedPlayer(reason) => format!(
+                "{}{}",
+                PLAYER_BAN_MESSAGE_PREFIX,
+                reason.as_deref().unwrap_or(DEFAULT_BAN_REASON)
+            ),
+            Self::NotWhitelisted => config.join.kick.not_whitelisted.clone(),
+            Self::Full => config.join.kick.full.clone(),
+            Self::Held => config.join.kick.held.clone(),
+            Self::Starting => config.join.kick.starting.clone(),
+            Self::Stopping => config.join.kick.stopping.clone(),
+            Self::Draining => config.join.kick.draining.clone(),
+        }
+    }
+}
+
+/// Reject client, kicking it with the message for the given rejection.
+///
+/// Should close connection afterwards.
+pub async fn reject(
+    client: &Client,
+    config: &Config,
+    rejection: &Rejection,
+    writer: &mut WriteHalf<'_>,
+) -> Result<(), ()> {
+    trace!(target: "lazymc", "Rejecting client: {:?}", rejection);
+    action::kick(client, &rejection.message(config), writer).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejection_messages() {
+        let config: Config = crate::util::test::config(
+            r#"
+            [lockout]
+            message = "lockout"
+            [join.kick]
+            starting = "starting"
+            stopping = "stopping"
+            draining = "draining"
+            not_whitelisted = "not whitelisted"
+            full = "full"
+            held = "held"
+            "#,
+        );
+
+        assert_eq!(Rejection::Lockout.message(&config), "lockout");
+        assert_eq!(Rejection::Starting.message(&config), "starting");
+        assert_eq!(Rejection::Stopping.message(&config), "stopping");
+        assert_eq!(Rejection::Draining.message(&config), "draining");
+        assert_eq!(
+            Rejection::NotWhitelisted.message(&config),
+            "not whitelisted"
+        );
+        assert_eq!(Rejection::Full.message(&config), "full");
+        assert_eq!(Rejection::Held.message(&config), "held");
+        assert_eq!(
+            Rejection::Banned(Some("griefing".into())).message(&config),
+            format!("{}griefing", BAN_MESSAGE_PREFIX)
+        );
+        assert_eq!(
+            Rejection::Banned(None).message(&config),
+            format!("{}{}", BAN_MESSAGE_PREFIX, DEFAULT_BAN_REASON)
+        );
+        assert_eq!(
+            Rejection::BannedPlayer(Some("griefing".into())).message(&config),
+            format!("{}griefing", PLAYER_BAN_MESSAGE_PREFIX)
+        );
+        assert_eq!(
+            Rejection::BannedPlayer(None).message(&config),
+            format!("{}{}", PLAYER_BAN_MESSAGE_PREFIX, DEFAULT_BAN_REASON)
+        );
+    }
+
+    #[test]
+    fn test_banned_message() {
+        let config: Config = crate::util::test::config("banned_message = \"Go away: {reason}\"");
+        assert_eq!(
+            Rejection::Banned(Some("griefing".into())).message(&config),
+            "Go away: griefing"
+        );
+        assert_eq!(
+            Rejection::Banned(None).message(&config),
+            format!("Go away: {}", DEFAULT_BAN_REASON)
+        );
+    }
+
+    #[test]
+    fn test_rejection_from_state() {
+        assert_eq!(Rejection::from_state(State::Stopped), Rejection::Starting);
+        assert_eq!(Rejection::from_state(State::Starting), Rejection::Starting);
+        assert_eq!(Rejection::from_state(State::Stopping), Rejection::Stopping);
+    }
+}
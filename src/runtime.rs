@@ -0,0 +1,51 @@
+use std::io;
+
+use tokio::runtime::{self, Runtime};
+
+use crate::config::Config;
+
+/// Build multi-threaded tokio runtime to complete all work on, sized as configured.
+pub fn build(config: &Config) -> io::Result<Runtime> {
+    builder(config).build()
+}
+
+/// Create runtime builder, with configured number of worker and blocking threads.
+fn builder(config: &Config) -> runtime::Builder {
+    let mut builder = runtime::Builder::new_multi_thread();
+    builder.enable_all();
+    let (workers, blocking) = threads(config);
+    if let Some(workers) = workers {
+        builder.worker_threads(workers);
+    }
+    if let Some(blocking) = blocking {
+        builder.max_blocking_threads(blocking);
+    }
+    builder
+}
+
+/// Get configured number of worker and blocking threads, `None` to use the tokio default.
+fn threads(config: &Config) -> (Option<usize>, Option<usize>) {
+    let count = |threads: u32| (threads > 0).then_some(threads as usize);
+    (
+        count(config.advanced.worker_threads),
+        count(config.advanced.blocking_threads),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threads() {
+        let config: Config =
+            crate::util::test::config("[advanced]\nworker_threads = 3\nblocking_threads = 7");
+        assert_eq!(threads(&config), (Some(3), Some(7)));
+        assert!(build(&config).is_ok());
+
+        // Tokio defaults if not configured
+        let config: Config = crate::util::test::config("");
+        assert_eq!(threads(&config), (None, None));
+        assert!(build(&config).is_ok());
+    }
+}
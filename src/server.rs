@@ -1,11 +1,15 @@
+use std::fs;
 use std::net::IpAddr;
-use std::sync::atomic::{AtomicU8, Ordering};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU8, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use futures::FutureExt;
+use minecraft_protocol::data::server_status::OnlinePlayer;
 use minecraft_protocol::version::v1_20_3::status::ServerStatus;
-use tokio::io::AsyncWriteExt;
+use regex::Regex;
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
 use tokio::process::{ChildStdin, Command};
 use tokio::sync::mpsc;
 use tokio::sync::watch;
@@ -14,16 +18,40 @@ use tokio::sync::Semaphore;
 use tokio::sync::{Mutex, RwLock, RwLockReadGuard};
 use tokio::time;
 
-use crate::config::{Config, Server as ConfigServer};
-use crate::mc::ban::{BannedIp, BannedIps};
+use crate::config::{
+    Advanced, Config, IdleWhen, ReadyCheck, Server as ConfigServer, ServerOutput, SleepMethod,
+    StartTimeoutAction,
+};
+use crate::event::{Event, Events};
+use crate::forge::ModLoader;
+#[cfg(feature = "lobby")]
+use crate::join::queue::Queue;
+use crate::mc::ban::{BannedIp, BannedIps, BannedPlayer, BannedPlayers};
+use crate::mc::server_properties::Properties;
 use crate::mc::whitelist::Whitelist;
 use crate::os;
+use crate::persist;
 use crate::proto::packets::play::join_game::JoinGameData;
+use crate::proxy;
+use crate::rejection::Rejection;
 
 /// Server cooldown after the process quit.
 /// Used to give it some more time to quit forgotten threads, such as for RCON.
 const SERVER_QUIT_COOLDOWN: Duration = Duration::from_millis(2500);
 
+/// Interval to check whether the server process quit at.
+const PROCESS_EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Minimum interval to persist server activity at while the server is online.
+const ACTIVITY_PERSIST_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Interval to check whether an adopted server process quit at.
+const ADOPTED_PROCESS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Command name of Java server processes.
+#[cfg(target_os = "linux")]
+const JAVA_PROCESS_NAME: &str = "java";
+
 /// RCON cooldown. Required period between RCON invocations.
 ///
 /// The Minecraft RCON implementation is very broken and brittle, this is used in the hopes to
@@ -56,6 +84,42 @@ pub struct Server {
     /// Set if a server process is running.
     pid: Mutex<Option<u32>>,
 
+    /// Java child process of the server process, if discovered.
+    ///
+    /// Signalled instead of the server process if known.
+    java_pid: Mutex<Option<u32>>,
+
+    /// Serializes freezing and unfreezing the server process.
+    ///
+    /// Held while signalling the process and updating the state, so both always agree.
+    freeze_lock: Mutex<()>,
+
+    /// Last time the server was woken, recorded under the freeze lock.
+    ///
+    /// A freeze requested before it was still waiting for the lock, and is aborted.
+    last_wake: Mutex<Option<Instant>>,
+
+    /// Start the server again once the current process quits.
+    restart_on_exit: AtomicBool,
+
+    /// Whether lazymc is shutting down, the server must not be started anymore.
+    draining: AtomicBool,
+
+    /// Whether the server is held after a start timeout, it must not be started until woken
+    /// manually.
+    held: AtomicBool,
+
+    /// Number of times starting the server was retried after a start timeout.
+    ///
+    /// Reset once the server is started.
+    start_retries: AtomicU32,
+
+    /// Whether a server process task is active.
+    ///
+    /// Guards against spawning a second server process, for example when the server state was
+    /// reset because the server stopped responding while its process is still running.
+    process_active: AtomicBool,
+
     /// Server process stdin handle.
     ///
     /// Used to send console commands (e.g., `stop`) to the server process.
@@ -72,12 +136,28 @@ pub struct Server {
     /// Will remain set once known, not cleared if server goes offline.
     status: RwLock<Option<ServerStatus>>,
 
+    /// Time last status was obtained from the server.
+    ///
+    /// Used to decide when cached status must be refreshed.
+    status_updated: RwLock<Option<Instant>>,
+
+    /// Last known online players sample.
+    ///
+    /// Sample from the last server status that had players online.
+    players_sample: RwLock<Vec<OnlinePlayer>>,
+
     /// Last active time.
     ///
     /// The last time there was activity on the server. Also set at the moment the server comes
     /// online.
     last_active: RwLock<Option<Instant>>,
 
+    /// Last time a player tried to join.
+    last_join: RwLock<Option<Instant>>,
+
+    /// Last time the server monitor polled the server.
+    monitor_heartbeat: RwLock<Option<Instant>>,
+
     /// Force server to stay online until.
     keep_online_until: RwLock<Option<Instant>>,
 
@@ -89,9 +169,15 @@ pub struct Server {
     /// List of banned IPs.
     banned_ips: RwLock<BannedIps>,
 
+    /// List of banned players.
+    banned_players: RwLock<BannedPlayers>,
+
     /// Whitelist if enabled.
     whitelist: RwLock<Option<Whitelist>>,
 
+    /// Last known server properties used for wake and join decisions.
+    properties: RwLock<Properties>,
+
     /// Lock for exclusive RCON operations.
     #[cfg(feature = "rcon")]
     rcon_lock: Semaphore,
@@ -100,6 +186,51 @@ pub struct Server {
     #[cfg(feature = "rcon")]
     rcon_last_stop: Mutex<Option<Instant>>,
 
+    /// Time to escalate stopping the server at.
+    ///
+    /// Set when the server is stopped over RCON, cleared once it is not stopping anymore.
+    #[cfg(feature = "rcon")]
+    stop_escalate_at: RwLock<Option<Instant>>,
+
+    /// Time the current server process was spawned at.
+    ///
+    /// Used to measure the server startup time. Cleared once the server is started.
+    spawned_at: RwLock<Option<Instant>>,
+
+    /// Persisted lazymc state.
+    persistent: RwLock<persist::State>,
+
+    /// File to persist lazymc state to.
+    ///
+    /// Set once persisted state is loaded, nothing is persisted if unset.
+    persistent_file: RwLock<Option<PathBuf>>,
+
+    /// Time server activity was last persisted at.
+    activity_persisted: RwLock<Option<Instant>>,
+
+    /// Cumulative server statistics.
+    ///
+    /// Durations only include completed states, see `stats`.
+    stats: RwLock<persist::Stats>,
+
+    /// Time the current server state was entered at.
+    state_since: RwLock<Instant>,
+
+    /// Time the current server session started at, set while the server is online.
+    session_start: RwLock<Option<SystemTime>>,
+
+    /// File to persist statistics to, nothing is persisted if unset.
+    stats_file: RwLock<Option<PathBuf>>,
+
+    /// File to cache the last server status in, nothing is cached if unset.
+    status_cache_file: RwLock<Option<PathBuf>>,
+
+    /// Server status last written to the status cache file, as JSON.
+    status_cached: RwLock<Option<String>>,
+
+    /// Whether Forge was detected from the server status.
+    forge_detected: AtomicBool,
+
     /// Probed join game data.
     pub probed_join_game: RwLock<Option<JoinGameData>>,
 
@@ -107,6 +238,13 @@ pub struct Server {
     ///
     /// Sent to clients when they connect to lobby. Recorded from server by probe.
     pub forge_payload: RwLock<Vec<Vec<u8>>>,
+
+    /// Server events, for local integrations to follow.
+    pub events: Events,
+
+    /// Queue of clients waiting for a free slot.
+    #[cfg(feature = "lobby")]
+    pub queue: Arc<Queue>,
 }
 
 impl Server {
@@ -158,6 +296,7 @@ impl Server {
 
         // Broadcast change
         let _ = self.state_watch_sender.send(new);
+        self.events.emit(Event::State { from: old, to: new });
 
         // Update kill at time for starting/stopping state
         *self.kill_at.write().await = match new {
@@ -170,18 +309,43 @@ impl Server {
             _ => None,
         };
 
+        // Only escalate stopping while stopping
+        #[cfg(feature = "rcon")]
+        if new != State::Stopping {
+            self.stop_escalate_at.write().await.take();
+        }
+
         // Online/offline messages
         match new {
             State::Started => info!(target: "lazymc::monitor", "Server is now online"),
             State::Stopped => info!(target: "lazymc::monitor", "Server is now sleeping"),
+            State::Frozen => info!(target: "lazymc::monitor", "Server is now frozen"),
             _ => {}
         }
 
-        // If Starting -> Started, update active time and keep it online for configured time
-        if old == State::Starting && new == State::Started {
+        // Update statistics, report them when going offline
+        if old == State::Started {
+            self.log_stats().await;
+        }
+        self.record_state_stats(old, new).await;
+
+        // If Starting/Frozen -> Started, update active time and keep it online for configured time
+        if matches!(old, State::Starting | State::Frozen) && new == State::Started {
+            self.start_retries.store(0, Ordering::Release);
             self.update_last_active().await;
             self.keep_online_for(Some(config.time.min_online_time))
                 .await;
+
+            // Learn startup time if the server process was freshly spawned
+            let spawned_at = self.spawned_at.write().await.take();
+            if let Some(spawned_at) = spawned_at {
+                self.record_startup_time(spawned_at.elapsed()).await;
+            }
+        }
+
+        // Remember whether the server is online, to resume it after lazymc restarts
+        if new == State::Started || old == State::Started {
+            self.persist_activity(new == State::Started).await;
         }
 
         true
@@ -194,7 +358,10 @@ impl Server {
     pub async fn update_status(&self, config: &Config, status: Option<ServerStatus>) {
         // Update state based on curren
         match (self.state(), &status) {
-            (State::Stopped | State::Starting, Some(_)) => {
+            (State::Stopped, Some(_)) => {
+                self.update_state(State::Started, config).await;
+            }
+            (State::Starting, Some(_)) if ReadyCheck::of(config).accepts(ReadyCheck::Status) => {
                 self.update_state(State::Started, config).await;
             }
             (State::Started, None) => {
@@ -205,36 +372,96 @@ impl Server {
 
         // Update last status if known
         if let Some(status) = status {
-            // Update last active time if there are online players
-            if status.players.online > 0 {
+            // Update last active time if there are enough online players, unless detecting activity
+            if config.time.keeps_awake(status.players.online) && !Advanced::detects_activity(config)
+            {
                 self.update_last_active().await;
             }
 
-            self.status.write().await.replace(status);
+            // Remember players sample
+            if !status.players.sample.is_empty() {
+                *self.players_sample.write().await = status.players.sample.clone();
+            }
+
+            let mut current = self.status.write().await;
+            let players_changed = current
+                .as_ref()
+                .map(|current| {
+                    (current.players.online, current.players.max)
+                        != (status.players.online, status.players.max)
+                })
+                .unwrap_or(true);
+            if players_changed {
+                self.events.emit(Event::Players {
+                    online: status.players.online,
+                    max: status.players.max,
+                });
+            }
+            current.replace(status);
+            drop(current);
+            self.status_updated.write().await.replace(Instant::now());
+
+            // Remember version for sleeping status after restarting lazymc
+            self.cache_version().await;
+            self.cache_status().await;
+        }
+    }
+
+    /// Claim refreshing cached status if it is older than the given TTL.
+    ///
+    /// Returns true if the caller should refresh. Resets the status age so concurrent callers
+    /// don't refresh too.
+    pub async fn claim_status_refresh(&self, ttl: Duration) -> bool {
+        let mut updated = self.status_updated.write().await;
+        if updated.map(|t| t.elapsed() < ttl).unwrap_or(false) {
+            return false;
         }
+        updated.replace(Instant::now());
+        true
     }
 
-    /// Try to start the server.
+    /// Try to start the server, or resume it if it is frozen.
     ///
-    /// Does nothing if currently not in stopped state.
+    /// Does nothing if currently not in stopped or frozen state.
     pub async fn start(config: Arc<Config>, server: Arc<Server>, username: Option<String>) -> bool {
-        // Must set state from stopped to starting
-        if !server
-            .update_state_from(Some(State::Stopped), State::Starting, &config)
-            .await
+        // Never start while shutting down
+        if server.is_draining() {
+            debug!(target: "lazymc", "Not starting server, lazymc is shutting down");
+            return false;
+        }
+
+        // Never start while held after start timeout
+        if server.is_held() {
+            debug!(target: "lazymc", "Not starting server, held after start timeout until woken manually");
+            return false;
+        }
+
+        // Unfreeze server if it is frozen, otherwise must set state from stopped to starting
+        #[cfg(unix)]
+        let resumed = unfreeze_server_signal(&config, &server).await;
+        #[cfg(not(unix))]
+        let resumed = false;
+        if !resumed
+            && !server
+                .update_state_from(Some(State::Stopped), State::Starting, &config)
+                .await
         {
             return false;
         }
 
+        server.events.emit(Event::Wake {
+            username: username.clone(),
+        });
+
         // Log starting message
+        let action = if resumed { "Unfreezing" } else { "Starting" };
         match username {
-            Some(username) => info!(target: "lazymc", "Starting server for '{}'...", username),
-            None => info!(target: "lazymc", "Starting server..."),
+            Some(username) => info!(target: "lazymc", "{} server for '{}'...", action, username),
+            None => info!(target: "lazymc", "{} server...", action),
         }
 
-        // Unfreeze server if it is frozen
-        #[cfg(unix)]
-        if config.server.freeze_process && unfreeze_server_signal(&config, &server).await {
+        // Frozen server is online right away
+        if resumed {
             return true;
         }
 
@@ -245,20 +472,60 @@ impl Server {
 
     /// Spawn the server task.
     ///
+    /// Does nothing if a server process is still active.
+    ///
     /// This should not be called directly.
     fn spawn_server_task(config: Arc<Config>, server: Arc<Server>) {
-        tokio::spawn(invoke_server_cmd(config, server).map(|_| ()));
+        if server
+            .process_active
+            .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+            .is_err()
+        {
+            warn!(target: "lazymc", "Server process is still running, not spawning another");
+            return;
+        }
+
+        tokio::spawn(async move {
+            // Flag is released by the task itself once the process quit
+            if invoke_server_cmd(config, server.clone()).await.is_err() {
+                server.process_active.store(false, Ordering::Release);
+            }
+        });
     }
 
-    /// Stop running server.
+    /// Put running server to sleep, with the configured sleep method.
     ///
-    /// This will attempt to stop the server with all available methods.
-    #[allow(unused_variables)]
+    /// Falls back to stopping the server process with all available methods.
     pub async fn stop(&self, config: &Config) -> bool {
-        // Try to freeze through signal
+        match SleepMethod::of(config) {
+            // Try to freeze through signal
+            #[cfg(unix)]
+            SleepMethod::Freeze => match freeze_server_signal(config, self).await {
+                Freeze::Frozen => true,
+                Freeze::Woken => false,
+                Freeze::Failed => self.stop_process(config).await,
+            },
+            _ => self.stop_process(config).await,
+        }
+    }
+
+    /// Stop running server process.
+    ///
+    /// Like `stop`, but never freezes the server process. Resumes it first if it is frozen.
+    #[allow(unused_variables)]
+    pub async fn stop_process(&self, config: &Config) -> bool {
+        // Resume frozen server so it can quit
         #[cfg(unix)]
-        if config.server.freeze_process && freeze_server_signal(config, self).await {
-            return true;
+        {
+            let _freeze = self.freeze_lock.lock().await;
+            if self
+                .update_state_from(Some(State::Frozen), State::Stopping, config)
+                .await
+            {
+                if let Some(pid) = self.signal_pid().await {
+                    let _ = os::unfreeze(pid, config.advanced.signal_process_group);
+                }
+            }
         }
 
         // Try to stop through RCON if started
@@ -285,13 +552,241 @@ impl Server {
     /// Force kill running server.
     ///
     /// This requires the server PID to be known.
-    pub async fn force_kill(&self) -> bool {
-        if let Some(pid) = *self.pid.lock().await {
-            return os::force_kill(pid);
+    pub async fn force_kill(&self, config: &Config) -> bool {
+        if let Some(pid) = self.signal_pid().await {
+            return os::force_kill(pid, config.advanced.signal_process_group).is_ok();
         }
         false
     }
 
+    /// Whether a server process is running.
+    pub async fn has_process(&self) -> bool {
+        self.pid.lock().await.is_some()
+    }
+
+    /// Get PID of the process to signal, the Java child process if known or the server process.
+    async fn signal_pid(&self) -> Option<u32> {
+        if let Some(pid) = *self.java_pid.lock().await {
+            return Some(pid);
+        }
+        *self.pid.lock().await
+    }
+
+    /// Remember PID of the server process, and write it to the PID file.
+    async fn set_process_pid(&self, config: &Config, pid: u32) {
+        self.pid.lock().await.replace(pid);
+
+        if let Some(file) = pid_file(config) {
+            if let Err(err) = persist::write_pid(&file, pid) {
+                warn!(target: "lazymc", "Failed to write server PID to {}: {}", persist::SERVER_PID_FILE, err);
+            }
+        }
+    }
+
+    /// Forget PID of the server process, and remove the PID file.
+    async fn clear_process_pid(&self, config: &Config) {
+        self.pid.lock().await.take();
+        self.java_pid.lock().await.take();
+
+        if let Some(file) = pid_file(config) {
+            let _ = fs::remove_file(file);
+        }
+    }
+
+    /// Reattach to a server that is already running when lazymc starts.
+    ///
+    /// If the server is responding, adopts the started state instead of spawning a new process.
+    /// The process itself is adopted too if it is known from the PID file.
+    ///
+    /// Returns `true` if reattached.
+    pub async fn reattach(config: Arc<Config>, server: Arc<Server>, responding: bool) -> bool {
+        let file = pid_file(&config);
+        let pid = file
+            .as_deref()
+            .and_then(persist::read_pid)
+            .filter(|pid| os::is_alive(*pid));
+
+        if !responding {
+            // Remove stale PID file
+            if let (Some(file), None) = (file, pid) {
+                let _ = fs::remove_file(file);
+            }
+            return false;
+        }
+
+        match pid {
+            Some(pid) => {
+                info!(target: "lazymc", "Server is already running, reattaching to process {}", pid)
+            }
+            None => info!(target: "lazymc", "Server is already running, reattaching"),
+        }
+
+        // Adopt process so we can signal it, and never spawn a second one
+        if let Some(pid) = pid {
+            if server
+                .process_active
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                server.pid.lock().await.replace(pid);
+                tokio::spawn(watch_adopted_process(config.clone(), server.clone(), pid));
+            }
+        }
+
+        server.update_state(State::Started, &config).await;
+        true
+    }
+
+    /// Wait for the server process to quit, with timeout.
+    ///
+    /// Returns `true` if no process is running anymore.
+    pub async fn wait_process_exit(&self, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        while self.has_process().await {
+            if Instant::now() >= deadline {
+                return false;
+            }
+            time::sleep(PROCESS_EXIT_POLL_INTERVAL).await;
+        }
+        true
+    }
+
+    /// Start draining, because lazymc is shutting down.
+    ///
+    /// The server won't be started anymore.
+    pub fn start_draining(&self) {
+        self.draining.store(true, Ordering::Release);
+    }
+
+    /// Whether lazymc is shutting down.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Acquire)
+    }
+
+    /// Whether the server is held after a start timeout.
+    pub fn is_held(&self) -> bool {
+        self.held.load(Ordering::Acquire)
+    }
+
+    /// Whether the server process is frozen.
+    ///
+    /// A frozen server still holds its port without ever responding on it, so it must not be
+    /// polled.
+    pub fn is_frozen(&self) -> bool {
+        self.state() == State::Frozen
+    }
+
+    /// Release the server if it is held after a start timeout, allowing it to start again.
+    pub fn release_hold(&self) {
+        if self.held.swap(false, Ordering::AcqRel) {
+            info!(target: "lazymc", "Server woken manually, no longer held after start timeout");
+        }
+    }
+
+    /// Take the configured action on a server that took too long to start.
+    ///
+    /// Returns false if the server process could not be killed.
+    pub async fn start_timed_out(&self, config: &Config) -> bool {
+        match config.server.start_timeout_action {
+            StartTimeoutAction::Kill => {
+                error!(target: "lazymc", "Force killing server, took too long to start");
+            }
+            StartTimeoutAction::Retry => {
+                // Only act once on this start attempt
+                self.kill_at.write().await.take();
+
+                let retries = self.start_retries.fetch_add(1, Ordering::AcqRel) + 1;
+                if retries <= config.server.start_timeout_retries {
+                    warn!(target: "lazymc", "Server took too long to start, retrying ({}/{})...", retries, config.server.start_timeout_retries);
+                    return self.restart(config).await;
+                }
+
+                error!(target: "lazymc", "Server took too long to start, giving up after {} retries", config.server.start_timeout_retries);
+                self.start_retries.store(0, Ordering::Release);
+            }
+            StartTimeoutAction::Hold => {
+                error!(target: "lazymc", "Server took too long to start, killing and holding until woken manually");
+                self.held.store(true, Ordering::Release);
+            }
+        }
+
+        self.force_kill(config).await
+    }
+
+    /// Force kill the server process, and start it again once it quit.
+    pub async fn restart(&self, config: &Config) -> bool {
+        self.restart_on_exit.store(true, Ordering::Release);
+        if !self.force_kill(config).await {
+            self.restart_on_exit.store(false, Ordering::Release);
+            return false;
+        }
+        true
+    }
+
+    /// Decide whether to escalate stopping the server.
+    ///
+    /// True if the server did not quit in time after stopping it over RCON.
+    #[cfg(feature = "rcon")]
+    pub async fn should_escalate_stop(&self) -> bool {
+        self.stop_escalate_at
+            .read()
+            .await
+            .map(|t| t <= Instant::now())
+            .unwrap_or(false)
+    }
+
+    /// Escalate stopping the server.
+    ///
+    /// Sends a stop signal, and force kills the server if that fails. Returns the escalation that
+    /// was used, or `None` if the server PID is unknown.
+    #[cfg(feature = "rcon")]
+    pub async fn escalate_stop(&self, config: &Config) -> Option<StopEscalation> {
+        self.stop_escalate_at.write().await.take();
+
+        let pid = self.signal_pid().await?;
+
+        #[cfg(unix)]
+        if os::kill_gracefully(pid, config.advanced.signal_process_group).is_ok() {
+            self.signaled_stop(config).await;
+            return Some(StopEscalation::Signal);
+        }
+
+        warn!(target: "lazymc", "Failed to send stop signal to server process, force killing");
+        match os::force_kill(pid, config.advanced.signal_process_group) {
+            Ok(()) => {}
+            Err(os::SignalError::NoProcess) => self.process_gone(config).await,
+            Err(_) => warn!(target: "lazymc", "Failed to force kill server"),
+        }
+        Some(StopEscalation::ForceKill)
+    }
+
+    /// Called when signalling the server process found it doesn't exist anymore.
+    ///
+    /// Forgets the stale PID, and marks the server stopped.
+    #[cfg(any(unix, feature = "rcon"))]
+    async fn process_gone(&self, config: &Config) {
+        warn!(target: "lazymc", "Server process is gone, marking server as stopped");
+        self.clear_process_pid(config).await;
+        self.update_state(State::Stopped, config).await;
+    }
+
+    /// Called after a stop signal was sent to the server.
+    ///
+    /// Moves force kill time forward to the graceful kill timeout, if configured.
+    #[cfg(unix)]
+    async fn signaled_stop(&self, config: &Config) {
+        if config.server.graceful_kill_timeout == 0 {
+            return;
+        }
+
+        let kill_at =
+            Instant::now() + Duration::from_secs(config.server.graceful_kill_timeout as u64);
+        let mut current = self.kill_at.write().await;
+        if current.map(|t| kill_at < t).unwrap_or(true) {
+            current.replace(kill_at);
+        }
+    }
+
     /// Decide whether the server should sleep.
     ///
     /// Always returns false if it is currently not online.
@@ -301,18 +796,29 @@ impl Server {
             return false;
         }
 
-        // Never sleep if players are online
-        let players_online = self
-            .status
-            .read()
-            .await
-            .as_ref()
-            .map(|status| status.players.online > 0)
-            .unwrap_or(false);
+        // Never sleep while in use, by online players or connected clients depending on config,
+        // online players only count through their observed activity when detecting activity
+        let players_online = !Advanced::detects_activity(config)
+            && self
+                .status
+                .read()
+                .await
+                .as_ref()
+                .map(|status| config.time.keeps_awake(status.players.online))
+                .unwrap_or(false);
         if players_online {
             trace!(target: "lazymc", "Not sleeping because players are online");
             return false;
         }
+        if !is_idle(
+            config.time.idle_when,
+            players_online,
+            proxy::STATS.active_sessions(),
+        ) {
+            trace!(target: "lazymc", "Not sleeping because clients are connected");
+            self.update_last_active().await;
+            return false;
+        }
 
         // Don't sleep when keep online until isn't expired
         let keep_online = self
@@ -348,64 +854,554 @@ impl Server {
         self.status.read().await
     }
 
-    /// Update the last active time.
-    pub async fn update_last_active(&self) {
-        self.last_active.write().await.replace(Instant::now());
+    /// Whether the server is full, based on the last known status.
+    pub async fn is_full(&self) -> bool {
+        self.status
+            .read()
+            .await
+            .as_ref()
+            .map(|status| status.players.max > 0 && status.players.online >= status.players.max)
+            .unwrap_or(false)
     }
 
-    /// Force the server to be online for the given number of seconds.
-    async fn keep_online_for(&self, duration: Option<u32>) {
-        *self.keep_online_until.write().await = duration
-            .filter(|d| *d > 0)
-            .map(|d| Instant::now() + Duration::from_secs(d as u64));
+    /// Number of free player slots, based on the last known status.
+    #[cfg(feature = "lobby")]
+    pub async fn free_slots(&self) -> Option<u32> {
+        self.status
+            .read()
+            .await
+            .as_ref()
+            .map(|status| status.players.max.saturating_sub(status.players.online))
     }
 
-    /// Check whether the given IP is banned.
+    /// Get last known online players sample.
+    pub async fn players_sample(&self) -> Vec<OnlinePlayer> {
+        self.players_sample.read().await.clone()
+    }
+
+    /// Update last known online players sample from player names.
     ///
-    /// This uses the latest known `banned-ips.json` contents if known.
-    /// If this feature is disabled, this will always return false.
-    pub async fn is_banned_ip(&self, ip: &IpAddr) -> bool {
-        self.banned_ips.read().await.is_banned(ip)
+    /// Keeps the current sample if no names are given.
+    #[cfg(feature = "rcon")]
+    pub async fn update_players_sample(&self, names: Vec<String>) {
+        if names.is_empty() {
+            return;
+        }
+
+        *self.players_sample.write().await = names
+            .into_iter()
+            .map(|name| OnlinePlayer {
+                name,
+                id: uuid::Uuid::nil(),
+            })
+            .collect();
     }
 
-    /// Get user ban entry.
-    pub async fn ban_entry(&self, ip: &IpAddr) -> Option<BannedIp> {
-        self.banned_ips.read().await.get(ip)
+    /// Update the last active time.
+    pub async fn update_last_active(&self) {
+        self.last_active.write().await.replace(Instant::now());
+
+        // Periodically persist activity while online
+        let persisted = *self.activity_persisted.read().await;
+        let due = persisted.map_or(true, |t| t.elapsed() >= ACTIVITY_PERSIST_INTERVAL);
+        if due && self.state() == State::Started {
+            self.persist_activity(true).await;
+        }
     }
 
-    /// Check whether the given IP is banned.
+    /// Remember server activity, and persist it.
     ///
-    /// This uses the latest known `banned-ips.json` contents if known.
-    /// If this feature is disabled, this will always return false.
-    pub fn is_banned_ip_blocking(&self, ip: &IpAddr) -> bool {
-        futures::executor::block_on(async { self.is_banned_ip(ip).await })
+    /// Not updated while lazymc is shutting down, so a server that was online when lazymc quit is
+    /// resumed on the next start.
+    async fn persist_activity(&self, online: bool) {
+        if self.is_draining() {
+            return;
+        }
+
+        let now = SystemTime::now();
+        let last_active = self
+            .last_active
+            .read()
+            .await
+            .and_then(|t| now.checked_sub(t.elapsed()))
+            .unwrap_or(now);
+        let last_active = last_active
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+
+        self.persistent
+            .write()
+            .await
+            .activity
+            .replace(persist::Activity {
+                online,
+                last_active,
+            });
+        self.activity_persisted
+            .write()
+            .await
+            .replace(Instant::now());
+        self.save_persistent().await;
     }
 
-    /// Check whether the given username is whitelisted.
+    /// Whether the server should be resumed when starting lazymc.
     ///
-    /// Returns `true` if no whitelist is currently used.
-    pub async fn is_whitelisted(&self, username: &str) -> bool {
-        self.whitelist
+    /// True if the server was online and active within the configured resume window before lazymc
+    /// quit.
+    pub async fn should_resume(&self, config: &Config) -> bool {
+        if config.server.resume_window == 0 {
+            return false;
+        }
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.persistent
             .read()
             .await
-            .as_ref()
-            .map(|w| w.is_whitelisted(username))
-            .unwrap_or(true)
+            .activity
+            .map_or(false, |activity| {
+                activity.is_recent(config.server.resume_window, now)
+            })
     }
 
-    /// Update the list of banned IPs.
-    pub async fn set_banned_ips(&self, ips: BannedIps) {
-        *self.banned_ips.write().await = ips;
+    /// Update the last time a player tried to join.
+    pub async fn update_last_join(&self) {
+        self.last_join.write().await.replace(Instant::now());
     }
 
-    /// Update the list of banned IPs.
-    pub fn set_banned_ips_blocking(&self, ips: BannedIps) {
-        futures::executor::block_on(async { self.set_banned_ips(ips).await })
+    /// Mark the server monitor as alive.
+    pub async fn update_monitor_heartbeat(&self) {
+        self.monitor_heartbeat.write().await.replace(Instant::now());
     }
 
-    /// Update the whitelist.
-    pub async fn set_whitelist(&self, whitelist: Option<Whitelist>) {
-        *self.whitelist.write().await = whitelist;
+    /// Whether the server monitor polled the server within the given time.
+    pub async fn is_monitor_alive(&self, timeout: Duration) -> bool {
+        self.monitor_heartbeat
+            .read()
+            .await
+            .map_or(false, |heartbeat| heartbeat.elapsed() <= timeout)
+    }
+
+    /// Decide whether the server should go back to sleep after probing.
+    ///
+    /// Only if it is online, and no player is online or tried to join since the given time.
+    pub async fn should_sleep_after_probe(&self, since: Instant) -> bool {
+        if self.state() != State::Started {
+            return false;
+        }
+
+        let joined = self.last_join.read().await.map_or(false, |t| t >= since);
+        let players_online = self
+            .status
+            .read()
+            .await
+            .as_ref()
+            .map_or(false, |status| status.players.online > 0);
+
+        !joined && !players_online
+    }
+
+    /// Force the server to be online for the given number of seconds.
+    async fn keep_online_for(&self, duration: Option<u32>) {
+        *self.keep_online_until.write().await = duration
+            .filter(|d| *d > 0)
+            .map(|d| Instant::now() + Duration::from_secs(d as u64));
+    }
+
+    /// Whether the server runs Forge.
+    ///
+    /// Uses the configured value if set, detected value otherwise.
+    pub fn is_forge(&self, config: &Config) -> bool {
+        config
+            .server
+            .forge
+            .unwrap_or_else(|| self.forge_detected.load(Ordering::Relaxed))
+    }
+
+    /// Set the mod loader detected from the server status.
+    pub fn set_mod_loader(&self, mod_loader: Option<ModLoader>) {
+        let forge = mod_loader.map(ModLoader::is_forge).unwrap_or(false);
+        if self.forge_detected.swap(forge, Ordering::Relaxed) != forge {
+            match mod_loader {
+                Some(mod_loader) => {
+                    info!(target: "lazymc::monitor", "Detected {:?} mod loader on server", mod_loader)
+                }
+                None => debug!(target: "lazymc::monitor", "No longer detecting Forge on server"),
+            }
+        }
+    }
+
+    /// Get the time since the server process was spawned, while it is starting.
+    ///
+    /// Returns `None` if the server is not starting from a freshly spawned process.
+    pub async fn starting_for(&self) -> Option<Duration> {
+        if self.state() != State::Starting {
+            return None;
+        }
+        self.spawned_at
+            .read()
+            .await
+            .map(|spawned_at| spawned_at.elapsed())
+    }
+
+    /// Get the learned average server startup time.
+    ///
+    /// Returns `None` if no startup time was recorded yet.
+    pub async fn startup_time(&self) -> Option<Duration> {
+        self.persistent.read().await.startup.average()
+    }
+
+    /// Record a server startup time, and persist it.
+    async fn record_startup_time(&self, duration: Duration) {
+        debug!(target: "lazymc", "Server started in {:.1}s", duration.as_secs_f32());
+        self.persistent.write().await.startup.record(duration);
+        self.save_persistent().await;
+    }
+
+    /// Get last known server version, remembered across restarts.
+    pub async fn cached_version(&self) -> Option<persist::Version> {
+        self.persistent.read().await.version.clone()
+    }
+
+    /// Remember the version from the current server status.
+    ///
+    /// Only persisted if it changed.
+    async fn cache_version(&self) {
+        let version = match self.status.read().await.as_ref() {
+            Some(status) => persist::Version {
+                name: status.version.name.clone(),
+                protocol: status.version.protocol,
+            },
+            None => return,
+        };
+
+        {
+            let mut persistent = self.persistent.write().await;
+            if persistent.version.as_ref() == Some(&version) {
+                return;
+            }
+            persistent.version.replace(version);
+        }
+        self.save_persistent().await;
+    }
+
+    /// Load server status cached by a previous run, if enabled.
+    ///
+    /// Seeds the sleeping status, so accurate info is presented right after starting lazymc.
+    pub async fn load_status_cache(&self, config: &Config) {
+        let file = match Advanced::status_cache_file(config) {
+            Some(file) => file,
+            None => return,
+        };
+
+        match persist::load::<Option<ServerStatus>>(&file) {
+            Ok(Some(status)) => {
+                if !status.players.sample.is_empty() {
+                    *self.players_sample.write().await = status.players.sample.clone();
+                }
+                self.status.write().await.get_or_insert(status);
+            }
+            Ok(None) => {}
+            Err(err) => {
+                warn!(target: "lazymc", "Failed to load cached server status from {}, ignoring: {}", file.display(), err);
+            }
+        }
+
+        self.status_cache_file.write().await.replace(file);
+    }
+
+    /// Write the current server status to the status cache file, if loaded before.
+    ///
+    /// Online players are not cached. Only written if the cached status changed.
+    async fn cache_status(&self) {
+        let file = match self.status_cache_file.read().await.clone() {
+            Some(file) => file,
+            None => return,
+        };
+        let mut status = match self.status.read().await.clone() {
+            Some(status) => status,
+            None => return,
+        };
+        status.players.online = 0;
+        status.players.sample = self.players_sample().await;
+
+        let json = match serde_json::to_string(&status) {
+            Ok(json) => json,
+            Err(_) => return,
+        };
+        let mut cached = self.status_cached.write().await;
+        if cached.as_ref() == Some(&json) {
+            return;
+        }
+
+        match persist::save(&file, &status) {
+            Ok(()) => {
+                cached.replace(json);
+            }
+            Err(err) => {
+                warn!(target: "lazymc", "Failed to cache server status to {}: {}", file.display(), err);
+            }
+        }
+    }
+
+    /// Get server statistics, including the current state.
+    pub async fn stats(&self) -> persist::Stats {
+        let mut stats = *self.stats.read().await;
+        let current = self.state_since.read().await.elapsed().as_secs_f64();
+        match self.state() {
+            State::Started => stats.uptime += current,
+            State::Stopped | State::Frozen => stats.sleep_time += current,
+            _ => {}
+        }
+        stats
+    }
+
+    /// Get the time the current server session started at, if online.
+    pub async fn session_start(&self) -> Option<SystemTime> {
+        *self.session_start.read().await
+    }
+
+    /// Log statistics of the current session and in total.
+    async fn log_stats(&self) {
+        let session = self
+            .session_start()
+            .await
+            .and_then(|start| start.elapsed().ok())
+            .unwrap_or_default();
+        let stats = self.stats().await;
+        info!(
+            target: "lazymc",
+            "Server was online for {}s, {}s in total, woken {} time(s), crashed {} time(s)",
+            session.as_secs(),
+            stats.uptime as u64,
+            stats.wakes,
+            stats.crashes,
+        );
+        info!(
+            target: "lazymc",
+            "Proxied {} bytes to server and {} bytes to clients in {} session(s), {} active",
+            proxy::STATS.bytes_to_server(),
+            proxy::STATS.bytes_to_client(),
+            proxy::STATS.sessions(),
+            proxy::STATS.active_sessions(),
+        );
+    }
+
+    /// Update statistics for a state change.
+    async fn record_state_stats(&self, old: State, new: State) {
+        let elapsed = {
+            let mut since = self.state_since.write().await;
+            let elapsed = since.elapsed().as_secs_f64();
+            *since = Instant::now();
+            elapsed
+        };
+
+        {
+            let mut stats = self.stats.write().await;
+            match old {
+                State::Started => stats.uptime += elapsed,
+                State::Stopped | State::Frozen => stats.sleep_time += elapsed,
+                _ => {}
+            }
+            if (old == State::Stopped && new == State::Starting)
+                || (old == State::Frozen && new == State::Started)
+            {
+                stats.wakes += 1;
+            }
+        }
+
+        *self.session_start.write().await = match new {
+            State::Started => Some(SystemTime::now()),
+            _ => None,
+        };
+
+        self.save_stats().await;
+    }
+
+    /// Record a server crash.
+    async fn record_crash(&self) {
+        self.stats.write().await.crashes += 1;
+        self.save_stats().await;
+    }
+
+    /// Record an attempt to wake the server blocked by the whitelist.
+    pub async fn record_blocked_wake(&self, username: &str, ip: IpAddr) {
+        info!(target: "lazymc", "User '{}' ({}) tried to wake server but is not whitelisted, disconnecting", username, ip);
+        self.stats.write().await.blocked_wakes += 1;
+        self.save_stats().await;
+    }
+
+    /// Save statistics, if enabled.
+    async fn save_stats(&self) {
+        let file = match self.stats_file.read().await.clone() {
+            Some(file) => file,
+            None => return,
+        };
+
+        if let Err(err) = persist::save(&file, &*self.stats.read().await) {
+            warn!(target: "lazymc", "Failed to save statistics to {}: {}", persist::STATS_FILE, err);
+        }
+    }
+
+    /// Load persisted lazymc state from the server directory.
+    ///
+    /// Once loaded, state changes are persisted to the same file.
+    pub async fn load_persistent(&self, config: &Config) {
+        let dir = match ConfigServer::server_directory(config) {
+            Some(dir) => dir,
+            None => return,
+        };
+        let file = dir.join(persist::FILE);
+
+        match persist::load(&file) {
+            Ok(state) => *self.persistent.write().await = state,
+            Err(err) => {
+                warn!(target: "lazymc", "Failed to load persisted state from {}, ignoring: {}", persist::FILE, err);
+            }
+        }
+
+        self.persistent_file.write().await.replace(file);
+
+        // Load statistics if persisted
+        if config.advanced.persist_stats {
+            let file = dir.join(persist::STATS_FILE);
+            match persist::load(&file) {
+                Ok(stats) => *self.stats.write().await = stats,
+                Err(err) => {
+                    warn!(target: "lazymc", "Failed to load statistics from {}, ignoring: {}", persist::STATS_FILE, err);
+                }
+            }
+            self.stats_file.write().await.replace(file);
+        }
+    }
+
+    /// Save persisted lazymc state, if loaded before.
+    async fn save_persistent(&self) {
+        let file = match self.persistent_file.read().await.clone() {
+            Some(file) => file,
+            None => return,
+        };
+
+        if let Err(err) = persist::save(&file, &*self.persistent.read().await) {
+            warn!(target: "lazymc", "Failed to save persisted state to {}: {}", persist::FILE, err);
+        }
+    }
+
+    /// Check whether the given IP is banned.
+    ///
+    /// This uses the latest known `banned-ips.json` contents if known.
+    /// If this feature is disabled, this will always return false.
+    pub async fn is_banned_ip(&self, ip: &IpAddr) -> bool {
+        self.banned_ips.read().await.is_banned(ip)
+    }
+
+    /// Get user ban entry.
+    pub async fn ban_entry(&self, ip: &IpAddr) -> Option<BannedIp> {
+        self.banned_ips.read().await.get(ip)
+    }
+
+    /// Check whether the given IP is banned.
+    ///
+    /// This uses the latest known `banned-ips.json` contents if known.
+    /// If this feature is disabled, this will always return false.
+    pub fn is_banned_ip_blocking(&self, ip: &IpAddr) -> bool {
+        futures::executor::block_on(async { self.is_banned_ip(ip).await })
+    }
+
+    /// Get active ban entry for the given username.
+    ///
+    /// This uses the latest known `banned-players.json` contents if known.
+    pub async fn banned_player(&self, username: &str) -> Option<BannedPlayer> {
+        self.banned_players.read().await.get(username)
+    }
+
+    /// Check whether the given username is whitelisted.
+    ///
+    /// Returns `true` if no whitelist is currently used.
+    pub async fn is_whitelisted(&self, username: &str) -> bool {
+        self.whitelist
+            .read()
+            .await
+            .as_ref()
+            .map(|w| w.is_whitelisted(username))
+            .unwrap_or(true)
+    }
+
+    /// Check whether the given user may wake the server.
+    ///
+    /// Returns the rejection if the server would refuse the user anyway, based on the last known
+    /// server properties and whitelist.
+    pub async fn wake_rejection(&self, config: &Config, username: &str) -> Option<Rejection> {
+        if self.is_held() {
+            return Some(Rejection::Held);
+        }
+        if config.server.wake_max_players && self.properties.read().await.is_closed() {
+            return Some(Rejection::Full);
+        }
+        if !self.is_whitelisted(username).await {
+            return Some(Rejection::NotWhitelisted);
+        }
+        None
+    }
+
+    /// Whether the server is in online mode.
+    ///
+    /// Lobby join methods can't handle encryption, and only work with servers in offline mode.
+    ///
+    /// Uses the configured hint, or the last known server properties. Assumes offline mode if
+    /// unknown.
+    #[cfg(feature = "lobby")]
+    pub async fn is_online_mode(&self, config: &Config) -> bool {
+        match config.server.online_mode {
+            Some(online_mode) => online_mode,
+            None => self.properties().await.online_mode.unwrap_or(false),
+        }
+    }
+
+    /// Get last known server properties.
+    pub async fn properties(&self) -> Properties {
+        *self.properties.read().await
+    }
+
+    /// Get last known server properties.
+    pub fn properties_blocking(&self) -> Properties {
+        futures::executor::block_on(async { self.properties().await })
+    }
+
+    /// Update the last known server properties.
+    pub async fn set_properties(&self, properties: Properties) {
+        *self.properties.write().await = properties;
+    }
+
+    /// Update the last known server properties.
+    pub fn set_properties_blocking(&self, properties: Properties) {
+        futures::executor::block_on(async { self.set_properties(properties).await })
+    }
+
+    /// Update the list of banned IPs.
+    pub async fn set_banned_ips(&self, ips: BannedIps) {
+        *self.banned_ips.write().await = ips;
+    }
+
+    /// Update the list of banned IPs.
+    pub fn set_banned_ips_blocking(&self, ips: BannedIps) {
+        futures::executor::block_on(async { self.set_banned_ips(ips).await })
+    }
+
+    /// Update the list of banned players.
+    pub async fn set_banned_players(&self, players: BannedPlayers) {
+        *self.banned_players.write().await = players;
+    }
+
+    /// Update the list of banned players.
+    pub fn set_banned_players_blocking(&self, players: BannedPlayers) {
+        futures::executor::block_on(async { self.set_banned_players(players).await })
+    }
+
+    /// Update the whitelist.
+    pub async fn set_whitelist(&self, whitelist: Option<Whitelist>) {
+        *self.whitelist.write().await = whitelist;
     }
 
     /// Update the whitelist.
@@ -428,20 +1424,50 @@ impl Server {
                 state_watch_sender,
                 state_watch_receiver,
                 pid: Default::default(),
+                java_pid: Default::default(),
+                freeze_lock: Default::default(),
+                last_wake: Default::default(),
+                process_active: AtomicBool::new(false),
+                restart_on_exit: AtomicBool::new(false),
+                draining: AtomicBool::new(false),
+                held: AtomicBool::new(false),
+                start_retries: AtomicU32::new(0),
                 stdin: Default::default(),
                 stdin_rx: Mutex::new(stdin_rx),
                 status: Default::default(),
+                status_updated: Default::default(),
+                players_sample: Default::default(),
                 last_active: Default::default(),
+                last_join: Default::default(),
+                monitor_heartbeat: Default::default(),
                 keep_online_until: Default::default(),
                 kill_at: Default::default(),
                 banned_ips: Default::default(),
+                banned_players: Default::default(),
                 whitelist: Default::default(),
+                properties: Default::default(),
                 #[cfg(feature = "rcon")]
                 rcon_lock: Semaphore::new(1),
                 #[cfg(feature = "rcon")]
                 rcon_last_stop: Default::default(),
+                #[cfg(feature = "rcon")]
+                stop_escalate_at: Default::default(),
+                spawned_at: Default::default(),
+                persistent: Default::default(),
+                persistent_file: Default::default(),
+                activity_persisted: Default::default(),
+                stats: Default::default(),
+                state_since: RwLock::new(Instant::now()),
+                session_start: Default::default(),
+                stats_file: Default::default(),
+                status_cache_file: Default::default(),
+                status_cached: Default::default(),
+                forge_detected: Default::default(),
                 probed_join_game: Default::default(),
                 forge_payload: Default::default(),
+                events: Default::default(),
+                #[cfg(feature = "lobby")]
+                queue: Default::default(),
             },
             stdin_tx,
         )
@@ -449,7 +1475,8 @@ impl Server {
 }
 
 /// Server state.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum State {
     /// Server is stopped.
     Stopped,
@@ -462,6 +1489,9 @@ pub enum State {
 
     /// Server is stopping.
     Stopping,
+
+    /// Server process is frozen, it is sleeping but resumes instantly.
+    Frozen,
 }
 
 impl State {
@@ -472,6 +1502,7 @@ impl State {
             1 => Self::Starting,
             2 => Self::Started,
             3 => Self::Stopping,
+            4 => Self::Frozen,
             _ => panic!("invalid State u8"),
         }
     }
@@ -483,37 +1514,117 @@ impl State {
             Self::Starting => 1,
             Self::Started => 2,
             Self::Stopping => 3,
+            Self::Frozen => 4,
         }
     }
 }
 
-/// Invoke server command, store PID and wait for it to quit.
-pub async fn invoke_server_cmd(
-    config: Arc<Config>,
-    state: Arc<Server>,
-) -> Result<(), Box<dyn std::error::Error>> {
-    // Configure command
-    let args = shlex::split(&config.server.command).expect("invalid server command");
+/// Escalation used to stop a server that didn't quit after RCON stop.
+#[cfg(feature = "rcon")]
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum StopEscalation {
+    /// Sent stop signal.
+    Signal,
+
+    /// Force killed, because sending stop signal failed.
+    ForceKill,
+}
+
+/// Get server process PID file path, in the server directory.
+pub fn pid_file(config: &Config) -> Option<PathBuf> {
+    ConfigServer::server_directory(config).map(|dir| dir.join(persist::SERVER_PID_FILE))
+}
+
+/// Watch an adopted server process until it quits.
+///
+/// The process isn't our child, so we can't wait on it directly.
+async fn watch_adopted_process(config: Arc<Config>, server: Arc<Server>, pid: u32) {
+    while os::is_alive(pid) {
+        time::sleep(ADOPTED_PROCESS_POLL_INTERVAL).await;
+    }
+
+    info!(target: "lazymc", "Adopted server process quit");
+    server.clear_process_pid(&config).await;
+    server.process_active.store(false, Ordering::Release);
+    server.update_state(State::Stopped, &config).await;
+}
+
+/// Discover the Java child process of the server process once started, to signal it directly.
+///
+/// Server commands are often wrapper scripts, with Java running as a child process.
+#[cfg(target_os = "linux")]
+async fn track_java_child(server: Arc<Server>, pid: u32) {
+    let mut state = server.state_receiver();
+    loop {
+        match *state.borrow_and_update() {
+            State::Started => break,
+            State::Stopped => return,
+            _ => {}
+        }
+        if state.changed().await.is_err() {
+            return;
+        }
+    }
+
+    let java = match os::linux::find_descendant(pid, JAVA_PROCESS_NAME) {
+        Some(java) => java,
+        None => {
+            debug!(target: "lazymc", "No Java child process of server process {} found", pid);
+            return;
+        }
+    };
+
+    // Server process may have quit in the meantime
+    if *server.pid.lock().await == Some(pid) {
+        debug!(target: "lazymc", "Found Java child process {} of server process {}, signalling it directly", java, pid);
+        server.java_pid.lock().await.replace(java);
+    }
+}
+
+/// Build the server start command.
+fn server_command(config: &Config) -> Command {
+    let args = config
+        .server
+        .start_command()
+        .expect("invalid server command");
     let mut cmd = Command::new(&args[0]);
     cmd.args(args.iter().skip(1));
+    cmd.envs(&config.server.env);
     cmd.kill_on_drop(true);
     cmd.stdin(std::process::Stdio::piped());
+    if config.advanced.server_output == ServerOutput::Capture {
+        cmd.stdout(std::process::Stdio::piped());
+        cmd.stderr(std::process::Stdio::piped());
+    }
 
     // Create a new process group so signals reach all child processes
     // (e.g., Java spawned by wrapper scripts common with modded servers)
     #[cfg(unix)]
-    unsafe {
-        cmd.pre_exec(|| {
-            libc::setpgid(0, 0);
-            Ok(())
-        });
+    if config.advanced.signal_process_group {
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::setpgid(0, 0);
+                Ok(())
+            });
+        }
     }
 
     // Set working directory
-    if let Some(ref dir) = ConfigServer::server_directory(&config) {
+    if let Some(ref dir) = ConfigServer::working_directory(config) {
         cmd.current_dir(dir);
     }
 
+    cmd
+}
+
+/// Invoke server command, store PID and wait for it to quit.
+pub async fn invoke_server_cmd(
+    config: Arc<Config>,
+    state: Arc<Server>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Configure command
+    let mut cmd = server_command(&config);
+
     // Spawn process
     let mut child = match cmd.spawn() {
         Ok(child) => child,
@@ -524,11 +1635,35 @@ pub async fn invoke_server_cmd(
     };
 
     // Remember PID
-    state
-        .pid
-        .lock()
-        .await
-        .replace(child.id().expect("unknown server PID"));
+    let pid = child.id().expect("unknown server PID");
+    state.set_process_pid(&config, pid).await;
+    state.spawned_at.write().await.replace(Instant::now());
+
+    // Discover Java child process of wrapper scripts once started
+    #[cfg(target_os = "linux")]
+    if config.advanced.signal_java_child {
+        tokio::spawn(track_java_child(state.clone(), pid));
+    }
+
+    // Capture server output
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(capture_output(
+            stdout,
+            config.clone(),
+            state.clone(),
+            log::Level::Info,
+            true,
+        ));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(capture_output(
+            stderr,
+            config.clone(),
+            state.clone(),
+            log::Level::Warn,
+            false,
+        ));
+    }
 
     // Store stdin handle for sending console commands (e.g., stop)
     let child_stdin = child
@@ -555,6 +1690,9 @@ pub async fn invoke_server_cmd(
         tokio::select! {
             line = stdin_rx.recv() => {
                 if let Some(line) = line {
+                    if let Some(echo) = stdin_echo(&config, state.state(), &line) {
+                        info!(target: "lazymc::stdin", "{}", echo);
+                    }
                     let mut stdin_lock = state.stdin.lock().await;
                     if let Some(child_stdin) = stdin_lock.as_mut() {
                         if child_stdin.write_all(line.as_bytes()).await.is_err() {
@@ -596,24 +1734,118 @@ pub async fn invoke_server_cmd(
 
     // Drop stdin_rx lock and forget server PID and stdin handle
     drop(stdin_rx);
-    state.pid.lock().await.take();
+    state.clear_process_pid(&config).await;
     state.stdin.lock().await.take();
+    state.spawned_at.write().await.take();
 
     // Give server a little more time to quit forgotten threads
     time::sleep(SERVER_QUIT_COOLDOWN).await;
 
+    // Allow spawning a new process, before possibly restarting below
+    state.process_active.store(false, Ordering::Release);
+
     // Set server state to stopped
     state.update_state(State::Stopped, &config).await;
 
     // Restart on crash
+    if crashed {
+        state.record_crash().await;
+    }
     if crashed && config.server.wake_on_crash {
         warn!(target: "lazymc", "Server crashed, restarting...");
         Server::start(config, state, None).await;
+    } else if state.restart_on_exit.swap(false, Ordering::AcqRel) {
+        info!(target: "lazymc", "Restarting server...");
+        Server::start(config, state, None).await;
     }
 
     Ok(())
 }
 
+/// Capture server output, emit each line through our logger.
+///
+/// If `detect_ready` is set, marks a starting server as started once a line matches the
+/// configured ready regex.
+///
+/// Returns once the output is closed.
+async fn capture_output(
+    output: impl AsyncRead + Unpin,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    level: log::Level,
+    detect_ready: bool,
+) {
+    let ready_regex = match &config.advanced.ready_regex {
+        regex if detect_ready && !regex.is_empty() => match Regex::new(regex) {
+            Ok(regex) => Some(regex),
+            Err(err) => {
+                error!(target: "lazymc", "Invalid ready regex, not detecting server startup from output: {}", err);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    // Read raw lines, output that is not valid UTF-8 must not stop capturing
+    let mut output = BufReader::new(output);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match output.read_until(b'\n', &mut buf).await {
+            Ok(0) => break,
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&buf);
+                let line = line.trim_end_matches(['\n', '\r']);
+                log!(target: "lazymc::server", level, "{}{}", config.advanced.server_output_prefix, line);
+
+                let ready = ready_regex
+                    .as_ref()
+                    .map(|r| r.is_match(line))
+                    .unwrap_or(false);
+                if ready
+                    && server
+                        .update_state_from(Some(State::Starting), State::Started, &config)
+                        .await
+                {
+                    debug!(target: "lazymc", "Server reported it is ready in its output");
+                }
+            }
+            Err(err) => {
+                debug!(target: "lazymc", "Failed to read server output: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+/// Warn if the server sleeps sooner than it takes to start, given the learned startup time.
+///
+/// The server may then go back to sleep before a player that woke it can join. Returns whether a
+/// warning was emitted.
+pub fn warn_sleep_after(config: &Config, startup: Option<Duration>) -> bool {
+    let startup = match startup {
+        Some(startup) if Duration::from_secs(config.time.sleep_after as u64) < startup => startup,
+        _ => return false,
+    };
+
+    warn!(
+        target: "lazymc",
+        "Server sleeps after {}s of inactivity, but takes {}s to start on average, it may go back to sleep before players can join. Consider increasing 'time.sleep_after'",
+        config.time.sleep_after,
+        startup.as_secs(),
+    );
+    true
+}
+
+/// Whether the server is idle according to the configured policy, given whether players are
+/// online and the number of connected clients.
+fn is_idle(idle_when: IdleWhen, players_online: bool, connections: u64) -> bool {
+    match idle_when {
+        IdleWhen::NoPlayers => !players_online,
+        IdleWhen::NoConnections => !players_online && connections == 0,
+    }
+}
+
 /// Stop server through RCON.
 #[cfg(feature = "rcon")]
 async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
@@ -659,6 +1891,13 @@ async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
     server.rcon_last_stop.lock().await.replace(Instant::now());
     server.update_state(State::Stopping, config).await;
 
+    // Escalate if server doesn't quit in time
+    server
+        .stop_escalate_at
+        .write()
+        .await
+        .replace(Instant::now() + Duration::from_secs(config.rcon.stop_grace_period as u64));
+
     // Gracefully close connection
     rcon.close().await;
 
@@ -667,6 +1906,21 @@ async fn stop_server_rcon(config: &Config, server: &Server) -> bool {
     true
 }
 
+/// Format line typed into lazymc's console to echo, prompted with the server state.
+///
+/// Returns `None` if echoing is disabled.
+fn stdin_echo(config: &Config, state: State, line: &str) -> Option<String> {
+    if !config.advanced.stdin_echo {
+        return None;
+    }
+    Some(format!(
+        "[{}] {}{}",
+        format!("{state:?}").to_lowercase(),
+        config.advanced.stdin_echo_prefix,
+        line.trim_end(),
+    ))
+}
+
 /// Stop server by writing "stop" command to its stdin.
 ///
 /// This triggers Minecraft's built-in shutdown, equivalent to typing "stop" in the console.
@@ -710,7 +1964,7 @@ async fn stop_server_stdin(config: &Config, server: &Server) -> bool {
 #[cfg(unix)]
 async fn stop_server_signal(config: &Config, server: &Server) -> bool {
     // Grab PID
-    let pid = match *server.pid.lock().await {
+    let pid = match server.signal_pid().await {
         Some(pid) => pid,
         None => {
             debug!(target: "lazymc", "Could not send stop signal to server process, PID unknown");
@@ -718,8 +1972,14 @@ async fn stop_server_signal(config: &Config, server: &Server) -> bool {
         }
     };
 
-    if !crate::os::kill_gracefully(pid) {
+    if let Err(err) = os::kill_gracefully(pid, config.advanced.signal_process_group) {
         error!(target: "lazymc", "Failed to send stop signal to server process");
+
+        // Server process being gone is as good as stopped
+        if err == os::SignalError::NoProcess {
+            server.process_gone(config).await;
+            return true;
+        }
         return false;
     }
 
@@ -729,63 +1989,1050 @@ async fn stop_server_signal(config: &Config, server: &Server) -> bool {
     server
         .update_state_from(Some(State::Started), State::Stopping, config)
         .await;
+    server.signaled_stop(config).await;
 
     true
 }
 
+/// Result of freezing the server process.
+#[cfg(unix)]
+#[derive(Debug, Eq, PartialEq)]
+enum Freeze {
+    /// Server process is frozen, or gone.
+    Frozen,
+
+    /// Server was woken while freezing it, it keeps running.
+    Woken,
+
+    /// Server process could not be frozen, it must be stopped instead.
+    Failed,
+}
+
 /// Freeze server by sending SIGSTOP signal.
 ///
+/// Does nothing if the server is not started, a starting server would be unfrozen as started.
 /// Only available on Unix.
 #[cfg(unix)]
-async fn freeze_server_signal(config: &Config, server: &Server) -> bool {
+async fn freeze_server_signal(config: &Config, server: &Server) -> Freeze {
+    let requested = Instant::now();
+    let _freeze = server.freeze_lock.lock().await;
+
+    // Wake got the lock first, it saw the server running and left it as is
+    let woken = *server.last_wake.lock().await;
+    if woken.map_or(false, |woken| woken >= requested) {
+        debug!(target: "lazymc", "Not freezing server process, server was woken meanwhile");
+        return Freeze::Woken;
+    }
+
+    let state = server.state();
+    if state != State::Started {
+        debug!(target: "lazymc", "Not freezing server process, server is {:?}", state);
+        return Freeze::Failed;
+    }
+
     // Grab PID
-    let pid = match *server.pid.lock().await {
+    let pid = match server.signal_pid().await {
         Some(pid) => pid,
         None => {
             debug!(target: "lazymc", "Could not send freeze signal to server process, PID unknown");
-            return false;
+            return Freeze::Failed;
         }
     };
 
-    if !os::freeze(pid) {
+    if let Err(err) = os::freeze(pid, config.advanced.signal_process_group) {
         error!(target: "lazymc", "Failed to send freeze signal to server process.");
-        return false;
+
+        // Server process being gone is as good as stopped
+        if err == os::SignalError::NoProcess {
+            server.process_gone(config).await;
+            return Freeze::Frozen;
+        }
+        return Freeze::Failed;
     }
 
     server
-        .update_state_from(Some(State::Starting), State::Stopped, config)
-        .await;
-    server
-        .update_state_from(Some(State::Started), State::Stopped, config)
+        .update_state_from(Some(State::Started), State::Frozen, config)
         .await;
 
-    true
+    Freeze::Frozen
 }
 
-/// Unfreeze server by sending SIGCONT signal.
+/// Unfreeze server by sending SIGCONT signal, it is online right away.
 ///
-/// Only available on Unix.
+/// Does nothing if the server is not frozen. Only available on Unix.
 #[cfg(unix)]
 async fn unfreeze_server_signal(config: &Config, server: &Server) -> bool {
-    // Grab PID
-    let pid = match *server.pid.lock().await {
-        Some(pid) => pid,
+    // Wait for freezing in progress, so a wake always ends unfrozen
+    let _freeze = server.freeze_lock.lock().await;
+    server.last_wake.lock().await.replace(Instant::now());
+    if !server
+        .update_state_from(Some(State::Frozen), State::Started, config)
+        .await
+    {
+        return false;
+    }
+
+    match server.signal_pid().await {
+        Some(pid) => {
+            if os::unfreeze(pid, config.advanced.signal_process_group).is_err() {
+                error!(target: "lazymc", "Failed to send unfreeze signal to server process.");
+            }
+        }
         None => {
-            debug!(target: "lazymc", "Could not send unfreeze signal to server process, PID unknown");
-            return false;
+            debug!(target: "lazymc", "Could not send unfreeze signal to server process, PID unknown")
         }
-    };
+    }
 
-    if !os::unfreeze(pid) {
-        error!(target: "lazymc", "Failed to send unfreeze signal to server process.");
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mc::whitelist;
+
+    #[test]
+    fn test_stdin_echo() {
+        let config: Config = crate::util::test::config("[advanced]\nstdin_echo = true");
+        assert_eq!(
+            stdin_echo(&config, State::Started, "list\n").as_deref(),
+            Some("[started] > list")
+        );
+
+        // Not echoed if disabled
+        let config: Config = crate::util::test::config("");
+        assert_eq!(stdin_echo(&config, State::Started, "list\n"), None);
     }
 
-    server
-        .update_state_from(Some(State::Stopping), State::Starting, config)
+    async fn capture_ready(line: &str, detect_ready: bool) -> State {
+        let config: Config = crate::util::test::config("[advanced]\nserver_output = \"capture\"");
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        server.update_state(State::Starting, &config).await;
+
+        let output = format!("{}\n", line);
+        capture_output(
+            output.as_bytes(),
+            Arc::new(config),
+            server.clone(),
+            log::Level::Info,
+            detect_ready,
+        )
         .await;
-    server
-        .update_state_from(Some(State::Stopped), State::Starting, config)
+        server.state()
+    }
+
+    #[tokio::test]
+    async fn test_ready_regex_match() {
+        let line = r#"[12:34:56] [Server thread/INFO]: Done (4.321s)! For help, type "help""#;
+        assert_eq!(capture_ready(line, true).await, State::Started);
+
+        // Only output of the stream used for detection marks the server ready
+        assert_eq!(capture_ready(line, false).await, State::Starting);
+    }
+
+    #[tokio::test]
+    async fn test_ready_after_invalid_utf8() {
+        let config: Config = crate::util::test::config("[advanced]\nserver_output = \"capture\"");
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        server.update_state(State::Starting, &config).await;
+
+        // Ready line after a line that is not valid UTF-8 is still detected
+        let mut output = b"[12:34:56] [Server thread/INFO]: \xff\xfe\n".to_vec();
+        output.extend_from_slice(
+            br#"[12:34:56] [Server thread/INFO]: Done (4.321s)! For help, type "help""#,
+        );
+        output.push(b'\n');
+        capture_output(
+            output.as_slice(),
+            Arc::new(config),
+            server.clone(),
+            log::Level::Info,
+            true,
+        )
         .await;
+        assert_eq!(server.state(), State::Started);
+    }
 
-    true
+    #[tokio::test]
+    async fn test_ready_regex_no_match() {
+        let line = "[12:34:56] [Server thread/INFO]: Preparing spawn area: 42%";
+        assert_eq!(capture_ready(line, true).await, State::Starting);
+    }
+
+    #[cfg(feature = "rcon")]
+    async fn stopping_server(config: &Config, grace: Duration) -> Server {
+        let (server, _) = Server::new();
+        server.update_state(State::Stopping, config).await;
+        server
+            .stop_escalate_at
+            .write()
+            .await
+            .replace(Instant::now() + grace);
+        server
+    }
+
+    #[cfg(feature = "rcon")]
+    #[tokio::test]
+    async fn test_stop_escalation_not_needed() {
+        let config: Config = crate::util::test::config("");
+        let server = stopping_server(&config, Duration::ZERO).await;
+        assert!(server.should_escalate_stop().await);
+
+        // Server quit after RCON stop, nothing to escalate
+        server.update_state(State::Stopped, &config).await;
+        assert!(!server.should_escalate_stop().await);
+    }
+
+    #[cfg(all(unix, feature = "rcon"))]
+    #[tokio::test]
+    async fn test_stop_escalation_signal() {
+        let config: Config = crate::util::test::config("");
+        let server = stopping_server(&config, Duration::from_secs(60)).await;
+        assert!(!server.should_escalate_stop().await);
+
+        // Server hangs after RCON stop, send stop signal
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        server.pid.lock().await.replace(child.id());
+        *server.stop_escalate_at.write().await = Some(Instant::now());
+        assert!(server.should_escalate_stop().await);
+        assert_eq!(
+            server.escalate_stop(&config).await,
+            Some(StopEscalation::Signal)
+        );
+        assert!(!server.should_escalate_stop().await);
+
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[cfg(feature = "rcon")]
+    #[tokio::test]
+    async fn test_stop_escalation_force_kill() {
+        let config: Config = crate::util::test::config("");
+        let server = stopping_server(&config, Duration::ZERO).await;
+
+        // Signalling a process that doesn't exist fails, escalate to force kill
+        server.pid.lock().await.replace(0x7fff_fff0);
+        assert_eq!(
+            server.escalate_stop(&config).await,
+            Some(StopEscalation::ForceKill)
+        );
+
+        // Process is gone, server is stopped
+        assert_eq!(server.state(), State::Stopped);
+        assert!(!server.has_process().await);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_stop_signal_no_process() {
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+        server.update_state(State::Started, &config).await;
+
+        // Stale PID marks the server stopped, as good as stopping it
+        server.pid.lock().await.replace(0x7fff_fff0);
+        assert!(stop_server_signal(&config, &server).await);
+        assert_eq!(server.state(), State::Stopped);
+        assert!(!server.has_process().await);
+    }
+
+    #[cfg(unix)]
+    async fn signal_stop(config: &Config) -> (Server, std::process::Child) {
+        let (server, _) = Server::new();
+        server.update_state(State::Started, config).await;
+
+        let child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        server.pid.lock().await.replace(child.id());
+        assert!(stop_server_signal(config, &server).await);
+        assert_eq!(server.state(), State::Stopping);
+
+        (server, child)
+    }
+
+    /// Put a started server running the given process to sleep.
+    #[cfg(unix)]
+    async fn sleep_server(config: &Config, child: &std::process::Child) -> Server {
+        let (server, _) = Server::new();
+        server.update_state(State::Started, config).await;
+        server.pid.lock().await.replace(child.id());
+        assert!(server.stop(config).await);
+        server
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sleep_method_freeze() {
+        use nix::sys::signal::Signal;
+        use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+        use nix::unistd::Pid;
+
+        let config: Config = crate::util::test::config("sleep_method = \"freeze\"");
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        let server = sleep_server(&config, &child).await;
+        assert_eq!(server.state(), State::Frozen);
+
+        // Process is frozen, not killed
+        let pid = Pid::from_raw(child.id() as i32);
+        assert!(matches!(
+            waitpid(pid, Some(WaitPidFlag::WUNTRACED)),
+            Ok(WaitStatus::Stopped(_, Signal::SIGSTOP))
+        ));
+
+        // Unfreezing resumes the process, server is online right away
+        let server = Arc::new(server);
+        assert!(Server::start(Arc::new(config), server.clone(), None).await);
+        assert_eq!(server.state(), State::Started);
+        assert!(matches!(
+            waitpid(pid, Some(WaitPidFlag::WCONTINUED)),
+            Ok(WaitStatus::Continued(_))
+        ));
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    /// Wait for the process with the given PID to be frozen or not, returns whether it is.
+    #[cfg(target_os = "linux")]
+    async fn wait_frozen(pid: u32, frozen: bool) -> bool {
+        // Process state follows the parenthesized command name, signals take effect asynchronously
+        let is_frozen = || {
+            let stat = fs::read_to_string(format!("/proc/{pid}/stat")).unwrap();
+            stat.rsplit(')')
+                .next()
+                .unwrap()
+                .trim_start()
+                .starts_with('T')
+        };
+        for _ in 0..100 {
+            if is_frozen() == frozen {
+                break;
+            }
+            time::sleep(Duration::from_millis(10)).await;
+        }
+        is_frozen()
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_freeze_unfreeze_concurrent() {
+        let config: Arc<Config> = Arc::new(crate::util::test::config("sleep_method = \"freeze\""));
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        server.update_state(State::Started, &config).await;
+        server.pid.lock().await.replace(child.id());
+
+        for i in 0..20 {
+            // Wake while freezing, alternate which one gets the freeze lock first
+            let lock = server.freeze_lock.lock().await;
+            let freeze = {
+                let (config, server) = (config.clone(), server.clone());
+                async move { server.stop(&config).await }
+            };
+            let wake = Server::start(config.clone(), server.clone(), None);
+            let (freeze, wake) = if i % 2 == 0 {
+                let freeze = tokio::spawn(freeze);
+                time::sleep(Duration::from_millis(10)).await;
+                (freeze, tokio::spawn(wake))
+            } else {
+                let wake = tokio::spawn(wake);
+                time::sleep(Duration::from_millis(10)).await;
+                (tokio::spawn(freeze), wake)
+            };
+            time::sleep(Duration::from_millis(10)).await;
+            drop(lock);
+            freeze.await.unwrap();
+            wake.await.unwrap();
+
+            // Server always ends running, process always matches server state
+            assert_eq!(server.state(), State::Started);
+            assert!(!wait_frozen(child.id(), false).await);
+        }
+
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sleep_method_freeze_starting() {
+        let config: Config = crate::util::test::config("sleep_method = \"freeze\"");
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        let (server, _) = Server::new();
+        server.update_state(State::Starting, &config).await;
+        server.pid.lock().await.replace(child.id());
+
+        // Starting server is not frozen, it would be unfrozen as started
+        assert_eq!(freeze_server_signal(&config, &server).await, Freeze::Failed);
+        assert_eq!(server.state(), State::Starting);
+
+        // Falls back to stopping the process
+        assert!(server.stop(&config).await);
+        assert_eq!(server.state(), State::Stopping);
+        use std::os::unix::process::ExitStatusExt;
+        let status = child.wait().unwrap();
+        assert_eq!(
+            status.signal(),
+            Some(nix::sys::signal::Signal::SIGTERM as i32)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_sleep_method_stop() {
+        let config: Config = crate::util::test::config("sleep_method = \"stop\"");
+        let mut child = std::process::Command::new("sleep")
+            .arg("60")
+            .spawn()
+            .unwrap();
+        let server = sleep_server(&config, &child).await;
+        assert_eq!(server.state(), State::Stopping);
+
+        // Process is killed through stop signal
+        use std::os::unix::process::ExitStatusExt;
+        let status = child.wait().unwrap();
+        assert_eq!(
+            status.signal(),
+            Some(nix::sys::signal::Signal::SIGTERM as i32)
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_graceful_kill_timeout() {
+        let config: Config =
+            crate::util::test::config("stop_timeout = 150\ngraceful_kill_timeout = 1");
+        let (server, mut child) = signal_stop(&config).await;
+
+        // Force kill after graceful kill timeout, not stop timeout
+        assert!(!server.should_kill().await);
+        time::sleep(Duration::from_millis(1100)).await;
+        assert!(server.should_kill().await);
+
+        child.wait().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_graceful_kill_timeout_disabled() {
+        let config: Config = crate::util::test::config("stop_timeout = 150");
+        let (server, mut child) = signal_stop(&config).await;
+
+        // Only force kill after stop timeout
+        let kill_at = server.kill_at.read().await.unwrap();
+        assert!(kill_at > Instant::now() + Duration::from_secs(140));
+
+        child.wait().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_stats_transitions() {
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+        let step = Duration::from_millis(50);
+
+        // Wake, come online, crash and wake again
+        time::sleep(step).await;
+        server.update_state(State::Starting, &config).await;
+        server.update_state(State::Started, &config).await;
+        assert!(server.session_start().await.is_some());
+        time::sleep(step).await;
+        server.record_crash().await;
+        server.update_state(State::Stopped, &config).await;
+        assert!(server.session_start().await.is_none());
+        server.update_state(State::Starting, &config).await;
+
+        let stats = server.stats().await;
+        assert_eq!(stats.wakes, 2);
+        assert_eq!(stats.crashes, 1);
+        assert!(stats.uptime >= step.as_secs_f64());
+        assert!(stats.sleep_time >= step.as_secs_f64());
+
+        // Stopping doesn't count as a wake
+        server.update_state(State::Stopping, &config).await;
+        server.update_state(State::Stopped, &config).await;
+        assert_eq!(server.stats().await.wakes, 2);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_start_single_flight() {
+        let dir = crate::util::test::temp_dir("single-flight");
+        let file = dir.join("spawns");
+        let config: Config = toml::from_str(&format!(
+            "[server]\ncommand = \"sh -c 'echo spawned >> {}; sleep 2'\"\nstart_timeout = 0\ndirectory = {:?}",
+            file.display(),
+            dir,
+        ))
+        .unwrap();
+        let config = Arc::new(config);
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Burst of simultaneous wakes
+        let wakes: Vec<_> = (0..32)
+            .map(|_| tokio::spawn(Server::start(config.clone(), server.clone(), None)))
+            .collect();
+        let mut started = 0;
+        for wake in wakes {
+            if wake.await.unwrap() {
+                started += 1;
+            }
+        }
+        assert_eq!(started, 1);
+        assert_eq!(server.state(), State::Starting);
+
+        // Server state reset while process is still running, must not spawn again
+        time::sleep(Duration::from_millis(500)).await;
+        server.update_state(State::Stopped, &config).await;
+        Server::start(config.clone(), server.clone(), None).await;
+        time::sleep(Duration::from_millis(500)).await;
+
+        let spawns = std::fs::read_to_string(&file).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(spawns.lines().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_command_env_working_dir() {
+        let dir = std::env::temp_dir().canonicalize().unwrap();
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [server]
+            command = "sh -c 'echo $LAZYMC_TEST_VAR; pwd'"
+            working_dir = "{}"
+            env = {{ LAZYMC_TEST_VAR = "hello world" }}
+            "#,
+            dir.display(),
+        ))
+        .unwrap();
+
+        let mut cmd = server_command(&config);
+        cmd.stdout(std::process::Stdio::piped());
+        let output = cmd.output().await.unwrap();
+        let output = String::from_utf8(output.stdout).unwrap();
+        let lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines, ["hello world", &dir.display().to_string()]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_command_args() {
+        // Arguments are passed as-is, without shell-style splitting or quoting
+        let config: Config = toml::from_str(
+            r#"
+            [server]
+            command = "false"
+            command_args = ["echo", "it's a  'test'", "$HOME"]
+            "#,
+        )
+        .unwrap();
+
+        let mut cmd = server_command(&config);
+        cmd.stdout(std::process::Stdio::piped());
+        let output = cmd.output().await.unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"it's a  'test' $HOME\n");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_server_command_args_signal() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let config: Config =
+            toml::from_str("[server]\ncommand_args = [\"sleep\", \"60\"]").unwrap();
+        let mut child = server_command(&config).spawn().unwrap();
+        let pid = child.id().unwrap();
+
+        // Spawned PID is the binary itself, not a shell wrapping it
+        let comm = std::fs::read_to_string(format!("/proc/{pid}/comm"));
+        if let Ok(comm) = comm {
+            assert_eq!(comm.trim(), "sleep");
+        }
+
+        assert!(os::kill_gracefully(pid, config.advanced.signal_process_group).is_ok());
+        let status = time::timeout(Duration::from_secs(5), child.wait())
+            .await
+            .expect("process did not exit on signal")
+            .unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+    }
+
+    fn status(online: u32) -> ServerStatus {
+        use minecraft_protocol::data::server_status::{OnlinePlayers, ServerVersion};
+
+        ServerStatus {
+            version: ServerVersion {
+                name: "1.20.4".into(),
+                protocol: 765,
+            },
+            players: OnlinePlayers {
+                max: 20,
+                online,
+                sample: vec![],
+            },
+            description: "A Minecraft Server".into(),
+            favicon: None,
+        }
+    }
+
+    #[test]
+    fn test_warn_sleep_after() {
+        let config: Config = crate::util::test::config("[time]\nsleep_after = 30");
+        assert!(warn_sleep_after(&config, Some(Duration::from_secs(45))));
+        assert!(!warn_sleep_after(&config, Some(Duration::from_secs(20))));
+        assert!(!warn_sleep_after(&config, None));
+    }
+
+    #[test]
+    fn test_is_idle_no_players() {
+        assert!(is_idle(IdleWhen::NoPlayers, false, 0));
+        assert!(is_idle(IdleWhen::NoPlayers, false, 3));
+        assert!(!is_idle(IdleWhen::NoPlayers, true, 3));
+    }
+
+    #[test]
+    fn test_is_idle_no_connections() {
+        assert!(is_idle(IdleWhen::NoConnections, false, 0));
+        assert!(!is_idle(IdleWhen::NoConnections, false, 1));
+        assert!(!is_idle(IdleWhen::NoConnections, true, 1));
+    }
+
+    #[tokio::test]
+    async fn test_should_sleep_after_probe() {
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+        let since = Instant::now();
+
+        // Not online yet
+        assert!(!server.should_sleep_after_probe(since).await);
+
+        server.update_status(&config, Some(status(0))).await;
+        assert_eq!(server.state(), State::Started);
+        assert!(server.should_sleep_after_probe(since).await);
+
+        // Player tried to join while probing
+        server.update_last_join().await;
+        assert!(!server.should_sleep_after_probe(since).await);
+        assert!(server.should_sleep_after_probe(Instant::now()).await);
+
+        // Player online
+        server.update_status(&config, Some(status(1))).await;
+        assert!(!server.should_sleep_after_probe(Instant::now()).await);
+    }
+
+    #[tokio::test]
+    async fn test_cache_version() {
+        let config: Config = crate::util::test::config("");
+        let file = std::env::temp_dir().join(format!(
+            "lazymc-test-version-{}-{}",
+            std::process::id(),
+            persist::FILE
+        ));
+        let (server, _) = Server::new();
+        server.persistent_file.write().await.replace(file.clone());
+        assert_eq!(server.cached_version().await, None);
+
+        server.update_status(&config, Some(status(0))).await;
+
+        let state: persist::State = persist::load(&file).unwrap();
+        let _ = std::fs::remove_file(&file);
+        let expected = persist::Version {
+            name: "1.20.4".into(),
+            protocol: 765,
+        };
+        assert_eq!(state.version.as_ref(), Some(&expected));
+        assert_eq!(server.cached_version().await, Some(expected));
+    }
+
+    #[tokio::test]
+    async fn test_keep_awake_min_players() {
+        let config: Config = crate::util::test::config(
+            "[time]\nsleep_after = 0\nmin_online_time = 0\nkeep_awake_min_players = 2",
+        );
+        let (server, _) = Server::new();
+        server.update_state(State::Starting, &config).await;
+
+        // Single player below threshold doesn't keep server awake
+        server.update_status(&config, Some(status(1))).await;
+        assert_eq!(server.state(), State::Started);
+        assert!(server.should_sleep(&config).await);
+
+        // Enough players keep server awake
+        server.update_status(&config, Some(status(2))).await;
+        assert!(!server.should_sleep(&config).await);
+        server.update_status(&config, Some(status(3))).await;
+        assert!(!server.should_sleep(&config).await);
+    }
+
+    #[tokio::test]
+    async fn test_status_cache() {
+        let file = std::env::temp_dir().join(format!(
+            "lazymc-test-status-cache-{}.json",
+            std::process::id()
+        ));
+        let config: Config =
+            crate::util::test::config(&format!("[advanced]\nstatus_cache_file = {:?}", file));
+
+        // Nothing cached yet, cache written on successful poll
+        let (server, _) = Server::new();
+        server.load_status_cache(&config).await;
+        assert!(server.status().await.is_none());
+        server.update_status(&config, Some(status(3))).await;
+
+        // Cached status seeds status of next lazymc run, without online players
+        let (server, _) = Server::new();
+        server.load_status_cache(&config).await;
+        let _ = std::fs::remove_file(&file);
+        let status = server.status().await.clone().unwrap();
+        assert_eq!(status.version.name, "1.20.4");
+        assert_eq!(status.version.protocol, 765);
+        assert_eq!(status.players.online, 0);
+        assert_eq!(status.players.max, 20);
+        assert_eq!(server.state(), State::Stopped);
+    }
+
+    #[tokio::test]
+    async fn test_resume_recent_activity() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-resume-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config: Config =
+            crate::util::test::config(&format!("directory = {:?}\nresume_window = 300", dir));
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        // Server online and active shortly before lazymc quit is resumed
+        let state = |online, last_active| persist::State {
+            activity: Some(persist::Activity {
+                online,
+                last_active,
+            }),
+            ..Default::default()
+        };
+        persist::save(&dir.join(persist::FILE), &state(true, now - 60)).unwrap();
+        let (server, _) = Server::new();
+        server.load_persistent(&config).await;
+        assert!(server.should_resume(&config).await);
+
+        // Server active long ago, or asleep, stays asleep
+        persist::save(&dir.join(persist::FILE), &state(true, now - 3600)).unwrap();
+        let (server, _) = Server::new();
+        server.load_persistent(&config).await;
+        assert!(!server.should_resume(&config).await);
+
+        persist::save(&dir.join(persist::FILE), &state(false, now)).unwrap();
+        let (server, _) = Server::new();
+        server.load_persistent(&config).await;
+        assert!(!server.should_resume(&config).await);
+
+        // Going online is persisted
+        server.update_state(State::Started, &config).await;
+        let persisted: persist::State = persist::load(&dir.join(persist::FILE)).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(persisted.activity.unwrap().is_recent(300, now));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_restart_unreachable() {
+        let dir = crate::util::test::temp_dir("restart-unreachable");
+        let config: Config = toml::from_str(&format!(
+            "[server]\ncommand = \"sleep 30\"\ndirectory = {:?}",
+            dir,
+        ))
+        .unwrap();
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // No process to restart
+        assert!(!server.restart(&config).await);
+        assert!(!server.restart_on_exit.load(Ordering::Acquire));
+
+        // Server is started again after killing it
+        let config = Arc::new(config);
+        Server::start(config.clone(), server.clone(), None).await;
+        time::sleep(Duration::from_millis(200)).await;
+        let pid = server.pid.lock().await.unwrap();
+        assert!(server.has_process().await);
+        assert!(server.restart(&config).await);
+
+        time::sleep(SERVER_QUIT_COOLDOWN + Duration::from_millis(500)).await;
+        let new_pid = *server.pid.lock().await;
+        assert!(new_pid.is_some());
+        assert_ne!(new_pid, Some(pid));
+        assert_eq!(server.state(), State::Starting);
+        server.force_kill(&config).await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Config with given command and a server directory unique to the given test.
+    fn reattach_config(test: &str, command: &str) -> (Arc<Config>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = toml::from_str(&format!(
+            "[server]\ncommand = {:?}\ndirectory = {:?}",
+            command, dir
+        ))
+        .unwrap();
+        (Arc::new(config), dir)
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reattach_running() {
+        let (config, dir) = reattach_config("reattach-running", "true");
+        let mut child = std::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        persist::write_pid(&dir.join(persist::SERVER_PID_FILE), child.id()).unwrap();
+
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        assert!(Server::reattach(config.clone(), server.clone(), true).await);
+        assert_eq!(server.state(), State::Started);
+        assert_eq!(*server.pid.lock().await, Some(child.id()));
+
+        // Never spawns a second process
+        assert!(server.process_active.load(Ordering::Acquire));
+
+        let _ = child.kill();
+        let _ = child.wait();
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reattach_running_unknown_process() {
+        let (config, dir) = reattach_config("reattach-unknown", "true");
+
+        // Responding server is adopted even without PID
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        assert!(Server::reattach(config, server.clone(), true).await);
+        assert_eq!(server.state(), State::Started);
+        assert!(!server.has_process().await);
+        assert!(!server.process_active.load(Ordering::Acquire));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_reattach_down() {
+        let (config, dir) = reattach_config("reattach-down", "true");
+        let file = dir.join(persist::SERVER_PID_FILE);
+        persist::write_pid(&file, 0x7fff_fff0).unwrap();
+
+        // Server not responding is spawned as usual, stale PID file is removed
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        assert!(!Server::reattach(config, server.clone(), false).await);
+        assert_eq!(server.state(), State::Stopped);
+        assert!(!server.has_process().await);
+        assert!(!file.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_pid_file_written_on_spawn() {
+        let (config, dir) = reattach_config("pid-file", "sleep 1");
+        let file = dir.join(persist::SERVER_PID_FILE);
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        Server::start(config.clone(), server.clone(), None).await;
+        time::sleep(Duration::from_millis(200)).await;
+        let pid = *server.pid.lock().await;
+        assert!(pid.is_some());
+        assert_eq!(persist::read_pid(&file), pid);
+
+        // Removed once process quit
+        assert!(server.wait_process_exit(Duration::from_secs(5)).await);
+        assert!(!file.exists());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Config for a server that never starts, with given start timeout action.
+    fn start_timeout_config(test: &str, action: &str) -> (Arc<Config>, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-{}-{}", test, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = toml::from_str(&format!(
+            "[server]\ncommand = \"sleep 30\"\ndirectory = {:?}\nstart_timeout_action = {:?}\nstart_timeout_retries = 1",
+            dir, action
+        ))
+        .unwrap();
+        (Arc::new(config), dir)
+    }
+
+    /// Start server, and wait for its process to be spawned.
+    async fn start_spawned(config: &Arc<Config>, server: &Arc<Server>) -> u32 {
+        assert!(Server::start(config.clone(), server.clone(), None).await);
+        time::sleep(Duration::from_millis(200)).await;
+        server.pid.lock().await.unwrap()
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_timeout_kill() {
+        let (config, dir) = start_timeout_config("start-timeout-kill", "kill");
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Killed, not started again
+        start_spawned(&config, &server).await;
+        assert!(server.start_timed_out(&config).await);
+        time::sleep(SERVER_QUIT_COOLDOWN + Duration::from_millis(500)).await;
+        assert!(!server.has_process().await);
+        assert_eq!(server.state(), State::Stopped);
+        assert!(!server.is_held());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_timeout_retry() {
+        let (config, dir) = start_timeout_config("start-timeout-retry", "retry");
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Started again after first timeout
+        let pid = start_spawned(&config, &server).await;
+        assert!(server.start_timed_out(&config).await);
+        time::sleep(SERVER_QUIT_COOLDOWN + Duration::from_millis(500)).await;
+        let new_pid = *server.pid.lock().await;
+        assert!(new_pid.is_some());
+        assert_ne!(new_pid, Some(pid));
+        assert_eq!(server.state(), State::Starting);
+
+        // Gives up once retries are exhausted
+        assert!(server.start_timed_out(&config).await);
+        time::sleep(SERVER_QUIT_COOLDOWN + Duration::from_millis(500)).await;
+        assert!(!server.has_process().await);
+        assert_eq!(server.state(), State::Stopped);
+        assert_eq!(server.start_retries.load(Ordering::Acquire), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_start_timeout_hold() {
+        let (config, dir) = start_timeout_config("start-timeout-hold", "hold");
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Killed and held, joining players don't wake it
+        start_spawned(&config, &server).await;
+        assert!(server.start_timed_out(&config).await);
+        assert!(server.wait_process_exit(Duration::from_secs(5)).await);
+        time::sleep(SERVER_QUIT_COOLDOWN + Duration::from_millis(500)).await;
+        assert!(server.is_held());
+        assert!(!Server::start(config.clone(), server.clone(), None).await);
+        assert_eq!(
+            server.wake_rejection(&config, "player").await,
+            Some(Rejection::Held)
+        );
+
+        // Starts again once woken manually
+        server.release_hold();
+        start_spawned(&config, &server).await;
+        server.force_kill(&config).await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_starting_for() {
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+        server.spawned_at.write().await.replace(Instant::now());
+
+        // Only known while starting
+        assert_eq!(server.starting_for().await, None);
+        server.update_state(State::Starting, &config).await;
+        assert!(server.starting_for().await.is_some());
+        server.update_state(State::Started, &config).await;
+        assert_eq!(server.starting_for().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_wake_rejection() {
+        let config: Config = crate::util::test::config("");
+        let dir =
+            std::env::temp_dir().join(format!("lazymc-test-wake-rejection-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(whitelist::WHITELIST_FILE),
+            r#"[{"name": "allowed", "uuid": null}]"#,
+        )
+        .unwrap();
+        let whitelist = whitelist::load_dir(&dir).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+        let (server, _) = Server::new();
+
+        // Whitelist off, anyone wakes the server
+        server
+            .set_properties(Properties::parse("white-list=false\nmax-players=20"))
+            .await;
+        assert_eq!(server.wake_rejection(&config, "intruder").await, None);
+
+        // Whitelist on, only whitelisted users wake the server
+        server
+            .set_properties(Properties::parse("white-list=true\nmax-players=20"))
+            .await;
+        server.set_whitelist(Some(whitelist)).await;
+        assert_eq!(server.wake_rejection(&config, "allowed").await, None);
+        assert_eq!(
+            server.wake_rejection(&config, "intruder").await,
+            Some(Rejection::NotWhitelisted)
+        );
+
+        // Server accepting no players is never woken
+        server
+            .set_properties(Properties::parse("white-list=true\nmax-players=0"))
+            .await;
+        assert_eq!(
+            server.wake_rejection(&config, "allowed").await,
+            Some(Rejection::Full)
+        );
+    }
+
+    #[cfg(feature = "lobby")]
+    #[tokio::test]
+    async fn test_is_online_mode() {
+        let (server, _) = Server::new();
+        let config: Config = crate::util::test::config("");
+        let hint: Config = crate::util::test::config("online_mode = false");
+
+        // Offline mode if unknown, lobby logs in without encryption
+        assert!(!server.is_online_mode(&config).await);
+
+        // Detected from server properties
+        server
+            .set_properties(Properties::parse("online-mode=true"))
+            .await;
+        assert!(server.is_online_mode(&config).await);
+
+        // Configured hint takes precedence over detection
+        assert!(!server.is_online_mode(&hint).await);
+    }
 }
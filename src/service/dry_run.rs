@@ -0,0 +1,194 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::time;
+
+use crate::config::Config;
+use crate::net;
+use crate::os;
+use crate::persist;
+use crate::server::{self, Server, State};
+use crate::service;
+
+/// Connect timeout when checking whether a server is already running.
+const RUNNING_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Start the server once to validate the setup, then stop it again and quit.
+///
+/// Must run on a multi-threaded tokio runtime. Releases the given lazymc lock file when done.
+pub async fn service(config: Arc<Config>, lock_file: Option<PathBuf>) -> Result<(), ()> {
+    info!(target: "lazymc", "Dry run, starting server once to validate setup...");
+
+    let result = dry_run(config).await;
+    service::server::unlock(lock_file.as_deref());
+    match result {
+        Ok(duration) => {
+            info!(target: "lazymc", "Dry run succeeded, server started in {:.2}s", duration.as_secs_f64());
+            Ok(())
+        }
+        Err(err) => {
+            error!(target: "lazymc", "Dry run failed: {}", err);
+            Err(())
+        }
+    }
+}
+
+/// Start the server, wait until it is ready, and stop it again.
+///
+/// Returns how long the server took to start.
+async fn dry_run(config: Arc<Config>) -> Result<Duration, String> {
+    // Never start a second server, a running one would answer instead or have its PID file clobbered
+    check_not_running(&config).await?;
+
+    // Keep stdin sender, server process stdin is forwarded from it
+    let (server, _stdin_tx) = Server::new();
+    let server = Arc::new(server);
+
+    // Poll status in background, detects started servers without ready line in output
+    let monitor = tokio::spawn(service::monitor::service(config.clone(), server.clone()));
+
+    let started_at = Instant::now();
+    Server::start(config.clone(), server.clone(), None).await;
+    let result = wait_for_start(&config, &server)
+        .await
+        .map(|_| started_at.elapsed());
+    monitor.abort();
+
+    stop(&config, &server).await;
+    result
+}
+
+/// Check that the server is not running already, outside of lazymc.
+async fn check_not_running(config: &Config) -> Result<(), String> {
+    let pid = server::pid_file(config)
+        .as_deref()
+        .and_then(persist::read_pid)
+        .filter(|pid| os::is_alive(*pid));
+    if let Some(pid) = pid {
+        return Err(format!("server is already running with PID {pid}"));
+    }
+
+    let connect = net::connect_endpoint(config, &config.server.address);
+    if let Ok(Ok(_)) = time::timeout(RUNNING_CHECK_TIMEOUT, connect).await {
+        return Err(format!(
+            "server is already running at {}",
+            config.server.address
+        ));
+    }
+
+    Ok(())
+}
+
+/// Wait for the server to start, with the configured start timeout.
+///
+/// Watches state changes, so a server that is marked started is never missed.
+async fn wait_for_start(config: &Config, server: &Server) -> Result<(), String> {
+    let mut state = server.state_receiver();
+    let wait = async {
+        loop {
+            match *state.borrow_and_update() {
+                State::Started => return Ok(()),
+                State::Starting => {}
+                _ => return Err("server process quit before it was ready".to_string()),
+            }
+            if state.changed().await.is_err() {
+                return Err("server state unavailable".into());
+            }
+        }
+    };
+
+    let timeout = Duration::from_secs(config.server.start_timeout as u64);
+    time::timeout(timeout, wait).await.map_err(|_| {
+        format!(
+            "server did not start within {}s",
+            config.server.start_timeout
+        )
+    })?
+}
+
+/// Stop the server process, force kill it if it doesn't quit in time.
+async fn stop(config: &Config, server: &Server) {
+    if !server.has_process().await {
+        return;
+    }
+
+    info!(target: "lazymc", "Dry run, stopping server...");
+    let timeout = Duration::from_secs(config.server.stop_timeout as u64);
+    server.stop_process(config).await;
+    if server.wait_process_exit(timeout).await {
+        return;
+    }
+
+    warn!(target: "lazymc", "Server did not stop within {}s, force killing", config.server.stop_timeout);
+    server.force_kill(config).await;
+    server.wait_process_exit(timeout).await;
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::fs;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    /// Build config for a fake server running the given shell script in the given directory.
+    fn script_config(dir: &PathBuf, script: &str) -> Arc<Config> {
+        fs::create_dir_all(dir).unwrap();
+        let config = format!(
+            "[server]\ncommand_args = [\"sh\", \"-c\", {:?}]\ndirectory = {:?}\nstart_timeout = 5\nstop_timeout = 5\n[rcon]\nenabled = false\n[advanced]\nserver_output = \"capture\"",
+            script, dir,
+        );
+        Arc::new(toml::from_str(&config).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_dry_run() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-dry-run-{}", std::process::id()));
+
+        // Server prints done line, quits on stop command
+        let config = script_config(
+            &dir,
+            r#"echo 'Done (0.10s)! For help, type "help"'; read cmd"#,
+        );
+        let result = dry_run(config).await;
+        let _ = fs::remove_dir_all(&dir);
+        assert!(result.unwrap() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_server_quits() {
+        let dir =
+            std::env::temp_dir().join(format!("lazymc-test-dry-run-quits-{}", std::process::id()));
+
+        let config = script_config(&dir, "echo 'Failed to load server'; exit 1");
+        let result = dry_run(config).await;
+        let _ = fs::remove_dir_all(&dir);
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_server_running() {
+        let dir = std::env::temp_dir().join(format!(
+            "lazymc-test-dry-run-running-{}",
+            std::process::id()
+        ));
+
+        // Server already answers on its address
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = Arc::try_unwrap(script_config(&dir, "exit 1")).unwrap();
+        config.server.address = net::Endpoint::Tcp(listener.local_addr().unwrap());
+        let result = dry_run(Arc::new(config)).await;
+        assert!(result.unwrap_err().contains("already running"));
+
+        // Server process from PID file is still alive
+        let config = script_config(&dir, "exit 1");
+        let file = dir.join(persist::SERVER_PID_FILE);
+        persist::write_pid(&file, std::process::id()).unwrap();
+        let result = dry_run(config).await;
+        let pid_kept = file.exists();
+        let _ = fs::remove_dir_all(&dir);
+        assert!(result.unwrap_err().contains("already running"));
+        assert!(pid_kept);
+    }
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::os::unix::fs::FileTypeExt;
+#[cfg(unix)]
+use std::path::Path;
+
+#[cfg(unix)]
+use tokio::io::AsyncWriteExt;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+#[cfg(unix)]
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+use crate::config::{Advanced, Config};
+#[cfg(unix)]
+use crate::event::Event;
+use crate::server::Server;
+
+/// Event socket service.
+///
+/// Streams server events as newline-delimited JSON to every client connected to the configured
+/// Unix socket.
+#[cfg(unix)]
+pub async fn service(config: Arc<Config>, server: Arc<Server>) {
+    let path = match Advanced::event_socket(&config) {
+        Some(path) => path,
+        None => return,
+    };
+
+    let listener = match bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: "lazymc::events", "Failed to listen for event clients on {}: {}", path.display(), err);
+            return;
+        }
+    };
+
+    info!(target: "lazymc::events", "Streaming events on {}", path.display());
+
+    serve(listener, server).await
+}
+
+/// Event socket service.
+///
+/// Event sockets are only supported on Unix.
+#[cfg(not(unix))]
+pub async fn service(config: Arc<Config>, _server: Arc<Server>) {
+    if Advanced::event_socket(&config).is_some() {
+        warn!(target: "lazymc::events", "Event socket is only supported on Unix, not streaming events");
+    }
+}
+
+/// Bind Unix socket at the given path.
+///
+/// Replaces a socket left behind by a previous run.
+#[cfg(unix)]
+fn bind(path: &Path) -> std::io::Result<UnixListener> {
+    if let Ok(meta) = fs::symlink_metadata(path) {
+        if meta.file_type().is_socket() {
+            fs::remove_file(path)?;
+        }
+    }
+
+    UnixListener::bind(path)
+}
+
+/// Accept event clients on the given listener.
+#[cfg(unix)]
+async fn serve(listener: UnixListener, server: Arc<Server>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                debug!(target: "lazymc::events", "Event client connected");
+                tokio::spawn(stream_events(stream, server.events.subscribe()));
+            }
+            Err(err) => {
+                debug!(target: "lazymc::events", "Failed to accept event client: {}", err);
+            }
+        }
+    }
+}
+
+/// Write events to a client until it disconnects.
+#[cfg(unix)]
+async fn stream_events(mut stream: UnixStream, mut events: Receiver<Event>) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(target: "lazymc::events", "Event client fell behind, skipped {} events", skipped);
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+
+        let mut line = match serde_json::to_vec(&event) {
+            Ok(line) => line,
+            Err(err) => {
+                error!(target: "lazymc::events", "Failed to serialize event: {}", err);
+                continue;
+            }
+        };
+        line.push(b'\n');
+
+        if stream.write_all(&line).await.is_err() {
+            debug!(target: "lazymc::events", "Event client disconnected");
+            return;
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::time;
+
+    use super::*;
+    use crate::server::State;
+
+    #[tokio::test]
+    async fn test_stream_state_change() {
+        let path =
+            std::env::temp_dir().join(format!("lazymc-test-events-{}.sock", std::process::id()));
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        tokio::spawn(serve(bind(&path).unwrap(), server.clone()));
+
+        // Wait for client to be subscribed before changing state
+        let stream = UnixStream::connect(&path).await.unwrap();
+        time::sleep(Duration::from_millis(100)).await;
+        server.update_state(State::Starting, &config).await;
+
+        let mut lines = BufReader::new(stream).lines();
+        let line = time::timeout(Duration::from_secs(5), lines.next_line())
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(
+            line,
+            r#"{"event":"state","from":"stopped","to":"starting"}"#
+        );
+    }
+}
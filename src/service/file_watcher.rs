@@ -6,8 +6,9 @@ use std::time::Duration;
 use notify::{watcher, DebouncedEvent, RecursiveMode, Watcher};
 
 use crate::config::{Config, Server as ConfigServer};
-use crate::mc::ban::{self, BannedIps};
-use crate::mc::{server_properties, whitelist};
+use crate::mc::ban::{self, BannedIps, BannedPlayers};
+use crate::mc::server_properties::{self, Properties};
+use crate::mc::whitelist;
 use crate::server::Server;
 
 /// File watcher debounce time.
@@ -29,6 +30,8 @@ pub fn service(config: Arc<Config>, server: Arc<Server>) {
     while {
         // Update all files once
         reload_bans(&config, &server, &dir.join(ban::FILE));
+        reload_banned_players(&config, &server, &dir.join(ban::PLAYERS_FILE));
+        reload_properties(&server, &dir);
         reload_whitelist(&config, &server, &dir);
 
         // Watch for changes, update accordingly
@@ -102,6 +105,16 @@ fn update(config: &Config, server: &Server, dir: &Path, path: &Path) {
         reload_bans(config, server, path);
     }
 
+    // Update banned players
+    if path.ends_with(ban::PLAYERS_FILE) {
+        reload_banned_players(config, server, path);
+    }
+
+    // Update server properties
+    if path.ends_with(server_properties::FILE) {
+        reload_properties(server, dir);
+    }
+
     // Update whitelist
     if path.ends_with(whitelist::WHITELIST_FILE)
         || path.ends_with(whitelist::OPS_FILE)
@@ -143,6 +156,43 @@ fn reload_bans(config: &Config, server: &Server, path: &Path) {
     }
 }
 
+/// Reload banned players.
+fn reload_banned_players(config: &Config, server: &Server, path: &Path) {
+    // Banned players must be enabled
+    if !config.server.block_banned_players {
+        return;
+    }
+
+    trace!(target: "lazymc", "Reloading banned players...");
+
+    // File must exist, clear list otherwise
+    if !path.is_file() {
+        debug!(target: "lazymc", "No banned players, {} does not exist", ban::PLAYERS_FILE);
+        server.set_banned_players_blocking(BannedPlayers::default());
+        return;
+    }
+
+    // Load and update banned players
+    match ban::load_players(path) {
+        Ok(players) => server.set_banned_players_blocking(players),
+        Err(err) => {
+            debug!(target: "lazymc", "Failed load banned players from {}, ignoring: {}", ban::PLAYERS_FILE, err);
+        }
+    }
+}
+
+/// Reload server properties used for wake and join decisions.
+fn reload_properties(server: &Server, dir: &Path) {
+    trace!(target: "lazymc", "Reloading server properties...");
+
+    // Load and update properties, assume defaults if file does not exist
+    let properties = Properties::load(dir.join(server_properties::FILE)).unwrap_or_else(|| {
+        debug!(target: "lazymc", "Failed to load {}, assuming defaults", server_properties::FILE);
+        Properties::default()
+    });
+    server.set_properties_blocking(properties);
+}
+
 /// Reload whitelisted users.
 fn reload_whitelist(config: &Config, server: &Server, dir: &Path) {
     // Whitelist must be enabled
@@ -151,9 +201,7 @@ fn reload_whitelist(config: &Config, server: &Server, dir: &Path) {
     }
 
     // Must be enabled in server.properties
-    let enabled = server_properties::read_property(dir.join(server_properties::FILE), "white-list")
-        .map(|v| v.trim() == "true")
-        .unwrap_or(false);
+    let enabled = server.properties_blocking().white_list;
     if !enabled {
         server.set_whitelist_blocking(None);
         debug!(target: "lazymc", "Not using whitelist, not enabled in {}", server_properties::FILE);
@@ -0,0 +1,81 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+
+use crate::config::Config;
+use crate::server::Server;
+use crate::service::listener;
+
+/// Log target of the health check service.
+const TARGET: &str = "lazymc::health";
+
+/// Response sent to health checks while healthy.
+pub const HEALTHY: &str = "ok";
+
+/// Maximum time since the last server monitor poll to still be healthy.
+///
+/// A single poll may take a while if the server is slow to respond to status and ping requests.
+const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Health check service.
+///
+/// Responds to every connection on the configured address with whether lazymc is healthy, for
+/// `lazymc health` to report.
+pub async fn service(config: Arc<Config>, server: Arc<Server>) {
+    if let Some(addr) = config.advanced.health_address {
+        listener::serve(addr, TARGET, "health check", server, respond).await
+    }
+}
+
+/// Respond to a health check with the current health.
+async fn respond(mut stream: TcpStream, server: Arc<Server>) {
+    let response = health(&server).await;
+    if let Err(err) = stream.write_all(format!("{response}\n").as_bytes()).await {
+        debug!(target: TARGET, "Failed to respond to health check: {}", err);
+    }
+}
+
+/// Get health check response.
+async fn health(server: &Server) -> String {
+    if !server.is_monitor_alive(HEARTBEAT_TIMEOUT).await {
+        return "unhealthy: server monitor stalled".into();
+    }
+    HEALTHY.into()
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health_response() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        tokio::spawn(listener::accept(
+            listener,
+            TARGET,
+            "health check",
+            server.clone(),
+            respond,
+        ));
+
+        let check = || async {
+            let mut response = String::new();
+            let mut stream = TcpStream::connect(addr).await.unwrap();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        // Unhealthy until the monitor polled
+        assert_eq!(check().await, "unhealthy: server monitor stalled\n");
+        server.update_monitor_heartbeat().await;
+        assert_eq!(check().await, "ok\n");
+    }
+}
@@ -0,0 +1,306 @@
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time;
+
+use crate::config::Config;
+use crate::proxy;
+use crate::server::{Server, State};
+use crate::service::listener;
+
+/// Log target of the HTTP status service.
+const TARGET: &str = "lazymc::http";
+
+/// Maximum size of an HTTP request head we read.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+
+/// Timeout for receiving an HTTP request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// HTTP status service.
+///
+/// Answers `GET /ping` with a JSON summary of the server state, for hosting panels that monitor
+/// liveness over HTTP rather than the Minecraft protocol.
+pub async fn service(config: Arc<Config>, server: Arc<Server>) {
+    if let Some(addr) = config.advanced.http_address {
+        listener::serve(addr, TARGET, "HTTP request", server, respond).await
+    }
+}
+
+/// Respond to a single HTTP request.
+async fn respond(stream: TcpStream, server: Arc<Server>) {
+    if let Err(err) = handle(stream, &server).await {
+        debug!(target: TARGET, "Failed to handle HTTP request: {}", err);
+    }
+}
+
+/// Handle a single HTTP request, then close the connection.
+async fn handle(mut stream: TcpStream, server: &Server) -> std::io::Result<()> {
+    let request = match time::timeout(REQUEST_TIMEOUT, read_request(&mut stream)).await {
+        Ok(request) => request?,
+        Err(_) => return Ok(()),
+    };
+
+    let response = match request_line(&request) {
+        Some(("GET", "/ping")) => {
+            let body = serde_json::to_string(&Ping::of(server).await)
+                .expect("failed to serialize ping response");
+            response("200 OK", "application/json", &body)
+        }
+        Some((_, "/ping")) => {
+            response("405 Method Not Allowed", "text/plain", "method not allowed")
+        }
+        Some(_) => response("404 Not Found", "text/plain", "not found"),
+        None => response("400 Bad Request", "text/plain", "bad request"),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read an HTTP request head, up to and including the empty line.
+async fn read_request(stream: &mut TcpStream) -> std::io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < MAX_REQUEST_SIZE {
+        let read = stream.read(&mut buf).await?;
+        if read == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..read]);
+    }
+    Ok(request)
+}
+
+/// Parse method and path from the request line of an HTTP request.
+fn request_line(request: &[u8]) -> Option<(&str, &str)> {
+    let line = std::str::from_utf8(request).ok()?.lines().next()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?;
+    let path = parts.next()?;
+    parts.next()?.starts_with("HTTP/").then_some((method, path))
+}
+
+/// Build an HTTP response with the given status and body.
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len(),
+    )
+}
+
+/// Ping response, summarizing server state.
+#[derive(Debug, Serialize)]
+struct Ping {
+    /// Server state.
+    state: State,
+
+    /// Whether the server is online and joinable.
+    online: bool,
+
+    /// Online players.
+    players: Players,
+
+    /// Server statistics.
+    stats: ServerStats,
+
+    /// Aggregate proxy statistics.
+    proxy: ProxyStats,
+}
+
+/// Online players in ping response.
+#[derive(Debug, Serialize)]
+struct Players {
+    /// Number of online players.
+    online: u32,
+
+    /// Maximum number of players, 0 if unknown.
+    max: u32,
+}
+
+/// Server statistics in ping response.
+#[derive(Debug, Serialize)]
+struct ServerStats {
+    /// Total time in seconds the server was online.
+    uptime: u64,
+
+    /// Total time in seconds the server was sleeping.
+    sleep_time: u64,
+
+    /// Number of times the server was woken.
+    wakes: u64,
+
+    /// Number of times the server crashed.
+    crashes: u64,
+
+    /// Number of attempts to wake the server blocked by the whitelist.
+    blocked_wakes: u64,
+
+    /// Unix timestamp in seconds the current server session started at, if online.
+    session_start: Option<u64>,
+}
+
+/// Proxy statistics in ping response.
+#[derive(Debug, Serialize)]
+struct ProxyStats {
+    /// Number of currently proxied sessions.
+    active_sessions: u64,
+
+    /// Total number of proxied sessions.
+    sessions: u64,
+
+    /// Bytes relayed from clients to the server.
+    bytes_to_server: u64,
+
+    /// Bytes relayed from the server to clients.
+    bytes_to_client: u64,
+}
+
+impl Ping {
+    /// Summarize the current server state.
+    async fn of(server: &Server) -> Self {
+        let state = server.state();
+        let online = state == State::Started;
+        let (players, max) = server
+            .status()
+            .await
+            .as_ref()
+            .map(|status| (status.players.online, status.players.max))
+            .unwrap_or_default();
+        let stats = server.stats().await;
+        let session_start = server
+            .session_start()
+            .await
+            .and_then(|start| start.duration_since(UNIX_EPOCH).ok())
+            .map(|start| start.as_secs());
+
+        Self {
+            state,
+            online,
+            players: Players {
+                // Last known status may be stale if the server is not online
+                online: if online { players } else { 0 },
+                max,
+            },
+            stats: ServerStats {
+                uptime: stats.uptime as u64,
+                sleep_time: stats.sleep_time as u64,
+                wakes: stats.wakes,
+                crashes: stats.crashes,
+                blocked_wakes: stats.blocked_wakes,
+                session_start,
+            },
+            proxy: ProxyStats {
+                active_sessions: proxy::STATS.active_sessions(),
+                sessions: proxy::STATS.sessions(),
+                bytes_to_server: proxy::STATS.bytes_to_server(),
+                bytes_to_client: proxy::STATS.bytes_to_client(),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use minecraft_protocol::data::server_status::{OnlinePlayers, ServerStatus, ServerVersion};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Send a raw HTTP request, and return the full response.
+    async fn request(addr: std::net::SocketAddr, request: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_ping() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        tokio::spawn(listener::accept(
+            listener,
+            TARGET,
+            "HTTP request",
+            server.clone(),
+            respond,
+        ));
+
+        let ping = "GET /ping HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let response = request(addr, ping).await;
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert_eq!(
+            head,
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close",
+                body.len()
+            )
+        );
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["state"], "stopped");
+        assert_eq!(body["online"], false);
+        assert_eq!(body["players"], serde_json::json!({"online": 0, "max": 0}));
+        for stat in ["uptime", "wakes", "crashes", "blocked_wakes"] {
+            assert_eq!(body["stats"][stat], 0, "{stat}");
+        }
+        assert!(body["stats"]["sleep_time"].is_u64());
+        assert!(body["stats"]["session_start"].is_null());
+
+        // Proxy statistics are aggregated over all sessions, other tests may proxy concurrently
+        for stat in [
+            "active_sessions",
+            "sessions",
+            "bytes_to_server",
+            "bytes_to_client",
+        ] {
+            assert!(body["proxy"][stat].is_u64(), "{stat}");
+        }
+
+        // Reflects state and players of the started server
+        let config: Config = crate::util::test::config("");
+        let status = ServerStatus {
+            version: ServerVersion {
+                name: "1.20.4".into(),
+                protocol: 765,
+            },
+            players: OnlinePlayers {
+                online: 3,
+                max: 20,
+                sample: vec![],
+            },
+            description: "A Minecraft Server".into(),
+            favicon: None,
+        };
+        server.update_status(&config, Some(status)).await;
+        let response = request(addr, ping).await;
+        let body: serde_json::Value =
+            serde_json::from_str(response.split_once("\r\n\r\n").unwrap().1).unwrap();
+        assert_eq!(body["state"], "started");
+        assert_eq!(body["online"], true);
+        assert_eq!(body["players"], serde_json::json!({"online": 3, "max": 20}));
+        assert!(body["stats"]["session_start"].is_u64());
+
+        // Only the ping endpoint exists
+        let response = request(addr, "GET / HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+        let response = request(addr, "POST /ping HTTP/1.1\r\n\r\n").await;
+        assert!(response.starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+    }
+
+    #[test]
+    fn test_request_line() {
+        assert_eq!(
+            request_line(b"GET /ping HTTP/1.1\r\nHost: localhost\r\n\r\n"),
+            Some(("GET", "/ping"))
+        );
+        assert_eq!(request_line(b"GET /ping\r\n\r\n"), None);
+        assert_eq!(request_line(b"\xff\r\n\r\n"), None);
+    }
+}
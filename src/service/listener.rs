@@ -0,0 +1,56 @@
+use std::future::Future;
+use std::net::SocketAddr;
+
+use tokio::net::{TcpListener, TcpStream};
+
+/// Listen for TCP connections on the given address, and handle each in its own task.
+///
+/// Logs to the given `target`, `what` names the requests the service handles. Every connection
+/// is handled with a clone of `state`.
+pub async fn serve<T, F, R>(
+    addr: SocketAddr,
+    target: &'static str,
+    what: &'static str,
+    state: T,
+    handle: F,
+) where
+    T: Clone,
+    F: Fn(TcpStream, T) -> R,
+    R: Future<Output = ()> + Send + 'static,
+{
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: target, "Failed to listen for {}s on {}: {}", what, addr, err);
+            return;
+        }
+    };
+
+    info!(target: target, "Listening for {}s on {}", what, addr);
+
+    accept(listener, target, what, state, handle).await
+}
+
+/// Accept TCP connections on the given listener, and handle each in its own task.
+pub async fn accept<T, F, R>(
+    listener: TcpListener,
+    target: &'static str,
+    what: &'static str,
+    state: T,
+    handle: F,
+) where
+    T: Clone,
+    F: Fn(TcpStream, T) -> R,
+    R: Future<Output = ()> + Send + 'static,
+{
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle(stream, state.clone()));
+            }
+            Err(err) => {
+                debug!(target: target, "Failed to accept {}: {}", what, err);
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
+pub mod dry_run;
+pub mod event_socket;
 pub mod file_watcher;
+pub mod health;
+pub mod http;
+pub mod listener;
 pub mod monitor;
 pub mod probe;
 pub mod server;
 pub mod signal;
 pub mod stdin;
+pub mod wake;
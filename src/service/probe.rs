@@ -1,29 +1,52 @@
 use std::sync::Arc;
+use std::time::Instant;
 
-use crate::config::{Config, Method};
+use crate::config::{Config, SleepMethod};
 use crate::probe;
 use crate::server::Server;
 
 /// Probe server.
 pub async fn service(config: Arc<Config>, state: Arc<Server>) {
     // Only probe if enabled or if we must
-    if !config.server.probe_on_start && !must_probe(&config) {
+    if !config.server.probe_on_start && !must_probe(&config, &state) {
         return;
     }
 
     // Probe
-    match probe::probe(config, state).await {
-        Ok(_) => info!(target: "lazymc::probe", "Succesfully probed server"),
+    let since = Instant::now();
+    match probe::probe(config.clone(), state.clone()).await {
+        Ok(started) => {
+            info!(target: "lazymc::probe", "Succesfully probed server");
+
+            // Sleep again if only started for probing
+            if started && config.server.sleep_after_probe {
+                sleep_after_probe(&config, &state, since).await;
+            }
+        }
         Err(_) => {
             error!(target: "lazymc::probe", "Failed to probe server, this may limit lazymc features")
         }
     }
 }
 
+/// Put server back to sleep after probing, unless players joined since the given time.
+async fn sleep_after_probe(config: &Config, server: &Server, since: Instant) {
+    if !server.should_sleep_after_probe(since).await {
+        info!(target: "lazymc::probe", "Keeping server online after probe, players joined");
+        return;
+    }
+
+    let method = SleepMethod::of(config).name();
+    info!(target: "lazymc::probe", "Server probed, putting it back to sleep ({})...", method);
+    if !server.stop(config).await {
+        warn!(target: "lazymc::probe", "Failed to put server back to sleep after probe");
+    }
+}
+
 /// Check whether we must probe.
-fn must_probe(config: &Config) -> bool {
+fn must_probe(config: &Config, server: &Server) -> bool {
     // Must probe with lobby and Forge
-    if config.server.forge && config.join.methods.contains(&Method::Lobby) {
+    if server.is_forge(config) && config.join.methods.iter().any(|m| m.uses_lobby()) {
         warn!(target: "lazymc::probe", "Starting server to probe for Forge lobby...");
         warn!(target: "lazymc::probe", "Set 'server.probe_on_start = true' to remove this warning");
         return true;
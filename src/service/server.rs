@@ -1,38 +1,55 @@
+use std::io;
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::BytesMut;
 use futures::FutureExt;
-use tokio::net::{TcpListener, TcpStream};
+use tokio::net::TcpStream;
+use tokio::sync::{oneshot, OwnedSemaphorePermit, Semaphore};
+use tokio::time;
 
-use crate::config::Config;
-use crate::proto::client::Client;
+use crate::config::{
+    Advanced, Config, FullAction, ReadyCheck, ServerOutput, SleepMethod, StartTimeoutAction,
+};
+use crate::monitor;
+use crate::net::{self, Endpoint};
+use crate::persist::{self, LockError};
+use crate::proto::client::{Client, ClientState};
 use crate::proxy::{self, ProxyHeader};
 use crate::server::{self, Server};
 use crate::service;
 use crate::status;
-use crate::util::error::{quit_error, ErrorHints};
+use crate::util::error::{quit_error, quit_error_msg, ErrorHints, ErrorHintsBuilder};
 
 /// Start lazymc.
 ///
 /// Main entrypoint to start all server/status/proxy logic.
 ///
-/// Spawns a tokio runtime to complete all work on.
-#[tokio::main(flavor = "multi_thread")]
-pub async fn service(config: Arc<Config>) -> Result<(), ()> {
+/// Must run on a multi-threaded tokio runtime. Releases the given lazymc lock file on shutdown.
+pub async fn service(config: Arc<Config>, lock_file: Option<PathBuf>) -> Result<(), ()> {
     // Load server state
     let (server, stdin_tx) = Server::new();
     let server = Arc::new(server);
+    server.load_persistent(&config).await;
+    server.load_status_cache(&config).await;
+    server::warn_sleep_after(&config, server.startup_time().await);
 
-    // Listen for new connections
-    let listener = TcpListener::bind(config.public.address)
+    // Reattach to server if it is still running, such as when lazymc itself restarted
+    let responding = monitor::poll_server(&config, &server, &config.server.address)
         .await
-        .map_err(|err| {
-            quit_error(
-                anyhow!(err).context("Failed to start proxy server"),
-                ErrorHints::default(),
-            );
-        })?;
+        .is_ok();
+    Server::reattach(config.clone(), server.clone(), responding).await;
+
+    // Listen for new connections
+    let listener = net::bind_listener(&config, config.public.address).map_err(|err| {
+        unlock(lock_file.as_deref());
+        quit_error(
+            anyhow!(err).context("Failed to start proxy server"),
+            ErrorHints::default(),
+        );
+    })?;
 
     info!(
         target: "lazymc",
@@ -40,20 +57,67 @@ pub async fn service(config: Arc<Config>) -> Result<(), ()> {
         config.public.address, config.server.address,
     );
 
-    if config.lockout.enabled {
+    if config.lockout.enabled && config.lockout.schedule.is_empty() {
         warn!(
             target: "lazymc",
             "Lockout mode is enabled, nobody will be able to connect through the proxy",
         );
+    } else if config.lockout.enabled {
+        warn!(
+            target: "lazymc",
+            "Lockout mode is scheduled, nobody will be able to connect through the proxy during lockout",
+        );
+    }
+
+    if ReadyCheck::of(&config) == ReadyCheck::Stdout
+        && (config.advanced.server_output != ServerOutput::Capture
+            || config.advanced.ready_regex.is_empty())
+    {
+        warn!(
+            target: "lazymc",
+            "Server readiness is checked through its output, but output is not captured or the ready regex is empty, server will never be ready",
+        );
+    }
+
+    if config.server.start_timeout_action == StartTimeoutAction::Hold && !config.wake.is_enabled() {
+        warn!(
+            target: "lazymc",
+            "Server is held after a start timeout until woken with a wake packet, but wake packets are not enabled, only restarting lazymc will release it",
+        );
+    }
+
+    debug!(target: "lazymc", "Server sleep method: {}", SleepMethod::of(&config).name());
+
+    if config.maintenance.enabled {
+        warn!(
+            target: "lazymc",
+            "Maintenance mode is enabled, only exempt clients will be able to join and wake the server",
+        );
     }
 
-    // Spawn services: monitor, signal handler, stdin reader
+    // Spawn services: monitor, stdin reader
     tokio::spawn(service::monitor::service(config.clone(), server.clone()));
-    tokio::spawn(service::signal::service(config.clone(), server.clone()));
-    tokio::spawn(service::stdin::service(stdin_tx));
+    let (stdin_shutdown, stdin_shutdown_rx) = oneshot::channel();
+    tokio::spawn(service::stdin::service(
+        stdin_tx,
+        stdin_shutdown_rx.map(|_| ()),
+    ));
+    tokio::spawn(service::wake::service(config.clone(), server.clone()));
+    tokio::spawn(service::event_socket::service(
+        config.clone(),
+        server.clone(),
+    ));
+    tokio::spawn(service::health::service(config.clone(), server.clone()));
+    tokio::spawn(service::http::service(config.clone(), server.clone()));
 
-    // Initiate server start
-    if config.server.wake_on_start {
+    // Initiate server start, or resume server that was active before lazymc restarted, unless the
+    // server is kept asleep for maintenance
+    if config.maintenance.enabled {
+        debug!(target: "lazymc", "Maintenance mode is enabled, not starting server");
+    } else if config.server.wake_on_start {
+        Server::start(config.clone(), server.clone(), None).await;
+    } else if server.should_resume(&config).await {
+        info!(target: "lazymc", "Server was active before lazymc restarted, resuming...");
         Server::start(config.clone(), server.clone(), None).await;
     }
 
@@ -64,17 +128,121 @@ pub async fn service(config: Arc<Config>) -> Result<(), ()> {
         || service::file_watcher::service(config, server)
     });
 
-    // Route all incomming connections
-    while let Ok((inbound, _)) = listener.accept().await {
-        route(inbound, config.clone(), server.clone());
+    // Route all incomming connections until shutdown
+    let limit = ConnectionLimit::new(config.advanced.max_connections);
+    let shutdown = service::signal::wait();
+    tokio::pin!(shutdown);
+    loop {
+        let (inbound, peer) = tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(_) => return Ok(()),
+            },
+        };
+
+        let permit = match limit.acquire() {
+            Ok(permit) => permit,
+            Err(()) => {
+                warn!(target: "lazymc", "Connection from {} dropped, reached maximum of {} connections", peer.ip(), config.advanced.max_connections);
+                continue;
+            }
+        };
+        route(inbound, config.clone(), server.clone(), permit);
     }
 
-    Ok(())
+    // Stop accepting connections and reading stdin, gracefully shut down
+    drop(listener);
+    let _ = stdin_shutdown.send(());
+    service::signal::shutdown(&config, &server, limit.drain()).await;
+    unlock(lock_file.as_deref());
+    service::signal::quit();
+}
+
+/// Acquire the lazymc lock file.
+///
+/// Quits with an error if another lazymc instance holds it. Returns the lock file path if locked.
+pub fn lock(config: &Config) -> Option<PathBuf> {
+    let file = Advanced::lock_file(config)?;
+
+    match persist::lock(&file) {
+        Ok(()) => Some(file),
+        Err(LockError::Held(pid)) => quit_error_msg(
+            format!("Another lazymc instance (PID {pid}) is already running for this server"),
+            ErrorHintsBuilder::default()
+                .add_info(format!(
+                    "remove '{}' if that instance is not lazymc",
+                    file.display()
+                ))
+                .build()
+                .unwrap(),
+        ),
+        Err(LockError::Io(err)) => {
+            warn!(target: "lazymc", "Failed to write lock file {}: {}", file.display(), err);
+            None
+        }
+    }
+}
+
+/// Release the lazymc lock file, if locked.
+pub fn unlock(file: Option<&Path>) {
+    if let Some(file) = file {
+        persist::unlock(file);
+    }
+}
+
+/// Time a trusted proxy has to send its PROXY header.
+const PROXY_HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Connection permit, shared by all tasks handling the same connection.
+///
+/// The permit is released once the last task handling the connection completes.
+pub type Permit = Arc<OwnedSemaphorePermit>;
+
+/// Number of connection permits if unlimited.
+const UNLIMITED_CONNECTIONS: u32 = u32::MAX >> 3;
+
+/// Limit on number of concurrently handled client connections.
+///
+/// Also tracks handled connections, to wait for them to complete on shutdown.
+struct ConnectionLimit {
+    semaphore: Arc<Semaphore>,
+    permits: u32,
+}
+
+impl ConnectionLimit {
+    /// Construct limit for the given number of connections, unlimited if 0.
+    fn new(max: u32) -> Self {
+        let permits = if max > 0 { max } else { UNLIMITED_CONNECTIONS };
+        Self {
+            semaphore: Arc::new(Semaphore::new(permits as usize)),
+            permits,
+        }
+    }
+
+    /// Acquire a connection permit, held until the connection is handled.
+    ///
+    /// Errors if the limit is reached.
+    fn acquire(&self) -> Result<OwnedSemaphorePermit, ()> {
+        self.semaphore.clone().try_acquire_owned().map_err(|_| ())
+    }
+
+    /// Wait for all handled connections to complete.
+    async fn drain(&self) {
+        let _ = self.semaphore.acquire_many(self.permits).await;
+    }
 }
 
 /// Route inbound TCP stream to correct service, spawning a new task.
+///
+/// The given connection permit is held until the spawned task completes.
 #[inline]
-fn route(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>) {
+fn route(
+    inbound: TcpStream,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    permit: OwnedSemaphorePermit,
+) {
     // Get user peer address
     let peer = match inbound.peer_addr() {
         Ok(peer) => peer,
@@ -84,6 +252,64 @@ fn route(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>) {
         }
     };
 
+    // Trusted proxy sends PROXY header with real client address first, read it in new task
+    if config.advanced.expects_proxy_header(&peer.ip()) {
+        tokio::spawn(async move {
+            let mut inbound = inbound;
+            match client_addr(&config, &mut inbound, peer).await {
+                Ok(client) => route_client(inbound, config, server, client, permit),
+                Err(err) => {
+                    warn!(target: "lazymc", "Connection from proxy {} without valid PROXY header, dropping: {}", peer.ip(), err)
+                }
+            }
+        });
+        return;
+    }
+
+    route_client(inbound, config, server, peer, permit)
+}
+
+/// Get the real client address of an inbound connection.
+///
+/// Reads the PROXY header if the peer is a trusted proxy, uses the peer address otherwise.
+async fn client_addr(
+    config: &Config,
+    inbound: &mut TcpStream,
+    peer: SocketAddr,
+) -> io::Result<SocketAddr> {
+    if !config.advanced.expects_proxy_header(&peer.ip()) {
+        return Ok(peer);
+    }
+
+    match time::timeout(PROXY_HEADER_TIMEOUT, proxy::read_proxy_header(inbound)).await {
+        Ok(source) => Ok(source?.unwrap_or(peer)),
+        Err(_) => Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "timed out reading PROXY header",
+        )),
+    }
+}
+
+/// Route inbound TCP stream of the given client to correct service, spawning a new task.
+#[inline]
+fn route_client(
+    inbound: TcpStream,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    peer: SocketAddr,
+    permit: OwnedSemaphorePermit,
+) {
+    // Drop connection if not allowed by access rules
+    if !config.access.is_allowed(&peer.ip()) {
+        info!(target: "lazymc", "Connection from {} refused by access rules, dropping", peer.ip());
+        return;
+    }
+
+    // Apply socket options
+    if let Err(err) = net::configure_stream(&config, &inbound) {
+        warn!(target: "lazymc", "Failed to configure connection socket: {}", err);
+    }
+
     // Check ban state, just drop connection if enabled
     let banned = server.is_banned_ip_blocking(&peer.ip());
     if banned && config.server.drop_banned_ips {
@@ -91,22 +317,47 @@ fn route(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>) {
         return;
     }
 
-    // Route connection through proper channel
-    let should_proxy =
-        !banned && server.state() == server::State::Started && !config.lockout.enabled;
-    if should_proxy {
-        route_proxy(inbound, config)
-    } else {
-        route_status(inbound, config, server, peer)
+    // Route connection through proper channel, status server handles cached status
+    let should_proxy = !banned
+        && server.state() == server::State::Started
+        && !config.lockout.is_locked_out(&peer.ip())
+        && !config.maintenance.applies_to(&peer.ip())
+        && config.advanced.status_cache_ttl == 0;
+    if !should_proxy {
+        route_status(inbound, config, server, peer, permit);
+        return;
     }
+
+    // Only logins may need to be queued, proxy everything else directly
+    if config.join.full_action != FullAction::Queue {
+        route_proxy(inbound, config, peer, permit);
+        return;
+    }
+
+    tokio::spawn(async move {
+        // Logins to a full server go through the join methods to queue the player
+        let queue = status::peek_next_state(&config, &inbound).await == Some(ClientState::Login)
+            && server.is_full().await;
+        if queue {
+            route_status(inbound, config, server, peer, permit)
+        } else {
+            route_proxy(inbound, config, peer, permit)
+        }
+    });
 }
 
 /// Route inbound TCP stream to status server, spawning a new task.
 #[inline]
-fn route_status(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>, peer: SocketAddr) {
+fn route_status(
+    inbound: TcpStream,
+    config: Arc<Config>,
+    server: Arc<Server>,
+    peer: SocketAddr,
+    permit: OwnedSemaphorePermit,
+) {
     // When server is not online, spawn a status server
     let client = Client::new(peer);
-    let service = status::serve(client, inbound, config, server).map(|r| {
+    let service = status::serve(client, inbound, config, server, Arc::new(permit)).map(|r| {
         if let Err(err) = r {
             warn!(target: "lazymc", "Failed to serve status: {:?}", err);
         }
@@ -115,46 +366,69 @@ fn route_status(inbound: TcpStream, config: Arc<Config>, server: Arc<Server>, pe
     tokio::spawn(service);
 }
 
-/// Route inbound TCP stream to proxy, spawning a new task.
+/// Route inbound TCP stream of the given client to proxy, spawning a new task.
 #[inline]
-fn route_proxy(inbound: TcpStream, config: Arc<Config>) {
+fn route_proxy(
+    inbound: TcpStream,
+    config: Arc<Config>,
+    client: SocketAddr,
+    permit: OwnedSemaphorePermit,
+) {
     // When server is online, proxy all
-    let service = proxy::proxy(
-        inbound,
-        ProxyHeader::Proxy.not_none(config.server.send_proxy_v2),
-        config.server.address,
-    )
-    .map(|r| {
-        if let Err(err) = r {
-            warn!(target: "lazymc", "Failed to proxy: {}", err);
-        }
-    });
+    let service = async move {
+        let _permit = permit;
+        proxy::proxy(
+            &config,
+            inbound,
+            client,
+            ProxyHeader::Proxy.not_none(config.server.send_proxy_v2),
+            &config.server.address,
+        )
+        .map(|r| {
+            if let Err(err) = r {
+                warn!(target: "lazymc", "Failed to proxy: {}", err);
+            }
+        })
+        .await
+    };
 
     tokio::spawn(service);
 }
 
-/// Route inbound TCP stream to proxy with queued data, spawning a new task.
+/// Route inbound TCP stream of the given client to proxy with queued data, spawning a new task.
+///
+/// The given connection permit is held until the spawned task completes.
 #[inline]
-pub fn route_proxy_queue(inbound: TcpStream, config: Arc<Config>, queue: BytesMut) {
-    route_proxy_address_queue(
-        inbound,
-        ProxyHeader::Proxy.not_none(config.server.send_proxy_v2),
-        config.server.address,
-        queue,
-    );
+pub fn route_proxy_queue(
+    inbound: TcpStream,
+    config: Arc<Config>,
+    client: SocketAddr,
+    queue: BytesMut,
+    permit: Permit,
+) {
+    let proxy_header = ProxyHeader::Proxy.not_none(config.server.send_proxy_v2);
+    let addr = config.server.address.clone();
+    route_proxy_address_queue(inbound, config, client, proxy_header, addr, queue, permit);
 }
 
-/// Route inbound TCP stream to proxy with given address and queued data, spawning a new task.
+/// Route inbound TCP stream of the given client to proxy with given address and queued data,
+/// spawning a new task.
+///
+/// The given connection permit is held until the spawned task completes.
 #[inline]
 pub fn route_proxy_address_queue(
     inbound: TcpStream,
+    config: Arc<Config>,
+    client: SocketAddr,
     proxy_header: ProxyHeader,
-    addr: SocketAddr,
+    addr: Endpoint,
     queue: BytesMut,
+    permit: Permit,
 ) {
     // When server is online, proxy all
     let service = async move {
-        proxy::proxy_with_queue(inbound, proxy_header, addr, &queue)
+        let _permit = permit;
+        proxy::proxy_with_queue(&config, inbound, client, proxy_header, &addr, &queue)
             .map(|r| {
                 if let Err(err) = r {
                     warn!(target: "lazymc", "Failed to proxy: {}", err);
@@ -165,3 +439,271 @@ pub fn route_proxy_address_queue(
 
     tokio::spawn(service);
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use minecraft_protocol::data::server_status::{OnlinePlayers, ServerStatus, ServerVersion};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::mc::ban;
+    use crate::util::test::{login_packets, login_packets_protocol};
+
+    #[test]
+    fn test_connection_limit() {
+        let limit = ConnectionLimit::new(2);
+        let first = limit.acquire().unwrap();
+        let _second = limit.acquire().unwrap();
+
+        // Connection over the limit is rejected until one is released
+        assert!(limit.acquire().is_err());
+        drop(first);
+        assert!(limit.acquire().is_ok());
+    }
+
+    #[test]
+    fn test_connection_limit_unlimited() {
+        let limit = ConnectionLimit::new(0);
+        let permits: Vec<_> = (0..100).map(|_| limit.acquire()).collect();
+        assert!(permits.iter().all(Result::is_ok));
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit_drain() {
+        let limit = ConnectionLimit::new(0);
+        let permit = limit.acquire().unwrap();
+
+        // Drain completes once the last connection is released
+        let timeout = Duration::from_millis(100);
+        assert!(time::timeout(timeout, limit.drain()).await.is_err());
+        drop(permit);
+        assert!(time::timeout(timeout, limit.drain()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_drop_banned_ips() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-drop-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ban::FILE);
+        fs::write(
+            &path,
+            r#"[{"ip": "127.0.0.1", "created": "", "source": "", "reason": "griefing"}]"#,
+        )
+        .unwrap();
+        let (server, _) = Server::new();
+        server.set_banned_ips(ban::load(&path).unwrap()).await;
+        let _ = fs::remove_dir_all(&dir);
+
+        // Dropping takes precedence over a configured kick message
+        let config: Config =
+            crate::util::test::config("drop_banned_ips = true\nbanned_message = \"Go away\"");
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, _) = listener.accept().await.unwrap();
+        let permit = ConnectionLimit::new(0).acquire().unwrap();
+        route(inbound, Arc::new(config), Arc::new(server), permit);
+
+        // Connection is closed without any response
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.is_empty());
+    }
+
+    /// Connect to a local listener, return the client and accepted inbound stream.
+    async fn connect() -> (TcpStream, TcpStream, SocketAddr) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, peer) = listener.accept().await.unwrap();
+        (client, inbound, peer)
+    }
+
+    #[tokio::test]
+    async fn test_client_addr_trusted_proxy() {
+        let config: Config = crate::util::test::config(
+            "[advanced]\naccept_proxy_protocol = true\ntrusted_proxies = [\"127.0.0.0/8\"]",
+        );
+
+        // Real client address is taken from header
+        let (mut client, mut inbound, peer) = connect().await;
+        client
+            .write_all(b"PROXY TCP4 203.0.113.7 127.0.0.1 51234 25565\r\n")
+            .await
+            .unwrap();
+        let addr = client_addr(&config, &mut inbound, peer).await.unwrap();
+        assert_eq!(addr, "203.0.113.7:51234".parse().unwrap());
+
+        // Trusted proxy must send header
+        let (mut client, mut inbound, peer) = connect().await;
+        client
+            .write_all(b"\x10\x00\xfd\x05\x09localhost")
+            .await
+            .unwrap();
+        assert!(client_addr(&config, &mut inbound, peer).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_client_addr_untrusted_peer() {
+        let config: Config = crate::util::test::config(
+            "[advanced]\naccept_proxy_protocol = true\ntrusted_proxies = [\"10.0.0.0/8\"]",
+        );
+
+        // Header from untrusted peer is not read, peer address is used
+        let (mut client, mut inbound, peer) = connect().await;
+        let header = b"PROXY TCP4 203.0.113.7 127.0.0.1 51234 25565\r\n";
+        client.write_all(header).await.unwrap();
+        assert_eq!(
+            client_addr(&config, &mut inbound, peer).await.unwrap(),
+            peer
+        );
+
+        let mut buf = [0; 6];
+        inbound.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"PROXY ");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_header_client_addr() {
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config: Config = crate::util::test::config(&format!(
+            "address = \"{}\"\nsend_proxy_v2 = true\n[advanced]\naccept_proxy_protocol = true\ntrusted_proxies = [\"127.0.0.0/8\"]",
+            backend.local_addr().unwrap(),
+        ));
+        let (server, _) = Server::new();
+        server.update_state(server::State::Started, &config).await;
+
+        // Trusted proxy connects with the real client address in a PROXY v2 header
+        let (mut client, inbound, _) = connect().await;
+        let header = proxy_protocol::encode(proxy_protocol::ProxyHeader::Version2 {
+            command: proxy_protocol::version2::ProxyCommand::Proxy,
+            transport_protocol: proxy_protocol::version2::ProxyTransportProtocol::Stream,
+            addresses: proxy_protocol::version2::ProxyAddresses::Ipv4 {
+                source: "203.0.113.7:51234".parse().unwrap(),
+                destination: "127.0.0.1:25565".parse().unwrap(),
+            },
+        })
+        .unwrap();
+        let login = crate::util::test::login_packets("Steve");
+        client.write_all(&header).await.unwrap();
+        client.write_all(&login).await.unwrap();
+        let permit = ConnectionLimit::new(0).acquire().unwrap();
+        route(inbound, Arc::new(config), Arc::new(server), permit);
+
+        // Header sent to the server carries the real client address, not the proxy address
+        let (mut outbound, _) = backend.accept().await.unwrap();
+        let source = time::timeout(
+            Duration::from_secs(5),
+            proxy::read_proxy_header(&mut outbound),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(source, Some("203.0.113.7:51234".parse().unwrap()));
+        let mut buf = vec![0; login.len()];
+        outbound.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, login);
+    }
+
+    /// Build config with the given join section, and a full started server at the given address.
+    async fn full_server(join: &str, address: SocketAddr) -> (Arc<Config>, Arc<Server>) {
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "address = \"{address}\"\ndirectory = {:?}\n[join]\n{join}",
+            std::env::temp_dir(),
+        )));
+        let (server, _) = Server::new();
+        let status = ServerStatus {
+            version: ServerVersion {
+                name: "1.20.4".into(),
+                protocol: 765,
+            },
+            players: OnlinePlayers {
+                online: 20,
+                max: 20,
+                sample: vec![],
+            },
+            description: "A Minecraft Server".into(),
+            favicon: None,
+        };
+        server.update_status(&config, Some(status)).await;
+        assert_eq!(server.state(), server::State::Started);
+        assert!(server.is_full().await);
+        (config, Arc::new(server))
+    }
+
+    #[tokio::test]
+    async fn test_full_server_queue() {
+        // Unused backend, queued clients must not be proxied
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (config, server) = full_server(
+            "full_action = \"queue\"\nmethods = [\"kick\"]",
+            backend.local_addr().unwrap(),
+        )
+        .await;
+        let (mut client, inbound, _) = connect().await;
+        client.write_all(&login_packets("player")).await.unwrap();
+        let permit = ConnectionLimit::new(0).acquire().unwrap();
+        route(inbound, config, server, permit);
+
+        // Client is occupied by join methods instead
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!response.is_empty());
+        assert!(time::timeout(Duration::from_millis(100), backend.accept())
+            .await
+            .is_err());
+    }
+
+    #[cfg(feature = "lobby")]
+    #[tokio::test]
+    async fn test_full_server_queue_method() {
+        // Unused backend, queued clients must not be proxied
+        let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let (config, server) = full_server(
+            "full_action = \"queue\"\nmethods = [\"queue\"]\n[public]\nprotocol = 763",
+            backend.local_addr().unwrap(),
+        )
+        .await;
+        let (mut client, inbound, _) = connect().await;
+        client
+            .write_all(&login_packets_protocol(763, "player"))
+            .await
+            .unwrap();
+        let permit = ConnectionLimit::new(0).acquire().unwrap();
+        route(inbound, config, server.clone(), permit);
+
+        // Player waits in the join queue for a free slot
+        time::timeout(Duration::from_secs(5), async {
+            while server.queue.is_empty() {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        assert_eq!(server.queue.len(), 1);
+        assert!(time::timeout(Duration::from_millis(100), backend.accept())
+            .await
+            .is_err());
+
+        // Leaves the queue once disconnected
+        drop(client);
+        time::timeout(Duration::from_secs(5), async {
+            while !server.queue.is_empty() {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+}
@@ -1,32 +1,137 @@
-use std::sync::Arc;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time;
 
 use crate::config::Config;
-use crate::server::{self, Server};
+use crate::server::Server;
 use crate::util::error;
 
-/// Signal handler task.
-pub async fn service(config: Arc<Config>, server: Arc<Server>) {
-    loop {
-        // Wait for SIGTERM/SIGINT signal
-        tokio::signal::ctrl_c().await.unwrap();
+/// Wait for a shutdown signal, SIGINT or SIGTERM.
+pub async fn wait() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
 
-        // Quit if stopped
-        if server.state() == server::State::Stopped {
-            quit();
+        let mut terminate = signal(SignalKind::terminate()).unwrap();
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
         }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await.unwrap();
+}
+
+/// Gracefully shut down.
+///
+/// The caller must have stopped accepting new connections. Rejects joining clients, optionally
+/// stops the server, and waits for in-flight connections to complete with the given drain future.
+///
+/// Quits immediately on a second shutdown signal.
+pub async fn shutdown(config: &Config, server: &Server, drain: impl Future<Output = ()>) {
+    info!(target: "lazymc", "Shutting down, press Ctrl+C again to quit immediately...");
+    server.start_draining();
 
-        // Try to stop server
-        let stopping = server.stop(&config).await;
+    tokio::spawn(async {
+        wait().await;
+        warn!(target: "lazymc", "Quitting immediately");
+        quit();
+    });
 
-        // If not stopping, maybe due to failure, just quit
-        if !stopping {
-            quit();
+    // Stop server and wait for it to quit
+    if config.advanced.stop_server_on_exit && server.has_process().await {
+        info!(target: "lazymc", "Stopping server before quitting...");
+        let timeout = Duration::from_secs(config.server.stop_timeout as u64);
+        if server.stop_process(config).await && !server.wait_process_exit(timeout).await {
+            warn!(target: "lazymc", "Server did not stop in time, force killing");
+            server.force_kill(config).await;
         }
     }
+
+    // Wait for connections to complete
+    let timeout = Duration::from_secs(config.advanced.shutdown_timeout as u64);
+    if time::timeout(timeout, drain).await.is_err() {
+        warn!(target: "lazymc", "Connections did not complete in time, quitting anyway");
+    }
 }
 
-/// Gracefully quit.
-fn quit() -> ! {
-    // TODO: gracefully quit self
+/// Quit.
+pub fn quit() -> ! {
     error::quit();
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// Start server with the given config, wait for its process to spawn.
+    #[cfg(unix)]
+    async fn running_server(config: &Arc<Config>) -> Arc<Server> {
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        assert!(Server::start(config.clone(), server.clone(), None).await);
+        time::sleep(Duration::from_millis(200)).await;
+        assert!(server.has_process().await);
+        server
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_stop_server() {
+        let dir = crate::util::test::temp_dir("shutdown-stop");
+        let config: Arc<Config> = Arc::new(
+            toml::from_str(&format!(
+                "[server]\ncommand_args = [\"sh\", \"-c\", \"read line\"]\ndirectory = {:?}",
+                dir,
+            ))
+            .unwrap(),
+        );
+        let server = running_server(&config).await;
+
+        shutdown(&config, &server, async {}).await;
+        assert!(!server.has_process().await);
+
+        // Server is never started again
+        assert!(server.is_draining());
+        assert!(!Server::start(config, server.clone(), None).await);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_keep_server() {
+        let dir = crate::util::test::temp_dir("shutdown-keep");
+        let config: Arc<Config> = Arc::new(
+            toml::from_str(&format!(
+                "[server]\ncommand = \"sleep 30\"\ndirectory = {:?}\n[advanced]\nstop_server_on_exit = false",
+                dir,
+            ))
+            .unwrap(),
+        );
+        let server = running_server(&config).await;
+
+        shutdown(&config, &server, async {}).await;
+        assert!(server.has_process().await);
+        assert!(server.is_draining());
+        server.force_kill(&config).await;
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drain_timeout() {
+        let config: Config = crate::util::test::config("[advanced]\nshutdown_timeout = 1");
+        let (server, _) = Server::new();
+
+        // Connections that don't complete don't block shutdown forever
+        let result = time::timeout(
+            Duration::from_secs(5),
+            shutdown(&config, &server, futures::future::pending()),
+        )
+        .await;
+        assert!(result.is_ok());
+    }
+}
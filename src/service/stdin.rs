@@ -1,34 +1,114 @@
-use std::io::BufRead;
+use std::future::Future;
+use std::io::{self, BufRead, BufReader};
+use std::thread;
 
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 
 /// Global stdin reader service.
 ///
-/// Runs a single persistent blocking thread that reads lines from lazymc's stdin
-/// and sends them through the channel. The server process consumes these lines
-/// when it is running.
+/// Runs a single persistent thread that reads lines from lazymc's stdin and sends them through
+/// the channel. The server process consumes these lines when it is running.
 ///
-/// This must be a single global task (not per-server-invocation) to avoid
-/// zombie `spawn_blocking` threads competing for stdin reads after server restarts.
-pub async fn service(sender: mpsc::UnboundedSender<String>) {
-    tokio::task::spawn_blocking(move || {
-        let stdin = std::io::stdin();
-        loop {
-            let mut line = String::new();
-            match stdin.lock().read_line(&mut line) {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    if sender.send(line).is_err() {
-                        break;
-                    }
-                }
-                Err(err) => {
-                    warn!(target: "lazymc", "Failed to read from stdin: {}", err);
+/// This must be a single global task (not per-server-invocation) to avoid zombie threads
+/// competing for stdin reads after server restarts.
+///
+/// Ends on EOF, or once the given shutdown future completes. A blocking read can't be cancelled,
+/// so the reader thread is detached rather than part of the runtime blocking pool, and does not
+/// hold up shutdown.
+pub async fn service(sender: mpsc::UnboundedSender<String>, shutdown: impl Future<Output = ()>) {
+    serve(BufReader::new(io::stdin()), sender, shutdown).await
+}
+
+/// Read lines from the given reader on a detached thread, until EOF or shutdown.
+async fn serve<R>(
+    reader: R,
+    sender: mpsc::UnboundedSender<String>,
+    shutdown: impl Future<Output = ()>,
+) where
+    R: BufRead + Send + 'static,
+{
+    let (done_tx, done_rx) = oneshot::channel();
+    let spawned = thread::Builder::new()
+        .name("lazymc-stdin".into())
+        .spawn(move || {
+            read_lines(reader, &sender);
+            let _ = done_tx.send(());
+        });
+    if let Err(err) = spawned {
+        warn!(target: "lazymc", "Failed to spawn stdin reader: {}", err);
+        return;
+    }
+
+    tokio::select! {
+        _ = done_rx => {}
+        _ = shutdown => {
+            trace!(target: "lazymc", "Stopped reading stdin, shutting down");
+        }
+    }
+}
+
+/// Read lines from the given reader, send them through the channel.
+fn read_lines(mut reader: impl BufRead, sender: &mpsc::UnboundedSender<String>) {
+    loop {
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => break, // EOF
+            Ok(_) => {
+                if sender.send(line).is_err() {
                     break;
                 }
             }
+            Err(err) => {
+                warn!(target: "lazymc", "Failed to read from stdin: {}", err);
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::time::Duration;
+
+    use tokio::time;
+
+    use super::*;
+
+    /// Reader that never yields any input, like an idle stdin.
+    struct Idle;
+
+    impl Read for Idle {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            thread::sleep(Duration::from_secs(60 * 60));
+            Ok(0)
         }
-    })
-    .await
-    .ok();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_without_input() {
+        let (sender, _receiver) = mpsc::unbounded_channel();
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+        let service = tokio::spawn(serve(BufReader::new(Idle), sender, async {
+            let _ = shutdown_rx.await;
+        }));
+
+        time::sleep(Duration::from_millis(100)).await;
+        assert!(!service.is_finished());
+
+        // Ends promptly on shutdown, even though the read is still blocked
+        shutdown_tx.send(()).unwrap();
+        time::timeout(Duration::from_secs(1), service)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_read_lines() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        serve(&b"list\nstop\n"[..], sender, futures::future::pending()).await;
+        assert_eq!(receiver.recv().await.as_deref(), Some("list\n"));
+        assert_eq!(receiver.recv().await.as_deref(), Some("stop\n"));
+    }
 }
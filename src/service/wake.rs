@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::net::UdpSocket;
+
+use crate::config::Config;
+use crate::server::Server;
+
+/// Minimum time between wake packets handled from the same IP.
+const RATE_LIMIT: Duration = Duration::from_secs(5);
+
+/// Interval to forget IPs whose rate limit has passed at.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maximum wake packet size we read.
+const MAX_PACKET_SIZE: usize = 512;
+
+/// Wake packet listener.
+///
+/// Starts the server on receiving a UDP packet containing the configured secret.
+pub async fn service(config: Arc<Config>, server: Arc<Server>) {
+    let port = match config.wake.udp_port {
+        Some(port) => port,
+        None => return,
+    };
+
+    if config.wake.secret.as_deref().unwrap_or("").is_empty() {
+        warn!(target: "lazymc::wake", "Wake packets are not enabled, no 'wake.secret' is configured");
+        return;
+    }
+
+    let addr = SocketAddr::new(config.public.address.ip(), port);
+    let socket = match UdpSocket::bind(addr).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            error!(target: "lazymc::wake", "Failed to listen for wake packets on {}: {}", addr, err);
+            return;
+        }
+    };
+
+    info!(target: "lazymc::wake", "Listening for wake packets on {}", addr);
+
+    serve(socket, config, server, RATE_LIMIT).await
+}
+
+/// Handle wake packets received on the given socket.
+async fn serve(socket: UdpSocket, config: Arc<Config>, server: Arc<Server>, rate_limit: Duration) {
+    let secret = config.wake.secret.clone().unwrap_or_default();
+    let mut last_seen: HashMap<IpAddr, Instant> = HashMap::new();
+    let mut last_prune = Instant::now();
+    let mut buf = [0u8; MAX_PACKET_SIZE];
+
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buf).await {
+            Ok(result) => result,
+            Err(err) => {
+                debug!(target: "lazymc::wake", "Failed to receive wake packet: {}", err);
+                continue;
+            }
+        };
+
+        if !config.access.is_allowed(&peer.ip()) {
+            continue;
+        }
+
+        if !is_secret(&buf[..len], secret.as_bytes()) {
+            warn!(target: "lazymc::wake", "Invalid wake packet from {}, ignoring", peer.ip());
+            continue;
+        }
+
+        // Rate limit per IP
+        let now = Instant::now();
+        let limited = last_seen
+            .get(&peer.ip())
+            .map_or(false, |seen| now.duration_since(*seen) < rate_limit);
+        if limited {
+            trace!(target: "lazymc::wake", "Wake packet from {} rate limited", peer.ip());
+            continue;
+        }
+        last_seen.insert(peer.ip(), now);
+
+        // Periodically forget IPs once their limit has passed
+        if now.duration_since(last_prune) >= PRUNE_INTERVAL {
+            last_seen.retain(|_, seen| now.duration_since(*seen) < rate_limit);
+            last_prune = now;
+        }
+
+        info!(target: "lazymc::wake", "Received wake packet from {}", peer.ip());
+        server.release_hold();
+        Server::start(config.clone(), server.clone(), None).await;
+    }
+}
+
+/// Check whether the packet matches the secret, ignoring trailing whitespace.
+///
+/// Compares in constant time to not leak the secret through timing.
+fn is_secret(packet: &[u8], secret: &[u8]) -> bool {
+    let packet = match packet.iter().rposition(|b| !b.is_ascii_whitespace()) {
+        Some(end) => &packet[..=end],
+        None => &[],
+    };
+
+    packet.len() == secret.len()
+        && packet
+            .iter()
+            .zip(secret)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_secret() {
+        assert!(is_secret(b"hunter2", b"hunter2"));
+        assert!(is_secret(b"hunter2\n", b"hunter2"));
+        assert!(!is_secret(b"hunter3", b"hunter2"));
+        assert!(!is_secret(b"hunter", b"hunter2"));
+        assert!(!is_secret(b"", b"hunter2"));
+    }
+
+    #[tokio::test]
+    async fn test_wake_packet() {
+        let config: Config = crate::util::test::config(
+            r#"
+            [wake]
+            secret = "hunter2"
+            "#,
+        );
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+        let mut state = server.state_receiver();
+
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        tokio::spawn(serve(socket, Arc::new(config), server, RATE_LIMIT));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+
+        // Invalid secret must not wake, nor rate limit the next packet
+        client.send_to(b"hunter3", addr).await.unwrap();
+        let changed = tokio::time::timeout(Duration::from_millis(200), state.changed()).await;
+        assert!(changed.is_err());
+
+        // Valid secret wakes
+        client.send_to(b"hunter2", addr).await.unwrap();
+        let changed = tokio::time::timeout(Duration::from_secs(2), state.changed()).await;
+        assert!(changed.is_ok());
+    }
+}
@@ -1,37 +1,46 @@
+use std::future::Future;
 use std::sync::Arc;
+use std::time::Duration;
 
 use bytes::BytesMut;
-use minecraft_protocol::data::server_status::{OnlinePlayers, ServerVersion};
+use minecraft_protocol::data::server_status::{OnlinePlayer, OnlinePlayers, ServerVersion};
 use minecraft_protocol::decoder::Decoder;
 use minecraft_protocol::encoder::Encoder;
 use minecraft_protocol::version::v1_14_4::handshake::Handshake;
 use minecraft_protocol::version::v1_14_4::login::LoginStart;
-use minecraft_protocol::version::v1_20_3::status::{ServerStatus, StatusResponse};
+use minecraft_protocol::version::v1_20_3::status::{
+    PingRequest, PingResponse, ServerStatus, StatusResponse,
+};
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::WriteHalf;
 use tokio::net::TcpStream;
+use tokio::time;
+use uuid::Uuid;
 
-use crate::config::{Config, Server as ConfigServer};
+use crate::config::{Config, FullAction, Server as ConfigServer, StatusVersion};
 use crate::join;
 use crate::mc::favicon;
-use crate::proto::action;
+use crate::monitor;
+use crate::persist;
 use crate::proto::client::{Client, ClientInfo, ClientState};
+use crate::proto::legacy;
 use crate::proto::packet::{self, RawPacket};
 use crate::proto::packets;
+use crate::rejection::{self, Rejection};
 use crate::server::{self, Server};
-
-/// The ban message prefix.
-const BAN_MESSAGE_PREFIX: &str = "Your IP address is banned from this server.\nReason: ";
-
-/// Default ban reason if unknown.
-const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
-
-/// The not-whitelisted kick message.
-const WHITELIST_MESSAGE: &str = "You are not white-listed on this server!";
+use crate::service;
+use crate::types;
 
 /// Server icon file path.
 const SERVER_ICON_FILE: &str = "server-icon.png";
 
+/// Maximum size of a handshake packet when peeking it.
+const HANDSHAKE_PEEK_SIZE: usize = 512;
+
+/// Interval to peek again at a handshake that is not complete yet.
+const HANDSHAKE_PEEK_INTERVAL: Duration = Duration::from_millis(10);
+
 /// Proxy the given inbound stream to a target address.
 // TODO: do not drop error here, return Box<dyn Error>
 pub async fn serve(
@@ -39,7 +48,24 @@ pub async fn serve(
     mut inbound: TcpStream,
     config: Arc<Config>,
     server: Arc<Server>,
+    permit: service::server::Permit,
 ) -> Result<(), ()> {
+    // Respond to legacy ping from old clients
+    let mut peek = [0u8; 2];
+    let read = match handshake_timeout(&config, inbound.peek(&mut peek)).await {
+        Some(read) => read.map_err(|_| ())?,
+        None => return Ok(()),
+    };
+    if legacy::is_ping(&peek[..read]) {
+        debug!(target: "lazymc", "Got legacy ping from client, responding with legacy status");
+        let status = client_status(&client, &ClientInfo::empty(), &config, &server).await;
+        inbound
+            .write_all(&legacy::status_response(&peek[..read], &status))
+            .await
+            .map_err(|_| ())?;
+        return Ok(());
+    }
+
     let (mut reader, mut writer) = inbound.split();
 
     // Incoming buffer and packet holding queue
@@ -50,8 +76,17 @@ pub async fn serve(
     let mut client_info = ClientInfo::empty();
 
     loop {
-        // Read packet from stream
-        let (packet, raw) = match packet::read_packet(&client, &mut buf, &mut reader).await {
+        // Read packet from stream, client must send handshake in time
+        let read = packet::read_packet(&client, &mut buf, &mut reader);
+        let read = if client.state() == ClientState::Handshake {
+            match handshake_timeout(&config, read).await {
+                Some(read) => read,
+                None => break,
+            }
+        } else {
+            read.await
+        };
+        let (packet, raw) = match read {
             Ok(Some(packet)) => packet,
             Ok(None) => break,
             Err(_) => {
@@ -97,12 +132,21 @@ pub async fn serve(
                 inbound_history.extend(raw);
             }
 
+            // Server list pings only wake the server if configured, joining players always do
+            if new_state == ClientState::Status
+                && config.advanced.wake_on_status
+                && may_wake_on_status(&client, &config, &server).await
+            {
+                Server::start(config.clone(), server.clone(), None).await;
+            }
+
             continue;
         }
 
         // Hijack server status packet
         if client_state == ClientState::Status && packet.id == packets::status::SERVER_STATUS {
-            let server_status = server_status(&client_info, &config, &server).await;
+            refresh_status_cache(&config, &server).await;
+            let server_status = client_status(&client, &client_info, &config, &server).await;
             let packet = StatusResponse { server_status };
 
             let mut data = Vec::new();
@@ -116,7 +160,7 @@ pub async fn serve(
 
         // Hijack ping packet
         if client_state == ClientState::Status && packet.id == packets::status::SERVER_PING {
-            writer.write_all(&raw).await.map_err(|_| ())?;
+            respond_ping(&client, &config, &packet, &mut writer).await?;
             continue;
         }
 
@@ -129,50 +173,131 @@ pub async fn serve(
                 .map(|p| p.name);
             client_info.username = username.clone();
 
-            // Kick if lockout is enabled
-            if config.lockout.enabled {
+            // Kick if shutting down
+            if server.is_draining() {
+                info!(target: "lazymc", "Kicked player because lazymc is shutting down");
+                rejection::reject(&client, &config, &Rejection::Draining, &mut writer).await?;
+                break;
+            }
+
+            // Kick if locked out
+            if config.lockout.is_locked_out(&client.peer.ip()) {
                 match username {
                     Some(username) => {
                         info!(target: "lazymc", "Kicked '{}' because lockout is enabled", username)
                     }
                     None => info!(target: "lazymc", "Kicked player because lockout is enabled"),
                 }
-                action::kick(&client, &config.lockout.message, &mut writer).await?;
+                rejection::reject(&client, &config, &Rejection::Lockout, &mut writer).await?;
+                break;
+            }
+
+            // Kick if under maintenance
+            if config.maintenance.applies_to(&client.peer.ip()) {
+                match username {
+                    Some(username) => {
+                        info!(target: "lazymc", "Kicked '{}' because of maintenance", username)
+                    }
+                    None => info!(target: "lazymc", "Kicked player because of maintenance"),
+                }
+                rejection::reject(&client, &config, &Rejection::Maintenance, &mut writer).await?;
                 break;
             }
 
             // Kick if client is banned
             if let Some(ban) = server.ban_entry(&client.peer.ip()).await {
                 if ban.is_banned() {
-                    let msg = if let Some(reason) = ban.reason {
-                        info!(target: "lazymc", "Login from banned IP {} ({}), disconnecting", client.peer.ip(), &reason);
-                        reason.to_string()
-                    } else {
-                        info!(target: "lazymc", "Login from banned IP {}, disconnecting", client.peer.ip());
-                        DEFAULT_BAN_REASON.to_string()
-                    };
-                    action::kick(&client, &format!("{BAN_MESSAGE_PREFIX}{msg}"), &mut writer)
-                        .await?;
+                    match ban.reason {
+                        Some(ref reason) => {
+                            info!(target: "lazymc", "Login from banned IP {} ({}), disconnecting", client.peer.ip(), reason)
+                        }
+                        None => {
+                            info!(target: "lazymc", "Login from banned IP {}, disconnecting", client.peer.ip())
+                        }
+                    }
+                    let rejection = Rejection::Banned(ban.reason);
+                    rejection::reject(&client, &config, &rejection, &mut writer).await?;
+                    break;
+                }
+            }
+
+            // Proxy to server if already online, status server only served cached status
+            // The server handles the login itself, and kicks with its own reason if it is full
+            if server.state() == server::State::Started {
+                if config.join.full_action == FullAction::Passthrough || !server.is_full().await {
+                    let mut queue =
+                        BytesMut::with_capacity(inbound_history.len() + raw.len() + buf.len());
+                    queue.extend(&inbound_history);
+                    queue.extend(&raw);
+                    queue.extend(&buf);
+                    service::server::route_proxy_queue(inbound, config, client.peer, queue, permit);
+                    return Ok(());
+                }
+
+                match username {
+                    Some(ref username) => {
+                        info!(target: "lazymc", "Server is full, queueing '{}'", username)
+                    }
+                    None => info!(target: "lazymc", "Server is full, queueing player"),
+                }
+            }
+
+            // Kick if player is banned, before waking the server
+            if let Some(ref username) = username {
+                if let Some(ban) = server.banned_player(username).await {
+                    match ban.reason {
+                        Some(ref reason) => {
+                            info!(target: "lazymc", "Login from banned player '{}' ({}), disconnecting", username, reason)
+                        }
+                        None => {
+                            info!(target: "lazymc", "Login from banned player '{}', disconnecting", username)
+                        }
+                    }
+                    let rejection = Rejection::BannedPlayer(ban.reason);
+                    rejection::reject(&client, &config, &rejection, &mut writer).await?;
                     break;
                 }
             }
 
-            // Kick if client is not whitelisted to wake server
+            // Kick if server would reject client anyway, don't wake it
             if let Some(ref username) = username {
-                if !server.is_whitelisted(username).await {
-                    info!(target: "lazymc", "User '{}' tried to wake server but is not whitelisted, disconnecting", username);
-                    action::kick(&client, WHITELIST_MESSAGE, &mut writer).await?;
+                if let Some(rejection) = server.wake_rejection(&config, username).await {
+                    match rejection {
+                        Rejection::NotWhitelisted => {
+                            server.record_blocked_wake(username, client.peer.ip()).await
+                        }
+                        Rejection::Held => {
+                            info!(target: "lazymc", "User '{}' tried to wake server but it is held after failing to start, disconnecting", username)
+                        }
+                        _ => {
+                            info!(target: "lazymc", "User '{}' tried to wake server but it accepts no players, disconnecting", username)
+                        }
+                    }
+                    rejection::reject(&client, &config, &rejection, &mut writer).await?;
                     break;
                 }
             }
 
             // Start server if not starting yet
-            Server::start(config.clone(), server.clone(), username).await;
+            server.update_last_join().await;
+            client_info.woke = Server::start(config.clone(), server.clone(), username).await;
 
             // Remember inbound packets
             inbound_history.extend(&raw);
             inbound_history.extend(&buf);
 
+            // Proxy to server right away if it resumed from being frozen
+            if client_info.woke && server.state() == server::State::Started {
+                service::server::route_proxy_queue(
+                    inbound,
+                    config,
+                    client.peer,
+                    inbound_history,
+                    permit,
+                );
+                return Ok(());
+            }
+
             // Build inbound packet queue with everything from login start (including this)
             let mut login_queue = BytesMut::with_capacity(raw.len() + buf.len());
             login_queue.extend(&raw);
@@ -190,6 +315,7 @@ pub async fn serve(
                 inbound,
                 inbound_history,
                 login_queue,
+                permit,
             )
             .await?;
             return Ok(());
@@ -204,6 +330,161 @@ pub async fn serve(
     Ok(())
 }
 
+/// Whether a client requesting status may wake the server.
+///
+/// Locked out, banned and clients under maintenance never wake the server.
+async fn may_wake_on_status(client: &Client, config: &Config, server: &Server) -> bool {
+    let ip = client.peer.ip();
+    if config.lockout.is_locked_out(&ip) || config.maintenance.applies_to(&ip) {
+        return false;
+    }
+    !server
+        .ban_entry(&ip)
+        .await
+        .map(|ban| ban.is_banned())
+        .unwrap_or(false)
+}
+
+/// Await the given future with the configured handshake timeout.
+///
+/// Returns `None` if the timeout was reached.
+async fn handshake_timeout<F: Future>(config: &Config, future: F) -> Option<F::Output> {
+    if config.advanced.handshake_timeout == 0 {
+        return Some(future.await);
+    }
+
+    let timeout = Duration::from_secs(config.advanced.handshake_timeout as u64);
+    match time::timeout(timeout, future).await {
+        Ok(output) => Some(output),
+        Err(_) => {
+            debug!(target: "lazymc", "Client did not send handshake in time, disconnecting");
+            None
+        }
+    }
+}
+
+/// Peek the state the client switches to with its handshake, without consuming it.
+///
+/// `None` if the client doesn't send a valid handshake in time, such as for a legacy ping.
+pub async fn peek_next_state(config: &Config, inbound: &TcpStream) -> Option<ClientState> {
+    let peek = async {
+        let mut buf = [0u8; HANDSHAKE_PEEK_SIZE];
+        loop {
+            let read = inbound.peek(&mut buf).await.ok()?;
+            if read == 0 || legacy::is_ping(&buf[..read]) {
+                return None;
+            }
+
+            match types::read_var_int(&buf[..read]) {
+                Ok((size, len)) => {
+                    let end = size.checked_add(usize::try_from(len).ok()?)?;
+                    if end > buf.len() {
+                        return None;
+                    }
+                    if end <= read {
+                        let packet =
+                            RawPacket::decode_with_len(&Client::dummy(), &buf[..end]).ok()?;
+                        if packet.id != packets::handshake::SERVER_HANDSHAKE {
+                            return None;
+                        }
+                        let handshake = Handshake::decode(&mut packet.data.as_slice()).ok()?;
+                        return ClientState::from_id(handshake.next_state);
+                    }
+                }
+                // Packet length may not be complete yet, a var-int is at most 5 bytes
+                Err(()) if read < 5 => {}
+                Err(()) => return None,
+            }
+
+            time::sleep(HANDSHAKE_PEEK_INTERVAL).await;
+        }
+    };
+
+    if config.advanced.handshake_timeout == 0 {
+        return peek.await;
+    }
+    let timeout = Duration::from_secs(config.advanced.handshake_timeout as u64);
+    time::timeout(timeout, peek).await.ok().flatten()
+}
+
+/// Respond to client ping request, echoing its token.
+///
+/// Delays response by configured synthetic latency.
+async fn respond_ping(
+    client: &Client,
+    config: &Config,
+    packet: &RawPacket,
+    writer: &mut WriteHalf<'_>,
+) -> Result<(), ()> {
+    let ping = PingRequest::decode(&mut packet.data.as_slice()).map_err(|_| {
+        debug!(target: "lazymc", "Got malformed ping from client, disconnecting");
+    })?;
+
+    if config.public.ping_delay_ms > 0 {
+        time::sleep(Duration::from_millis(config.public.ping_delay_ms as u64)).await;
+    }
+
+    packet::write_packet(PingResponse { time: ping.time }, client, writer).await
+}
+
+/// Refresh cached server status in the background if it is stale.
+///
+/// Only when server is online and status caching is enabled.
+async fn refresh_status_cache(config: &Arc<Config>, server: &Arc<Server>) {
+    if config.advanced.status_cache_ttl == 0 || server.state() != server::State::Started {
+        return;
+    }
+
+    let ttl = Duration::from_secs(config.advanced.status_cache_ttl as u64);
+    if !server.claim_status_refresh(ttl).await {
+        return;
+    }
+
+    trace!(target: "lazymc", "Cached server status is stale, refreshing");
+    let (config, server) = (config.clone(), server.clone());
+    tokio::spawn(async move {
+        if let Ok(Some(status)) =
+            monitor::poll_server(&config, &server, &config.server.address).await
+        {
+            server.update_status(&config, Some(status)).await;
+        }
+    });
+}
+
+/// Build server status object to respond to the given client with.
+///
+/// Shows maintenance status to clients maintenance applies to.
+async fn client_status(
+    client: &Client,
+    client_info: &ClientInfo,
+    config: &Config,
+    server: &Server,
+) -> ServerStatus {
+    let status = server_status(client_info, config, server).await;
+    if config.maintenance.applies_to(&client.peer.ip()) {
+        return maintenance_status(config, status);
+    }
+    status
+}
+
+/// Turn the given server status into maintenance status.
+///
+/// Keeps the favicon and player maximum, hides online players.
+fn maintenance_status(config: &Config, mut status: ServerStatus) -> ServerStatus {
+    status.description = config.maintenance.motd.clone();
+    status.players.online = 0;
+    status.players.sample = vec![];
+
+    // Protocol never matches, so clients show the version name instead of the player count
+    if !config.maintenance.version.is_empty() {
+        status.version = ServerVersion {
+            name: config.maintenance.version.clone(),
+            protocol: 0,
+        };
+    }
+    status
+}
+
 /// Build server status object to respond to client with.
 async fn server_status(client_info: &ClientInfo, config: &Config, server: &Server) -> ServerStatus {
     let status = server.status().await;
@@ -217,14 +498,10 @@ async fn server_status(client_info: &ClientInfo, config: &Config, server: &Serve
     // Select version and player max from last known server status
     let (version, max) = match status.as_ref() {
         Some(status) => (status.version.clone(), status.players.max),
-        None => (
-            ServerVersion {
-                name: config.public.version.clone(),
-                protocol: config.public.protocol,
-            },
-            0,
-        ),
+        None => (fallback_version(config, server.cached_version().await), 0),
     };
+    let version = status_version(config, client_info.protocol(), version);
+    let version = sleeping_version(config, version);
 
     // Select description, use server MOTD if enabled, or use configured
     let description = {
@@ -235,6 +512,7 @@ async fn server_status(client_info: &ClientInfo, config: &Config, server: &Serve
                 server::State::Stopped | server::State::Started => config.motd.sleeping.clone(),
                 server::State::Starting => config.motd.starting.clone(),
                 server::State::Stopping => config.motd.stopping.clone(),
+                server::State::Frozen => config.motd.frozen.clone(),
             }
         }
     };
@@ -250,6 +528,9 @@ async fn server_status(client_info: &ClientInfo, config: &Config, server: &Serve
         }
     }
 
+    // Build player sample
+    let sample = players_sample(config, server.players_sample().await);
+
     // Build status resposne
     ServerStatus {
         version,
@@ -257,12 +538,77 @@ async fn server_status(client_info: &ClientInfo, config: &Config, server: &Serve
         players: OnlinePlayers {
             online: 0,
             max,
-            sample: vec![],
+            sample,
         },
         favicon,
     }
 }
 
+/// Version to use if there is no server status yet.
+///
+/// Prefers the version remembered from an earlier server status over the configured hint.
+fn fallback_version(config: &Config, cached: Option<persist::Version>) -> ServerVersion {
+    match cached {
+        Some(cached) => ServerVersion {
+            name: cached.name,
+            protocol: cached.protocol,
+        },
+        None => ServerVersion {
+            name: config.public.version.clone(),
+            protocol: config.public.protocol,
+        },
+    }
+}
+
+/// Select version to report according to the configured policy, given the last seen version.
+fn status_version(
+    config: &Config,
+    client_protocol: Option<u32>,
+    last_seen: ServerVersion,
+) -> ServerVersion {
+    match config.public.status_version {
+        StatusVersion::LastSeen => last_seen,
+        StatusVersion::Configured => ServerVersion {
+            name: config.public.version.clone(),
+            protocol: config.public.protocol,
+        },
+        StatusVersion::Mirror => ServerVersion {
+            protocol: client_protocol.unwrap_or(last_seen.protocol),
+            ..last_seen
+        },
+    }
+}
+
+/// Apply configured sleeping version overrides to the given version.
+fn sleeping_version(config: &Config, mut version: ServerVersion) -> ServerVersion {
+    if let Some(protocol) = config.public.sleeping_protocol {
+        version.protocol = protocol;
+    }
+    if let Some(name) = &config.public.sleeping_version_name {
+        version.name = name.clone();
+    }
+    version
+}
+
+/// Build player sample for status while server is not online.
+///
+/// Uses last known online players if enabled and known, configured lines otherwise.
+fn players_sample(config: &Config, last_known: Vec<OnlinePlayer>) -> Vec<OnlinePlayer> {
+    if config.motd.sample_from_server && !last_known.is_empty() {
+        return last_known;
+    }
+
+    config
+        .motd
+        .sample
+        .iter()
+        .map(|line| OnlinePlayer {
+            name: line.clone(),
+            id: Uuid::nil(),
+        })
+        .collect()
+}
+
 /// Get server status favicon.
 ///
 /// This always returns a favicon, returning the default one if none is set.
@@ -287,3 +633,643 @@ async fn server_favicon(config: &Config) -> String {
 
     favicon::encode_favicon(&data)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::net::SocketAddr;
+
+    use tokio::io::AsyncReadExt;
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::mc::{ban, whitelist};
+    use crate::proto;
+    use crate::util::test::{handshake_packet, login_packets};
+
+    fn player(name: &str) -> OnlinePlayer {
+        OnlinePlayer {
+            name: name.into(),
+            id: Uuid::nil(),
+        }
+    }
+
+    fn version() -> ServerVersion {
+        ServerVersion {
+            name: "1.20.3".into(),
+            protocol: 765,
+        }
+    }
+
+    #[test]
+    fn test_sleeping_version_override() {
+        let config: Config = crate::util::test::config(
+            r#"
+            [public]
+            sleeping_protocol = 0
+            sleeping_version_name = "Click to wake"
+            "#,
+        );
+
+        let version = sleeping_version(&config, version());
+        assert_eq!(version.protocol, 0);
+        assert_eq!(version.name, "Click to wake");
+
+        // Real version hint is left untouched
+        assert_eq!(config.public.protocol, proto::PROTO_DEFAULT_PROTOCOL);
+        assert_eq!(config.public.version, proto::PROTO_DEFAULT_VERSION);
+    }
+
+    #[test]
+    fn test_sleeping_version_default() {
+        let config: Config = crate::util::test::config("");
+
+        let version = sleeping_version(&config, version());
+        assert_eq!(version.protocol, 765);
+        assert_eq!(version.name, "1.20.3");
+    }
+
+    /// Config with the given status version policy, and configured version 1.20.1.
+    fn status_version_config(policy: &str) -> Config {
+        crate::util::test::config(&format!(
+            "[public]\nversion = \"1.20.1\"\nprotocol = 763\nstatus_version = \"{policy}\""
+        ))
+    }
+
+    #[test]
+    fn test_status_version_last_seen() {
+        let config = status_version_config("last_seen");
+        let status = status_version(&config, Some(767), version());
+        assert_eq!((status.name.as_str(), status.protocol), ("1.20.3", 765));
+    }
+
+    #[test]
+    fn test_status_version_configured() {
+        let config = status_version_config("configured");
+        let status = status_version(&config, Some(767), version());
+        assert_eq!((status.name.as_str(), status.protocol), ("1.20.1", 763));
+    }
+
+    #[test]
+    fn test_status_version_mirror() {
+        let config = status_version_config("mirror");
+        let status = status_version(&config, Some(767), version());
+        assert_eq!((status.name.as_str(), status.protocol), ("1.20.3", 767));
+
+        // Falls back to last seen version without handshake, as with legacy pings
+        let status = status_version(&config, None, version());
+        assert_eq!(status.protocol, 765);
+    }
+
+    #[tokio::test]
+    async fn test_ping_token_roundtrip() {
+        let config: Config = crate::util::test::config("[public]\nping_delay_ms = 100");
+        let client = Client::dummy();
+        let token = 0x0123_4567_89ab_cdef;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut outbound = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (mut inbound, _) = listener.accept().await.unwrap();
+
+        let mut data = Vec::new();
+        PingRequest { time: token }.encode(&mut data).unwrap();
+        let request = RawPacket::new(packets::status::SERVER_PING, data);
+
+        let start = std::time::Instant::now();
+        respond_ping(&client, &config, &request, &mut inbound.split().1)
+            .await
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        let (response, _) = packet::read_packet(&client, &mut buf, &mut outbound.split().0)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        assert_eq!(response.id, packets::status::CLIENT_PING);
+        let ping = PingResponse::decode(&mut response.data.as_slice()).unwrap();
+        assert_eq!(ping.time, token);
+    }
+
+    #[tokio::test]
+    async fn test_status_cache_refresh() {
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+        let ttl = Duration::from_millis(100);
+
+        // Refresh if status is not known yet
+        assert!(server.claim_status_refresh(ttl).await);
+
+        let status = ServerStatus {
+            version: version(),
+            players: OnlinePlayers {
+                online: 0,
+                max: 20,
+                sample: vec![],
+            },
+            description: "A Minecraft Server".into(),
+            favicon: None,
+        };
+        server.update_status(&config, Some(status)).await;
+
+        // Cached status is used within TTL
+        assert!(!server.claim_status_refresh(ttl).await);
+
+        // Refresh once TTL has passed, only once
+        tokio::time::sleep(ttl).await;
+        assert!(server.claim_status_refresh(ttl).await);
+        assert!(!server.claim_status_refresh(ttl).await);
+    }
+
+    #[test]
+    fn test_players_sample_configured() {
+        let config: Config = crate::util::test::config(
+            r#"
+            [motd]
+            sample = ["Join to start", "the server"]
+            "#,
+        );
+
+        let sample = players_sample(&config, vec![player("Steve")]);
+        let names: Vec<_> = sample.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Join to start", "the server"]);
+    }
+
+    #[test]
+    fn test_players_sample_from_server() {
+        let config: Config = crate::util::test::config(
+            r#"
+            [motd]
+            sample = ["Join to start"]
+            sample_from_server = true
+            "#,
+        );
+
+        let sample = players_sample(&config, vec![player("Steve"), player("Alex")]);
+        let names: Vec<_> = sample.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["Steve", "Alex"]);
+
+        // Fall back to configured lines if no players are known
+        let sample = players_sample(&config, vec![]);
+        assert_eq!(sample[0].name, "Join to start");
+    }
+
+    #[test]
+    fn test_fallback_version_cached() {
+        let config: Config = crate::util::test::config("");
+
+        let fallback = fallback_version(&config, None);
+        assert_eq!(fallback.name, config.public.version);
+        assert_eq!(fallback.protocol, config.public.protocol);
+
+        let cached = persist::Version {
+            name: "1.19.4".into(),
+            protocol: 762,
+        };
+        let fallback = fallback_version(&config, Some(cached));
+        assert_eq!(fallback.name, "1.19.4");
+        assert_eq!(fallback.protocol, 762);
+    }
+
+    #[tokio::test]
+    async fn test_sleeping_status_cached_version() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-status-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = persist::State {
+            version: Some(persist::Version {
+                name: "1.19.4".into(),
+                protocol: 762,
+            }),
+            ..Default::default()
+        };
+        persist::save(&dir.join(persist::FILE), &state).unwrap();
+
+        let config: Config =
+            crate::util::test::config(&format!("directory = {:?}", dir.display().to_string()));
+        let (server, _) = Server::new();
+        server.load_persistent(&config).await;
+
+        let status = server_status(&ClientInfo::empty(), &config, &server).await;
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(status.version.name, "1.19.4");
+        assert_eq!(status.version.protocol, 762);
+    }
+
+    #[tokio::test]
+    async fn test_frozen_motd() {
+        let config: Config = crate::util::test::config("");
+        let (server, _) = Server::new();
+
+        let status = server_status(&ClientInfo::empty(), &config, &server).await;
+        assert_eq!(status.description, config.motd.sleeping);
+
+        server.update_state(server::State::Frozen, &config).await;
+        let status = server_status(&ClientInfo::empty(), &config, &server).await;
+        assert_eq!(status.description, config.motd.frozen);
+    }
+
+    #[test]
+    fn test_maintenance_status() {
+        let config: Config = crate::util::test::config("[maintenance]\nenabled = true");
+        let status = ServerStatus {
+            version: version(),
+            description: "A Minecraft Server".into(),
+            players: OnlinePlayers {
+                online: 3,
+                max: 20,
+                sample: vec![player("Steve")],
+            },
+            favicon: None,
+        };
+
+        let status = maintenance_status(&config, status);
+        assert_eq!(status.description, config.maintenance.motd);
+        assert_eq!(status.version.name, "Maintenance");
+        assert_eq!(status.version.protocol, 0);
+        assert_eq!(status.players.online, 0);
+        assert_eq!(status.players.max, 20);
+        assert!(status.players.sample.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_kick() {
+        let config: Config = crate::util::test::config(
+            "[maintenance]\nenabled = true\nmessage = \"Down for maintenance\"",
+        );
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Joining player is kicked, and does not wake the server
+        let (mut client, serve) = serve_local_server(Arc::new(config), server.clone()).await;
+        client.write_all(&login_packets("Steve")).await.unwrap();
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(serve.await.unwrap().is_ok());
+        assert!(String::from_utf8_lossy(&response).contains("Down for maintenance"));
+        assert_eq!(server.state(), server::State::Stopped);
+    }
+
+    /// Serve status to a single connection from a local client.
+    async fn serve_local(config: Config) -> (TcpStream, tokio::task::JoinHandle<Result<(), ()>>) {
+        let (server, _) = Server::new();
+        serve_local_server(Arc::new(config), Arc::new(server)).await
+    }
+
+    /// Serve status to a single connection from a local client, with the given server.
+    async fn serve_local_server(
+        config: Arc<Config>,
+        server: Arc<Server>,
+    ) -> (TcpStream, tokio::task::JoinHandle<Result<(), ()>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (inbound, peer) = listener.accept().await.unwrap();
+        let permit = crate::util::test::permit();
+        let serve = tokio::spawn(serve(Client::new(peer), inbound, config, server, permit));
+        (client, serve)
+    }
+
+    #[tokio::test]
+    async fn test_peek_next_state() {
+        let config: Config = crate::util::test::config("");
+        let cases = [
+            (
+                handshake_packet(ClientState::Status),
+                Some(ClientState::Status),
+            ),
+            (login_packets("Steve"), Some(ClientState::Login)),
+            (vec![legacy::PING, 0x01, 0xFA], None),
+        ];
+        for (data, state) in cases {
+            let (mut client, mut inbound) = crate::util::test::stream_pair().await;
+
+            // Handshake may arrive in parts
+            let sent = data[..3].to_vec();
+            client.write_all(&sent).await.unwrap();
+            let write = tokio::spawn(async move {
+                time::sleep(Duration::from_millis(50)).await;
+                client.write_all(&data[3..]).await.unwrap();
+                client
+            });
+            assert_eq!(peek_next_state(&config, &inbound).await, state);
+
+            // Handshake is left for the service the connection is routed to
+            let _client = write.await.unwrap();
+            let mut buf = [0u8; 3];
+            inbound.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf.as_slice(), sent);
+        }
+    }
+
+    /// Start server that is online and full, with the given join config.
+    async fn full_server(join: &str, address: SocketAddr) -> (Arc<Config>, Arc<Server>) {
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "address = \"{}\"\ndirectory = {:?}\n[join]\n{}",
+            address,
+            std::env::temp_dir(),
+            join,
+        )));
+        let (server, _) = Server::new();
+        let status = ServerStatus {
+            version: version(),
+            players: OnlinePlayers {
+                online: 20,
+                max: 20,
+                sample: vec![],
+            },
+            description: "A Minecraft Server".into(),
+            favicon: None,
+        };
+        server.update_status(&config, Some(status)).await;
+        assert!(server.is_full().await);
+        (config, Arc::new(server))
+    }
+
+    #[tokio::test]
+    async fn test_full_server_kick_passthrough() {
+        // Backend that kicks every login because it is full
+        let backend = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let kick = RawPacket::new(0, br#"{"text":"The server is full!"}"#.to_vec())
+            .encode_with_len(&Client::dummy())
+            .unwrap();
+        let backend_kick = kick.clone();
+        let backend_addr = backend.local_addr().unwrap();
+        let backend = tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let mut login = vec![0u8; login_packets("player").len()];
+            stream.read_exact(&mut login).await.unwrap();
+            stream.write_all(&backend_kick).await.unwrap();
+            login
+        });
+
+        let (config, server) = full_server("", backend_addr).await;
+        let (mut client, _) = serve_local_server(config, server).await;
+        client.write_all(&login_packets("player")).await.unwrap();
+
+        // Login reaches the server, and its kick is relayed to the client unchanged
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, kick);
+        assert_eq!(backend.await.unwrap(), login_packets("player"));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_holds_permit() {
+        // Backend that keeps the connection open until the client disconnects
+        let backend = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let backend_addr = backend.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = backend.accept().await.unwrap();
+            let _ = stream.read_to_end(&mut Vec::new()).await;
+        });
+
+        let (config, server) = full_server("", backend_addr).await;
+        let limit = Arc::new(Semaphore::new(1));
+        let permit = Arc::new(limit.clone().try_acquire_owned().unwrap());
+        let (mut client, inbound) = crate::util::test::stream_pair().await;
+        let peer = inbound.peer_addr().unwrap();
+        let serve = tokio::spawn(serve(Client::new(peer), inbound, config, server, permit));
+        client.write_all(&login_packets("player")).await.unwrap();
+
+        // Connection is still counted while proxied, after the status server handed it off
+        assert!(serve.await.unwrap().is_ok());
+        assert_eq!(limit.available_permits(), 0);
+
+        // Permit is released once the proxied connection closes
+        drop(client);
+        time::timeout(Duration::from_secs(5), limit.acquire())
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_blocked_wake_recorded() {
+        let dir =
+            std::env::temp_dir().join(format!("lazymc-test-whitelist-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join(whitelist::WHITELIST_FILE),
+            r#"[{"name": "allowed", "uuid": null}]"#,
+        )
+        .unwrap();
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "directory = {:?}\n[join]\nmethods = [\"kick\"]",
+            dir,
+        )));
+        let (server, _) = Server::new();
+        server
+            .set_whitelist(Some(whitelist::load_dir(&dir).unwrap()))
+            .await;
+        let server = Arc::new(server);
+
+        for (username, blocked) in [("intruder", 1), ("allowed", 1)] {
+            let (mut client, serve) = serve_local_server(config.clone(), server.clone()).await;
+            client.write_all(&login_packets(username)).await.unwrap();
+            let mut response = Vec::new();
+            time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(serve.await.unwrap().is_ok());
+            assert_eq!(server.stats().await.blocked_wakes, blocked);
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Write banned IPs file banning localhost to the given directory, and load it.
+    fn ban_localhost(dir: &std::path::Path) -> ban::BannedIps {
+        fs::create_dir_all(dir).unwrap();
+        let path = dir.join(ban::FILE);
+        fs::write(
+            &path,
+            r#"[{"ip": "127.0.0.1", "created": "", "source": "", "reason": "griefing"}]"#,
+        )
+        .unwrap();
+        ban::load(&path).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_banned_message() {
+        let dir = std::env::temp_dir().join(format!("lazymc-test-banned-{}", std::process::id()));
+        let config: Arc<Config> = Arc::new(crate::util::test::config(
+            "banned_message = \"Go away: {reason}\"",
+        ));
+        let (server, _) = Server::new();
+        server.set_banned_ips(ban_localhost(&dir)).await;
+        let _ = fs::remove_dir_all(&dir);
+
+        // Banned client is kicked with the configured message
+        let (mut client, serve) = serve_local_server(config, Arc::new(server)).await;
+        client.write_all(&login_packets("griefer")).await.unwrap();
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(serve.await.unwrap().is_ok());
+        assert!(String::from_utf8_lossy(&response).contains("Go away: griefing"));
+    }
+
+    #[tokio::test]
+    async fn test_banned_player() {
+        let dir =
+            std::env::temp_dir().join(format!("lazymc-test-banned-player-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ban::PLAYERS_FILE);
+        fs::write(
+            &path,
+            r#"[{"uuid": null, "name": "Griefer", "expires": "forever", "reason": "griefing"}]"#,
+        )
+        .unwrap();
+        let config: Arc<Config> = Arc::new(crate::util::test::config(&format!(
+            "directory = {:?}\nblock_banned_players = true\n[join]\nmethods = [\"kick\"]",
+            dir,
+        )));
+        let (server, _) = Server::new();
+        server
+            .set_banned_players(ban::load_players(&path).unwrap())
+            .await;
+        let server = Arc::new(server);
+
+        // Banned player is kicked with the player ban message as JSON text, others wake the server
+        let banned = r"You are banned from this server.\nReason: griefing";
+        for (username, expected) in [("griefer", banned), ("Steve", "Server is starting")] {
+            let (mut client, serve) = serve_local_server(config.clone(), server.clone()).await;
+            client.write_all(&login_packets(username)).await.unwrap();
+            let mut response = Vec::new();
+            time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+                .await
+                .unwrap()
+                .unwrap();
+            assert!(serve.await.unwrap().is_ok());
+            assert!(String::from_utf8_lossy(&response).contains(expected));
+        }
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_timeout() {
+        let config: Config = crate::util::test::config("[advanced]\nhandshake_timeout = 1");
+        let (mut client, serve) = serve_local(config).await;
+
+        // Connection is dropped once the timeout is reached
+        let result = time::timeout(Duration::from_secs(5), serve).await;
+        assert!(result.expect("connection not dropped").unwrap().is_ok());
+        let mut buf = [0u8; 1];
+        assert_eq!(client.read(&mut buf).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_handshake_in_time() {
+        let config: Config = crate::util::test::config("[advanced]\nhandshake_timeout = 1");
+        let (mut client, serve) = serve_local(config).await;
+
+        // Legacy ping sent in time is answered
+        client.write_all(&[legacy::PING, 0x01]).await.unwrap();
+        let mut buf = [0u8; 1];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf[0], 0xFF);
+        assert!(serve.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_status_handshake_no_wake() {
+        let config: Arc<Config> =
+            Arc::new(crate::util::test::config("[join]\nmethods = [\"kick\"]"));
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Server list ping doesn't wake the server
+        let (mut client, serve) = serve_local_server(config.clone(), server.clone()).await;
+        client
+            .write_all(&handshake_packet(ClientState::Status))
+            .await
+            .unwrap();
+        client.shutdown().await.unwrap();
+        assert!(serve.await.unwrap().is_ok());
+        assert_eq!(server.stats().await.wakes, 0);
+
+        // Joining player does
+        let (mut client, serve) = serve_local_server(config, server.clone()).await;
+        client.write_all(&login_packets("Steve")).await.unwrap();
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(serve.await.unwrap().is_ok());
+        assert_eq!(server.stats().await.wakes, 1);
+    }
+
+    #[tokio::test]
+    async fn test_status_handshake_wake_on_status() {
+        let config: Arc<Config> = Arc::new(crate::util::test::config(
+            "[advanced]\nwake_on_status = true",
+        ));
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        let (mut client, serve) = serve_local_server(config, server.clone()).await;
+        client
+            .write_all(&handshake_packet(ClientState::Status))
+            .await
+            .unwrap();
+        client.shutdown().await.unwrap();
+        assert!(serve.await.unwrap().is_ok());
+        assert_eq!(server.stats().await.wakes, 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_first_joiner_held() {
+        let dir =
+            std::env::temp_dir().join(format!("lazymc-test-first-joiner-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config: Arc<Config> = Arc::new(
+            toml::from_str(&format!(
+                "[server]\ncommand = \"sleep 30\"\ndirectory = {:?}\n[join]\nmethods = [\"kick\"]\n[join.hold]\nfirst_joiner = true",
+                dir,
+            ))
+            .unwrap(),
+        );
+        let (server, _) = Server::new();
+        let server = Arc::new(server);
+
+        // Player waking the server is held
+        let (mut first, _serve) = serve_local_server(config.clone(), server.clone()).await;
+        first.write_all(&login_packets("Steve")).await.unwrap();
+        time::sleep(Duration::from_millis(200)).await;
+        let mut buf = [0u8; 1];
+        assert!(
+            time::timeout(Duration::from_millis(300), first.read(&mut buf))
+                .await
+                .is_err()
+        );
+
+        // Player joining while starting is kicked
+        let (mut second, serve) = serve_local_server(config, server.clone()).await;
+        second.write_all(&login_packets("Alex")).await.unwrap();
+        let mut response = Vec::new();
+        time::timeout(Duration::from_secs(5), second.read_to_end(&mut response))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(serve.await.unwrap().is_ok());
+        assert!(String::from_utf8_lossy(&response).contains("Server is starting"));
+
+        server.force_kill(&config).await;
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
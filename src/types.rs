@@ -27,3 +27,11 @@ pub fn encode_var_int(i: i32) -> Result<Vec<u8>, ()> {
     minecraft_protocol::encoder::var_int::encode(&i, &mut buf).map_err(|_| ())?;
     Ok(buf)
 }
+
+/// Encode string, prefixed with its length as var-int.
+#[cfg(feature = "lobby")]
+pub fn encode_string(s: &str) -> Result<Vec<u8>, ()> {
+    let mut buf = encode_var_int(s.len() as i32)?;
+    buf.extend_from_slice(s.as_bytes());
+    Ok(buf)
+}
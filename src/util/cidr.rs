@@ -0,0 +1,145 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use serde::de::{Error, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// IP network in CIDR notation, such as `192.168.0.0/16`.
+///
+/// A plain IP address is parsed as a network of just that address.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Cidr {
+    /// Network address.
+    addr: IpAddr,
+
+    /// Network prefix length in bits.
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Check whether the given IP is part of this network.
+    ///
+    /// IPv4-mapped IPv6 addresses, as seen on dual-stack listeners, match IPv4 networks.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let ip = match ip {
+            IpAddr::V6(ip) => ip
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(IpAddr::V6(*ip)),
+            ip => *ip,
+        };
+
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+
+        let addr: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid IP address '{}'", addr))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => match prefix.trim().parse() {
+                Ok(prefix) if prefix <= max => prefix,
+                _ => return Err(format!("invalid network prefix length '{}'", prefix)),
+            },
+            None => max,
+        };
+
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let cidr = String::deserialize(d)?;
+        cidr.parse()
+            .map_err(|_| Error::invalid_value(Unexpected::Str(&cidr), &"IP address or CIDR"))
+    }
+}
+
+impl Serialize for Cidr {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.collect_str(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_contains_ipv4() {
+        let cidr: Cidr = "192.168.0.0/16".parse().unwrap();
+        assert!(cidr.contains(&ip("192.168.1.2")));
+        assert!(!cidr.contains(&ip("192.169.0.1")));
+        assert!(!cidr.contains(&ip("::1")));
+
+        let all: Cidr = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(&ip("1.2.3.4")));
+    }
+
+    #[test]
+    fn test_cidr_contains_ipv6() {
+        let cidr: Cidr = "2001:db8::/32".parse().unwrap();
+        assert!(cidr.contains(&ip("2001:db8::1")));
+        assert!(!cidr.contains(&ip("2001:db9::1")));
+    }
+
+    #[test]
+    fn test_cidr_single_address() {
+        let cidr: Cidr = "10.0.0.1".parse().unwrap();
+        assert_eq!(cidr.to_string(), "10.0.0.1/32");
+        assert!(cidr.contains(&ip("10.0.0.1")));
+        assert!(!cidr.contains(&ip("10.0.0.2")));
+    }
+
+    #[test]
+    fn test_cidr_contains_ipv4_mapped() {
+        let cidr: Cidr = "127.0.0.0/8".parse().unwrap();
+        assert!(cidr.contains(&ip("::ffff:127.0.0.1")));
+    }
+
+    #[test]
+    fn test_cidr_invalid() {
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+        assert!("10.0.0/8".parse::<Cidr>().is_err());
+        assert!("::/129".parse::<Cidr>().is_err());
+    }
+}
@@ -1,7 +1,14 @@
+pub mod cidr;
 pub mod cli;
 pub mod error;
+pub mod schedule;
 pub mod serde;
+pub mod socks5;
+pub mod srv;
 pub mod style;
+#[cfg(test)]
+pub mod test;
+pub mod text;
 
 use std::env;
 use std::path::PathBuf;
@@ -0,0 +1,297 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDateTime, Timelike};
+use serde::de::{Error, Unexpected};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Day names, in weekday order starting on monday.
+const DAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+/// Minutes in a day.
+const DAY_MINUTES: u16 = 24 * 60;
+
+/// Recurring time window in local time, such as `mon-fri 22:00-06:00`.
+///
+/// Days are optional and default to every day. They may be a range (`mon-fri`), a list
+/// (`sat,sun`) or a combination. A window ending before it starts continues into the next day,
+/// the days apply to the day it starts on.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimeWindow {
+    /// Active days, bit 0 is monday.
+    days: u8,
+
+    /// Start in minutes since midnight.
+    start: u16,
+
+    /// End in minutes since midnight, up to and including 24:00.
+    end: u16,
+}
+
+impl TimeWindow {
+    /// Check whether the given local time is inside this window.
+    pub fn contains(&self, at: &NaiveDateTime) -> bool {
+        let day = at.weekday().num_days_from_monday();
+        let yesterday = (day + 6) % 7;
+        let minute = (at.hour() * 60 + at.minute()) as u16;
+
+        if self.start <= self.end {
+            self.on_day(day) && minute >= self.start && minute < self.end
+        } else {
+            (self.on_day(day) && minute >= self.start)
+                || (self.on_day(yesterday) && minute < self.end)
+        }
+    }
+
+    /// Whether this window starts on the given day, 0 being monday.
+    fn on_day(&self, day: u32) -> bool {
+        self.days & (1 << day) != 0
+    }
+}
+
+impl FromStr for TimeWindow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (days, times) = match s.trim().rsplit_once(' ') {
+            Some((days, times)) => (parse_days(days.trim())?, times),
+            None => (0b111_1111, s.trim()),
+        };
+
+        let (start, end) = times
+            .split_once('-')
+            .ok_or_else(|| format!("invalid time range '{}'", times))?;
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end || start == DAY_MINUTES {
+            return Err(format!("invalid time range '{}'", times));
+        }
+
+        Ok(Self { days, start, end })
+    }
+}
+
+impl fmt::Display for TimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_days(f, self.days)?;
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60,
+        )
+    }
+}
+
+impl Serialize for TimeWindow {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeWindow {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let window = String::deserialize(d)?;
+        window.parse().map_err(|_| {
+            Error::invalid_value(
+                Unexpected::Str(&window),
+                &"time window such as 'mon-fri 22:00-06:00'",
+            )
+        })
+    }
+}
+
+/// Recurring time of day in local time, such as `fri 18:00`.
+///
+/// Days are optional and default to every day, they are specified like in `TimeWindow`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct TimeOfDay {
+    /// Active days, bit 0 is monday.
+    days: u8,
+
+    /// Time in minutes since midnight.
+    minute: u16,
+}
+
+impl TimeOfDay {
+    /// Check whether the given local time is within the minute of this time of day.
+    pub fn matches(&self, at: &NaiveDateTime) -> bool {
+        let day = at.weekday().num_days_from_monday();
+        let minute = (at.hour() * 60 + at.minute()) as u16;
+        self.days & (1 << day) != 0 && minute == self.minute
+    }
+}
+
+impl FromStr for TimeOfDay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (days, time) = match s.trim().rsplit_once(' ') {
+            Some((days, time)) => (parse_days(days.trim())?, time),
+            None => (0b111_1111, s.trim()),
+        };
+
+        let minute = parse_time(time)?;
+        if minute == DAY_MINUTES {
+            return Err(format!("invalid time '{}'", time));
+        }
+
+        Ok(Self { days, minute })
+    }
+}
+
+impl fmt::Display for TimeOfDay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_days(f, self.days)?;
+        write!(f, "{:02}:{:02}", self.minute / 60, self.minute % 60)
+    }
+}
+
+impl Serialize for TimeOfDay {
+    fn serialize<S>(&self, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TimeOfDay {
+    fn deserialize<D>(d: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let time = String::deserialize(d)?;
+        time.parse().map_err(|_| {
+            Error::invalid_value(Unexpected::Str(&time), &"time of day such as 'fri 18:00'")
+        })
+    }
+}
+
+/// Write days followed by a space, such as `sat,sun `, nothing if every day.
+fn write_days(f: &mut fmt::Formatter<'_>, days: u8) -> fmt::Result {
+    if days == 0b111_1111 {
+        return Ok(());
+    }
+    let days: Vec<_> = (0..7)
+        .filter(|day| days & (1 << day) != 0)
+        .map(|day| DAYS[day])
+        .collect();
+    write!(f, "{} ", days.join(","))
+}
+
+/// Parse days, such as `mon-fri` or `sat,sun`, into a bit set.
+fn parse_days(days: &str) -> Result<u8, String> {
+    let day = |name: &str| {
+        let name = name.trim().to_lowercase();
+        DAYS.iter()
+            .position(|day| name.starts_with(day))
+            .ok_or_else(|| format!("invalid day '{}'", name))
+    };
+
+    let mut set = 0;
+    for part in days.split(',') {
+        let (from, to) = match part.split_once('-') {
+            Some((from, to)) => (day(from)?, day(to)?),
+            None => (day(part)?, day(part)?),
+        };
+
+        // Ranges may wrap around the end of the week
+        let mut d = from;
+        loop {
+            set |= 1 << d;
+            if d == to {
+                break;
+            }
+            d = (d + 1) % 7;
+        }
+    }
+    Ok(set)
+}
+
+/// Parse time of day, such as `22:00`, into minutes since midnight.
+fn parse_time(time: &str) -> Result<u16, String> {
+    let err = || format!("invalid time '{}'", time);
+    let (hour, minute) = time.trim().split_once(':').ok_or_else(err)?;
+    let hour: u16 = hour.parse().map_err(|_| err())?;
+    let minute: u16 = minute.parse().map_err(|_| err())?;
+    if minute >= 60 || hour * 60 + minute > DAY_MINUTES {
+        return Err(err());
+    }
+    Ok(hour * 60 + minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    /// Local time on the given day of january 2024, the 1st being a monday.
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_time_window_daily() {
+        let window: TimeWindow = "09:30-17:00".parse().unwrap();
+        assert!(!window.contains(&at(1, 9, 29)));
+        assert!(window.contains(&at(1, 9, 30)));
+        assert!(window.contains(&at(7, 16, 59)));
+        assert!(!window.contains(&at(7, 17, 0)));
+    }
+
+    #[test]
+    fn test_time_window_overnight_days() {
+        let window: TimeWindow = "mon-fri 22:00-06:00".parse().unwrap();
+        assert_eq!(window.to_string(), "mon,tue,wed,thu,fri 22:00-06:00");
+
+        // Friday night into saturday morning
+        assert!(window.contains(&at(5, 23, 0)));
+        assert!(window.contains(&at(6, 5, 59)));
+        assert!(!window.contains(&at(6, 6, 0)));
+
+        // Not saturday night into sunday, nor monday morning
+        assert!(!window.contains(&at(6, 23, 0)));
+        assert!(!window.contains(&at(7, 23, 0)));
+        assert!(!window.contains(&at(1, 3, 0)));
+    }
+
+    #[test]
+    fn test_time_of_day() {
+        let time: TimeOfDay = "18:00".parse().unwrap();
+        assert!(!time.matches(&at(1, 17, 59)));
+        assert!(time.matches(&at(1, 18, 0)));
+        assert!(time.matches(&at(7, 18, 0)));
+        assert!(!time.matches(&at(1, 18, 1)));
+
+        // Only on given days
+        let time: TimeOfDay = "sat,sun 09:30".parse().unwrap();
+        assert!(time.matches(&at(6, 9, 30)));
+        assert!(!time.matches(&at(5, 9, 30)));
+        assert_eq!(time.to_string(), "sat,sun 09:30");
+
+        assert!("24:00".parse::<TimeOfDay>().is_err());
+        assert!("18:00-19:00".parse::<TimeOfDay>().is_err());
+        assert!("someday 18:00".parse::<TimeOfDay>().is_err());
+    }
+
+    #[test]
+    fn test_time_window_invalid() {
+        assert!("25:00-26:00".parse::<TimeWindow>().is_err());
+        assert!("10:00-10:00".parse::<TimeWindow>().is_err());
+        assert!("someday 10:00-11:00".parse::<TimeWindow>().is_err());
+        assert!("10:00".parse::<TimeWindow>().is_err());
+        assert!("sat,sun 00:00-24:00".parse::<TimeWindow>().is_ok());
+    }
+}
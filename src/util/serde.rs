@@ -1,8 +1,11 @@
-use std::net::{SocketAddr, ToSocketAddrs};
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
 
 use serde::de::{Error, Unexpected};
 use serde::{Deserialize, Deserializer};
 
+use crate::net::{Endpoint, Host};
+
 /// Deserialize a `Vec` into a `HashMap` by key.
 pub fn to_socket_addrs<'de, D>(d: D) -> Result<SocketAddr, D::Error>
 where
@@ -10,7 +13,56 @@ where
 {
     // Deserialize string
     let addr = String::deserialize(d)?;
+    parse_socket_addr(&addr)
+}
+
+/// Deserialize an endpoint, a Unix socket path prefixed with `unix:` or a socket address.
+///
+/// Hostnames are kept, to resolve them again later. A bare hostname without port is resolved
+/// through its Minecraft SRV record when it is first connected to.
+pub fn to_endpoint<'de, D>(d: D) -> Result<Endpoint, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    // Deserialize string
+    let addr = String::deserialize(d)?;
+
+    if let Some(path) = addr.strip_prefix("unix:") {
+        #[cfg(unix)]
+        return Ok(Endpoint::Unix(path.into()));
+
+        #[cfg(not(unix))]
+        return Err(Error::invalid_value(
+            Unexpected::Str(path),
+            &"IP or resolvable host and port, Unix sockets are not supported on this platform",
+        ));
+    }
+
+    // Keep hostname, unless given a raw IP address
+    if let Ok(addr) = addr.parse() {
+        return Ok(Endpoint::Tcp(addr));
+    }
+
+    // Bare hostname, resolved through its SRV record when connecting
+    if !addr.contains(':') && addr.parse::<IpAddr>().is_err() {
+        return Ok(Endpoint::Host(Arc::new(Host::unresolved(addr, None))));
+    }
+    let resolved = parse_socket_addr(&addr)?;
+    match addr
+        .rsplit_once(':')
+        .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+    {
+        Some((host, port)) => Ok(Endpoint::Host(Arc::new(Host::new(
+            host.into(),
+            Some(port),
+            resolved,
+        )))),
+        None => Ok(Endpoint::Tcp(resolved)),
+    }
+}
 
+/// Resolve or parse a socket address.
+fn parse_socket_addr<E: Error>(addr: &str) -> Result<SocketAddr, E> {
     // Try to socket address to resolve
     match addr.to_socket_addrs() {
         Ok(mut addr) => {
@@ -24,7 +76,6 @@ where
     }
 
     // Parse raw IP address
-    addr.parse().map_err(|_| {
-        Error::invalid_value(Unexpected::Str(&addr), &"IP or resolvable host and port")
-    })
+    addr.parse()
+        .map_err(|_| Error::invalid_value(Unexpected::Str(addr), &"IP or resolvable host and port"))
 }
@@ -0,0 +1,215 @@
+//! Minimal SOCKS5 client, to tunnel connections through a proxy.
+//!
+//! Only supports the CONNECT command without authentication.
+//!
+//! See: <https://datatracker.ietf.org/doc/html/rfc1928>
+
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// SOCKS protocol version.
+const VERSION: u8 = 0x05;
+
+/// Authentication method: no authentication required.
+const METHOD_NO_AUTH: u8 = 0x00;
+
+/// CONNECT command.
+const CMD_CONNECT: u8 = 0x01;
+
+/// Address types.
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// Reply code on success.
+const REPLY_SUCCEEDED: u8 = 0x00;
+
+/// Target to connect to through the proxy.
+#[derive(Debug, Clone, Copy)]
+pub enum Target<'a> {
+    /// Socket address.
+    Addr(SocketAddr),
+
+    /// Hostname and port, resolved by the proxy.
+    Domain(&'a str, u16),
+}
+
+impl fmt::Display for Target<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Addr(addr) => addr.fmt(f),
+            Self::Domain(host, port) => write!(f, "{host}:{port}"),
+        }
+    }
+}
+
+/// Tunnel the given stream to the proxy through to the given target.
+///
+/// The stream is connected to the target once this returns.
+pub async fn connect<S>(stream: &mut S, target: Target<'_>) -> io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    // Greeting, only offer no authentication
+    stream.write_all(&[VERSION, 1, METHOD_NO_AUTH]).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != VERSION {
+        return Err(error("proxy is not a SOCKS5 proxy"));
+    }
+    if choice[1] != METHOD_NO_AUTH {
+        return Err(error("proxy requires authentication"));
+    }
+
+    // Request connection to target
+    let mut request = vec![VERSION, CMD_CONNECT, 0x00];
+    let port = match target {
+        Target::Addr(SocketAddr::V4(addr)) => {
+            request.push(ATYP_IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        Target::Addr(SocketAddr::V6(addr)) => {
+            request.push(ATYP_IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+            addr.port()
+        }
+        Target::Domain(host, port) => {
+            let len = u8::try_from(host.len()).map_err(|_| error("hostname too long"))?;
+            request.push(ATYP_DOMAIN);
+            request.push(len);
+            request.extend_from_slice(host.as_bytes());
+            port
+        }
+    };
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    // Read reply, skip bound address
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != VERSION {
+        return Err(error("invalid SOCKS5 reply"));
+    }
+    if reply[1] != REPLY_SUCCEEDED {
+        return Err(error(&format!(
+            "proxy failed to connect to {target} (reply {})",
+            reply[1]
+        )));
+    }
+    let len = match reply[3] {
+        ATYP_IPV4 => 4,
+        ATYP_IPV6 => 16,
+        ATYP_DOMAIN => stream.read_u8().await? as usize,
+        _ => return Err(error("invalid SOCKS5 bound address type")),
+    };
+    let mut bound = vec![0u8; len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(())
+}
+
+/// Build SOCKS5 error.
+fn error(msg: &str) -> io::Error {
+    io::Error::other(format!("SOCKS5: {msg}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    /// Accept a single SOCKS5 client and reply with the given code.
+    ///
+    /// Returns the accepted stream and the raw CONNECT request.
+    async fn mock_proxy(listener: TcpListener, reply: u8) -> (TcpStream, Vec<u8>) {
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        let mut greeting = [0u8; 3];
+        stream.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [VERSION, 1, METHOD_NO_AUTH]);
+        stream.write_all(&[VERSION, METHOD_NO_AUTH]).await.unwrap();
+
+        let mut request = vec![0u8; 4];
+        stream.read_exact(&mut request).await.unwrap();
+        let len = match request[3] {
+            ATYP_IPV4 => 4,
+            ATYP_IPV6 => 16,
+            ATYP_DOMAIN => {
+                let len = stream.read_u8().await.unwrap();
+                request.push(len);
+                len as usize
+            }
+            atyp => panic!("invalid address type {atyp}"),
+        };
+        let mut rest = vec![0u8; len + 2];
+        stream.read_exact(&mut rest).await.unwrap();
+        request.extend_from_slice(&rest);
+        stream
+            .write_all(&[VERSION, reply, 0, ATYP_IPV4, 127, 0, 0, 1, 0x63, 0xDD])
+            .await
+            .unwrap();
+        (stream, request)
+    }
+
+    #[tokio::test]
+    async fn test_connect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let proxy = tokio::spawn(mock_proxy(listener, REPLY_SUCCEEDED));
+
+        connect(&mut stream, Target::Addr("10.0.0.1:25565".parse().unwrap()))
+            .await
+            .unwrap();
+        let (mut tunnel, request) = proxy.await.unwrap();
+        assert_eq!(
+            request,
+            [VERSION, CMD_CONNECT, 0, ATYP_IPV4, 10, 0, 0, 1, 0x63, 0xDD]
+        );
+
+        // Stream is tunneled once connected
+        stream.write_all(b"hello").await.unwrap();
+        let mut data = [0u8; 5];
+        tunnel.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"hello");
+    }
+
+    #[tokio::test]
+    async fn test_connect_refused() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        tokio::spawn(mock_proxy(listener, 0x05));
+
+        let err = connect(&mut stream, Target::Addr("10.0.0.1:25565".parse().unwrap()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("reply 5"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_connect_domain() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut stream = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let proxy = tokio::spawn(mock_proxy(listener, REPLY_SUCCEEDED));
+
+        // Hostname is sent for the proxy to resolve
+        connect(&mut stream, Target::Domain("mc.internal", 25565))
+            .await
+            .unwrap();
+        let (_, request) = proxy.await.unwrap();
+        let mut expected = vec![VERSION, CMD_CONNECT, 0, ATYP_DOMAIN, 11];
+        expected.extend_from_slice(b"mc.internal");
+        expected.extend_from_slice(&[0x63, 0xDD]);
+        assert_eq!(request, expected);
+    }
+}
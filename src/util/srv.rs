@@ -0,0 +1,122 @@
+use std::cmp::Reverse;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+
+use hickory_resolver::error::ResolveErrorKind;
+use hickory_resolver::TokioAsyncResolver;
+use tokio::net::lookup_host;
+
+/// SRV service name prefix Minecraft uses.
+pub const SERVICE: &str = "_minecraft._tcp.";
+
+/// Default Minecraft port, used if the host has no SRV record.
+pub const DEFAULT_PORT: u16 = 25565;
+
+/// Resolve a bare hostname to a socket address, following its Minecraft SRV record.
+///
+/// Falls back to the A/AAAA records of the host on the default port if there is no SRV record.
+pub async fn resolve(host: &str) -> io::Result<SocketAddr> {
+    resolve_with(host, lookup_srv, lookup_addr).await
+}
+
+/// Resolve a bare hostname with the given SRV and address resolvers.
+async fn resolve_with<S, SR, A, AR>(host: &str, srv: S, addr: A) -> io::Result<SocketAddr>
+where
+    S: FnOnce(String) -> SR,
+    SR: Future<Output = io::Result<Option<(String, u16)>>>,
+    A: FnOnce(String, u16) -> AR,
+    AR: Future<Output = io::Result<SocketAddr>>,
+{
+    let (target, port) = match srv(format!("{SERVICE}{host}")).await {
+        Ok(Some(record)) => record,
+        Ok(None) => (host.into(), DEFAULT_PORT),
+        Err(err) => {
+            debug!(target: "lazymc", "Failed to look up SRV record for {}, using host: {}", host, err);
+            (host.into(), DEFAULT_PORT)
+        }
+    };
+
+    addr(target, port).await
+}
+
+/// Resolve host and port to a socket address through the system resolver.
+async fn lookup_addr(host: String, port: u16) -> io::Result<SocketAddr> {
+    lookup_host((host.as_str(), port))
+        .await?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no addresses found"))
+}
+
+/// Look up the SRV record with the highest precedence for the given name.
+///
+/// Uses the nameservers from the system configuration.
+async fn lookup_srv(name: String) -> io::Result<Option<(String, u16)>> {
+    let resolver = TokioAsyncResolver::tokio_from_system_conf().map_err(io::Error::other)?;
+    let lookup = match resolver.srv_lookup(name.as_str()).await {
+        Ok(lookup) => lookup,
+        Err(err) if matches!(err.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            return Ok(None)
+        }
+        Err(err) => return Err(io::Error::other(err)),
+    };
+
+    // Lowest priority, then highest weight
+    Ok(lookup
+        .iter()
+        .min_by_key(|srv| (srv.priority(), Reverse(srv.weight())))
+        .map(|srv| (srv.target().to_utf8(), srv.port())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve_srv_record() {
+        let addr = resolve_with(
+            "mc.example.com",
+            |name| async move {
+                assert_eq!(name, "_minecraft._tcp.mc.example.com");
+                Ok(Some(("backend.example.com.".into(), 25570)))
+            },
+            |host, port| async move {
+                assert_eq!(host, "backend.example.com.");
+                Ok(SocketAddr::new([10, 0, 0, 2].into(), port))
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(addr, "10.0.0.2:25570".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_plain_record() {
+        let addr = resolve_with(
+            "mc.example.com",
+            |_| async { Ok(None) },
+            |host, port| async move {
+                assert_eq!(host, "mc.example.com");
+                Ok(SocketAddr::new([10, 0, 0, 1].into(), port))
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(addr, "10.0.0.1:25565".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_srv_failure_falls_back() {
+        let addr = resolve_with(
+            "mc.example.com",
+            |_| async { Err(io::Error::new(io::ErrorKind::TimedOut, "timeout")) },
+            |host, port| async move {
+                assert_eq!(host, "mc.example.com");
+                Ok(SocketAddr::new([10, 0, 0, 1].into(), port))
+            },
+        )
+        .await
+        .unwrap();
+        assert_eq!(addr, "10.0.0.1:25565".parse().unwrap());
+    }
+}
@@ -0,0 +1,95 @@
+//! Shared helpers for tests.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use minecraft_protocol::encoder::Encoder;
+use minecraft_protocol::version::v1_14_4::handshake::Handshake;
+use minecraft_protocol::version::v1_14_4::login::LoginStart;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+
+use crate::config::Config;
+use crate::proto::client::{Client, ClientState};
+use crate::proto::packet::RawPacket;
+use crate::service::server::Permit;
+
+/// Parse a test configuration.
+///
+/// The given TOML is appended to a `[server]` section with `command = "true"`, so leading keys
+/// without a section header configure the server.
+pub fn config(toml: &str) -> Config {
+    toml::from_str(&format!("[server]\ncommand = \"true\"\n{toml}")).unwrap()
+}
+
+/// Create a temporary directory unique to the given test.
+///
+/// Use as server directory, so tests running in parallel don't share PID and state files.
+pub fn temp_dir(test: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lazymc-test-{}-{}", test, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Acquire a connection permit, from a limit of one connection.
+pub fn permit() -> Permit {
+    Arc::new(Arc::new(Semaphore::new(1)).try_acquire_owned().unwrap())
+}
+
+/// Connect a local TCP stream pair.
+pub async fn stream_pair() -> (TcpStream, TcpStream) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let connect = TcpStream::connect(listener.local_addr().unwrap());
+    let (connected, accepted) = tokio::join!(connect, listener.accept());
+    (connected.unwrap(), accepted.unwrap().0)
+}
+
+/// Protocol version test clients use, 1.20.3.
+const PROTOCOL: i32 = 765;
+
+/// Encode handshake packet switching to the given state.
+pub fn handshake_packet(next_state: ClientState) -> Vec<u8> {
+    handshake_packet_protocol(PROTOCOL, next_state)
+}
+
+/// Encode handshake packet with the given protocol version, switching to the given state.
+fn handshake_packet_protocol(protocol: i32, next_state: ClientState) -> Vec<u8> {
+    let handshake = Handshake {
+        protocol_version: protocol,
+        server_addr: "localhost".into(),
+        server_port: 25565,
+        next_state: next_state.to_id(),
+    };
+
+    let mut data = Vec::new();
+    handshake.encode(&mut data).unwrap();
+    RawPacket::new(0, data)
+        .encode_with_len(&Client::dummy())
+        .unwrap()
+}
+
+/// Encode handshake and login start packets, as sent by a joining client.
+pub fn login_packets(username: &str) -> Vec<u8> {
+    login_packets_protocol(PROTOCOL, username)
+}
+
+/// Encode handshake and login start packets, as sent by a joining client with the given protocol
+/// version.
+pub fn login_packets_protocol(protocol: i32, username: &str) -> Vec<u8> {
+    let mut packets = handshake_packet_protocol(protocol, ClientState::Login);
+    packets.extend(login_start_packet(username));
+    packets
+}
+
+/// Encode login start packet, as sent by a joining client after the handshake.
+pub fn login_start_packet(username: &str) -> Vec<u8> {
+    let login_start = LoginStart {
+        name: username.into(),
+    };
+
+    let mut data = Vec::new();
+    login_start.encode(&mut data).unwrap();
+    RawPacket::new(0, data)
+        .encode_with_len(&Client::dummy())
+        .unwrap()
+}
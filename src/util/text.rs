@@ -0,0 +1,24 @@
+/// Strip formatting codes from the given text.
+pub fn strip_formatting(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '§' {
+            chars.next();
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_formatting() {
+        assert_eq!(strip_formatting("§2Sleeping§r\nJoin"), "Sleeping\nJoin");
+        assert_eq!(strip_formatting("trailing §"), "trailing ");
+    }
+}